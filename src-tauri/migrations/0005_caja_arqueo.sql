@@ -0,0 +1,19 @@
+PRAGMA foreign_keys = ON;
+
+-- CAJA: arqueo (fondo inicial + conteo de efectivo al cierre)
+ALTER TABLE caja ADD COLUMN monto_apertura    INTEGER NOT NULL DEFAULT 0 CHECK (monto_apertura >= 0);
+ALTER TABLE caja ADD COLUMN cerrada_por       INTEGER REFERENCES usuario(id_usuario);
+ALTER TABLE caja ADD COLUMN efectivo_esperado INTEGER;   -- apertura + efectivo cobrado
+ALTER TABLE caja ADD COLUMN efectivo_contado  INTEGER;   -- NULL => se cerró sin arqueo
+ALTER TABLE caja ADD COLUMN diferencia        INTEGER;   -- contado - esperado (>0 sobrante | <0 faltante)
+
+-- CAJA_ARQUEO_ITEM: conteo por denominación
+CREATE TABLE IF NOT EXISTS caja_arqueo_item (
+  id_caja       INTEGER NOT NULL REFERENCES caja(id_caja) ON DELETE CASCADE,
+  denominacion  INTEGER NOT NULL CHECK (denominacion > 0),
+  cantidad      INTEGER NOT NULL CHECK (cantidad >= 0),
+  subtotal      INTEGER NOT NULL CHECK (subtotal >= 0),
+  PRIMARY KEY (id_caja, denominacion)
+);
+
+CREATE INDEX IF NOT EXISTS ix_caja_cerrada_por ON caja(cerrada_por);
@@ -0,0 +1,17 @@
+PRAGMA foreign_keys = ON;
+
+-- CAJA_MOVIMIENTO: efectivo que entra/sale del cajón sin ser una venta
+CREATE TABLE IF NOT EXISTS caja_movimiento (
+  id_caja_movimiento  INTEGER PRIMARY KEY,
+  id_caja             INTEGER NOT NULL REFERENCES caja(id_caja),
+  tipo                TEXT NOT NULL
+    CHECK (tipo IN ('retiro','ingreso','pago_proveedor')),
+  monto               INTEGER NOT NULL CHECK (monto > 0),
+  motivo              TEXT NOT NULL CHECK (length(trim(motivo)) > 0),
+  id_usuario          INTEGER NOT NULL REFERENCES usuario(id_usuario),
+  fecha_hora          DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  id_gasto_negocio    INTEGER REFERENCES gasto_negocio(id_gasto_negocio) -- si se imputó como gasto
+);
+
+CREATE INDEX IF NOT EXISTS ix_caja_mov_caja  ON caja_movimiento(id_caja);
+CREATE INDEX IF NOT EXISTS ix_caja_mov_fecha ON caja_movimiento(fecha_hora);
@@ -0,0 +1,94 @@
+PRAGMA foreign_keys = ON;
+
+-- CIERRE_Z: foto inmutable y numerada de cada caja al cerrarse
+CREATE TABLE IF NOT EXISTS cierre_z (
+  id_cierre_z         INTEGER PRIMARY KEY,
+  numero              INTEGER NOT NULL UNIQUE,          -- correlativo 1,2,3...
+  id_caja             INTEGER NOT NULL UNIQUE REFERENCES caja(id_caja),
+  id_operador         INTEGER NOT NULL REFERENCES usuario(id_usuario), -- abrió la caja
+  id_usuario_cierre   INTEGER NOT NULL REFERENCES usuario(id_usuario), -- la cerró
+  abierta_en          DATETIME NOT NULL,
+  cerrada_en          DATETIME NOT NULL,
+
+  cantidad_ventas     INTEGER NOT NULL DEFAULT 0,
+  total_ventas        INTEGER NOT NULL DEFAULT 0,
+  cantidad_anuladas   INTEGER NOT NULL DEFAULT 0,
+  total_descuentos    INTEGER NOT NULL DEFAULT 0,
+
+  monto_apertura      INTEGER NOT NULL DEFAULT 0,
+  total_ingresos      INTEGER NOT NULL DEFAULT 0,       -- caja_movimiento
+  total_egresos       INTEGER NOT NULL DEFAULT 0,
+  efectivo_esperado   INTEGER NOT NULL DEFAULT 0,
+  efectivo_contado    INTEGER,                          -- NULL => sin arqueo
+  diferencia          INTEGER,
+
+  creado_en           DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+CREATE TABLE IF NOT EXISTS cierre_z_medio (
+  id_cierre_z   INTEGER NOT NULL REFERENCES cierre_z(id_cierre_z),
+  medio         TEXT NOT NULL,
+  cantidad      INTEGER NOT NULL DEFAULT 0,   -- cantidad de pagos
+  total         INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (id_cierre_z, medio)
+);
+
+-- Cambios sobre ventas de una caja ya cerrada (no tocan el Z original)
+CREATE TABLE IF NOT EXISTS cierre_z_ajuste (
+  id_ajuste       INTEGER PRIMARY KEY,
+  id_cierre_z     INTEGER NOT NULL REFERENCES cierre_z(id_cierre_z),
+  id_venta        INTEGER NOT NULL REFERENCES venta(id_venta),
+  medio           TEXT NOT NULL,
+  monto_anterior  INTEGER NOT NULL DEFAULT 0,
+  monto_nuevo     INTEGER NOT NULL DEFAULT 0,
+  diferencia      INTEGER NOT NULL,            -- nuevo - anterior
+  origen          TEXT NOT NULL,               -- 'edicion' | 'anulacion' | ...
+  id_usuario      INTEGER NOT NULL REFERENCES usuario(id_usuario),
+  fecha_hora      DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+CREATE INDEX IF NOT EXISTS ix_cierre_z_ajuste_z     ON cierre_z_ajuste(id_cierre_z);
+CREATE INDEX IF NOT EXISTS ix_cierre_z_ajuste_venta ON cierre_z_ajuste(id_venta);
+
+-- Inmutabilidad
+DROP TRIGGER IF EXISTS cierre_z_no_upd;
+CREATE TRIGGER cierre_z_no_upd
+BEFORE UPDATE ON cierre_z
+BEGIN
+  SELECT RAISE(ABORT, 'El cierre Z es inmutable');
+END;
+
+DROP TRIGGER IF EXISTS cierre_z_no_del;
+CREATE TRIGGER cierre_z_no_del
+BEFORE DELETE ON cierre_z
+BEGIN
+  SELECT RAISE(ABORT, 'El cierre Z es inmutable');
+END;
+
+DROP TRIGGER IF EXISTS cierre_z_medio_no_upd;
+CREATE TRIGGER cierre_z_medio_no_upd
+BEFORE UPDATE ON cierre_z_medio
+BEGIN
+  SELECT RAISE(ABORT, 'El cierre Z es inmutable');
+END;
+
+DROP TRIGGER IF EXISTS cierre_z_medio_no_del;
+CREATE TRIGGER cierre_z_medio_no_del
+BEFORE DELETE ON cierre_z_medio
+BEGIN
+  SELECT RAISE(ABORT, 'El cierre Z es inmutable');
+END;
+
+DROP TRIGGER IF EXISTS cierre_z_ajuste_no_upd;
+CREATE TRIGGER cierre_z_ajuste_no_upd
+BEFORE UPDATE ON cierre_z_ajuste
+BEGIN
+  SELECT RAISE(ABORT, 'Los ajustes de cierre Z no se modifican');
+END;
+
+DROP TRIGGER IF EXISTS cierre_z_ajuste_no_del;
+CREATE TRIGGER cierre_z_ajuste_no_del
+BEFORE DELETE ON cierre_z_ajuste
+BEGIN
+  SELECT RAISE(ABORT, 'Los ajustes de cierre Z no se modifican');
+END;
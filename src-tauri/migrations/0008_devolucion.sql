@@ -0,0 +1,68 @@
+PRAGMA foreign_keys = ON;
+
+-- DEVOLUCION: reintegro total o parcial de una venta finalizada
+CREATE TABLE IF NOT EXISTS devolucion (
+  id_devolucion INTEGER PRIMARY KEY,
+  id_venta      INTEGER NOT NULL REFERENCES venta(id_venta),
+  id_caja       INTEGER NOT NULL REFERENCES caja(id_caja),  -- caja de donde sale el reintegro
+  id_usuario    INTEGER NOT NULL REFERENCES usuario(id_usuario),
+  fecha_hora    DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  motivo        TEXT,
+  total         INTEGER NOT NULL DEFAULT 0 CHECK (total >= 0)
+);
+
+CREATE TABLE IF NOT EXISTS devolucion_item (
+  id_devolucion_item  INTEGER PRIMARY KEY,
+  id_devolucion       INTEGER NOT NULL REFERENCES devolucion(id_devolucion) ON DELETE CASCADE,
+  id_item             INTEGER NOT NULL REFERENCES venta_item(id_item),
+  id_producto         INTEGER NOT NULL REFERENCES producto(id_producto),
+  cantidad            INTEGER NOT NULL CHECK (cantidad > 0),
+  precio_unitario     INTEGER NOT NULL CHECK (precio_unitario >= 0), -- el cobrado en la venta
+  costo_unitario      INTEGER NOT NULL CHECK (costo_unitario >= 0),  -- costo_unitario_en_venta
+  subtotal            INTEGER NOT NULL CHECK (subtotal >= 0)
+);
+
+CREATE TABLE IF NOT EXISTS devolucion_pago (
+  id_devolucion_pago  INTEGER PRIMARY KEY,
+  id_devolucion       INTEGER NOT NULL REFERENCES devolucion(id_devolucion) ON DELETE CASCADE,
+  medio               TEXT NOT NULL CHECK (medio IN ('efectivo','debito','credito','transferencia')),
+  monto               INTEGER NOT NULL CHECK (monto > 0)
+);
+
+CREATE INDEX IF NOT EXISTS ix_devolucion_venta     ON devolucion(id_venta);
+CREATE INDEX IF NOT EXISTS ix_devolucion_caja      ON devolucion(id_caja);
+CREATE INDEX IF NOT EXISTS ix_devolucion_fecha     ON devolucion(fecha_hora);
+CREATE INDEX IF NOT EXISTS ix_devolucion_item_item ON devolucion_item(id_item);
+
+-- Z: reintegros hechos desde la caja
+ALTER TABLE cierre_z ADD COLUMN total_devoluciones INTEGER NOT NULL DEFAULT 0;
+
+-- Líneas que cuentan para rentabilidad: vendidas (+) y devueltas (-).
+-- La devolución cuenta en la fecha en que se hizo, no en la de la venta.
+DROP VIEW IF EXISTS v_linea_rentabilidad;
+CREATE VIEW v_linea_rentabilidad AS
+SELECT
+  v.id_venta,
+  v.id_usuario,
+  v.fecha_hora,
+  vi.id_producto,
+  vi.cantidad,
+  vi.subtotal,
+  vi.costo_unitario_en_venta * vi.cantidad AS costo,
+  'venta' AS origen
+FROM venta v
+JOIN venta_item vi ON vi.id_venta = v.id_venta
+WHERE v.estado = 'finalizada'
+UNION ALL
+SELECT
+  d.id_venta,
+  v.id_usuario,
+  d.fecha_hora,
+  di.id_producto,
+  -di.cantidad,
+  -di.subtotal,
+  -(di.costo_unitario * di.cantidad),
+  'devolucion'
+FROM devolucion d
+JOIN devolucion_item di ON di.id_devolucion = d.id_devolucion
+JOIN venta v            ON v.id_venta = d.id_venta;
@@ -0,0 +1,7 @@
+PRAGMA foreign_keys = ON;
+
+-- VENTA: anulación de ventas finalizadas (los items quedan para auditoría)
+ALTER TABLE venta ADD COLUMN anulada_en             DATETIME;
+ALTER TABLE venta ADD COLUMN anulada_por            INTEGER REFERENCES usuario(id_usuario);
+ALTER TABLE venta ADD COLUMN anulacion_aprobada_por INTEGER REFERENCES usuario(id_usuario); -- admin que autorizó
+ALTER TABLE venta ADD COLUMN anulacion_motivo       TEXT;
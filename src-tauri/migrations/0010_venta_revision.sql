@@ -0,0 +1,17 @@
+PRAGMA foreign_keys = ON;
+
+-- VENTA_REVISION: foto de cada versión anterior de una venta editada por admin
+CREATE TABLE IF NOT EXISTS venta_revision (
+  id_revision   INTEGER PRIMARY KEY,
+  id_venta      INTEGER NOT NULL REFERENCES venta(id_venta),
+  numero        INTEGER NOT NULL,                   -- versión 1, 2, ... de esa venta
+  total         INTEGER NOT NULL,
+  items_json    TEXT NOT NULL,                      -- [{id_producto, producto, cantidad, ...}]
+  pagos_json    TEXT NOT NULL,                      -- [{medio, monto, referencia}]
+  id_usuario    INTEGER NOT NULL REFERENCES usuario(id_usuario), -- quien editó
+  motivo        TEXT NOT NULL CHECK (length(trim(motivo)) > 0),
+  fecha_hora    DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  UNIQUE (id_venta, numero)
+);
+
+CREATE INDEX IF NOT EXISTS ix_venta_revision_venta ON venta_revision(id_venta);
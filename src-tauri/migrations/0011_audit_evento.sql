@@ -0,0 +1,30 @@
+PRAGMA foreign_keys = ON;
+
+-- AUDIT_EVENTO: quién cambió qué y cuándo (estado antes/después en JSON)
+CREATE TABLE IF NOT EXISTS audit_evento (
+  id_evento     INTEGER PRIMARY KEY,
+  fecha_hora    DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  id_usuario    INTEGER REFERENCES usuario(id_usuario), -- actor (NULL = sin sesión)
+  entidad       TEXT NOT NULL CHECK (length(trim(entidad)) > 0),
+  id_entidad    INTEGER,
+  accion        TEXT NOT NULL CHECK (length(trim(accion)) > 0),
+  antes_json    TEXT,
+  despues_json  TEXT
+);
+
+CREATE INDEX IF NOT EXISTS ix_audit_fecha   ON audit_evento(fecha_hora);
+CREATE INDEX IF NOT EXISTS ix_audit_usuario ON audit_evento(id_usuario);
+CREATE INDEX IF NOT EXISTS ix_audit_entidad ON audit_evento(entidad, id_entidad);
+
+-- El registro de auditoría no se modifica ni se borra
+CREATE TRIGGER IF NOT EXISTS trg_audit_evento_no_update
+BEFORE UPDATE ON audit_evento
+BEGIN
+  SELECT RAISE(ABORT, 'audit_evento es inmutable');
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_audit_evento_no_delete
+BEFORE DELETE ON audit_evento
+BEGIN
+  SELECT RAISE(ABORT, 'audit_evento es inmutable');
+END;
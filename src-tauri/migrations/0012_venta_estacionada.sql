@@ -0,0 +1,34 @@
+PRAGMA foreign_keys = ON;
+
+-- Carritos estacionados: varias ventas en_curso por operador.
+-- estacionada_en NULL = carrito activo en pantalla.
+ALTER TABLE venta ADD COLUMN etiqueta TEXT;
+ALTER TABLE venta ADD COLUMN estacionada_en DATETIME;
+-- Última modificación del carrito (para detectar huérfanos)
+ALTER TABLE venta ADD COLUMN actualizada_en DATETIME;
+
+CREATE INDEX IF NOT EXISTS ix_venta_en_curso ON venta(estado, id_caja, id_usuario);
+
+CREATE TRIGGER IF NOT EXISTS trg_item_actividad_ins
+AFTER INSERT ON venta_item
+FOR EACH ROW
+BEGIN
+  UPDATE venta SET actualizada_en = DATETIME('now','localtime')
+   WHERE id_venta = NEW.id_venta AND estado = 'en_curso';
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_item_actividad_upd
+AFTER UPDATE OF cantidad, precio_unitario ON venta_item
+FOR EACH ROW
+BEGIN
+  UPDATE venta SET actualizada_en = DATETIME('now','localtime')
+   WHERE id_venta = NEW.id_venta AND estado = 'en_curso';
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_item_actividad_del
+AFTER DELETE ON venta_item
+FOR EACH ROW
+BEGIN
+  UPDATE venta SET actualizada_en = DATETIME('now','localtime')
+   WHERE id_venta = OLD.id_venta AND estado = 'en_curso';
+END;
@@ -0,0 +1,67 @@
+PRAGMA foreign_keys = ON;
+
+-- Descuentos manuales por línea o por ticket.
+-- venta_item.precio_unitario sigue siendo el precio de lista (bruto);
+-- venta_item.descuento es lo descontado en la línea (incluye su parte del
+-- descuento de ticket) y subtotal = cantidad * precio_unitario - descuento.
+ALTER TABLE venta_item ADD COLUMN descuento INTEGER NOT NULL DEFAULT 0 CHECK (descuento >= 0);
+
+CREATE TABLE IF NOT EXISTS venta_descuento (
+  id_descuento  INTEGER PRIMARY KEY,
+  id_venta      INTEGER NOT NULL REFERENCES venta(id_venta) ON DELETE CASCADE,
+  id_item       INTEGER REFERENCES venta_item(id_item) ON DELETE CASCADE, -- NULL = ticket
+  tipo          TEXT NOT NULL CHECK (tipo IN ('porcentaje','monto')),
+  valor         INTEGER NOT NULL CHECK (valor > 0),          -- % entero o ARS
+  monto         INTEGER NOT NULL DEFAULT 0 CHECK (monto >= 0), -- ARS aplicado
+  motivo        TEXT NOT NULL CHECK (length(trim(motivo)) > 0),
+  id_usuario    INTEGER NOT NULL REFERENCES usuario(id_usuario),
+  aprobado_por  INTEGER REFERENCES usuario(id_usuario),
+  fecha_hora    DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+CREATE INDEX IF NOT EXISTS ix_venta_descuento_venta ON venta_descuento(id_venta);
+CREATE INDEX IF NOT EXISTS ix_venta_descuento_item  ON venta_descuento(id_item);
+
+-- Tope de descuento (% sobre el bruto de la línea o del ticket) por rol
+CREATE TABLE IF NOT EXISTS descuento_limite (
+  rol_tipo  TEXT PRIMARY KEY CHECK (rol_tipo IN ('admin','operador')),
+  max_pct   INTEGER NOT NULL CHECK (max_pct BETWEEN 0 AND 100)
+);
+
+INSERT OR IGNORE INTO descuento_limite (rol_tipo, max_pct) VALUES
+  ('admin', 100),
+  ('operador', 10);
+
+-- Subtotal neto de descuento
+DROP TRIGGER IF EXISTS trg_item_ins;
+DROP TRIGGER IF EXISTS trg_item_upd;
+
+CREATE TRIGGER trg_item_ins
+AFTER INSERT ON venta_item
+FOR EACH ROW
+BEGIN
+  UPDATE venta_item
+     SET subtotal = NEW.cantidad * NEW.precio_unitario - NEW.descuento
+   WHERE id_item = NEW.id_item;
+
+  UPDATE venta
+     SET total = (SELECT COALESCE(SUM(subtotal),0)
+                    FROM venta_item
+                   WHERE id_venta = NEW.id_venta)
+   WHERE id_venta = NEW.id_venta;
+END;
+
+CREATE TRIGGER trg_item_upd
+AFTER UPDATE OF cantidad, precio_unitario, descuento ON venta_item
+FOR EACH ROW
+BEGIN
+  UPDATE venta_item
+     SET subtotal = NEW.cantidad * NEW.precio_unitario - NEW.descuento
+   WHERE id_item = NEW.id_item;
+
+  UPDATE venta
+     SET total = (SELECT COALESCE(SUM(subtotal),0)
+                    FROM venta_item
+                   WHERE id_venta = NEW.id_venta)
+   WHERE id_venta = NEW.id_venta;
+END;
@@ -0,0 +1,26 @@
+PRAGMA foreign_keys = ON;
+
+-- Códigos de barra EAN por producto (uno o varios)
+CREATE TABLE IF NOT EXISTS producto_codigo_barra (
+  id_codigo    INTEGER PRIMARY KEY,
+  id_producto  INTEGER NOT NULL REFERENCES producto(id_producto) ON DELETE CASCADE,
+  codigo       TEXT NOT NULL UNIQUE CHECK (length(trim(codigo)) > 0),
+  creado_en    DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+CREATE INDEX IF NOT EXISTS ix_codigo_barra_producto ON producto_codigo_barra(id_producto);
+
+-- PLU: número corto de balanza / teclado
+ALTER TABLE producto ADD COLUMN plu INTEGER CHECK (plu IS NULL OR plu > 0);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_producto_plu ON producto(plu) WHERE plu IS NOT NULL;
+
+-- EAN-13 de balanza: PP + PLU(5) + VALOR(5) + dígito verificador.
+-- 'precio' => VALOR en ARS; 'peso' => VALOR en gramos (precio de lista por kg)
+CREATE TABLE IF NOT EXISTS codigo_embebido (
+  prefijo  TEXT PRIMARY KEY CHECK (length(prefijo) = 2 AND prefijo GLOB '2[0-9]'),
+  tipo     TEXT NOT NULL CHECK (tipo IN ('precio','peso'))
+);
+
+INSERT OR IGNORE INTO codigo_embebido (prefijo, tipo) VALUES
+  ('20', 'precio'),
+  ('21', 'peso');
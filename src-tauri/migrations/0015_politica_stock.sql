@@ -0,0 +1,15 @@
+PRAGMA foreign_keys = ON;
+
+-- Ajustes generales del sistema (clave / valor)
+CREATE TABLE IF NOT EXISTS ajuste (
+  clave           TEXT PRIMARY KEY,
+  valor           TEXT NOT NULL,
+  actualizado_en  DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+-- Política ante stock negativo: 'permitir' | 'advertir' | 'bloquear'
+INSERT OR IGNORE INTO ajuste (clave, valor) VALUES ('stock_negativo', 'advertir');
+
+-- NULL = usa la política global
+ALTER TABLE producto ADD COLUMN politica_stock TEXT
+  CHECK (politica_stock IS NULL OR politica_stock IN ('permitir','advertir','bloquear'));
@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- Efectivo: lo que entregó el cliente y el vuelto.
+-- venta_pago.monto sigue siendo el neto (lo que cuenta para la venta y la caja).
+ALTER TABLE venta_pago ADD COLUMN monto_entregado INTEGER
+  CHECK (monto_entregado IS NULL OR monto_entregado >= monto);
+ALTER TABLE venta_pago ADD COLUMN vuelto INTEGER NOT NULL DEFAULT 0
+  CHECK (vuelto >= 0);
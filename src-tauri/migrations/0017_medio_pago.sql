@@ -0,0 +1,86 @@
+PRAGMA foreign_keys = ON;
+
+-- MEDIO_PAGO: reemplaza el CHECK fijo de venta_pago.medio
+--   recargo_pct  : lo que se le cobra de más al cliente (sobre el monto)
+--   comision_pct : lo que retiene la procesadora (sobre monto + recargo), gasto
+CREATE TABLE IF NOT EXISTS medio_pago (
+  codigo        TEXT PRIMARY KEY CHECK (length(trim(codigo)) > 0),
+  nombre        TEXT NOT NULL,
+  recargo_pct   REAL NOT NULL DEFAULT 0 CHECK (recargo_pct >= 0),
+  comision_pct  REAL NOT NULL DEFAULT 0 CHECK (comision_pct BETWEEN 0 AND 100),
+  activo        INTEGER NOT NULL DEFAULT 1 CHECK (activo IN (0,1)),
+  orden         INTEGER NOT NULL DEFAULT 0
+);
+
+-- Planes de cuotas (tarjeta de crédito). Reemplazan los % del medio.
+CREATE TABLE IF NOT EXISTS medio_pago_plan (
+  codigo_medio  TEXT NOT NULL REFERENCES medio_pago(codigo) ON DELETE CASCADE,
+  cuotas        INTEGER NOT NULL CHECK (cuotas > 1),
+  recargo_pct   REAL NOT NULL DEFAULT 0 CHECK (recargo_pct >= 0),
+  comision_pct  REAL NOT NULL DEFAULT 0 CHECK (comision_pct BETWEEN 0 AND 100),
+  PRIMARY KEY (codigo_medio, cuotas)
+);
+
+INSERT OR IGNORE INTO medio_pago (codigo, nombre, orden) VALUES
+  ('efectivo',      'Efectivo',      1),
+  ('debito',        'Débito',        2),
+  ('credito',       'Crédito',       3),
+  ('transferencia', 'Transferencia', 4);
+
+-- VENTA_PAGO: sin CHECK de medio, con cuotas / recargo / comisión (ARS).
+-- El trigger de venta lee venta_pago: se baja mientras se reconstruye la tabla.
+DROP TRIGGER IF EXISTS trg_venta_check_pagos_total;
+
+CREATE TABLE venta_pago_nueva (
+  id_pago          INTEGER PRIMARY KEY,
+  id_venta         INTEGER NOT NULL REFERENCES venta(id_venta) ON DELETE CASCADE,
+  medio            TEXT NOT NULL REFERENCES medio_pago(codigo),
+  monto            INTEGER NOT NULL CHECK (monto > 0),
+  referencia       TEXT,
+  monto_entregado  INTEGER CHECK (monto_entregado IS NULL OR monto_entregado >= monto),
+  vuelto           INTEGER NOT NULL DEFAULT 0 CHECK (vuelto >= 0),
+  cuotas           INTEGER NOT NULL DEFAULT 1 CHECK (cuotas >= 1),
+  recargo          INTEGER NOT NULL DEFAULT 0 CHECK (recargo >= 0),
+  comision         INTEGER NOT NULL DEFAULT 0 CHECK (comision >= 0)
+);
+
+INSERT INTO venta_pago_nueva (id_pago, id_venta, medio, monto, referencia, monto_entregado, vuelto)
+SELECT id_pago, id_venta, medio, monto, referencia, monto_entregado, vuelto
+FROM venta_pago;
+
+DROP TABLE venta_pago;
+ALTER TABLE venta_pago_nueva RENAME TO venta_pago;
+
+CREATE INDEX IF NOT EXISTS idx_venta_pago_id_venta ON venta_pago(id_venta);
+CREATE INDEX IF NOT EXISTS idx_venta_pago_medio    ON venta_pago(medio);
+
+-- monto es lo que cubre la venta; el recargo va aparte
+CREATE TRIGGER trg_venta_check_pagos_total
+BEFORE UPDATE OF estado ON venta
+WHEN NEW.estado = 'finalizada'
+BEGIN
+  SELECT
+    CASE
+      WHEN (
+        SELECT IFNULL(SUM(monto), 0)
+        FROM venta_pago
+        WHERE id_venta = NEW.id_venta
+      ) <> NEW.total
+      THEN RAISE(ABORT, 'Total de pagos distinto al total de la venta')
+    END;
+END;
+
+-- DEVOLUCION_PAGO: mismo criterio para los reintegros
+CREATE TABLE devolucion_pago_nueva (
+  id_devolucion_pago  INTEGER PRIMARY KEY,
+  id_devolucion       INTEGER NOT NULL REFERENCES devolucion(id_devolucion) ON DELETE CASCADE,
+  medio               TEXT NOT NULL REFERENCES medio_pago(codigo),
+  monto               INTEGER NOT NULL CHECK (monto > 0)
+);
+
+INSERT INTO devolucion_pago_nueva (id_devolucion_pago, id_devolucion, medio, monto)
+SELECT id_devolucion_pago, id_devolucion, medio, monto
+FROM devolucion_pago;
+
+DROP TABLE devolucion_pago;
+ALTER TABLE devolucion_pago_nueva RENAME TO devolucion_pago;
@@ -0,0 +1,23 @@
+PRAGMA foreign_keys = ON;
+
+-- Emisiones de ticket por venta: la primera es el original, el resto copias
+CREATE TABLE IF NOT EXISTS ticket_impresion (
+  id_impresion  INTEGER PRIMARY KEY,
+  id_venta      INTEGER NOT NULL REFERENCES venta(id_venta) ON DELETE CASCADE,
+  copia         INTEGER NOT NULL CHECK (copia IN (0,1)),
+  formato       TEXT NOT NULL CHECK (formato IN ('texto','pdf','escpos')),
+  destino       TEXT,                                   -- archivo o dispositivo
+  id_usuario    INTEGER NOT NULL REFERENCES usuario(id_usuario),
+  fecha_hora    DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+CREATE INDEX IF NOT EXISTS ix_ticket_impresion_venta ON ticket_impresion(id_venta);
+
+-- Encabezado / pie del ticket
+INSERT OR IGNORE INTO ajuste (clave, valor) VALUES
+  ('negocio_nombre',    'Mi negocio'),
+  ('negocio_direccion', ''),
+  ('negocio_cuit',      ''),
+  ('negocio_telefono',  ''),
+  ('ticket_pie',        '¡Gracias por su compra!'),
+  ('ticket_ancho',      '42');
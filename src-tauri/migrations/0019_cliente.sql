@@ -0,0 +1,23 @@
+PRAGMA foreign_keys = ON;
+
+-- CLIENTE (mayoristas, cuentas corrientes, facturación)
+CREATE TABLE IF NOT EXISTS cliente (
+  id_cliente     INTEGER PRIMARY KEY,
+  nombre         TEXT NOT NULL CHECK (length(trim(nombre)) > 0),
+  cuit           TEXT,                                  -- CUIT/CUIL o DNI, solo dígitos
+  condicion_iva  TEXT NOT NULL DEFAULT 'consumidor_final'
+    CHECK (condicion_iva IN ('consumidor_final','responsable_inscripto','monotributo','exento')),
+  telefono       TEXT,
+  email          TEXT,
+  direccion      TEXT,
+  notas          TEXT,
+  activo         INTEGER NOT NULL DEFAULT 1 CHECK (activo IN (0,1)),
+  creado_en      DATETIME NOT NULL DEFAULT (DATETIME('now','localtime'))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS ux_cliente_cuit   ON cliente(cuit) WHERE cuit IS NOT NULL;
+CREATE INDEX IF NOT EXISTS ix_cliente_nombre        ON cliente(nombre COLLATE NOCASE);
+
+-- Cliente (opcional) de la venta
+ALTER TABLE venta ADD COLUMN id_cliente INTEGER REFERENCES cliente(id_cliente);
+CREATE INDEX IF NOT EXISTS ix_venta_cliente ON venta(id_cliente);
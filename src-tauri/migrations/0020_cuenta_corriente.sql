@@ -0,0 +1,61 @@
+PRAGMA foreign_keys = ON;
+
+-- Fiado: la venta se cobra con el medio 'cuenta_corriente' y queda como cargo
+-- del cliente (venta_pago). Los cobros posteriores se imputan a esos cargos.
+INSERT OR IGNORE INTO medio_pago (codigo, nombre, orden) VALUES
+  ('cuenta_corriente', 'Cuenta corriente', 5);
+
+-- 0 = sin cuenta corriente
+ALTER TABLE cliente ADD COLUMN limite_credito INTEGER NOT NULL DEFAULT 0 CHECK (limite_credito >= 0);
+
+-- Cobro a cuenta (entra por la caja si hay una abierta)
+CREATE TABLE IF NOT EXISTS cc_cobro (
+  id_cobro    INTEGER PRIMARY KEY,
+  id_cliente  INTEGER NOT NULL REFERENCES cliente(id_cliente),
+  fecha_hora  DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  medio       TEXT NOT NULL REFERENCES medio_pago(codigo) CHECK (medio <> 'cuenta_corriente'),
+  monto       INTEGER NOT NULL CHECK (monto > 0),
+  referencia  TEXT,
+  id_caja     INTEGER REFERENCES caja(id_caja),
+  id_usuario  INTEGER NOT NULL REFERENCES usuario(id_usuario)
+);
+
+CREATE INDEX IF NOT EXISTS ix_cc_cobro_cliente ON cc_cobro(id_cliente);
+CREATE INDEX IF NOT EXISTS ix_cc_cobro_caja    ON cc_cobro(id_caja);
+
+-- Qué cargo (venta_pago en cuenta corriente) cancela cada cobro
+CREATE TABLE IF NOT EXISTS cc_imputacion (
+  id_imputacion  INTEGER PRIMARY KEY,
+  id_cobro       INTEGER NOT NULL REFERENCES cc_cobro(id_cobro) ON DELETE CASCADE,
+  id_pago        INTEGER NOT NULL REFERENCES venta_pago(id_pago),
+  monto          INTEGER NOT NULL CHECK (monto > 0)
+);
+
+CREATE INDEX IF NOT EXISTS ix_cc_imputacion_cobro ON cc_imputacion(id_cobro);
+CREATE INDEX IF NOT EXISTS ix_cc_imputacion_pago  ON cc_imputacion(id_pago);
+
+-- Movimientos de la cuenta: cargos (+) salen de venta_pago, créditos (-) de
+-- cobros y de reintegros de devoluciones hechos a la cuenta
+DROP VIEW IF EXISTS v_cc_movimiento;
+CREATE VIEW v_cc_movimiento AS
+  SELECT v.id_cliente,
+         v.fecha_hora,
+         'venta'             AS tipo,
+         v.id_venta          AS id_referencia,
+         vp.id_pago          AS id_pago,
+         vp.monto + vp.recargo AS importe
+    FROM venta_pago vp
+    JOIN venta v ON v.id_venta = vp.id_venta
+   WHERE vp.medio = 'cuenta_corriente'
+     AND v.estado = 'finalizada'
+     AND v.id_cliente IS NOT NULL
+  UNION ALL
+  SELECT c.id_cliente, c.fecha_hora, 'cobro', c.id_cobro, NULL, -c.monto
+    FROM cc_cobro c
+  UNION ALL
+  SELECT v.id_cliente, d.fecha_hora, 'devolucion', d.id_devolucion, NULL, -dp.monto
+    FROM devolucion_pago dp
+    JOIN devolucion d ON d.id_devolucion = dp.id_devolucion
+    JOIN venta v      ON v.id_venta = d.id_venta
+   WHERE dp.medio = 'cuenta_corriente'
+     AND v.id_cliente IS NOT NULL;
@@ -0,0 +1,52 @@
+PRAGMA foreign_keys = ON;
+
+-- Configuración fiscal (el CUIT del emisor es negocio_cuit)
+INSERT OR IGNORE INTO ajuste (clave, valor) VALUES
+  ('fiscal_habilitado',    '0'),                     -- '1' = facturar al finalizar
+  ('fiscal_punto_venta',   '1'),
+  ('fiscal_condicion_iva', 'responsable_inscripto'), -- del emisor
+  ('fiscal_alicuota_iva',  '21'),
+  ('fiscal_url',           '');                      -- servicio que habla con la autoridad
+
+-- Facturas y notas de crédito. Se crean 'pendiente' y una cola las manda a
+-- autorizar; el número se asigna al enviarlas y queda fijo para reintentos.
+CREATE TABLE IF NOT EXISTS comprobante (
+  id_comprobante    INTEGER PRIMARY KEY,
+  tipo              TEXT NOT NULL CHECK (tipo IN ('factura','nota_credito')),
+  letra             TEXT NOT NULL CHECK (letra IN ('A','B','C')),
+  cbte_tipo         INTEGER NOT NULL,         -- código de la autoridad (1, 3, 6, 8, 11, 13)
+  punto_venta       INTEGER NOT NULL CHECK (punto_venta > 0),
+  numero            INTEGER,
+  id_venta          INTEGER NOT NULL REFERENCES venta(id_venta),
+  id_devolucion     INTEGER REFERENCES devolucion(id_devolucion),
+  id_asociado       INTEGER REFERENCES comprobante(id_comprobante), -- factura de la NC
+  doc_tipo          INTEGER NOT NULL,         -- 80 CUIT, 99 consumidor final
+  doc_nro           TEXT NOT NULL,
+  importe_neto      INTEGER NOT NULL,
+  importe_iva       INTEGER NOT NULL,
+  importe_total     INTEGER NOT NULL CHECK (importe_total > 0),
+  alicuota_iva      REAL NOT NULL,
+  estado            TEXT NOT NULL DEFAULT 'pendiente'
+                    CHECK (estado IN ('pendiente','autorizado','rechazado')),
+  cae               TEXT,
+  cae_vencimiento   TEXT,                     -- 'YYYY-MM-DD'
+  intentos          INTEGER NOT NULL DEFAULT 0,
+  ultimo_error      TEXT,
+  proximo_intento   DATETIME,                 -- NULL = cuanto antes
+  creado_en         DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  autorizado_en     DATETIME,
+  id_usuario        INTEGER NOT NULL REFERENCES usuario(id_usuario),
+  CHECK ((tipo = 'nota_credito') = (id_devolucion IS NOT NULL AND id_asociado IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS ux_comprobante_numero
+  ON comprobante(punto_venta, cbte_tipo, numero) WHERE numero IS NOT NULL;
+
+-- Una factura vigente por venta y una NC por devolución (las rechazadas no cuentan)
+CREATE UNIQUE INDEX IF NOT EXISTS ux_comprobante_factura_venta
+  ON comprobante(id_venta) WHERE tipo = 'factura' AND estado <> 'rechazado';
+CREATE UNIQUE INDEX IF NOT EXISTS ux_comprobante_nc_devolucion
+  ON comprobante(id_devolucion) WHERE tipo = 'nota_credito' AND estado <> 'rechazado';
+
+CREATE INDEX IF NOT EXISTS ix_comprobante_cola
+  ON comprobante(estado, proximo_intento);
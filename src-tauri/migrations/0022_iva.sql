@@ -0,0 +1,73 @@
+PRAGMA foreign_keys = ON;
+
+-- Alícuota de IVA por producto. Los precios son finales (IVA incluido);
+-- 0 = exento.
+ALTER TABLE producto ADD COLUMN alicuota_iva REAL NOT NULL DEFAULT 21
+  CHECK (alicuota_iva IN (0, 10.5, 21));
+
+-- Se congela en la línea al agregarla, igual que costo_unitario_en_venta
+ALTER TABLE venta_item ADD COLUMN alicuota_iva REAL NOT NULL DEFAULT 21
+  CHECK (alicuota_iva >= 0);
+
+UPDATE venta_item
+   SET alicuota_iva = (SELECT p.alicuota_iva FROM producto p WHERE p.id_producto = venta_item.id_producto);
+
+-- Neto / IVA / bruto por línea (el bruto es el subtotal, ya con descuentos)
+DROP VIEW IF EXISTS v_venta_item_iva;
+CREATE VIEW v_venta_item_iva AS
+SELECT
+  vi.id_item,
+  vi.id_venta,
+  vi.alicuota_iva,
+  vi.subtotal AS bruto,
+  CAST(ROUND(vi.subtotal / (1 + vi.alicuota_iva / 100.0)) AS INTEGER) AS neto,
+  vi.subtotal - CAST(ROUND(vi.subtotal / (1 + vi.alicuota_iva / 100.0)) AS INTEGER) AS iva
+FROM venta_item vi;
+
+-- Lo devuelto lleva la alícuota de la línea vendida
+DROP VIEW IF EXISTS v_devolucion_item_iva;
+CREATE VIEW v_devolucion_item_iva AS
+SELECT
+  di.id_devolucion_item,
+  di.id_devolucion,
+  vi.alicuota_iva,
+  di.subtotal AS bruto,
+  CAST(ROUND(di.subtotal / (1 + vi.alicuota_iva / 100.0)) AS INTEGER) AS neto,
+  di.subtotal - CAST(ROUND(di.subtotal / (1 + vi.alicuota_iva / 100.0)) AS INTEGER) AS iva
+FROM devolucion_item di
+JOIN venta_item vi ON vi.id_item = di.id_item;
+
+-- Rentabilidad con neto e IVA
+DROP VIEW IF EXISTS v_linea_rentabilidad;
+CREATE VIEW v_linea_rentabilidad AS
+SELECT
+  v.id_venta,
+  v.id_usuario,
+  v.fecha_hora,
+  vi.id_producto,
+  vi.cantidad,
+  vi.subtotal,
+  vi.costo_unitario_en_venta * vi.cantidad AS costo,
+  'venta' AS origen,
+  x.neto,
+  x.iva
+FROM venta v
+JOIN venta_item vi      ON vi.id_venta = v.id_venta
+JOIN v_venta_item_iva x ON x.id_item = vi.id_item
+WHERE v.estado = 'finalizada'
+UNION ALL
+SELECT
+  d.id_venta,
+  v.id_usuario,
+  d.fecha_hora,
+  di.id_producto,
+  -di.cantidad,
+  -di.subtotal,
+  -(di.costo_unitario * di.cantidad),
+  'devolucion',
+  -x.neto,
+  -x.iva
+FROM devolucion d
+JOIN devolucion_item di      ON di.id_devolucion = d.id_devolucion
+JOIN v_devolucion_item_iva x ON x.id_devolucion_item = di.id_devolucion_item
+JOIN venta v                 ON v.id_venta = d.id_venta;
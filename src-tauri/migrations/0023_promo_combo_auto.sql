@@ -0,0 +1,4 @@
+PRAGMA foreign_keys = ON;
+
+-- Aplicar combos solos al agregar ítems al carrito ('1' / '0')
+INSERT OR IGNORE INTO ajuste (clave, valor) VALUES ('promo_combo_auto', '0');
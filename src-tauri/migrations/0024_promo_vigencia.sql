@@ -0,0 +1,34 @@
+PRAGMA foreign_keys = ON;
+
+-- Vigencia de combos. Fechas 'YYYY-MM-DD' inclusive (NULL = sin límite),
+-- días como máscara de bits (bit 0 = domingo ... bit 6 = sábado, 127 = todos)
+-- y franja horaria 'HH:MM' (desde incluida, hasta excluida; si desde > hasta
+-- cruza la medianoche). Empiezan y terminan solas: no hay que tocar 'activo'.
+ALTER TABLE promo_combo ADD COLUMN vigente_desde TEXT;
+ALTER TABLE promo_combo ADD COLUMN vigente_hasta TEXT;
+ALTER TABLE promo_combo ADD COLUMN dias_semana INTEGER NOT NULL DEFAULT 127
+  CHECK (dias_semana BETWEEN 1 AND 127);
+ALTER TABLE promo_combo ADD COLUMN hora_desde TEXT;
+ALTER TABLE promo_combo ADD COLUMN hora_hasta TEXT;
+
+-- Estado de cada combo ahora: 'inactiva' | 'proxima' | 'vencida' | 'vigente',
+-- y si el día y la hora actuales entran en su franja.
+DROP VIEW IF EXISTS v_promo_combo_estado;
+CREATE VIEW v_promo_combo_estado AS
+SELECT
+  c.id_combo,
+  CASE
+    WHEN c.activo = 0 THEN 'inactiva'
+    WHEN c.vigente_desde IS NOT NULL AND DATE('now','localtime') < c.vigente_desde THEN 'proxima'
+    WHEN c.vigente_hasta IS NOT NULL AND DATE('now','localtime') > c.vigente_hasta THEN 'vencida'
+    ELSE 'vigente'
+  END AS estado,
+  CASE
+    WHEN (c.dias_semana >> CAST(strftime('%w','now','localtime') AS INTEGER)) & 1 = 0 THEN 0
+    WHEN c.hora_desde IS NULL OR c.hora_hasta IS NULL THEN 1
+    WHEN c.hora_desde <= c.hora_hasta THEN
+      strftime('%H:%M','now','localtime') >= c.hora_desde AND strftime('%H:%M','now','localtime') < c.hora_hasta
+    ELSE
+      strftime('%H:%M','now','localtime') >= c.hora_desde OR strftime('%H:%M','now','localtime') < c.hora_hasta
+  END AS en_horario
+FROM promo_combo c;
@@ -0,0 +1,33 @@
+PRAGMA foreign_keys = ON;
+
+-- Reglas de promoción por cantidad sobre un producto:
+--   'nxm'            : lleva N, paga M (2x1, 3x2...)
+--   'segunda_unidad' : cada 2 unidades, una con pct_descuento % off
+--   'escala'         : precio unitario según cantidad (promo_regla_escala)
+CREATE TABLE IF NOT EXISTS promo_regla (
+  id_regla       INTEGER PRIMARY KEY,
+  nombre         TEXT NOT NULL CHECK (length(trim(nombre)) > 0),
+  tipo           TEXT NOT NULL CHECK (tipo IN ('nxm','segunda_unidad','escala')),
+  id_producto    INTEGER NOT NULL REFERENCES producto(id_producto),
+  lleva          INTEGER,
+  paga           INTEGER,
+  pct_descuento  INTEGER,
+  activo         INTEGER NOT NULL DEFAULT 1 CHECK (activo IN (0,1)),
+  creado_en      DATETIME NOT NULL DEFAULT (DATETIME('now','localtime')),
+  CHECK (tipo <> 'nxm' OR (paga >= 1 AND lleva > paga)),
+  CHECK (tipo <> 'segunda_unidad' OR pct_descuento BETWEEN 1 AND 100)
+);
+
+CREATE TABLE IF NOT EXISTS promo_regla_escala (
+  id_regla         INTEGER NOT NULL REFERENCES promo_regla(id_regla) ON DELETE CASCADE,
+  cantidad_min     INTEGER NOT NULL CHECK (cantidad_min >= 1),
+  precio_unitario  INTEGER NOT NULL CHECK (precio_unitario >= 0),
+  PRIMARY KEY (id_regla, cantidad_min)
+);
+
+CREATE INDEX IF NOT EXISTS ix_promo_regla_producto ON promo_regla(id_producto) WHERE activo = 1;
+
+-- El descuento que genera una regla queda como descuento de línea ('monto')
+-- con referencia a la regla. Se recalcula solo cuando cambian las líneas.
+ALTER TABLE venta_descuento ADD COLUMN id_regla INTEGER REFERENCES promo_regla(id_regla);
+CREATE INDEX IF NOT EXISTS ix_venta_descuento_regla ON venta_descuento(id_regla);
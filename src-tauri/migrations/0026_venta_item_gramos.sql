@@ -0,0 +1,6 @@
+PRAGMA foreign_keys = ON;
+
+-- Etiquetas de balanza: peso de cada unidad de la línea (gramos). Los
+-- productos que se venden por peso llevan el stock en gramos, así que lo
+-- que se descuenta del stock es cantidad * gramos y no la cantidad.
+ALTER TABLE venta_item ADD COLUMN gramos INTEGER CHECK (gramos IS NULL OR gramos > 0);
@@ -0,0 +1,34 @@
+PRAGMA foreign_keys = ON;
+
+-- Renglones del comprobante, congelados al crearlo: lo que se manda a
+-- autorizar no cambia aunque la venta se edite mientras espera en la cola.
+CREATE TABLE IF NOT EXISTS comprobante_item (
+  id_comprobante_item INTEGER PRIMARY KEY,
+  id_comprobante      INTEGER NOT NULL REFERENCES comprobante(id_comprobante),
+  descripcion         TEXT NOT NULL,
+  cantidad            INTEGER NOT NULL,
+  precio_unitario     INTEGER NOT NULL,
+  importe             INTEGER NOT NULL,
+  alicuota_iva        REAL NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS ix_comprobante_item
+  ON comprobante_item(id_comprobante);
+
+-- Los que ya estaban en cola se congelan con lo que hay hoy
+INSERT INTO comprobante_item (id_comprobante, descripcion, cantidad, precio_unitario, importe, alicuota_iva)
+SELECT c.id_comprobante, p.nombre, vi.cantidad, vi.precio_unitario, vi.subtotal, vi.alicuota_iva
+  FROM comprobante c
+  JOIN venta_item vi ON vi.id_venta = c.id_venta
+  JOIN producto p    ON p.id_producto = vi.id_producto
+ WHERE c.tipo = 'factura'
+ ORDER BY c.id_comprobante, vi.id_item;
+
+INSERT INTO comprobante_item (id_comprobante, descripcion, cantidad, precio_unitario, importe, alicuota_iva)
+SELECT c.id_comprobante, p.nombre, di.cantidad, di.precio_unitario, di.subtotal, vi.alicuota_iva
+  FROM comprobante c
+  JOIN devolucion_item di ON di.id_devolucion = c.id_devolucion
+  JOIN venta_item vi      ON vi.id_item = di.id_item
+  JOIN producto p         ON p.id_producto = di.id_producto
+ WHERE c.tipo = 'nota_credito'
+ ORDER BY c.id_comprobante, di.id_devolucion_item;
@@ -0,0 +1,12 @@
+PRAGMA foreign_keys = ON;
+
+-- Cómo se vende el producto: 'unidad' o 'peso'. Los de peso llevan el stock
+-- en gramos y el precio / costo de lista por kg; sólo ellos entran por
+-- etiqueta de balanza y sólo por ahí (no por unidad).
+ALTER TABLE producto ADD COLUMN unidad_venta TEXT NOT NULL DEFAULT 'unidad'
+  CHECK (unidad_venta IN ('unidad','peso'));
+
+-- Los que ya se vendieron por balanza son de peso
+UPDATE producto
+   SET unidad_venta = 'peso'
+ WHERE id_producto IN (SELECT DISTINCT id_producto FROM venta_item WHERE gramos IS NOT NULL);
@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- Definiciones de descuento (venta_descuento) de cada versión: id_item
+-- apunta a la línea de items_json de esa misma versión.
+ALTER TABLE venta_revision ADD COLUMN descuentos_json TEXT NOT NULL DEFAULT '[]';
@@ -4,10 +4,13 @@ use tauri::State;
 
 use crate::AppState;
 
+use super::model::PnlGastoCategoria;
 use super::model::{PnlMeta, PnlPeriodo, PnlReporte, PnlReporteInput, PnlTotales};
 use super::repo::{
-    pnl_gastos_por_categoria, pnl_ingresos_por_medio_pago, pnl_periodos_gastos, pnl_periodos_ventas,
+    pnl_gastos_por_categoria, pnl_ingresos_por_medio_pago, pnl_periodos_devoluciones,
+    pnl_periodos_gastos, pnl_periodos_medios_pago, pnl_periodos_ventas,
 };
+use crate::users::permisos::requerir_admin;
 
 fn now_local_sql() -> &'static str {
     "DATETIME('now','localtime')"
@@ -32,12 +35,17 @@ fn criterio_gastos_fijos(group_by: &str) -> &'static str {
     match group_by {
         "dia" | "semana" => "prorrateo_mensual_por_dia",
         "mes" | "total" => "sin_prorrateo",
-        _ => "desconocido", 
+        _ => "desconocido",
     }
 }
 
 #[tauri::command]
-pub async fn pnl_reporte(state: State<'_, AppState>, input: PnlReporteInput) -> Result<PnlReporte, String> {
+pub async fn pnl_reporte(
+    state: State<'_, AppState>,
+    input: PnlReporteInput,
+) -> Result<PnlReporte, String> {
+    requerir_admin(&state).await?;
+
     validar_group_by(&input.group_by)?;
 
     let pool = &state.pool;
@@ -50,12 +58,37 @@ pub async fn pnl_reporte(state: State<'_, AppState>, input: PnlReporteInput) ->
         &input.hasta,
         &input.group_by,
         input.id_usuario,
+        input.id_cliente,
         incluir_no_finalizadas,
     )
     .await?;
 
+    // 1b) Devoluciones (restan ventas y COGS en el período en que se hicieron)
+    let devoluciones_rows = pnl_periodos_devoluciones(
+        pool,
+        &input.desde,
+        &input.hasta,
+        &input.group_by,
+        input.id_usuario,
+        input.id_cliente,
+    )
+    .await?;
+
     // 2) Gastos/ingresos extra (con prorrateo SOLO para dia/semana, según repo.rs)
-    let gastos_rows = pnl_periodos_gastos(pool, &input.desde, &input.hasta, &input.group_by).await?;
+    let gastos_rows =
+        pnl_periodos_gastos(pool, &input.desde, &input.hasta, &input.group_by).await?;
+
+    // 2b) Recargos (ingreso) y comisiones (gasto operativo) de medios de pago
+    let medios_rows = pnl_periodos_medios_pago(
+        pool,
+        &input.desde,
+        &input.hasta,
+        &input.group_by,
+        input.id_usuario,
+        input.id_cliente,
+        incluir_no_finalizadas,
+    )
+    .await?;
 
     // 3) Merge por periodo_key
     let mut map: HashMap<String, PnlPeriodo> = HashMap::new();
@@ -69,15 +102,54 @@ pub async fn pnl_reporte(state: State<'_, AppState>, input: PnlReporteInput) ->
                 desde: r.desde,
                 hasta: r.hasta,
                 ventas_brutas: r.ventas_brutas,
+                descuentos: r.descuentos,
+                devoluciones: 0,
                 costo_mercaderia_vendida: r.costo_mercaderia_vendida,
+                costo_devuelto: 0,
                 margen_bruto,
+                iva_ventas: r.iva_ventas,
+                iva_devoluciones: 0,
+                ventas_sin_iva: r.ventas_brutas - r.iva_ventas,
                 ingresos_extra: 0,
                 egresos_operativos: 0,
+                recargos_medios_pago: 0,
+                comisiones_medios_pago: 0,
                 resultado_neto: margen_bruto, // se ajusta luego
             },
         );
     }
 
+    for d in devoluciones_rows {
+        let entry = map.entry(d.periodo_key.clone()).or_insert(PnlPeriodo {
+            periodo_key: d.periodo_key.clone(),
+            desde: input.desde.clone(),
+            hasta: input.hasta.clone(),
+            ventas_brutas: 0,
+            descuentos: 0,
+            devoluciones: 0,
+            costo_mercaderia_vendida: 0,
+            costo_devuelto: 0,
+            margen_bruto: 0,
+            iva_ventas: 0,
+            iva_devoluciones: 0,
+            ventas_sin_iva: 0,
+            ingresos_extra: 0,
+            egresos_operativos: 0,
+            recargos_medios_pago: 0,
+            comisiones_medios_pago: 0,
+            resultado_neto: 0,
+        });
+
+        entry.devoluciones = d.devoluciones;
+        entry.costo_devuelto = d.costo_devuelto;
+        entry.iva_devoluciones = d.iva_devoluciones;
+        entry.ventas_sin_iva = (entry.ventas_brutas - entry.devoluciones)
+            - (entry.iva_ventas - entry.iva_devoluciones);
+        entry.margen_bruto = (entry.ventas_brutas - entry.devoluciones)
+            - (entry.costo_mercaderia_vendida - entry.costo_devuelto);
+        entry.resultado_neto = entry.margen_bruto;
+    }
+
     for g in gastos_rows {
         let entry = map.entry(g.periodo_key.clone()).or_insert(PnlPeriodo {
             periodo_key: g.periodo_key.clone(),
@@ -85,16 +157,58 @@ pub async fn pnl_reporte(state: State<'_, AppState>, input: PnlReporteInput) ->
             desde: input.desde.clone(),
             hasta: input.hasta.clone(),
             ventas_brutas: 0,
+            descuentos: 0,
+            devoluciones: 0,
             costo_mercaderia_vendida: 0,
+            costo_devuelto: 0,
             margen_bruto: 0,
+            iva_ventas: 0,
+            iva_devoluciones: 0,
+            ventas_sin_iva: 0,
             ingresos_extra: 0,
             egresos_operativos: 0,
+            recargos_medios_pago: 0,
+            comisiones_medios_pago: 0,
             resultado_neto: 0,
         });
 
         entry.ingresos_extra = g.ingresos_extra;
         entry.egresos_operativos = g.egresos_operativos;
-        entry.resultado_neto = (entry.margen_bruto + entry.ingresos_extra) - entry.egresos_operativos;
+        entry.resultado_neto =
+            (entry.margen_bruto + entry.ingresos_extra) - entry.egresos_operativos;
+    }
+
+    let (mut recargos_rango, mut comisiones_rango) = (0i64, 0i64);
+    for m in medios_rows {
+        recargos_rango += m.recargos;
+        comisiones_rango += m.comisiones;
+
+        let entry = map.entry(m.periodo_key.clone()).or_insert(PnlPeriodo {
+            periodo_key: m.periodo_key.clone(),
+            desde: input.desde.clone(),
+            hasta: input.hasta.clone(),
+            ventas_brutas: 0,
+            descuentos: 0,
+            devoluciones: 0,
+            costo_mercaderia_vendida: 0,
+            costo_devuelto: 0,
+            margen_bruto: 0,
+            iva_ventas: 0,
+            iva_devoluciones: 0,
+            ventas_sin_iva: 0,
+            ingresos_extra: 0,
+            egresos_operativos: 0,
+            recargos_medios_pago: 0,
+            comisiones_medios_pago: 0,
+            resultado_neto: 0,
+        });
+
+        entry.recargos_medios_pago = m.recargos;
+        entry.comisiones_medios_pago = m.comisiones;
+        entry.ingresos_extra += m.recargos;
+        entry.egresos_operativos += m.comisiones;
+        entry.resultado_neto =
+            (entry.margen_bruto + entry.ingresos_extra) - entry.egresos_operativos;
     }
 
     // 4) Orden
@@ -105,18 +219,45 @@ pub async fn pnl_reporte(state: State<'_, AppState>, input: PnlReporteInput) ->
     let mut tot = PnlTotales::default();
     for p in &periodos {
         tot.ventas_brutas += p.ventas_brutas;
+        tot.descuentos += p.descuentos;
+        tot.devoluciones += p.devoluciones;
         tot.costo_mercaderia_vendida += p.costo_mercaderia_vendida;
+        tot.costo_devuelto += p.costo_devuelto;
+        tot.iva_ventas += p.iva_ventas;
+        tot.iva_devoluciones += p.iva_devoluciones;
+        tot.ventas_sin_iva += p.ventas_sin_iva;
         tot.ingresos_extra += p.ingresos_extra;
         tot.egresos_operativos += p.egresos_operativos;
+        tot.recargos_medios_pago += p.recargos_medios_pago;
+        tot.comisiones_medios_pago += p.comisiones_medios_pago;
     }
-    tot.margen_bruto = tot.ventas_brutas - tot.costo_mercaderia_vendida;
+    let ventas_netas = tot.ventas_brutas - tot.devoluciones;
+    tot.margen_bruto = ventas_netas - (tot.costo_mercaderia_vendida - tot.costo_devuelto);
     tot.resultado_neto = (tot.margen_bruto + tot.ingresos_extra) - tot.egresos_operativos;
 
-    tot.margen_bruto_pct = calc_pct(tot.margen_bruto, tot.ventas_brutas);
-    tot.resultado_neto_pct = calc_pct(tot.resultado_neto, tot.ventas_brutas);
+    tot.margen_bruto_pct = calc_pct(tot.margen_bruto, ventas_netas);
+    tot.resultado_neto_pct = calc_pct(tot.resultado_neto, ventas_netas);
 
     // 6) Desglose por categoría (global del rango)
-    let gastos_por_categoria = pnl_gastos_por_categoria(pool, &input.desde, &input.hasta).await?;
+    let mut gastos_por_categoria =
+        pnl_gastos_por_categoria(pool, &input.desde, &input.hasta).await?;
+    if comisiones_rango != 0 {
+        gastos_por_categoria.push(PnlGastoCategoria {
+            categoria: "comisiones_medios_pago".to_string(),
+            ingresos: 0,
+            egresos: comisiones_rango,
+            neto: -comisiones_rango,
+        });
+    }
+    if recargos_rango != 0 {
+        gastos_por_categoria.push(PnlGastoCategoria {
+            categoria: "recargos_medios_pago".to_string(),
+            ingresos: recargos_rango,
+            egresos: 0,
+            neto: recargos_rango,
+        });
+    }
+    gastos_por_categoria.sort_by(|a, b| a.categoria.cmp(&b.categoria));
 
     // 7) Ingresos por medio de pago
     let ingresos_por_medio_pago = Some(
@@ -125,6 +266,7 @@ pub async fn pnl_reporte(state: State<'_, AppState>, input: PnlReporteInput) ->
             &input.desde,
             &input.hasta,
             input.id_usuario,
+            input.id_cliente,
             incluir_no_finalizadas,
         )
         .await?,
@@ -15,6 +15,9 @@ pub struct PnlReporteInput {
     /// filtrar ventas por usuario (operador)
     pub id_usuario: Option<i64>,
 
+    /// filtrar ventas por cliente (no afecta gastos)
+    pub id_cliente: Option<i64>,
+
     /// si true, NO filtra por estado finalizada
     pub incluir_no_finalizadas: Option<bool>,
 }
@@ -44,11 +47,19 @@ pub struct PnlMeta {
 #[derive(Debug, Serialize, Default, Clone)]
 pub struct PnlTotales {
     pub ventas_brutas: i64,
+    pub descuentos: i64, // descuentos manuales otorgados (ya restados de ventas_brutas)
+    pub devoluciones: i64,
     pub costo_mercaderia_vendida: i64,
+    pub costo_devuelto: i64,
     pub margen_bruto: i64,
     pub margen_bruto_pct: Option<f64>,
+    pub iva_ventas: i64,       // IVA contenido en ventas_brutas
+    pub iva_devoluciones: i64, // IVA contenido en devoluciones
+    pub ventas_sin_iva: i64,   // (ventas_brutas - devoluciones) sin IVA
     pub ingresos_extra: i64,
     pub egresos_operativos: i64,
+    pub recargos_medios_pago: i64,   // incluidos en ingresos_extra
+    pub comisiones_medios_pago: i64, // incluidas en egresos_operativos
     pub resultado_neto: i64,
     pub resultado_neto_pct: Option<f64>,
 }
@@ -60,11 +71,20 @@ pub struct PnlPeriodo {
     pub hasta: String,
 
     pub ventas_brutas: i64,
+    pub descuentos: i64, // informativo: ya restados de ventas_brutas
+    pub devoluciones: i64,
     pub costo_mercaderia_vendida: i64,
-    pub margen_bruto: i64,
+    pub costo_devuelto: i64,
+    pub margen_bruto: i64, // (ventas - devoluciones) - (cmv - costo devuelto)
+
+    pub iva_ventas: i64,
+    pub iva_devoluciones: i64,
+    pub ventas_sin_iva: i64,
 
     pub ingresos_extra: i64,
     pub egresos_operativos: i64,
+    pub recargos_medios_pago: i64,   // incluidos en ingresos_extra
+    pub comisiones_medios_pago: i64, // incluidas en egresos_operativos
     pub resultado_neto: i64,
 }
 
@@ -75,6 +95,16 @@ pub struct PnlPeriodoVentasRow {
     pub hasta: String,
     pub ventas_brutas: i64,
     pub costo_mercaderia_vendida: i64,
+    pub descuentos: i64,
+    pub iva_ventas: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PnlPeriodoDevolucionesRow {
+    pub periodo_key: String,
+    pub devoluciones: i64,
+    pub costo_devuelto: i64,
+    pub iva_devoluciones: i64,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -84,6 +114,14 @@ pub struct PnlPeriodoGastosRow {
     pub egresos_operativos: i64,
 }
 
+/// Recargos cobrados y comisiones de la procesadora (venta_pago)
+#[derive(Debug, Serialize, FromRow)]
+pub struct PnlPeriodoMediosPagoRow {
+    pub periodo_key: String,
+    pub recargos: i64,
+    pub comisiones: i64,
+}
+
 #[derive(Debug, Serialize, FromRow)]
 pub struct PnlGastoCategoria {
     pub categoria: String,
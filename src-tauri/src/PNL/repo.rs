@@ -1,20 +1,25 @@
 use sqlx::SqlitePool;
 
 use super::model::{
-    PnlGastoCategoria, PnlMedioPago, PnlPeriodoGastosRow, PnlPeriodoVentasRow,
+    PnlGastoCategoria, PnlMedioPago, PnlPeriodoDevolucionesRow, PnlPeriodoGastosRow,
+    PnlPeriodoMediosPagoRow, PnlPeriodoVentasRow,
 };
 
-fn key_expr_ventas(group_by: &str) -> Result<String, String> {
+fn key_expr_fecha(col: &str, group_by: &str) -> Result<String, String> {
     let expr = match group_by {
-        "dia" => "DATE(v.fecha_hora, 'localtime')".to_string(),
-        "semana" => "STRFTIME('%Y-W%W', v.fecha_hora, 'localtime')".to_string(),
-        "mes" => "STRFTIME('%Y-%m', v.fecha_hora, 'localtime')".to_string(),
+        "dia" => format!("DATE({col}, 'localtime')"),
+        "semana" => format!("STRFTIME('%Y-W%W', {col}, 'localtime')"),
+        "mes" => format!("STRFTIME('%Y-%m', {col}, 'localtime')"),
         "total" => "'TOTAL'".to_string(),
         _ => return Err("group_by inválido: usar 'dia' | 'semana' | 'mes' | 'total'".to_string()),
     };
     Ok(expr)
 }
 
+fn key_expr_ventas(group_by: &str) -> Result<String, String> {
+    key_expr_fecha("v.fecha_hora", group_by)
+}
+
 fn key_expr_gastos(group_by: &str) -> Result<String, String> {
     let expr = match group_by {
         "dia" => "g.fecha".to_string(),
@@ -50,6 +55,7 @@ pub async fn pnl_periodos_ventas(
     hasta: &str,
     group_by: &str,
     id_usuario: Option<i64>,
+    id_cliente: Option<i64>,
     incluir_no_finalizadas: bool,
 ) -> Result<Vec<PnlPeriodoVentasRow>, String> {
     let key = key_expr_ventas(group_by)?;
@@ -76,6 +82,9 @@ pub async fn pnl_periodos_ventas(
     if id_usuario.is_some() {
         sql.push_str(" AND v.id_usuario = ? ");
     }
+    if id_cliente.is_some() {
+        sql.push_str(" AND v.id_cliente = ? ");
+    }
 
     sql.push_str(&format!(
         r#"
@@ -84,9 +93,12 @@ pub async fn pnl_periodos_ventas(
         cogs AS (
           SELECT
             {key} AS periodo_key,
-            COALESCE(SUM(vi.costo_unitario_en_venta * vi.cantidad), 0) AS costo_mercaderia_vendida
+            COALESCE(SUM(vi.costo_unitario_en_venta * vi.cantidad), 0) AS costo_mercaderia_vendida,
+            COALESCE(SUM(vi.descuento), 0) AS descuentos,
+            COALESCE(SUM(x.iva), 0) AS iva_ventas
           FROM venta v
           LEFT JOIN venta_item vi ON vi.id_venta = v.id_venta
+          LEFT JOIN v_venta_item_iva x ON x.id_item = vi.id_item
           WHERE DATE(v.fecha_hora,'localtime') BETWEEN ? AND ?
         "#
     ));
@@ -99,6 +111,9 @@ pub async fn pnl_periodos_ventas(
     if id_usuario.is_some() {
         sql.push_str(" AND v.id_usuario = ? ");
     }
+    if id_cliente.is_some() {
+        sql.push_str(" AND v.id_cliente = ? ");
+    }
 
     sql.push_str(
         r#"
@@ -109,7 +124,9 @@ pub async fn pnl_periodos_ventas(
           v.desde,
           v.hasta,
           v.ventas_brutas,
-          COALESCE(c.costo_mercaderia_vendida, 0) AS costo_mercaderia_vendida
+          COALESCE(c.costo_mercaderia_vendida, 0) AS costo_mercaderia_vendida,
+          COALESCE(c.descuentos, 0) AS descuentos,
+          COALESCE(c.iva_ventas, 0) AS iva_ventas
         FROM ventas v
         LEFT JOIN cogs c ON c.periodo_key = v.periodo_key
         ORDER BY v.periodo_key ASC
@@ -123,18 +140,124 @@ pub async fn pnl_periodos_ventas(
     if let Some(u) = id_usuario {
         q = q.bind(u);
     }
+    if let Some(c) = id_cliente {
+        q = q.bind(c);
+    }
 
     q = q.bind(desde).bind(hasta);
 
     if let Some(u) = id_usuario {
         q = q.bind(u);
     }
+    if let Some(c) = id_cliente {
+        q = q.bind(c);
+    }
 
     q.fetch_all(pool)
         .await
         .map_err(|e| format!("pnl_periodos_ventas: {e}"))
 }
 
+/// Devoluciones por período (fecha de la devolución). Filtra por el
+/// operador de la venta original, igual que las ventas.
+pub async fn pnl_periodos_devoluciones(
+    pool: &SqlitePool,
+    desde: &str,
+    hasta: &str,
+    group_by: &str,
+    id_usuario: Option<i64>,
+    id_cliente: Option<i64>,
+) -> Result<Vec<PnlPeriodoDevolucionesRow>, String> {
+    let key = key_expr_fecha("d.fecha_hora", group_by)?;
+
+    let mut sql = format!(
+        r#"
+        SELECT
+          {key} AS periodo_key,
+          COALESCE(SUM(di.subtotal), 0) AS devoluciones,
+          COALESCE(SUM(di.costo_unitario * di.cantidad), 0) AS costo_devuelto,
+          COALESCE(SUM(x.iva), 0) AS iva_devoluciones
+        FROM devolucion d
+        JOIN devolucion_item di      ON di.id_devolucion = d.id_devolucion
+        JOIN v_devolucion_item_iva x ON x.id_devolucion_item = di.id_devolucion_item
+        JOIN venta v                 ON v.id_venta = d.id_venta
+        WHERE DATE(d.fecha_hora,'localtime') BETWEEN ? AND ?
+        "#,
+    );
+    if id_usuario.is_some() {
+        sql.push_str(" AND v.id_usuario = ? ");
+    }
+    if id_cliente.is_some() {
+        sql.push_str(" AND v.id_cliente = ? ");
+    }
+    sql.push_str(&format!(" GROUP BY {key} ORDER BY {key} ASC "));
+
+    let mut q = sqlx::query_as::<_, PnlPeriodoDevolucionesRow>(&sql)
+        .bind(desde)
+        .bind(hasta);
+
+    if let Some(u) = id_usuario {
+        q = q.bind(u);
+    }
+    if let Some(c) = id_cliente {
+        q = q.bind(c);
+    }
+
+    q.fetch_all(pool)
+        .await
+        .map_err(|e| format!("pnl_periodos_devoluciones: {e}"))
+}
+
+/// Recargos y comisiones de medios de pago, por fecha de la venta.
+pub async fn pnl_periodos_medios_pago(
+    pool: &SqlitePool,
+    desde: &str,
+    hasta: &str,
+    group_by: &str,
+    id_usuario: Option<i64>,
+    id_cliente: Option<i64>,
+    incluir_no_finalizadas: bool,
+) -> Result<Vec<PnlPeriodoMediosPagoRow>, String> {
+    let key = key_expr_ventas(group_by)?;
+
+    let mut sql = format!(
+        r#"
+        SELECT
+          {key} AS periodo_key,
+          COALESCE(SUM(vp.recargo), 0)  AS recargos,
+          COALESCE(SUM(vp.comision), 0) AS comisiones
+        FROM venta_pago vp
+        JOIN venta v ON v.id_venta = vp.id_venta
+        WHERE DATE(v.fecha_hora,'localtime') BETWEEN ? AND ?
+        "#,
+    );
+    if let Some(f) = estado_filter_sql(incluir_no_finalizadas) {
+        sql.push_str(" AND ");
+        sql.push_str(f);
+    }
+    if id_usuario.is_some() {
+        sql.push_str(" AND v.id_usuario = ? ");
+    }
+    if id_cliente.is_some() {
+        sql.push_str(" AND v.id_cliente = ? ");
+    }
+    sql.push_str(&format!(" GROUP BY {key} ORDER BY {key} ASC "));
+
+    let mut q = sqlx::query_as::<_, PnlPeriodoMediosPagoRow>(&sql)
+        .bind(desde)
+        .bind(hasta);
+
+    if let Some(u) = id_usuario {
+        q = q.bind(u);
+    }
+    if let Some(c) = id_cliente {
+        q = q.bind(c);
+    }
+
+    q.fetch_all(pool)
+        .await
+        .map_err(|e| format!("pnl_periodos_medios_pago: {e}"))
+}
 
 pub async fn pnl_periodos_gastos(
     pool: &SqlitePool,
@@ -256,15 +379,19 @@ pub async fn pnl_ingresos_por_medio_pago(
     desde: &str,
     hasta: &str,
     id_usuario: Option<i64>,
+    id_cliente: Option<i64>,
     incluir_no_finalizadas: bool,
 ) -> Result<Vec<PnlMedioPago>, String> {
+    // cobros (+) y reintegros de devoluciones (-)
     let mut sql = r#"
       SELECT
-        vp.medio AS medio,
-        COALESCE(SUM(vp.monto), 0) AS monto
-      FROM venta_pago vp
-      INNER JOIN venta v ON v.id_venta = vp.id_venta
-      WHERE DATE(v.fecha_hora,'localtime') BETWEEN ? AND ?
+        x.medio AS medio,
+        COALESCE(SUM(x.monto), 0) AS monto
+      FROM (
+        SELECT vp.medio AS medio, vp.monto AS monto
+        FROM venta_pago vp
+        INNER JOIN venta v ON v.id_venta = vp.id_venta
+        WHERE DATE(v.fecha_hora,'localtime') BETWEEN ? AND ?
     "#
     .to_string();
 
@@ -275,8 +402,28 @@ pub async fn pnl_ingresos_por_medio_pago(
     if id_usuario.is_some() {
         sql.push_str(" AND v.id_usuario = ? ");
     }
+    if id_cliente.is_some() {
+        sql.push_str(" AND v.id_cliente = ? ");
+    }
 
-    sql.push_str(" GROUP BY vp.medio ORDER BY vp.medio ASC ");
+    sql.push_str(
+        r#"
+        UNION ALL
+        SELECT dp.medio, -dp.monto
+        FROM devolucion_pago dp
+        INNER JOIN devolucion d ON d.id_devolucion = dp.id_devolucion
+        INNER JOIN venta v      ON v.id_venta = d.id_venta
+        WHERE DATE(d.fecha_hora,'localtime') BETWEEN ? AND ?
+        "#,
+    );
+    if id_usuario.is_some() {
+        sql.push_str(" AND v.id_usuario = ? ");
+    }
+    if id_cliente.is_some() {
+        sql.push_str(" AND v.id_cliente = ? ");
+    }
+
+    sql.push_str(" ) x GROUP BY x.medio ORDER BY x.medio ASC ");
 
     let mut q = sqlx::query_as::<_, PnlMedioPago>(&sql)
         .bind(desde)
@@ -285,6 +432,18 @@ pub async fn pnl_ingresos_por_medio_pago(
     if let Some(u) = id_usuario {
         q = q.bind(u);
     }
+    if let Some(c) = id_cliente {
+        q = q.bind(c);
+    }
+
+    q = q.bind(desde).bind(hasta);
+
+    if let Some(u) = id_usuario {
+        q = q.bind(u);
+    }
+    if let Some(c) = id_cliente {
+        q = q.bind(c);
+    }
 
     q.fetch_all(pool)
         .await
@@ -1,7 +1,24 @@
 use sqlx::SqlitePool;
-use std::sync::{Arc, Mutex};
+
+use crate::users::sesion::SesionService;
 
 pub struct AppState {
     pub pool: SqlitePool,
-    pub session_user: Arc<Mutex<Option<i64>>>,
+    pub sesion: SesionService,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// Estado de prueba sobre una base en memoria; con `usuario` deja la
+    /// sesión iniciada (el seed trae al 1 como admin y al 2 como operador).
+    pub async fn prueba(usuario: Option<(i64, crate::users::permisos::Rol)>) -> AppState {
+        let state = AppState {
+            pool: crate::db::pool_prueba().await,
+            sesion: SesionService::default(),
+        };
+        if let Some((id_usuario, rol)) = usuario {
+            state.sesion.iniciar(id_usuario, rol).expect("sesión");
+        }
+        state
+    }
 }
@@ -0,0 +1,22 @@
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::users::permisos::requerir_admin;
+
+use super::model::{AuditEventoRow, AuditListarInput};
+use super::repo;
+
+// CONSULTA (solo admin): filtros por fecha, usuario y entidad
+
+#[tauri::command]
+pub async fn audit_evento_listar(
+    state: State<'_, AppState>,
+    input: Option<AuditListarInput>,
+) -> Result<Vec<AuditEventoRow>, String> {
+    requerir_admin(&state).await?;
+
+    let filtro = input.unwrap_or_default();
+    repo::listar(&state.pool, &filtro)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// INPUTS
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditListarInput {
+    pub desde: Option<String>, // "YYYY-MM-DD"
+    pub hasta: Option<String>, // "YYYY-MM-DD"
+    pub id_usuario: Option<i64>,
+    pub entidad: Option<String>,
+    pub id_entidad: Option<i64>,
+    pub accion: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// OUTPUTS
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditEventoRow {
+    pub id_evento: i64,
+    pub fecha_hora: String,
+    pub id_usuario: Option<i64>,
+    pub usuario: Option<String>,
+    pub entidad: String,
+    pub id_entidad: Option<i64>,
+    pub accion: String,
+    pub antes_json: Option<String>,   // JSON crudo (el front lo parsea)
+    pub despues_json: Option<String>,
+}
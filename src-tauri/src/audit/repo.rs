@@ -0,0 +1,225 @@
+use serde_json::{json, Value};
+use sqlx::{Executor, Sqlite, SqliteConnection, SqlitePool};
+
+use super::model::{AuditEventoRow, AuditListarInput};
+
+/// Registra un evento de auditoría. Recibe el pool o la transacción en curso
+/// (`&mut *tx`) para que el evento quede atado al cambio que describe.
+pub async fn registrar<'e, E>(
+    exec: E,
+    id_usuario: Option<i64>,
+    entidad: &str,
+    id_entidad: Option<i64>,
+    accion: &str,
+    antes: Option<&Value>,
+    despues: Option<&Value>,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO audit_evento (id_usuario, entidad, id_entidad, accion, antes_json, despues_json)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+    )
+    .bind(id_usuario)
+    .bind(entidad)
+    .bind(id_entidad)
+    .bind(accion)
+    .bind(antes.map(Value::to_string))
+    .bind(despues.map(Value::to_string))
+    .execute(exec)
+    .await?;
+
+    Ok(())
+}
+
+// codigo, nombre, precio, costo, activo, stock, modo, factor, alícuota, unidad de venta
+type FilaProducto = (String, String, i64, i64, i64, i64, String, i64, f64, String);
+
+/// Estado de un producto (datos, precios y stock) para el antes/después
+pub async fn producto_snapshot<'e, E>(exec: E, id_producto: i64) -> Result<Option<Value>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row: Option<FilaProducto> = sqlx::query_as(
+        r#"
+        SELECT p.codigo_producto, p.nombre, p.precio_venta_actual, p.costo_actual,
+               p.activo, COALESCE(ps.stock_actual, 0),
+               p.reposicion_modo, p.reposicion_factor, p.alicuota_iva, p.unidad_venta
+        FROM producto p
+        LEFT JOIN producto_stock ps ON ps.id_producto = p.id_producto
+        WHERE p.id_producto = ?1
+        "#,
+    )
+    .bind(id_producto)
+    .fetch_optional(exec)
+    .await?;
+
+    Ok(row.map(|(codigo, nombre, precio, costo, activo, stock, modo, factor, alicuota_iva, unidad_venta)| {
+        json!({
+            "codigo_producto": codigo,
+            "nombre": nombre,
+            "precio_venta_actual": precio,
+            "costo_actual": costo,
+            "activo": activo,
+            "stock_actual": stock,
+            "reposicion_modo": modo,
+            "reposicion_factor": factor,
+            "alicuota_iva": alicuota_iva,
+            "unidad_venta": unidad_venta,
+        })
+    }))
+}
+
+/// Estado de una venta (cabecera, líneas y pagos) para el antes/después
+pub async fn venta_snapshot(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<Option<Value>, sqlx::Error> {
+    let cab: Option<(String, i64, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT estado, total, etiqueta, estacionada_en FROM venta WHERE id_venta = ?1",
+    )
+    .bind(id_venta)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some((estado, total, etiqueta, estacionada_en)) = cab else { return Ok(None) };
+
+    let items: Vec<(i64, i64, i64, i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_item, id_producto, cantidad, precio_unitario, descuento, subtotal
+        FROM venta_item
+        WHERE id_venta = ?1
+        ORDER BY id_item
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let pagos: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT medio, monto FROM venta_pago WHERE id_venta = ?1 ORDER BY id_pago",
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Some(json!({
+        "estado": estado,
+        "total": total,
+        "etiqueta": etiqueta,
+        "estacionada_en": estacionada_en,
+        "items": items.iter()
+            .map(|(id_item, id_producto, cantidad, precio_unitario, descuento, subtotal)| json!({
+                "id_item": id_item,
+                "id_producto": id_producto,
+                "cantidad": cantidad,
+                "precio_unitario": precio_unitario,
+                "descuento": descuento,
+                "subtotal": subtotal,
+            }))
+            .collect::<Vec<_>>(),
+        "pagos": pagos.iter()
+            .map(|(medio, monto)| json!({ "medio": medio, "monto": monto }))
+            .collect::<Vec<_>>(),
+    })))
+}
+
+pub async fn listar(
+    pool: &SqlitePool,
+    filtro: &AuditListarInput,
+) -> Result<Vec<AuditEventoRow>, sqlx::Error> {
+    let limit = filtro.limit.unwrap_or(200).clamp(1, 1000);
+    let offset = filtro.offset.unwrap_or(0).max(0);
+
+    sqlx::query_as::<_, AuditEventoRow>(
+        r#"
+        SELECT a.id_evento, a.fecha_hora, a.id_usuario, u.nombre AS usuario,
+               a.entidad, a.id_entidad, a.accion, a.antes_json, a.despues_json
+        FROM audit_evento a
+        LEFT JOIN usuario u ON u.id_usuario = a.id_usuario
+        WHERE (?1 IS NULL OR DATE(a.fecha_hora) >= DATE(?1))
+          AND (?2 IS NULL OR DATE(a.fecha_hora) <= DATE(?2))
+          AND (?3 IS NULL OR a.id_usuario = ?3)
+          AND (?4 IS NULL OR a.entidad = ?4)
+          AND (?5 IS NULL OR a.id_entidad = ?5)
+          AND (?6 IS NULL OR a.accion = ?6)
+        ORDER BY a.fecha_hora DESC, a.id_evento DESC
+        LIMIT ?7 OFFSET ?8
+        "#,
+    )
+    .bind(filtro.desde.as_deref())
+    .bind(filtro.hasta.as_deref())
+    .bind(filtro.id_usuario)
+    .bind(filtro.entidad.as_deref().map(str::trim).filter(|s| !s.is_empty()))
+    .bind(filtro.id_entidad)
+    .bind(filtro.accion.as_deref().map(str::trim).filter(|s| !s.is_empty()))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::audit::commands::audit_evento_listar;
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+    use crate::AppState;
+
+    #[tokio::test]
+    async fn la_venta_deja_rastro_con_antes_y_despues() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "efectivo", "monto": 600 }])).await;
+
+        let filtro = AuditListarInput {
+            entidad: Some(" venta ".into()),
+            id_entidad: Some(id_venta),
+            ..Default::default()
+        };
+        let eventos = audit_evento_listar(state.clone(), Some(filtro)).await.unwrap();
+        let acciones: Vec<&str> = eventos.iter().map(|e| e.accion.as_str()).collect();
+        assert_eq!(acciones, vec!["finalizar", "agregar_item", "iniciar"]);
+        assert!(eventos.iter().all(|e| e.id_usuario == Some(1)));
+
+        let fin = &eventos[0];
+        let antes: Value = serde_json::from_str(fin.antes_json.as_deref().unwrap()).unwrap();
+        let despues: Value = serde_json::from_str(fin.despues_json.as_deref().unwrap()).unwrap();
+        assert_eq!(antes["estado"], "en_curso");
+        assert_eq!(despues["estado"], "finalizada");
+        assert_eq!(despues["pagos"], json!([{ "medio": "efectivo", "monto": 600 }]));
+    }
+
+    #[tokio::test]
+    async fn el_registro_es_inmutable_y_sigue_a_la_transaccion() {
+        let state = AppState::prueba(None).await;
+        let pool = &state.pool;
+
+        let mut tx = pool.begin().await.unwrap();
+        registrar(&mut *tx, Some(1), "producto", Some(7), "editar", None, None).await.unwrap();
+        tx.rollback().await.unwrap();
+
+        registrar(pool, Some(1), "producto", Some(8), "crear", None, Some(&json!({ "nombre": "pan" })))
+            .await
+            .unwrap();
+        let filtro = AuditListarInput { entidad: Some("producto".into()), ..Default::default() };
+        let eventos = listar(pool, &filtro).await.unwrap();
+        assert_eq!(eventos.len(), 1);
+        assert_eq!(eventos[0].id_entidad, Some(8));
+
+        assert!(sqlx::query("UPDATE audit_evento SET accion = 'otra'").execute(pool).await.is_err());
+        assert!(sqlx::query("DELETE FROM audit_evento").execute(pool).await.is_err());
+    }
+}
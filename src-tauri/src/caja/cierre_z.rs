@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::caja::model::{
+    ArqueoItemRow, CierreZAjusteRow, CierreZDetalle, CierreZMedioRow, CierreZRow,
+};
+
+// Cierre Z: se genera dentro de la misma transacción que cierra la caja.
+// Las tablas cierre_z* son inmutables (triggers); lo que cambie después
+// sobre ventas de esa caja queda como cierre_z_ajuste.
+
+/// Genera el Z de una caja recién cerrada. Devuelve el número correlativo.
+pub async fn generar(
+    conn: &mut SqliteConnection,
+    id_caja: i64,
+    id_usuario_cierre: i64,
+) -> Result<i64, sqlx::Error> {
+    let (id_cierre_z, numero): (i64, i64) = sqlx::query_as(
+        r#"
+        INSERT INTO cierre_z (
+          numero, id_caja, id_operador, id_usuario_cierre, abierta_en, cerrada_en,
+          cantidad_ventas, total_ventas, cantidad_anuladas, total_descuentos,
+          monto_apertura, total_ingresos, total_egresos,
+          efectivo_esperado, efectivo_contado, diferencia, total_devoluciones
+        )
+        SELECT
+          (SELECT COALESCE(MAX(numero), 0) + 1 FROM cierre_z),
+          c.id_caja, c.abierta_por, ?2, c.abierta_en, c.cerrada_en,
+          (SELECT COUNT(*) FROM venta v
+            WHERE v.id_caja = c.id_caja AND v.estado = 'finalizada'),
+          (SELECT COALESCE(SUM(v.total), 0) FROM venta v
+            WHERE v.id_caja = c.id_caja AND v.estado = 'finalizada'),
+          (SELECT COUNT(*) FROM venta v
+            WHERE v.id_caja = c.id_caja AND v.estado = 'anulada'),
+          -- lo descontado en cada línea al vender (manual, de ticket o de
+          -- regla); no depende del precio de lista que haya al cerrar
+          (SELECT COALESCE(SUM(vi.descuento), 0)
+             FROM venta v
+             JOIN venta_item vi ON vi.id_venta = v.id_venta
+            WHERE v.id_caja = c.id_caja AND v.estado = 'finalizada'),
+          c.monto_apertura,
+          (SELECT COALESCE(SUM(m.monto), 0) FROM caja_movimiento m
+            WHERE m.id_caja = c.id_caja AND m.tipo = 'ingreso'),
+          (SELECT COALESCE(SUM(m.monto), 0) FROM caja_movimiento m
+            WHERE m.id_caja = c.id_caja AND m.tipo <> 'ingreso'),
+          COALESCE(c.efectivo_esperado, 0), c.efectivo_contado, c.diferencia,
+          (SELECT COALESCE(SUM(d.total), 0) FROM devolucion d
+            WHERE d.id_caja = c.id_caja)
+        FROM caja c
+        WHERE c.id_caja = ?1 AND c.estado = 'cerrada'
+        RETURNING id_cierre_z, numero
+        "#,
+    )
+    .bind(id_caja)
+    .bind(id_usuario_cierre)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO cierre_z_medio (id_cierre_z, medio, cantidad, total)
+        SELECT ?1, vp.medio, COUNT(*), SUM(vp.monto)
+          FROM venta v
+          JOIN venta_pago vp ON vp.id_venta = v.id_venta
+         WHERE v.id_caja = ?2 AND v.estado = 'finalizada'
+         GROUP BY vp.medio
+        "#,
+    )
+    .bind(id_cierre_z)
+    .bind(id_caja)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(numero)
+}
+
+/// Cobros de la venta por medio tal como cuentan para el Z
+/// (vacío si la venta no está finalizada).
+pub async fn pagos_por_medio(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64)>(
+        r#"
+        SELECT vp.medio, SUM(vp.monto)
+          FROM venta v
+          JOIN venta_pago vp ON vp.id_venta = v.id_venta
+         WHERE v.id_venta = ?1 AND v.estado = 'finalizada'
+         GROUP BY vp.medio
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Si la caja de la venta ya tiene Z, registra como ajuste la diferencia
+/// por medio entre `antes` y el estado actual de la venta.
+pub async fn registrar_ajustes(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    antes: &[(String, i64)],
+    origen: &str,
+    id_usuario: i64,
+) -> Result<(), sqlx::Error> {
+    let id_cierre_z: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT z.id_cierre_z
+          FROM venta v
+          JOIN cierre_z z ON z.id_caja = v.id_caja
+         WHERE v.id_venta = ?1
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(id_cierre_z) = id_cierre_z else { return Ok(()) };
+
+    let despues = pagos_por_medio(conn, id_venta).await?;
+
+    // medio -> (anterior, nuevo)
+    let mut por_medio: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for (medio, monto) in antes {
+        por_medio.entry(medio.clone()).or_default().0 += monto;
+    }
+    for (medio, monto) in despues {
+        por_medio.entry(medio).or_default().1 += monto;
+    }
+
+    for (medio, (anterior, nuevo)) in por_medio {
+        if anterior == nuevo {
+            continue;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO cierre_z_ajuste (
+              id_cierre_z, id_venta, medio, monto_anterior, monto_nuevo,
+              diferencia, origen, id_usuario
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(id_cierre_z)
+        .bind(id_venta)
+        .bind(medio)
+        .bind(anterior)
+        .bind(nuevo)
+        .bind(nuevo - anterior)
+        .bind(origen)
+        .bind(id_usuario)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// CONSULTAS
+
+const SELECT_CIERRE_Z: &str = r#"
+    SELECT z.id_cierre_z, z.numero, z.id_caja, z.id_operador, u.nombre AS operador,
+           z.id_usuario_cierre, z.abierta_en, z.cerrada_en,
+           z.cantidad_ventas, z.total_ventas, z.cantidad_anuladas, z.total_descuentos,
+           z.monto_apertura, z.total_ingresos, z.total_egresos,
+           z.efectivo_esperado, z.efectivo_contado, z.diferencia, z.total_devoluciones
+      FROM cierre_z z
+      JOIN usuario u ON u.id_usuario = z.id_operador
+"#;
+
+pub async fn listar(
+    pool: &SqlitePool,
+    desde: &str,
+    hasta: &str,
+) -> Result<Vec<CierreZRow>, sqlx::Error> {
+    let sql = format!(
+        "{SELECT_CIERRE_Z} WHERE DATE(z.cerrada_en) BETWEEN DATE(?1) AND DATE(?2) ORDER BY z.numero DESC"
+    );
+    sqlx::query_as::<_, CierreZRow>(&sql)
+        .bind(desde)
+        .bind(hasta)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn detalle(
+    pool: &SqlitePool,
+    id_cierre_z: i64,
+) -> Result<Option<CierreZDetalle>, sqlx::Error> {
+    let sql = format!("{SELECT_CIERRE_Z} WHERE z.id_cierre_z = ?1");
+    let Some(cierre) = sqlx::query_as::<_, CierreZRow>(&sql)
+        .bind(id_cierre_z)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let por_medio = sqlx::query_as::<_, CierreZMedioRow>(
+        "SELECT medio, cantidad, total FROM cierre_z_medio WHERE id_cierre_z = ?1 ORDER BY medio",
+    )
+    .bind(id_cierre_z)
+    .fetch_all(pool)
+    .await?;
+
+    let arqueo = sqlx::query_as::<_, ArqueoItemRow>(
+        "SELECT denominacion, cantidad, subtotal FROM caja_arqueo_item
+          WHERE id_caja = ?1 ORDER BY denominacion DESC",
+    )
+    .bind(cierre.id_caja)
+    .fetch_all(pool)
+    .await?;
+
+    let ajustes = sqlx::query_as::<_, CierreZAjusteRow>(
+        r#"
+        SELECT id_ajuste, id_venta, medio, monto_anterior, monto_nuevo,
+               diferencia, origen, id_usuario, fecha_hora
+          FROM cierre_z_ajuste
+         WHERE id_cierre_z = ?1
+         ORDER BY fecha_hora ASC, id_ajuste ASC
+        "#,
+    )
+    .bind(id_cierre_z)
+    .fetch_all(pool)
+    .await?;
+
+    let total_ajustes = ajustes.iter().map(|a| a.diferencia).sum();
+
+    Ok(Some(CierreZDetalle { cierre, por_medio, arqueo, ajustes, total_ajustes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use crate::caja::repo as caja_repo;
+    use crate::users::permisos::Rol;
+    use crate::ventas::{descuentos, prueba};
+    use crate::AppState;
+
+    #[tokio::test]
+    async fn descuentos_del_z_salen_de_lo_vendido() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 2, 0).await;
+        let yerba = prueba::producto(pool, "yerba", 1000, 600, 10).await;
+
+        // 10% de ticket sobre 2000 (dentro del tope del operador)
+        let id_venta = prueba::carrito(&state, &[(yerba, 2)]).await;
+        let input: descuentos::DescuentoAplicarInput = serde_json::from_value(json!({
+            "id_venta": id_venta, "tipo": "porcentaje", "valor": 10, "motivo": "cliente habitual"
+        }))
+        .unwrap();
+        descuentos::venta_descuento_aplicar(state.clone(), input).await.unwrap();
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 1800 }]))
+            .await
+            .unwrap();
+
+        // un aumento posterior no cambia lo descontado en la venta
+        sqlx::query("UPDATE producto SET precio_venta_actual = 1500 WHERE id_producto = ?1")
+            .bind(yerba)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let arqueo = caja_repo::cerrar_caja(pool, id_caja, 2, None).await.unwrap();
+        let (ventas, descuentos): (i64, i64) = sqlx::query_as(
+            "SELECT total_ventas, total_descuentos FROM cierre_z WHERE id_caja = ?1",
+        )
+        .bind(id_caja)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(arqueo.efectivo_esperado, 1800);
+        assert_eq!((ventas, descuentos), (1800, 200));
+    }
+}
@@ -1,27 +1,21 @@
 use tauri::State;
 use serde::{Deserialize, Serialize};
 use crate::app_state::AppState;
-use crate::users::auth::AuthState;
 use crate::caja::repo;
+use crate::caja_movimiento::repo as caja_mov_repo;
+use crate::caja::cierre_z;
+use crate::caja::model::{
+    ArqueoCaja, CierreZDetalle, CierreZRow, ConteoDenominacion, DiferenciaCajaRow, DiferenciaOperadorRow,
+};
 use sqlx::Row;
+use crate::users::permisos::{requerir_admin, requerir_sesion};
+use crate::audit::repo as audit_repo;
 
 // UTILIDADES
 
 #[tauri::command]
 pub async fn ping_inline() -> &'static str { "pong" }
 
-fn read_uid(auth: &AuthState, state: &AppState) -> Option<i64> {
-    //  Intentar desde AuthState
-    if let Ok(g) = auth.current_user_id.read() {
-        if g.is_some() { return *g; }
-    }
-    if let Ok(g) = state.session_user.lock() {
-        *g
-    } else {
-        None
-    }
-}
-
 // CONSULTAS BÁSICAS
 
 #[tauri::command]
@@ -48,27 +42,32 @@ pub async fn caja_estado(state: State<'_, AppState>) -> Result<bool, String> {
 #[derive(Serialize)]
 pub struct CajaAbrirOut { pub id_caja: i64 }
 
+#[derive(Deserialize)]
+pub struct CajaAbrirInput {
+    #[serde(default)]
+    pub monto_apertura: i64, // fondo inicial en efectivo
+}
+
 #[tauri::command]
 pub async fn caja_abrir(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>
+    input: Option<CajaAbrirInput>,
 ) -> Result<CajaAbrirOut, String> {
-
-    let uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión para abrir caja".to_string())?;
+    let uid = requerir_sesion(&state).await?.id_usuario;
+    let monto_apertura = input.map(|i| i.monto_apertura).unwrap_or(0);
 
     // Si ya hay caja abierta, la cerramos automáticamente
     if repo::existe_caja_abierta(&state.pool).await.map_err(|e| e.to_string())? {
         if let Some(id) = repo::ultima_caja_abierta_id(&state.pool)
             .await.map_err(|e| e.to_string())? 
         {
-            repo::cerrar_caja(&state.pool, id, uid)
+            repo::cerrar_caja(&state.pool, id, uid, None)
                 .await
                 .map_err(|e| e.to_string())?;
         }
     }
 
-    let id_nueva = repo::abrir_caja(&state.pool, uid)
+    let id_nueva = repo::abrir_caja(&state.pool, uid, monto_apertura)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -78,26 +77,34 @@ pub async fn caja_abrir(
 // CERRAR CAJA INDIVIDUAL
 
 #[derive(Serialize)]
-pub struct CajaCerrarOut { pub id_caja: i64 }
+pub struct CajaCerrarOut {
+    pub id_caja: i64,
+    pub arqueo: ArqueoCaja,
+}
+
+#[derive(Deserialize)]
+pub struct CajaCerrarInput {
+    /// Conteo por denominación. Sin conteo la caja se cierra sin arqueo.
+    pub conteo: Option<Vec<ConteoDenominacion>>,
+}
 
 #[tauri::command]
 pub async fn caja_cerrar(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>
+    input: Option<CajaCerrarInput>,
 ) -> Result<CajaCerrarOut, String> {
-
-    let uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión para cerrar la caja".to_string())?;
+    let uid = requerir_sesion(&state).await?.id_usuario;
 
     let id = repo::ultima_caja_abierta_id(&state.pool)
         .await.map_err(|e| e.to_string())?
         .ok_or_else(|| "No hay caja abierta".to_string())?;
 
-    repo::cerrar_caja(&state.pool, id, uid)
+    let conteo = input.and_then(|i| i.conteo);
+    let arqueo = repo::cerrar_caja(&state.pool, id, uid, conteo.as_deref())
         .await
         .map_err(|e| e.to_string())?;
 
-    Ok(CajaCerrarOut { id_caja: id })
+    Ok(CajaCerrarOut { id_caja: id, arqueo })
 }
 
 
@@ -115,23 +122,27 @@ pub struct CajaResumenDiario {
     pub id_cajas: Vec<i64>,
     pub cantidad_cajas: i32,
     pub cantidad_ventas: i32,
-    pub total_general: i64,
-    pub por_medio: Vec<MedioPagoResumen>,
+    pub total_general: i64,              // neto de devoluciones
+    pub por_medio: Vec<MedioPagoResumen>, // neto de devoluciones
+    pub total_devoluciones: i64,
+    pub cobros_cuenta_corriente: Vec<MedioPagoResumen>, // cobros a cuenta del día, por medio
+    pub total_cobros_cuenta_corriente: i64,
+    pub monto_apertura: i64,    // suma de fondos iniciales de las cajas del día
+    pub total_ingresos: i64,    // caja_movimiento tipo 'ingreso'
+    pub total_egresos: i64,     // retiros + pagos a proveedor
+    pub efectivo_esperado: i64, // apertura + efectivo cobrado +/- movimientos
 }
 
 #[tauri::command]
 pub async fn caja_resumen_diario(
-    state: State<'_, AppState>,
-    auth: State<'_, AuthState>
+    state: State<'_, AppState>
 ) -> Result<CajaResumenDiario, String> {
-
-    let uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión.".to_string())?;
+    let uid = requerir_sesion(&state).await?.id_usuario;
 
     // cajas del día
     let cajas = sqlx::query(
         r#"
-        SELECT id_caja
+        SELECT id_caja, monto_apertura
         FROM caja
         WHERE abierta_por = ?
           AND abierta_en >= datetime('now','localtime','start of day')
@@ -191,51 +202,209 @@ pub async fn caja_resumen_diario(
         .await
         .map_err(|e| e.to_string())?;
 
-    let por_medio = pagos.into_iter().map(|r| MedioPagoResumen {
+    // reintegros de devoluciones hechas hoy por el usuario (restan por medio)
+    let reintegros = sqlx::query(
+        r#"
+        SELECT dp.medio AS medio,
+               SUM(dp.monto) AS total_medio
+        FROM devolucion d
+        JOIN devolucion_pago dp ON dp.id_devolucion = d.id_devolucion
+        WHERE d.id_usuario = ?
+          AND d.fecha_hora >= datetime('now','localtime','start of day')
+          AND d.fecha_hora <  datetime('now','localtime','start of day','+1 day')
+        GROUP BY dp.medio
+        "#)
+        .bind(uid)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut por_medio: Vec<MedioPagoResumen> = pagos.into_iter().map(|r| MedioPagoResumen {
         medio: r.get("medio"),
         total_medio: r.get("total_medio"),
     }).collect();
 
+    let mut total_devoluciones = 0i64;
+    for r in reintegros {
+        let medio: String = r.get("medio");
+        let monto: i64 = r.get("total_medio");
+        total_devoluciones += monto;
+        match por_medio.iter_mut().find(|m| m.medio == medio) {
+            Some(m) => m.total_medio -= monto,
+            None => por_medio.push(MedioPagoResumen { medio, total_medio: -monto }),
+        }
+    }
+
+    // cobros de cuenta corriente hechos hoy por el usuario (no son ventas del día)
+    let cobros_cuenta_corriente: Vec<MedioPagoResumen> = sqlx::query(
+        r#"
+        SELECT medio, SUM(monto) AS total_medio
+        FROM cc_cobro
+        WHERE id_usuario = ?
+          AND fecha_hora >= datetime('now','localtime','start of day')
+          AND fecha_hora <  datetime('now','localtime','start of day','+1 day')
+        GROUP BY medio
+        "#)
+        .bind(uid)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|r| MedioPagoResumen {
+            medio: r.get("medio"),
+            total_medio: r.get("total_medio"),
+        })
+        .collect();
+    let total_cobros_cuenta_corriente = cobros_cuenta_corriente.iter().map(|m| m.total_medio).sum();
+
+    let mut monto_apertura = 0i64;
+    let mut efectivo_esperado = 0i64;
+    {
+        let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+        for r in &cajas {
+            monto_apertura += r.get::<i64,_>("monto_apertura");
+            efectivo_esperado += repo::efectivo_esperado(&mut conn, r.get("id_caja"))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let movs = caja_mov_repo::totales_por_cajas(&state.pool, &id_cajas)
+        .await
+        .map_err(|e| e.to_string())?;
+
     let cantidad_cajas = id_cajas.len() as i32;
 
     Ok(CajaResumenDiario {
         id_cajas,
         cantidad_cajas,
         cantidad_ventas: ventas_count,
-        total_general,
+        total_general: total_general - total_devoluciones,
         por_medio,
+        total_devoluciones,
+        cobros_cuenta_corriente,
+        total_cobros_cuenta_corriente,
+        monto_apertura,
+        total_ingresos: movs.total_ingresos,
+        total_egresos: movs.total_egresos,
+        efectivo_esperado,
     })
 }
 
 // NUEVO — CERRAR TODAS LAS CAJAS DEL DÍA DEL USUARIO
 
+#[derive(Deserialize)]
+pub struct ArqueoCajaInput {
+    pub id_caja: i64,
+    pub conteo: Vec<ConteoDenominacion>,
+}
+
 #[derive(Deserialize)]
 pub struct CierreDiarioInput {
     pub id_cajas: Vec<i64>,
+    /// Conteo por caja (opcional). Las cajas sin conteo se cierran sin arqueo.
+    #[serde(default)]
+    pub arqueos: Vec<ArqueoCajaInput>,
 }
 
 #[tauri::command]
 pub async fn caja_cerrar_diario(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     input: CierreDiarioInput
-) -> Result<(), String> {
-
-    let uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión.".to_string())?;
+) -> Result<Vec<ArqueoCaja>, String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
 
+    let mut out = Vec::with_capacity(input.id_cajas.len());
     for id in input.id_cajas {
-        repo::cerrar_caja(&state.pool, id, uid)
+        let conteo = input.arqueos.iter()
+            .find(|a| a.id_caja == id)
+            .map(|a| a.conteo.as_slice());
+
+        let arqueo = repo::cerrar_caja(&state.pool, id, uid, conteo)
             .await
             .map_err(|e| e.to_string())?;
+        out.push(arqueo);
     }
 
-    Ok(())
+    Ok(out)
+}
+
+// REPORTE ADMIN — DIFERENCIAS DE ARQUEO POR OPERADOR
+
+#[derive(Serialize)]
+pub struct CajaDiferenciasReporte {
+    pub fecha_desde: String,
+    pub fecha_hasta: String,
+    pub por_operador: Vec<DiferenciaOperadorRow>,
+    pub cajas: Vec<DiferenciaCajaRow>,
+}
+
+#[tauri::command]
+pub async fn caja_reporte_diferencias(
+    state: State<'_, AppState>,
+    desde: Option<String>,
+    hasta: Option<String>,
+    id_usuario: Option<i64>,
+) -> Result<CajaDiferenciasReporte, String> {
+    requerir_admin(&state).await?;
+
+    let hoy = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let (fecha_desde, fecha_hasta) = match (desde, hasta) {
+        (Some(d), Some(h)) => (d, h),
+        (Some(d), None) => (d.clone(), d),
+        (None, Some(h)) => (h.clone(), h),
+        (None, None) => (hoy.clone(), hoy),
+    };
+
+    let por_operador = repo::diferencias_por_operador(&state.pool, &fecha_desde, &fecha_hasta, id_usuario)
+        .await
+        .map_err(|e| e.to_string())?;
+    let cajas = repo::diferencias_por_caja(&state.pool, &fecha_desde, &fecha_hasta, id_usuario)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(CajaDiferenciasReporte { fecha_desde, fecha_hasta, por_operador, cajas })
+}
+
+// CIERRES Z (admin)
+
+#[tauri::command]
+pub async fn caja_cierre_z_listar(
+    state: State<'_, AppState>,
+    desde: Option<String>,
+    hasta: Option<String>,
+) -> Result<Vec<CierreZRow>, String> {
+    requerir_admin(&state).await?;
+
+    let hoy = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let desde = desde.unwrap_or_else(|| hoy.clone());
+    let hasta = hasta.unwrap_or(hoy);
+
+    cierre_z::listar(&state.pool, &desde, &hasta)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn caja_cierre_z_detalle(
+    state: State<'_, AppState>,
+    id_cierre_z: i64,
+) -> Result<CierreZDetalle, String> {
+    requerir_admin(&state).await?;
+
+    cierre_z::detalle(&state.pool, id_cierre_z)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Cierre Z inexistente".to_string())
 }
 
 // LOGOUT
 #[tauri::command]
-pub async fn auth_logout(auth: State<'_, AuthState>) -> Result<(), String> {
-    *auth.current_user_id.write().map_err(|_| "lock")? = None;
+pub async fn auth_logout(state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(id_usuario) = state.sesion.cerrar()? {
+        audit_repo::registrar(&state.pool, Some(id_usuario), "sesion", Some(id_usuario), "logout", None, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
     Ok(())
 }
@@ -1,3 +1,4 @@
 pub mod model;
 pub mod repo;
-pub mod commands;
\ No newline at end of file
+pub mod commands;
+pub mod cierre_z;
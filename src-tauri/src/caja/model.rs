@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EstadoCaja { Abierta, Cerrada }
 
@@ -19,3 +22,108 @@ pub struct Caja {
     pub cerrada_por: Option<i64>,
     pub cerrada_en: Option<String>,
 }
+
+// ARQUEO
+
+/// Una línea del conteo de efectivo al cierre (ej: 10 billetes de 1000)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConteoDenominacion {
+    pub denominacion: i64,
+    pub cantidad: i64,
+}
+
+/// Resultado del cierre. contado/diferencia en None si se cerró sin arqueo.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArqueoCaja {
+    pub id_caja: i64,
+    pub monto_apertura: i64,
+    pub efectivo_esperado: i64,
+    pub efectivo_contado: Option<i64>,
+    pub diferencia: Option<i64>, // >0 sobrante | <0 faltante
+    pub numero_z: i64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DiferenciaCajaRow {
+    pub id_caja: i64,
+    pub abierta_por: i64,
+    pub operador: String,
+    pub abierta_en: String,       
+    pub cerrada_en: Option<String>,
+    pub monto_apertura: i64,
+    pub efectivo_esperado: Option<i64>,
+    pub efectivo_contado: Option<i64>,
+    pub diferencia: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DiferenciaOperadorRow {
+    pub id_usuario: i64,
+    pub operador: String,
+    pub cajas_cerradas: i64,
+    pub cajas_con_arqueo: i64,
+    pub total_sobrante: i64,
+    pub total_faltante: i64, // en positivo
+    pub diferencia_neta: i64,
+}
+
+// CIERRE Z
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CierreZRow {
+    pub id_cierre_z: i64,
+    pub numero: i64,
+    pub id_caja: i64,
+    pub id_operador: i64,
+    pub operador: String,
+    pub id_usuario_cierre: i64,
+    pub abierta_en: String,
+    pub cerrada_en: String,
+    pub cantidad_ventas: i64,
+    pub total_ventas: i64,
+    pub cantidad_anuladas: i64,
+    pub total_descuentos: i64,
+    pub monto_apertura: i64,
+    pub total_ingresos: i64,
+    pub total_egresos: i64,
+    pub efectivo_esperado: i64,
+    pub efectivo_contado: Option<i64>,
+    pub diferencia: Option<i64>,
+    pub total_devoluciones: i64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CierreZMedioRow {
+    pub medio: String,
+    pub cantidad: i64,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CierreZAjusteRow {
+    pub id_ajuste: i64,
+    pub id_venta: i64,
+    pub medio: String,
+    pub monto_anterior: i64,
+    pub monto_nuevo: i64,
+    pub diferencia: i64,
+    pub origen: String,
+    pub id_usuario: i64,
+    pub fecha_hora: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CierreZDetalle {
+    pub cierre: CierreZRow,
+    pub por_medio: Vec<CierreZMedioRow>,
+    pub arqueo: Vec<ArqueoItemRow>,
+    pub ajustes: Vec<CierreZAjusteRow>,
+    pub total_ajustes: i64, // suma de diferencias posteriores al cierre
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ArqueoItemRow {
+    pub denominacion: i64,
+    pub cantidad: i64,
+    pub subtotal: i64,
+}
@@ -1,5 +1,10 @@
-use sqlx::{SqlitePool, Row};
-use crate::caja::model::{Caja, EstadoCaja};
+use sqlx::{SqliteConnection, SqlitePool, Row};
+use crate::caja::cierre_z;
+use crate::audit::repo as audit_repo;
+use serde_json::json;
+use crate::caja::model::{
+    ArqueoCaja, Caja, ConteoDenominacion, DiferenciaCajaRow, DiferenciaOperadorRow, EstadoCaja,
+};
 
 pub async fn existe_caja_abierta(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
     let ok: Option<i64> = sqlx::query_scalar(
@@ -8,14 +13,28 @@ pub async fn existe_caja_abierta(pool: &SqlitePool) -> Result<bool, sqlx::Error>
     Ok(ok.is_some())
 }
 
-pub async fn abrir_caja(pool: &SqlitePool, user_id: i64) -> Result<i64, sqlx::Error> {
+pub async fn abrir_caja(pool: &SqlitePool, user_id: i64, monto_apertura: i64) -> Result<i64, sqlx::Error> {
+    if monto_apertura < 0 {
+        return Err(sqlx::Error::Protocol(
+            "El fondo inicial no puede ser negativo.".into()
+        ));
+    }
+
     let res = sqlx::query(
-        "INSERT INTO caja (abierta_por, estado, abierta_en)
-            VALUES (?1,'abierta',DATETIME('now','localtime'));"
+        "INSERT INTO caja (abierta_por, estado, abierta_en, monto_apertura)
+            VALUES (?1,'abierta',DATETIME('now','localtime'),?2);"
     )
     .bind(user_id)
+    .bind(monto_apertura)
     .execute(pool).await?;
-    Ok(res.last_insert_rowid())
+    let id_caja = res.last_insert_rowid();
+
+    audit_repo::registrar(
+        pool, Some(user_id), "caja", Some(id_caja), "abrir",
+        None, Some(&json!({ "monto_apertura": monto_apertura })),
+    ).await?;
+
+    Ok(id_caja)
 }
 
 pub async fn ultima_caja_abierta_id(pool: &SqlitePool) -> Result<Option<i64>, sqlx::Error> {
@@ -27,32 +46,128 @@ pub async fn ultima_caja_abierta_id(pool: &SqlitePool) -> Result<Option<i64>, sq
     Ok(id)
 }
 
-pub async fn cerrar_caja(pool: &SqlitePool, id_caja: i64, _user_id: i64) -> Result<(), sqlx::Error> {
-    let hay_venta_en_curso: i64 = sqlx::query_scalar(
-        "SELECT EXISTS(
-            SELECT 1
-            FROM venta
-            WHERE estado = 'en_curso'
-            LIMIT 1
-        );"
+/// Efectivo que debería haber en el cajón: fondo inicial + cobros en efectivo
+/// de ventas finalizadas + ingresos - retiros/pagos (caja_movimiento) -
+/// reintegros en efectivo de devoluciones + cobros de cuenta corriente en
+/// efectivo. vp.monto ya es neto del vuelto; el recargo del medio (si lo
+/// hubiera) también entra al cajón.
+pub async fn efectivo_esperado(conn: &mut SqliteConnection, id_caja: i64) -> Result<i64, sqlx::Error> {
+    let esperado: Option<i64> = sqlx::query_scalar(
+        "SELECT c.monto_apertura + COALESCE((
+                SELECT SUM(vp.monto + vp.recargo)
+                  FROM venta v
+                  JOIN venta_pago vp ON vp.id_venta = v.id_venta
+                 WHERE v.id_caja = c.id_caja
+                   AND v.estado = 'finalizada'
+                   AND vp.medio = 'efectivo'
+            ), 0)
+            + COALESCE((
+                SELECT SUM(CASE WHEN m.tipo = 'ingreso' THEN m.monto ELSE -m.monto END)
+                  FROM caja_movimiento m
+                 WHERE m.id_caja = c.id_caja
+            ), 0)
+            - COALESCE((
+                SELECT SUM(dp.monto)
+                  FROM devolucion d
+                  JOIN devolucion_pago dp ON dp.id_devolucion = d.id_devolucion
+                 WHERE d.id_caja = c.id_caja
+                   AND dp.medio = 'efectivo'
+            ), 0)
+            + COALESCE((
+                SELECT SUM(cc.monto)
+                  FROM cc_cobro cc
+                 WHERE cc.id_caja = c.id_caja
+                   AND cc.medio = 'efectivo'
+            ), 0)
+           FROM caja c
+          WHERE c.id_caja = ?1;"
     )
-    .fetch_one(pool)
+    .bind(id_caja)
+    .fetch_optional(&mut *conn)
     .await?;
 
-    if hay_venta_en_curso == 1 {
-        return Err(sqlx::Error::Protocol(
-            "No se puede cerrar caja: hay una venta en curso.".into()
-        ));
+    esperado.ok_or_else(|| sqlx::Error::Protocol("Caja inexistente.".into()))
+}
+
+/// Cierra la caja. Si viene `conteo`, guarda el arqueo por denominación y
+/// la diferencia contra el efectivo esperado. Genera el cierre Z.
+pub async fn cerrar_caja(
+    pool: &SqlitePool,
+    id_caja: i64,
+    user_id: i64,
+    conteo: Option<&[ConteoDenominacion]>,
+) -> Result<ArqueoCaja, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Sólo bloquean los carritos de esta caja (activos o estacionados)
+    let en_curso: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM venta WHERE estado = 'en_curso' AND id_caja = ?1;"
+    )
+    .bind(id_caja)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if en_curso > 0 {
+        return Err(sqlx::Error::Protocol(format!(
+            "No se puede cerrar caja: hay {en_curso} venta(s) en curso o estacionada(s). Retomalas o descartalas."
+        )));
     }
 
+    let esperado = efectivo_esperado(&mut tx, id_caja).await?;
+
+    let contado = match conteo {
+        None => None,
+        Some(lineas) => {
+            let mut total = 0i64;
+            for l in lineas {
+                if l.denominacion <= 0 || l.cantidad < 0 {
+                    return Err(sqlx::Error::Protocol(format!(
+                        "Conteo inválido: denominación {} x {}", l.denominacion, l.cantidad
+                    )));
+                }
+                if l.cantidad == 0 {
+                    continue;
+                }
+                let subtotal = l.denominacion * l.cantidad;
+
+                // misma denominación repetida => se acumula
+                sqlx::query(
+                    "INSERT INTO caja_arqueo_item (id_caja, denominacion, cantidad, subtotal)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT(id_caja, denominacion) DO UPDATE SET
+                        cantidad = cantidad + excluded.cantidad,
+                        subtotal = subtotal + excluded.subtotal;"
+                )
+                .bind(id_caja)
+                .bind(l.denominacion)
+                .bind(l.cantidad)
+                .bind(subtotal)
+                .execute(&mut *tx)
+                .await?;
+
+                total += subtotal;
+            }
+            Some(total)
+        }
+    };
+    let diferencia = contado.map(|c| c - esperado);
+
     let res = sqlx::query(
         "UPDATE caja
             SET estado='cerrada',
-                cerrada_en=DATETIME('now','localtime')
+                cerrada_en=DATETIME('now','localtime'),
+                cerrada_por=?2,
+                efectivo_esperado=?3,
+                efectivo_contado=?4,
+                diferencia=?5
           WHERE id_caja=?1 AND estado='abierta';"
     )
     .bind(id_caja)
-    .execute(pool)
+    .bind(user_id)
+    .bind(esperado)
+    .bind(contado)
+    .bind(diferencia)
+    .execute(&mut *tx)
     .await?;
 
     if res.rows_affected() == 0 {
@@ -61,12 +176,42 @@ pub async fn cerrar_caja(pool: &SqlitePool, id_caja: i64, _user_id: i64) -> Resu
         ));
     }
 
-    Ok(())
+    let numero_z = cierre_z::generar(&mut tx, id_caja, user_id).await?;
+
+    let monto_apertura: i64 = sqlx::query_scalar(
+        "SELECT monto_apertura FROM caja WHERE id_caja=?1"
+    )
+    .bind(id_caja)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    audit_repo::registrar(
+        &mut *tx, Some(user_id), "caja", Some(id_caja), "cerrar",
+        Some(&json!({ "estado": "abierta" })),
+        Some(&json!({
+            "estado": "cerrada",
+            "numero_z": numero_z,
+            "efectivo_esperado": esperado,
+            "efectivo_contado": contado,
+            "diferencia": diferencia,
+        })),
+    ).await?;
+
+    tx.commit().await?;
+
+    Ok(ArqueoCaja {
+        id_caja,
+        monto_apertura,
+        efectivo_esperado: esperado,
+        efectivo_contado: contado,
+        diferencia,
+        numero_z,
+    })
 }
 
 pub async fn obtener_caja(pool: &SqlitePool, id_caja: i64) -> Result<Option<Caja>, sqlx::Error> {
     let row = sqlx::query(
-        "SELECT id_caja, abierta_por, abierta_en, estado, cerrada_en
+        "SELECT id_caja, abierta_por, abierta_en, estado, cerrada_por, cerrada_en
            FROM caja
           WHERE id_caja=?1"
     )
@@ -81,7 +226,143 @@ pub async fn obtener_caja(pool: &SqlitePool, id_caja: i64) -> Result<Option<Caja
             "abierta" => EstadoCaja::Abierta,
             _ => EstadoCaja::Cerrada,
         },
-        cerrada_por: None,
+        cerrada_por: r.try_get("cerrada_por").ok().flatten(),
         cerrada_en: r.try_get("cerrada_en").ok(),
     }))
 }
+
+// REPORTE DE DIFERENCIAS (sobrante/faltante)
+
+/// Cajas cerradas en el rango [desde, hasta] (fechas 'YYYY-MM-DD', inclusive)
+pub async fn diferencias_por_caja(
+    pool: &SqlitePool,
+    desde: &str,
+    hasta: &str,
+    id_usuario: Option<i64>,
+) -> Result<Vec<DiferenciaCajaRow>, sqlx::Error> {
+    sqlx::query_as::<_, DiferenciaCajaRow>(
+        "SELECT c.id_caja, c.abierta_por, u.nombre AS operador,
+                c.abierta_en, c.cerrada_en, c.monto_apertura,
+                c.efectivo_esperado, c.efectivo_contado, c.diferencia
+           FROM caja c
+           JOIN usuario u ON u.id_usuario = c.abierta_por
+          WHERE c.estado = 'cerrada'
+            AND DATE(c.cerrada_en) BETWEEN DATE(?1) AND DATE(?2)
+            AND (?3 IS NULL OR c.abierta_por = ?3)
+          ORDER BY c.cerrada_en DESC, c.id_caja DESC;"
+    )
+    .bind(desde)
+    .bind(hasta)
+    .bind(id_usuario)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn diferencias_por_operador(
+    pool: &SqlitePool,
+    desde: &str,
+    hasta: &str,
+    id_usuario: Option<i64>,
+) -> Result<Vec<DiferenciaOperadorRow>, sqlx::Error> {
+    sqlx::query_as::<_, DiferenciaOperadorRow>(
+        "SELECT u.id_usuario, u.nombre AS operador,
+                COUNT(*) AS cajas_cerradas,
+                COUNT(c.efectivo_contado) AS cajas_con_arqueo,
+                COALESCE(SUM(CASE WHEN c.diferencia > 0 THEN c.diferencia ELSE 0 END), 0) AS total_sobrante,
+                COALESCE(SUM(CASE WHEN c.diferencia < 0 THEN -c.diferencia ELSE 0 END), 0) AS total_faltante,
+                COALESCE(SUM(c.diferencia), 0) AS diferencia_neta
+           FROM caja c
+           JOIN usuario u ON u.id_usuario = c.abierta_por
+          WHERE c.estado = 'cerrada'
+            AND DATE(c.cerrada_en) BETWEEN DATE(?1) AND DATE(?2)
+            AND (?3 IS NULL OR c.abierta_por = ?3)
+          GROUP BY u.id_usuario, u.nombre
+          ORDER BY total_faltante DESC, u.nombre;"
+    )
+    .bind(desde)
+    .bind(hasta)
+    .bind(id_usuario)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+    use crate::AppState;
+    use tauri::Manager;
+
+    fn conteo(lineas: &[(i64, i64)]) -> Vec<ConteoDenominacion> {
+        lineas
+            .iter()
+            .map(|&(denominacion, cantidad)| ConteoDenominacion { denominacion, cantidad })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn fondo_negativo_no_abre() {
+        let state = AppState::prueba(None).await;
+        assert!(abrir_caja(&state.pool, 1, -1).await.is_err());
+        assert!(!existe_caja_abierta(&state.pool).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn arqueo_calcula_esperado_y_diferencia() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 2, 1000).await;
+        let pan = prueba::producto(pool, "pan", 500, 200, 10).await;
+
+        // el cliente entrega 1000 por 500: al cajón entra el neto
+        prueba::venta(&state, &[(pan, 1)], serde_json::json!([
+            { "medio": "efectivo", "monto": 500, "monto_entregado": 1000 }
+        ]))
+        .await;
+        prueba::venta(&state, &[(pan, 2)], serde_json::json!([{ "medio": "debito", "monto": 1000 }])).await;
+        assert_eq!(prueba::stock(pool, pan).await, 7);
+        sqlx::query("INSERT INTO caja_movimiento (id_caja, tipo, monto, motivo, id_usuario) VALUES (?1, 'retiro', 200, 'Cambio', 2)")
+            .bind(id_caja)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(efectivo_esperado(&mut conn, id_caja).await.unwrap(), 1300);
+        drop(conn);
+
+        // con un carrito abierto no se puede cerrar
+        let carrito = prueba::carrito(&state, &[(pan, 1)]).await;
+        assert!(cerrar_caja(pool, id_caja, 2, None).await.is_err());
+        sqlx::query("DELETE FROM venta WHERE id_venta = ?1").bind(carrito).execute(pool).await.unwrap();
+
+        let lineas = conteo(&[(1000, 1), (100, 2), (50, 1), (100, 0)]);
+        let arqueo = cerrar_caja(pool, id_caja, 2, Some(&lineas)).await.unwrap();
+        assert_eq!(arqueo.monto_apertura, 1000);
+        assert_eq!(arqueo.efectivo_esperado, 1300);
+        assert_eq!(arqueo.efectivo_contado, Some(1250));
+        assert_eq!(arqueo.diferencia, Some(-50));
+
+        let items: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM caja_arqueo_item WHERE id_caja = ?1")
+            .bind(id_caja)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(items, 3);
+
+        assert!(cerrar_caja(pool, id_caja, 2, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn conteo_invalido_no_cierra() {
+        let state = AppState::prueba(None).await;
+        let id_caja = abrir_caja(&state.pool, 2, 0).await.unwrap();
+        let lineas = conteo(&[(0, 3)]);
+        assert!(cerrar_caja(&state.pool, id_caja, 2, Some(&lineas)).await.is_err());
+        assert!(existe_caja_abierta(&state.pool).await.unwrap());
+    }
+}
@@ -0,0 +1,160 @@
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::caja::repo as caja_repo;
+use crate::users::permisos::{requerir_admin_o_aprobacion, requerir_sesion};
+
+use super::model::{CajaMovimientoInput, CajaMovimientoRow, TipoMovimiento};
+use super::repo;
+
+// REGISTRAR (sobre la caja abierta)
+
+#[tauri::command]
+pub async fn caja_movimiento_registrar(
+    state: State<'_, AppState>,
+    input: CajaMovimientoInput,
+) -> Result<i64, String> {
+    // Imputar a gasto_negocio es lo mismo que gasto_registrar: admin o aprobación
+    let (uid, aprobado_por) = if input.registrar_gasto {
+        let (sesion, aprobado_por) =
+            requerir_admin_o_aprobacion(&state, input.aprobacion.as_ref()).await?;
+        (sesion.id_usuario, Some(aprobado_por))
+    } else {
+        (requerir_sesion(&state).await?.id_usuario, None)
+    };
+
+    let tipo = TipoMovimiento::parse(&input.tipo)
+        .ok_or_else(|| format!("Tipo de movimiento inválido: {}", input.tipo))?;
+
+    if input.monto <= 0 {
+        return Err("monto inválido (> 0)".into());
+    }
+    let motivo = input.motivo.trim();
+    if motivo.is_empty() {
+        return Err("motivo obligatorio".into());
+    }
+    if input.registrar_gasto && !tipo.es_egreso() {
+        return Err("Un ingreso no se puede registrar como gasto".into());
+    }
+
+    let id_caja = caja_repo::ultima_caja_abierta_id(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No hay caja abierta".to_string())?;
+
+    let categoria = input
+        .categoria_gasto
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .unwrap_or("otros");
+
+    repo::movimiento_insert(
+        &state.pool,
+        id_caja,
+        uid,
+        tipo,
+        input.monto,
+        motivo,
+        aprobado_por.map(|id| (categoria, id)),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// LISTAR (por defecto, la caja abierta)
+
+#[tauri::command]
+pub async fn caja_movimiento_listar(
+    state: State<'_, AppState>,
+    id_caja: Option<i64>,
+) -> Result<Vec<CajaMovimientoRow>, String> {
+    requerir_sesion(&state).await?;
+
+    let id_caja = match id_caja {
+        Some(id) => id,
+        None => caja_repo::ultima_caja_abierta_id(&state.pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "No hay caja abierta".to_string())?,
+    };
+
+    repo::movimientos_por_caja(&state.pool, id_caja)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::permisos::Rol;
+    use serde_json::json;
+    use tauri::Manager;
+
+    fn input(v: serde_json::Value) -> CajaMovimientoInput {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn tipos_y_egresos() {
+        assert_eq!(TipoMovimiento::parse(" Retiro "), Some(TipoMovimiento::Retiro));
+        assert_eq!(TipoMovimiento::parse("deposito"), None);
+        assert!(TipoMovimiento::PagoProveedor.es_egreso());
+        assert!(!TipoMovimiento::Ingreso.es_egreso());
+    }
+
+    #[tokio::test]
+    async fn gasto_desde_caja_pide_admin_o_aprobacion() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        sqlx::query("INSERT INTO caja (abierta_por, estado) VALUES (2, 'abierta')")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        let con_gasto = json!({ "tipo": "pago_proveedor", "monto": 500, "motivo": "Flete", "registrar_gasto": true });
+        let err = caja_movimiento_registrar(state.clone(), input(con_gasto)).await.unwrap_err();
+        assert_eq!(err, "Se requiere la aprobación de un administrador");
+
+        // sin imputar gasto el operador puede retirar
+        let retiro = json!({ "tipo": "retiro", "monto": 300, "motivo": "Cambio" });
+        caja_movimiento_registrar(state.clone(), input(retiro)).await.unwrap();
+
+        let gastos: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM gasto_negocio")
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(gastos, 0);
+
+        let totales = repo::totales_por_cajas(&state.pool, &[1]).await.unwrap();
+        assert_eq!((totales.total_ingresos, totales.total_egresos), (0, 300));
+    }
+
+    #[tokio::test]
+    async fn admin_imputa_el_gasto() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        sqlx::query("INSERT INTO caja (abierta_por, estado) VALUES (1, 'abierta')")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        let con_gasto = json!({
+            "tipo": "pago_proveedor", "monto": 500, "motivo": "Flete",
+            "registrar_gasto": true, "categoria_gasto": " fletes "
+        });
+        caja_movimiento_registrar(state.clone(), input(con_gasto)).await.unwrap();
+
+        let movs = repo::movimientos_por_caja(&state.pool, 1).await.unwrap();
+        let id_gasto = movs[0].id_gasto_negocio.expect("gasto vinculado");
+        let (categoria, monto): (String, i64) =
+            sqlx::query_as("SELECT categoria, monto FROM gasto_negocio WHERE id_gasto_negocio = ?1")
+                .bind(id_gasto)
+                .fetch_one(&state.pool)
+                .await
+                .unwrap();
+        assert_eq!((categoria.as_str(), monto), ("fletes", 500));
+    }
+}
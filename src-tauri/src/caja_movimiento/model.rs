@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::users::permisos::AprobacionInput;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TipoMovimiento { Retiro, Ingreso, PagoProveedor }
+
+impl TipoMovimiento {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TipoMovimiento::Retiro => "retiro",
+            TipoMovimiento::Ingreso => "ingreso",
+            TipoMovimiento::PagoProveedor => "pago_proveedor",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<TipoMovimiento> {
+        match s.trim().to_lowercase().as_str() {
+            "retiro" => Some(TipoMovimiento::Retiro),
+            "ingreso" => Some(TipoMovimiento::Ingreso),
+            "pago_proveedor" => Some(TipoMovimiento::PagoProveedor),
+            _ => None,
+        }
+    }
+
+    /// true si el movimiento saca efectivo del cajón
+    pub fn es_egreso(self) -> bool {
+        !matches!(self, TipoMovimiento::Ingreso)
+    }
+}
+
+// INPUTS
+
+#[derive(Debug, Deserialize)]
+pub struct CajaMovimientoInput {
+    pub tipo: String, // 'retiro' | 'ingreso' | 'pago_proveedor'
+    pub monto: i64,
+    pub motivo: String,
+    /// Si es egreso y viene en true, se imputa también en gasto_negocio
+    #[serde(default)]
+    pub registrar_gasto: bool,
+    pub categoria_gasto: Option<String>, // default 'otros'
+    /// Para un operador que imputa el gasto: credenciales del admin que lo autoriza
+    pub aprobacion: Option<AprobacionInput>,
+}
+
+// OUTPUTS
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CajaMovimientoRow {
+    pub id_caja_movimiento: i64,
+    pub id_caja: i64,
+    pub tipo: String,
+    pub monto: i64,
+    pub motivo: String,
+    pub id_usuario: i64,
+    pub usuario: String,
+    pub fecha_hora: String,
+    pub id_gasto_negocio: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CajaMovimientoTotales {
+    pub total_ingresos: i64,
+    pub total_egresos: i64, // retiros + pagos a proveedor
+}
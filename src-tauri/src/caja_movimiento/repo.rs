@@ -0,0 +1,134 @@
+use serde_json::json;
+use sqlx::SqlitePool;
+
+use crate::audit::repo as audit_repo;
+
+use super::model::{CajaMovimientoRow, CajaMovimientoTotales, TipoMovimiento};
+
+/// Registra el movimiento en la caja. Si `gasto` viene (categoría y quién lo
+/// autorizó), el egreso también se carga en gasto_negocio (misma transacción).
+pub async fn movimiento_insert(
+    pool: &SqlitePool,
+    id_caja: i64,
+    id_usuario: i64,
+    tipo: TipoMovimiento,
+    monto: i64,
+    motivo: &str,
+    gasto: Option<(&str, i64)>,
+) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let abierta: Option<i64> = sqlx::query_scalar(
+        "SELECT 1 FROM caja WHERE id_caja = ?1 AND estado = 'abierta'"
+    )
+    .bind(id_caja)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if abierta.is_none() {
+        return Err(sqlx::Error::Protocol("La caja no está abierta.".into()));
+    }
+
+    let id_gasto: Option<i64> = match gasto {
+        Some((cat, _)) if tipo.es_egreso() => {
+            let id = sqlx::query_scalar::<_, i64>(
+                r#"
+                INSERT INTO gasto_negocio (fecha_hora, categoria, descripcion, monto, id_usuario)
+                VALUES (DATETIME('now','localtime'), ?1, ?2, ?3, ?4)
+                RETURNING id_gasto_negocio
+                "#,
+            )
+            .bind(cat)
+            .bind(format!("Caja #{id_caja} — {motivo}"))
+            .bind(monto)
+            .bind(id_usuario)
+            .fetch_one(&mut *tx)
+            .await?;
+            Some(id)
+        }
+        _ => None,
+    };
+
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO caja_movimiento (id_caja, tipo, monto, motivo, id_usuario, id_gasto_negocio)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        RETURNING id_caja_movimiento
+        "#,
+    )
+    .bind(id_caja)
+    .bind(tipo.as_str())
+    .bind(monto)
+    .bind(motivo)
+    .bind(id_usuario)
+    .bind(id_gasto)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    audit_repo::registrar(
+        &mut *tx,
+        Some(id_usuario),
+        "caja_movimiento",
+        Some(id),
+        "crear",
+        None,
+        Some(&json!({
+            "id_caja": id_caja,
+            "tipo": tipo.as_str(),
+            "monto": monto,
+            "motivo": motivo,
+            "id_gasto_negocio": id_gasto,
+            "gasto_aprobado_por": id_gasto.and(gasto.map(|(_, aprobado_por)| aprobado_por)),
+        })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn movimientos_por_caja(
+    pool: &SqlitePool,
+    id_caja: i64,
+) -> Result<Vec<CajaMovimientoRow>, sqlx::Error> {
+    sqlx::query_as::<_, CajaMovimientoRow>(
+        r#"
+        SELECT m.id_caja_movimiento, m.id_caja, m.tipo, m.monto, m.motivo,
+               m.id_usuario, u.nombre AS usuario, m.fecha_hora, m.id_gasto_negocio
+        FROM caja_movimiento m
+        JOIN usuario u ON u.id_usuario = m.id_usuario
+        WHERE m.id_caja = ?1
+        ORDER BY m.fecha_hora ASC, m.id_caja_movimiento ASC
+        "#,
+    )
+    .bind(id_caja)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn totales_por_cajas(
+    pool: &SqlitePool,
+    id_cajas: &[i64],
+) -> Result<CajaMovimientoTotales, sqlx::Error> {
+    let mut out = CajaMovimientoTotales::default();
+
+    for id in id_cajas {
+        let (ingresos, egresos): (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+              COALESCE(SUM(CASE WHEN tipo = 'ingreso' THEN monto ELSE 0 END), 0),
+              COALESCE(SUM(CASE WHEN tipo <> 'ingreso' THEN monto ELSE 0 END), 0)
+            FROM caja_movimiento
+            WHERE id_caja = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+        out.total_ingresos += ingresos;
+        out.total_egresos += egresos;
+    }
+
+    Ok(out)
+}
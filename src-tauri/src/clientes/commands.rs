@@ -0,0 +1,181 @@
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::{requerir_admin, requerir_sesion};
+
+use super::model::{
+    ClienteActualizarInput, ClienteBuscarInput, ClienteHistorial, ClienteHistorialInput,
+    ClienteInput, ClienteRow, VentaClienteInput,
+};
+use super::repo;
+
+async fn cliente_o_error(state: &AppState, id_cliente: i64) -> Result<ClienteRow, String> {
+    repo::obtener(&state.pool, id_cliente)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Cliente inexistente".to_string())
+}
+
+// ALTA (también desde el POS)
+
+#[tauri::command]
+pub async fn cliente_crear(state: State<'_, AppState>, input: ClienteInput) -> Result<i64, String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let id = repo::insertar(&state.pool, &input).await?;
+
+    let despues = cliente_o_error(&state, id).await?;
+    audit_repo::registrar(&state.pool, Some(uid), "cliente", Some(id), "crear", None, Some(&json!(despues)))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn cliente_actualizar(
+    state: State<'_, AppState>,
+    input: ClienteActualizarInput,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let antes = cliente_o_error(&state, input.id_cliente).await?;
+    repo::actualizar(&state.pool, input.id_cliente, &input.datos).await?;
+    let despues = cliente_o_error(&state, input.id_cliente).await?;
+
+    audit_repo::registrar(
+        &state.pool,
+        Some(uid),
+        "cliente",
+        Some(input.id_cliente),
+        "actualizar",
+        Some(&json!(antes)),
+        Some(&json!(despues)),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cliente_set_activo(
+    state: State<'_, AppState>,
+    id_cliente: i64,
+    activo: bool,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let antes = cliente_o_error(&state, id_cliente).await?;
+    sqlx::query("UPDATE cliente SET activo = ?1 WHERE id_cliente = ?2")
+        .bind(activo)
+        .bind(id_cliente)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &state.pool,
+        Some(uid),
+        "cliente",
+        Some(id_cliente),
+        if activo { "restaurar" } else { "desactivar" },
+        Some(&json!({ "activo": antes.activo })),
+        Some(&json!({ "activo": activo })),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// CONSULTAS
+
+#[tauri::command]
+pub async fn cliente_obtener(state: State<'_, AppState>, id_cliente: i64) -> Result<ClienteRow, String> {
+    requerir_sesion(&state).await?;
+    cliente_o_error(&state, id_cliente).await
+}
+
+#[tauri::command]
+pub async fn cliente_buscar(
+    state: State<'_, AppState>,
+    input: Option<ClienteBuscarInput>,
+) -> Result<Vec<ClienteRow>, String> {
+    requerir_sesion(&state).await?;
+
+    let input = input.unwrap_or_default();
+    let limit = input.limit.unwrap_or(50).clamp(1, 500);
+
+    repo::buscar(&state.pool, input.q.as_deref(), input.incluir_inactivos, limit)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cliente_historial(
+    state: State<'_, AppState>,
+    input: ClienteHistorialInput,
+) -> Result<ClienteHistorial, String> {
+    requerir_admin(&state).await?;
+
+    let cliente = cliente_o_error(&state, input.id_cliente).await?;
+    let limit = input.limit.unwrap_or(200).clamp(1, 1000);
+
+    repo::historial(&state.pool, cliente, input.desde.as_deref(), input.hasta.as_deref(), limit).await
+}
+
+// VENTA: asignar / quitar el cliente mientras la venta está en curso
+
+#[tauri::command]
+pub async fn venta_asignar_cliente(
+    state: State<'_, AppState>,
+    input: VentaClienteInput,
+) -> Result<(), String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let fila: Option<(String, Option<i64>)> =
+        sqlx::query_as("SELECT estado, id_cliente FROM venta WHERE id_venta = ?1")
+            .bind(input.id_venta)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (estado, antes) = fila.ok_or_else(|| "Venta inexistente".to_string())?;
+    if estado != "en_curso" {
+        return Err("La venta no está en curso".into());
+    }
+
+    if let Some(id_cliente) = input.id_cliente {
+        let activo: Option<i64> = sqlx::query_scalar("SELECT activo FROM cliente WHERE id_cliente = ?1")
+            .bind(id_cliente)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        match activo {
+            Some(1) => {}
+            Some(_) => return Err("El cliente está inactivo".into()),
+            None => return Err("Cliente inexistente".into()),
+        }
+    }
+
+    sqlx::query("UPDATE venta SET id_cliente = ?1 WHERE id_venta = ?2")
+        .bind(input.id_cliente)
+        .bind(input.id_venta)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "venta",
+        Some(input.id_venta),
+        "asignar_cliente",
+        Some(&json!({ "id_cliente": antes })),
+        Some(&json!({ "id_cliente": input.id_cliente })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
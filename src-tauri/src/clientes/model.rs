@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const CONDICIONES_IVA: [&str; 4] =
+    ["consumidor_final", "responsable_inscripto", "monotributo", "exento"];
+
+// INPUTS
+
+#[derive(Debug, Deserialize)]
+pub struct ClienteInput {
+    pub nombre: String,
+    pub cuit: Option<String>, // se aceptan guiones / espacios
+    pub condicion_iva: Option<String>, // default 'consumidor_final'
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+    pub direccion: Option<String>,
+    pub notas: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClienteActualizarInput {
+    pub id_cliente: i64,
+    #[serde(flatten)]
+    pub datos: ClienteInput,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ClienteBuscarInput {
+    pub q: Option<String>, // nombre, CUIT o teléfono
+    #[serde(default)]
+    pub incluir_inactivos: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClienteHistorialInput {
+    pub id_cliente: i64,
+    pub desde: Option<String>, // "YYYY-MM-DD"
+    pub hasta: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VentaClienteInput {
+    pub id_venta: i64,
+    pub id_cliente: Option<i64>, // None = quitar
+}
+
+// OUTPUTS
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ClienteRow {
+    pub id_cliente: i64,
+    pub nombre: String,
+    pub cuit: Option<String>,
+    pub condicion_iva: String,
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+    pub direccion: Option<String>,
+    pub notas: Option<String>,
+    pub activo: i64,
+    pub creado_en: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ClienteCompraRow {
+    pub id_venta: i64,
+    pub fecha_hora: String,
+    pub usuario: String,
+    pub total: i64,
+    pub cant_items: i64,
+    pub unidades: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ClienteProductoRow {
+    pub id_producto: i64,
+    pub producto: String,
+    pub unidades: i64,
+    pub importe: i64,
+    pub ultima_compra: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClienteHistorial {
+    pub cliente: ClienteRow,
+    pub compras: Vec<ClienteCompraRow>,
+    pub productos: Vec<ClienteProductoRow>, // más comprados en el rango
+    pub cantidad_compras: i64,
+    pub total_comprado: i64,
+    pub ticket_promedio: i64,
+    pub ultima_compra: Option<String>,
+}
@@ -0,0 +1,300 @@
+use sqlx::SqlitePool;
+
+use super::model::{
+    ClienteCompraRow, ClienteHistorial, ClienteInput, ClienteProductoRow, ClienteRow,
+    CONDICIONES_IVA,
+};
+
+const COLUMNAS: &str = "id_cliente, nombre, cuit, condicion_iva, telefono, email, direccion, notas, activo, creado_en";
+
+/// CUIT/CUIL (11 dígitos, con dígito verificador) o DNI (7-8 dígitos)
+pub fn normalizar_cuit(raw: &str) -> Result<String, String> {
+    let d: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+    match d.len() {
+        7 | 8 => Ok(d),
+        11 => {
+            let pesos = [5, 4, 3, 2, 7, 6, 5, 4, 3, 2];
+            let suma: u32 = d
+                .bytes()
+                .take(10)
+                .zip(pesos)
+                .map(|(b, p)| (b - b'0') as u32 * p)
+                .sum();
+            let dv = match 11 - suma % 11 {
+                11 => 0,
+                10 => 9,
+                n => n,
+            };
+            if dv == (d.as_bytes()[10] - b'0') as u32 {
+                Ok(d)
+            } else {
+                Err(format!("CUIT inválido: {raw}"))
+            }
+        }
+        _ => Err(format!("CUIT/DNI inválido: {raw}")),
+    }
+}
+
+fn opcional(v: &Option<String>) -> Option<String> {
+    v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+/// Datos listos para guardar: (nombre, cuit, condicion_iva)
+fn validar(input: &ClienteInput) -> Result<(String, Option<String>, String), String> {
+    let nombre = input.nombre.trim().to_string();
+    if nombre.is_empty() {
+        return Err("El nombre es obligatorio".into());
+    }
+    let cuit = opcional(&input.cuit).map(|c| normalizar_cuit(&c)).transpose()?;
+    let condicion = input
+        .condicion_iva
+        .as_deref()
+        .unwrap_or("consumidor_final")
+        .trim()
+        .to_lowercase();
+    if !CONDICIONES_IVA.contains(&condicion.as_str()) {
+        return Err(format!("Condición de IVA inválida: {condicion}"));
+    }
+    if condicion != "consumidor_final" && cuit.as_deref().map(str::len) != Some(11) {
+        return Err("Para esa condición de IVA el CUIT es obligatorio".into());
+    }
+    Ok((nombre, cuit, condicion))
+}
+
+fn error_unico(e: sqlx::Error) -> String {
+    match &e {
+        sqlx::Error::Database(db) if db.message().contains("UNIQUE") => {
+            "Ya existe un cliente con ese CUIT/DNI".into()
+        }
+        _ => e.to_string(),
+    }
+}
+
+pub async fn insertar(pool: &SqlitePool, input: &ClienteInput) -> Result<i64, String> {
+    let (nombre, cuit, condicion) = validar(input)?;
+
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO cliente (nombre, cuit, condicion_iva, telefono, email, direccion, notas)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        RETURNING id_cliente
+        "#,
+    )
+    .bind(nombre)
+    .bind(cuit)
+    .bind(condicion)
+    .bind(opcional(&input.telefono))
+    .bind(opcional(&input.email))
+    .bind(opcional(&input.direccion))
+    .bind(opcional(&input.notas))
+    .fetch_one(pool)
+    .await
+    .map_err(error_unico)
+}
+
+pub async fn actualizar(pool: &SqlitePool, id_cliente: i64, input: &ClienteInput) -> Result<(), String> {
+    let (nombre, cuit, condicion) = validar(input)?;
+
+    let res = sqlx::query(
+        r#"
+        UPDATE cliente
+           SET nombre = ?1, cuit = ?2, condicion_iva = ?3, telefono = ?4,
+               email = ?5, direccion = ?6, notas = ?7
+         WHERE id_cliente = ?8
+        "#,
+    )
+    .bind(nombre)
+    .bind(cuit)
+    .bind(condicion)
+    .bind(opcional(&input.telefono))
+    .bind(opcional(&input.email))
+    .bind(opcional(&input.direccion))
+    .bind(opcional(&input.notas))
+    .bind(id_cliente)
+    .execute(pool)
+    .await
+    .map_err(error_unico)?;
+
+    if res.rows_affected() == 0 {
+        return Err("Cliente inexistente".into());
+    }
+    Ok(())
+}
+
+pub async fn obtener(pool: &SqlitePool, id_cliente: i64) -> Result<Option<ClienteRow>, sqlx::Error> {
+    sqlx::query_as::<_, ClienteRow>(&format!("SELECT {COLUMNAS} FROM cliente WHERE id_cliente = ?1"))
+        .bind(id_cliente)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn buscar(
+    pool: &SqlitePool,
+    q: Option<&str>,
+    incluir_inactivos: bool,
+    limit: i64,
+) -> Result<Vec<ClienteRow>, sqlx::Error> {
+    let q = q.map(str::trim).filter(|s| !s.is_empty());
+    // el CUIT se guarda sin guiones: se busca también por los dígitos
+    let digitos: Option<String> = q
+        .map(|s| s.chars().filter(|c| c.is_ascii_digit()).collect::<String>())
+        .filter(|d| !d.is_empty());
+
+    sqlx::query_as::<_, ClienteRow>(&format!(
+        r#"
+        SELECT {COLUMNAS}
+        FROM cliente
+        WHERE (?1 = 1 OR activo = 1)
+          AND (
+            ?2 IS NULL
+            OR nombre LIKE '%' || ?2 || '%' COLLATE NOCASE
+            OR telefono LIKE '%' || ?2 || '%'
+            OR (?3 IS NOT NULL AND cuit LIKE ?3 || '%')
+          )
+        ORDER BY nombre COLLATE NOCASE ASC
+        LIMIT ?4
+        "#
+    ))
+    .bind(incluir_inactivos)
+    .bind(q)
+    .bind(digitos)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Compras finalizadas del cliente en el rango (fechas opcionales)
+pub async fn historial(
+    pool: &SqlitePool,
+    cliente: ClienteRow,
+    desde: Option<&str>,
+    hasta: Option<&str>,
+    limit: i64,
+) -> Result<ClienteHistorial, String> {
+    let id_cliente = cliente.id_cliente;
+
+    let compras = sqlx::query_as::<_, ClienteCompraRow>(
+        r#"
+        SELECT
+          v.id_venta,
+          v.fecha_hora,
+          u.nombre AS usuario,
+          v.total,
+          COUNT(vi.id_item)              AS cant_items,
+          COALESCE(SUM(vi.cantidad), 0)  AS unidades
+        FROM venta v
+        JOIN usuario u ON u.id_usuario = v.id_usuario
+        LEFT JOIN venta_item vi ON vi.id_venta = v.id_venta
+        WHERE v.id_cliente = ?1
+          AND v.estado = 'finalizada'
+          AND (?2 IS NULL OR date(v.fecha_hora) >= date(?2))
+          AND (?3 IS NULL OR date(v.fecha_hora) <= date(?3))
+        GROUP BY v.id_venta
+        ORDER BY v.fecha_hora DESC
+        LIMIT ?4
+        "#,
+    )
+    .bind(id_cliente)
+    .bind(desde)
+    .bind(hasta)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("cliente_historial(compras): {e}"))?;
+
+    let (cantidad_compras, total_comprado, ultima_compra): (i64, i64, Option<String>) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*), COALESCE(SUM(v.total), 0), MAX(v.fecha_hora)
+        FROM venta v
+        WHERE v.id_cliente = ?1
+          AND v.estado = 'finalizada'
+          AND (?2 IS NULL OR date(v.fecha_hora) >= date(?2))
+          AND (?3 IS NULL OR date(v.fecha_hora) <= date(?3))
+        "#,
+    )
+    .bind(id_cliente)
+    .bind(desde)
+    .bind(hasta)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("cliente_historial(totales): {e}"))?;
+
+    let productos = sqlx::query_as::<_, ClienteProductoRow>(
+        r#"
+        SELECT
+          p.id_producto,
+          p.nombre AS producto,
+          SUM(vi.cantidad)  AS unidades,
+          SUM(vi.subtotal)  AS importe,
+          MAX(v.fecha_hora) AS ultima_compra
+        FROM venta v
+        JOIN venta_item vi ON vi.id_venta = v.id_venta
+        JOIN producto p    ON p.id_producto = vi.id_producto
+        WHERE v.id_cliente = ?1
+          AND v.estado = 'finalizada'
+          AND (?2 IS NULL OR date(v.fecha_hora) >= date(?2))
+          AND (?3 IS NULL OR date(v.fecha_hora) <= date(?3))
+        GROUP BY p.id_producto
+        ORDER BY importe DESC
+        LIMIT 20
+        "#,
+    )
+    .bind(id_cliente)
+    .bind(desde)
+    .bind(hasta)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("cliente_historial(productos): {e}"))?;
+
+    Ok(ClienteHistorial {
+        cliente,
+        compras,
+        productos,
+        cantidad_compras,
+        total_comprado,
+        ticket_promedio: if cantidad_compras > 0 { total_comprado / cantidad_compras } else { 0 },
+        ultima_compra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cliente(cuit: Option<&str>, condicion: &str) -> ClienteInput {
+        serde_json::from_value(serde_json::json!({
+            "nombre": "Almacén Don José", "cuit": cuit, "condicion_iva": condicion
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cuit_con_digito_verificador() {
+        assert_eq!(normalizar_cuit("20-12345678-6"), Ok("20123456786".to_string()));
+        assert_eq!(normalizar_cuit("30 71234567 1"), Ok("30712345671".to_string()));
+        assert_eq!(normalizar_cuit("20-12345678-5"), Err("CUIT inválido: 20-12345678-5".to_string()));
+        // 11 - resto: 11 => 0 y 10 => 9
+        assert!(normalizar_cuit("20300000070").is_ok());
+        assert!(normalizar_cuit("20300000029").is_ok());
+    }
+
+    #[test]
+    fn dni_o_largo_invalido() {
+        assert_eq!(normalizar_cuit("12.345.678"), Ok("12345678".to_string()));
+        assert_eq!(normalizar_cuit("1234567"), Ok("1234567".to_string()));
+        assert_eq!(normalizar_cuit("123456789"), Err("CUIT/DNI inválido: 123456789".to_string()));
+        assert!(normalizar_cuit("").is_err());
+    }
+
+    #[test]
+    fn condicion_de_iva_pide_cuit() {
+        let (_, cuit, condicion) = validar(&cliente(Some("20-12345678-6"), "responsable_inscripto")).unwrap();
+        assert_eq!((cuit.as_deref(), condicion.as_str()), (Some("20123456786"), "responsable_inscripto"));
+
+        // un DNI no alcanza fuera de consumidor final
+        let err = validar(&cliente(Some("12345678"), "monotributo")).unwrap_err();
+        assert_eq!(err, "Para esa condición de IVA el CUIT es obligatorio");
+        assert!(validar(&cliente(Some("12345678"), "consumidor_final")).is_ok());
+        assert!(validar(&cliente(None, "exento")).is_err());
+    }
+}
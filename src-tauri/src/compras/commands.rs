@@ -1,5 +1,7 @@
 use crate::AppState;
 use tauri::State;
+use crate::users::permisos::requerir_admin;
+use crate::audit::repo as audit_repo;
 
 #[tauri::command]
 pub async fn registrar_compra(
@@ -10,12 +12,18 @@ pub async fn registrar_compra(
     referencia: Option<String>,
     mantenerCosto: bool,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if cantidad <= 0 {
         return Err("La cantidad debe ser positiva".into());
     }
 
     let pool = &state.pool;
 
+    let antes = audit_repo::producto_snapshot(pool, idProducto)
+        .await
+        .map_err(|e| e.to_string())?;
+
     crate::compras::repo::registrar_compra_repo(
         pool,
         idProducto,
@@ -27,5 +35,12 @@ pub async fn registrar_compra(
     .await
     .map_err(|e| e.to_string())?;
 
+    let despues = audit_repo::producto_snapshot(pool, idProducto)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit_repo::registrar(pool, Some(uid), "producto", Some(idProducto), "compra", antes.as_ref(), despues.as_ref())
+        .await
+        .map_err(|e| e.to_string())?;
+
     Ok(())
 }
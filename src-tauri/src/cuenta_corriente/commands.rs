@@ -0,0 +1,144 @@
+use serde_json::json;
+use sqlx::SqliteConnection;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::repo as audit_repo;
+use crate::caja::repo as caja_repo;
+use crate::users::permisos::{requerir_admin, requerir_admin_o_aprobacion, requerir_sesion, AprobacionInput};
+
+use super::model::{AntiguedadSaldos, CobroOut, CobroRegistrarInput, ControlCredito, EstadoCuenta, EstadoCuentaInput};
+use super::repo;
+
+// Venta a cuenta al finalizar: cliente habilitado y dentro del límite.
+// Pasarse del límite necesita un admin (o su aprobación) y queda auditado.
+pub(crate) async fn controlar(
+    state: &AppState,
+    conn: &mut SqliteConnection,
+    uid: i64,
+    id_venta: i64,
+    aprobacion: Option<&AprobacionInput>,
+) -> Result<Option<ControlCredito>, String> {
+    let Some(control) = repo::controlar_credito(&mut *conn, id_venta).await? else {
+        return Ok(None);
+    };
+    if !control.excedido {
+        return Ok(Some(control));
+    }
+
+    let detalle = format!(
+        "La venta supera el límite de crédito del cliente (saldo {}, venta {}, límite {})",
+        control.saldo, control.cargo, control.limite_credito
+    );
+    let (_, aprobado_por) = requerir_admin_o_aprobacion(state, aprobacion)
+        .await
+        .map_err(|e| format!("{detalle}. {e}"))?;
+
+    let despues = json!({
+        "aprobado_por": aprobado_por,
+        "id_cliente": control.id_cliente,
+        "saldo": control.saldo,
+        "cargo": control.cargo,
+        "limite_credito": control.limite_credito,
+    });
+    audit_repo::registrar(&mut *conn, Some(uid), "venta", Some(id_venta), "credito_excedido", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(control))
+}
+
+#[tauri::command]
+pub async fn cliente_limite_credito_fijar(
+    state: State<'_, AppState>,
+    id_cliente: i64,
+    limite_credito: i64,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    if limite_credito < 0 {
+        return Err("El límite de crédito no puede ser negativo".into());
+    }
+
+    let anterior: Option<i64> = sqlx::query_scalar("SELECT limite_credito FROM cliente WHERE id_cliente = ?1")
+        .bind(id_cliente)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let anterior = anterior.ok_or("Cliente inexistente")?;
+
+    sqlx::query("UPDATE cliente SET limite_credito = ?1 WHERE id_cliente = ?2")
+        .bind(limite_credito)
+        .bind(id_cliente)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &state.pool,
+        Some(uid),
+        "cliente",
+        Some(id_cliente),
+        "limite_credito",
+        Some(&json!({ "limite_credito": anterior })),
+        Some(&json!({ "limite_credito": limite_credito })),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cc_estado_cuenta(
+    state: State<'_, AppState>,
+    input: EstadoCuentaInput,
+) -> Result<EstadoCuenta, String> {
+    requerir_sesion(&state).await?;
+
+    repo::estado_cuenta(&state.pool, &input)
+        .await?
+        .ok_or_else(|| "Cliente inexistente".to_string())
+}
+
+// El cobro entra por la caja abierta; en efectivo es obligatoria para que
+// el arqueo lo cuente.
+#[tauri::command]
+pub async fn cc_cobro_registrar(
+    state: State<'_, AppState>,
+    input: CobroRegistrarInput,
+) -> Result<CobroOut, String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let id_caja = caja_repo::ultima_caja_abierta_id(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if id_caja.is_none() && input.medio.trim().eq_ignore_ascii_case("efectivo") {
+        return Err("Para cobrar en efectivo tiene que haber una caja abierta".into());
+    }
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let out = repo::registrar_cobro(&mut tx, uid, id_caja, &input).await?;
+
+    let despues = json!({
+        "id_cliente": input.id_cliente,
+        "medio": input.medio.trim().to_lowercase(),
+        "monto": input.monto,
+        "id_caja": id_caja,
+        "imputaciones": out.imputaciones,
+        "a_favor": out.a_favor,
+        "saldo": out.saldo,
+    });
+    audit_repo::registrar(&mut *tx, Some(uid), "cc_cobro", Some(out.id_cobro), "registrar", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+#[tauri::command]
+pub async fn cc_antiguedad_saldos(state: State<'_, AppState>) -> Result<AntiguedadSaldos, String> {
+    requerir_admin(&state).await?;
+
+    repo::antiguedad(&state.pool).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const MEDIO_CUENTA_CORRIENTE: &str = "cuenta_corriente";
+
+// INPUTS
+
+#[derive(Debug, Deserialize)]
+pub struct ImputacionInput {
+    pub id_pago: i64, // venta_pago en cuenta corriente
+    pub monto: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CobroRegistrarInput {
+    pub id_cliente: i64,
+    pub monto: i64,
+    pub medio: String, // cualquier medio salvo 'cuenta_corriente'
+    pub referencia: Option<String>,
+    /// None = se imputa a las ventas más viejas primero.
+    /// El sobrante queda como saldo a favor.
+    pub imputaciones: Option<Vec<ImputacionInput>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EstadoCuentaInput {
+    pub id_cliente: i64,
+    pub desde: Option<String>, // "YYYY-MM-DD"; el saldo inicial acumula lo anterior
+    pub hasta: Option<String>,
+}
+
+// OUTPUTS
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CargoPendienteRow {
+    pub id_pago: i64,
+    pub id_venta: i64,
+    pub fecha_hora: String,
+    pub importe: i64,  // monto + recargo del pago
+    pub imputado: i64, // cobros aplicados
+    pub pendiente: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct MovimientoRow {
+    pub fecha_hora: String,
+    pub tipo: String, // venta | cobro | devolucion
+    pub id_referencia: i64,
+    pub importe: i64, // + cargo, - crédito
+    #[sqlx(default)]
+    pub saldo: i64,   // acumulado
+}
+
+#[derive(Debug, Serialize)]
+pub struct EstadoCuenta {
+    pub id_cliente: i64,
+    pub cliente: String,
+    pub limite_credito: i64,
+    pub saldo: i64,      // > 0 = debe
+    pub disponible: i64, // límite - saldo (puede ser negativo)
+    pub saldo_inicial: i64,
+    pub movimientos: Vec<MovimientoRow>,
+    pub pendientes: Vec<CargoPendienteRow>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ImputacionRow {
+    pub id_pago: i64,
+    pub id_venta: i64,
+    pub monto: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CobroOut {
+    pub id_cobro: i64,
+    pub imputaciones: Vec<ImputacionRow>,
+    pub a_favor: i64, // parte del cobro sin imputar
+    pub saldo: i64,   // saldo del cliente después del cobro
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AntiguedadRow {
+    pub id_cliente: i64,
+    pub cliente: String,
+    pub limite_credito: i64,
+    pub saldo: i64,
+    pub d0_30: i64,
+    pub d31_60: i64,
+    pub d60_mas: i64,
+    pub a_favor: i64, // crédito sin aplicar (saldo negativo)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AntiguedadSaldos {
+    pub clientes: Vec<AntiguedadRow>,
+    pub total_saldo: i64,
+    pub total_0_30: i64,
+    pub total_31_60: i64,
+    pub total_60_mas: i64,
+}
+
+/// Resultado del control de crédito al vender a cuenta.
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlCredito {
+    pub id_cliente: i64,
+    pub limite_credito: i64,
+    pub saldo: i64, // antes de esta venta
+    pub cargo: i64,
+    pub excedido: bool,
+}
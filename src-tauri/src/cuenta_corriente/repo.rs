@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::model::{
+    AntiguedadRow, AntiguedadSaldos, CargoPendienteRow, CobroOut, CobroRegistrarInput, ControlCredito,
+    EstadoCuenta, EstadoCuentaInput, ImputacionRow, MovimientoRow, MEDIO_CUENTA_CORRIENTE,
+};
+
+// Cargos (pagos en cuenta corriente de ventas finalizadas) con lo que ya se
+// les imputó. Sale de v_cc_movimiento, así que cuadra con venta_pago.
+const SQL_CARGOS: &str = r#"
+    SELECT id_pago, id_venta, fecha_hora, importe, imputado, importe - imputado AS pendiente
+      FROM (
+        SELECT m.id_pago,
+               m.id_referencia AS id_venta,
+               m.fecha_hora,
+               m.importe,
+               COALESCE((SELECT SUM(i.monto) FROM cc_imputacion i WHERE i.id_pago = m.id_pago), 0) AS imputado
+          FROM v_cc_movimiento m
+         WHERE m.tipo = 'venta'
+           AND m.id_cliente = ?1
+      )
+     WHERE importe - imputado > 0
+     ORDER BY fecha_hora, id_pago
+"#;
+
+/// Saldo de la cuenta: > 0 el cliente debe, < 0 tiene saldo a favor.
+pub async fn saldo(conn: &mut SqliteConnection, id_cliente: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(SUM(importe), 0) FROM v_cc_movimiento WHERE id_cliente = ?1")
+        .bind(id_cliente)
+        .fetch_one(&mut *conn)
+        .await
+}
+
+pub async fn cargos_pendientes(
+    conn: &mut SqliteConnection,
+    id_cliente: i64,
+) -> Result<Vec<CargoPendienteRow>, sqlx::Error> {
+    sqlx::query_as::<_, CargoPendienteRow>(SQL_CARGOS)
+        .bind(id_cliente)
+        .fetch_all(&mut *conn)
+        .await
+}
+
+/// Crédito que no está aplicado a ninguna venta: cobros sin imputar (o
+/// imputados a ventas que después se anularon) + reintegros a la cuenta.
+async fn credito_libre(conn: &mut SqliteConnection, id_cliente: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE((SELECT SUM(c.monto) FROM cc_cobro c WHERE c.id_cliente = ?1), 0)
+             - COALESCE((
+                 SELECT SUM(i.monto)
+                   FROM cc_imputacion i
+                   JOIN v_cc_movimiento m ON m.tipo = 'venta' AND m.id_pago = i.id_pago
+                  WHERE m.id_cliente = ?1
+               ), 0)
+             - COALESCE((SELECT SUM(m.importe) FROM v_cc_movimiento m
+                          WHERE m.id_cliente = ?1 AND m.tipo = 'devolucion'), 0)
+        "#,
+    )
+    .bind(id_cliente)
+    .fetch_one(&mut *conn)
+    .await
+}
+
+/// Control de crédito de una venta en curso que ya tiene sus pagos cargados.
+/// None si no usa cuenta corriente. Falla si la venta no tiene cliente o el
+/// cliente no tiene cuenta habilitada; el exceso de límite lo decide quien llama.
+pub async fn controlar_credito(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<Option<ControlCredito>, String> {
+    let cargo: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(monto + recargo), 0) FROM venta_pago WHERE id_venta = ?1 AND medio = ?2",
+    )
+    .bind(id_venta)
+    .bind(MEDIO_CUENTA_CORRIENTE)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if cargo <= 0 {
+        return Ok(None);
+    }
+
+    let id_cliente: Option<i64> = sqlx::query_scalar("SELECT id_cliente FROM venta WHERE id_venta = ?1")
+        .bind(id_venta)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let id_cliente =
+        id_cliente.ok_or("Para vender en cuenta corriente hay que asignar un cliente a la venta")?;
+
+    let (activo, limite_credito): (i64, i64) =
+        sqlx::query_as("SELECT activo, limite_credito FROM cliente WHERE id_cliente = ?1")
+            .bind(id_cliente)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    if activo != 1 {
+        return Err("El cliente está inactivo".into());
+    }
+    if limite_credito <= 0 {
+        return Err("El cliente no tiene cuenta corriente habilitada".into());
+    }
+
+    // la venta todavía está en curso: el saldo no la incluye
+    let saldo = saldo(conn, id_cliente).await.map_err(|e| e.to_string())?;
+
+    Ok(Some(ControlCredito {
+        id_cliente,
+        limite_credito,
+        saldo,
+        cargo,
+        excedido: saldo + cargo > limite_credito,
+    }))
+}
+
+pub async fn estado_cuenta(pool: &SqlitePool, input: &EstadoCuentaInput) -> Result<Option<EstadoCuenta>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+
+    let cliente: Option<(String, i64)> =
+        sqlx::query_as("SELECT nombre, limite_credito FROM cliente WHERE id_cliente = ?1")
+            .bind(input.id_cliente)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    let Some((nombre, limite_credito)) = cliente else {
+        return Ok(None);
+    };
+
+    let todos = sqlx::query_as::<_, MovimientoRow>(
+        "SELECT fecha_hora, tipo, id_referencia, importe
+           FROM v_cc_movimiento
+          WHERE id_cliente = ?1
+          ORDER BY fecha_hora, id_referencia",
+    )
+    .bind(input.id_cliente)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut saldo = 0i64;
+    let mut saldo_inicial = 0i64;
+    let mut movimientos = Vec::new();
+    for mut m in todos {
+        saldo += m.importe;
+        m.saldo = saldo;
+        let dia = m.fecha_hora.get(..10).unwrap_or(&m.fecha_hora);
+        if input.desde.as_deref().is_some_and(|d| dia < d) {
+            saldo_inicial = saldo;
+            continue;
+        }
+        if input.hasta.as_deref().is_some_and(|h| dia > h) {
+            continue;
+        }
+        movimientos.push(m);
+    }
+
+    let pendientes = cargos_pendientes(&mut conn, input.id_cliente)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(EstadoCuenta {
+        id_cliente: input.id_cliente,
+        cliente: nombre,
+        limite_credito,
+        saldo,
+        disponible: limite_credito - saldo,
+        saldo_inicial,
+        movimientos,
+        pendientes,
+    }))
+}
+
+/// Registra el cobro y lo imputa a las ventas indicadas (o a las más viejas).
+pub async fn registrar_cobro(
+    conn: &mut SqliteConnection,
+    id_usuario: i64,
+    id_caja: Option<i64>,
+    input: &CobroRegistrarInput,
+) -> Result<CobroOut, String> {
+    if input.monto <= 0 {
+        return Err("El monto del cobro debe ser mayor a 0".into());
+    }
+    let medio = input.medio.trim().to_lowercase();
+    if medio == MEDIO_CUENTA_CORRIENTE {
+        return Err("Un cobro de cuenta corriente no puede hacerse a cuenta corriente".into());
+    }
+    let activo: Option<i64> = sqlx::query_scalar("SELECT activo FROM medio_pago WHERE codigo = ?1")
+        .bind(&medio)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match activo {
+        None => return Err(format!("Medio de pago inexistente: '{medio}'")),
+        Some(0) => return Err(format!("El medio de pago '{medio}' está inactivo")),
+        Some(_) => {}
+    }
+
+    let existe: Option<i64> = sqlx::query_scalar("SELECT id_cliente FROM cliente WHERE id_cliente = ?1")
+        .bind(input.id_cliente)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if existe.is_none() {
+        return Err("Cliente inexistente".into());
+    }
+
+    let pendientes = cargos_pendientes(conn, input.id_cliente)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // (id_pago, monto) a imputar
+    let mut aplicar: Vec<(i64, i64)> = Vec::new();
+    match &input.imputaciones {
+        Some(imputaciones) => {
+            for imp in imputaciones {
+                if imp.monto <= 0 {
+                    return Err("Monto de imputación inválido (> 0)".into());
+                }
+                let cargo = pendientes
+                    .iter()
+                    .find(|c| c.id_pago == imp.id_pago)
+                    .ok_or_else(|| format!("El pago {} no es un saldo pendiente del cliente", imp.id_pago))?;
+                let ya: i64 = aplicar.iter().filter(|(id, _)| *id == imp.id_pago).map(|(_, m)| m).sum();
+                if ya + imp.monto > cargo.pendiente {
+                    return Err(format!(
+                        "La venta {} tiene {} pendiente: no se le pueden imputar {}",
+                        cargo.id_venta, cargo.pendiente, ya + imp.monto
+                    ));
+                }
+                aplicar.push((imp.id_pago, imp.monto));
+            }
+            let total: i64 = aplicar.iter().map(|(_, m)| m).sum();
+            if total > input.monto {
+                return Err(format!("Las imputaciones ({total}) superan el monto cobrado ({})", input.monto));
+            }
+        }
+        None => {
+            let mut resto = input.monto;
+            for c in &pendientes {
+                if resto == 0 {
+                    break;
+                }
+                let m = resto.min(c.pendiente);
+                aplicar.push((c.id_pago, m));
+                resto -= m;
+            }
+        }
+    }
+
+    let referencia = input
+        .referencia
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty());
+
+    let id_cobro = sqlx::query(
+        "INSERT INTO cc_cobro (id_cliente, medio, monto, referencia, id_caja, id_usuario)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )
+    .bind(input.id_cliente)
+    .bind(&medio)
+    .bind(input.monto)
+    .bind(referencia)
+    .bind(id_caja)
+    .bind(id_usuario)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+
+    let mut imputaciones = Vec::with_capacity(aplicar.len());
+    for (id_pago, monto) in aplicar {
+        sqlx::query("INSERT INTO cc_imputacion (id_cobro, id_pago, monto) VALUES (?1, ?2, ?3)")
+            .bind(id_cobro)
+            .bind(id_pago)
+            .bind(monto)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let id_venta = pendientes
+            .iter()
+            .find(|c| c.id_pago == id_pago)
+            .map(|c| c.id_venta)
+            .unwrap_or_default();
+        imputaciones.push(ImputacionRow { id_pago, id_venta, monto });
+    }
+
+    let imputado: i64 = imputaciones.iter().map(|i| i.monto).sum();
+    let saldo = saldo(conn, input.id_cliente).await.map_err(|e| e.to_string())?;
+
+    Ok(CobroOut {
+        id_cobro,
+        imputaciones,
+        a_favor: input.monto - imputado,
+        saldo,
+    })
+}
+
+/// Antigüedad de saldos al día de hoy. El crédito libre (cobros sin imputar,
+/// reintegros) se aplica primero a las deudas más viejas.
+pub async fn antiguedad(pool: &SqlitePool) -> Result<AntiguedadSaldos, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+
+    let clientes: Vec<(i64, String, i64)> = sqlx::query_as(
+        "SELECT c.id_cliente, c.nombre, c.limite_credito
+           FROM cliente c
+          WHERE EXISTS (SELECT 1 FROM v_cc_movimiento m WHERE m.id_cliente = c.id_cliente)
+          ORDER BY c.nombre COLLATE NOCASE",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // días de cada cargo pendiente
+    let dias: HashMap<i64, i64> = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT id_pago,
+                CAST(julianday('now','localtime') - julianday(fecha_hora) AS INTEGER)
+           FROM v_cc_movimiento
+          WHERE tipo = 'venta'",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut out = AntiguedadSaldos::default();
+    for (id_cliente, cliente, limite_credito) in clientes {
+        let pendientes = cargos_pendientes(&mut conn, id_cliente).await?;
+        let mut libre = credito_libre(&mut conn, id_cliente).await?;
+
+        let mut fila = AntiguedadRow {
+            id_cliente,
+            cliente,
+            limite_credito,
+            ..Default::default()
+        };
+        for c in &pendientes {
+            let aplicado = libre.clamp(0, c.pendiente);
+            libre -= aplicado;
+            let resto = c.pendiente - aplicado;
+            match dias.get(&c.id_pago).copied().unwrap_or(0) {
+                d if d <= 30 => fila.d0_30 += resto,
+                d if d <= 60 => fila.d31_60 += resto,
+                _ => fila.d60_mas += resto,
+            }
+        }
+        fila.a_favor = libre.max(0);
+        fila.saldo = fila.d0_30 + fila.d31_60 + fila.d60_mas - fila.a_favor;
+
+        if fila.saldo == 0 && fila.a_favor == 0 {
+            continue;
+        }
+        out.total_saldo += fila.saldo;
+        out.total_0_30 += fila.d0_30;
+        out.total_31_60 += fila.d31_60;
+        out.total_60_mas += fila.d60_mas;
+        out.clientes.push(fila);
+    }
+
+    Ok(out)
+}
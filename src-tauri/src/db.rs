@@ -64,3 +64,13 @@ pub async fn optimize_sqlite(pool: &SqlitePool) -> anyhow::Result<()> {
     sqlx::query("PRAGMA optimize;").execute(pool).await?;
     Ok(())
 }
+
+// Base descartable para tests: archivo nuevo en el temp del sistema, con la
+// misma configuración y migraciones que la app (varias conexiones, WAL).
+#[cfg(test)]
+pub async fn pool_prueba() -> SqlitePool {
+    let path = env::temp_dir().join(format!("ventas-prueba-{}.db", uuid::Uuid::new_v4()));
+    init_db_with_url(&format!("sqlite:{}", path.to_string_lossy()))
+        .await
+        .expect("base de prueba")
+}
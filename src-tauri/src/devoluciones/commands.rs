@@ -0,0 +1,56 @@
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::caja::repo as caja_repo;
+use crate::fiscal::commands as fiscal;
+use crate::users::permisos::requerir_sesion;
+
+use super::model::{DevolucionCrearInput, DevolucionOut, DevolucionRow, ItemDevolvible};
+use super::repo;
+
+/// Qué se puede devolver de una venta (cantidades pendientes por línea)
+#[tauri::command]
+pub async fn devolucion_items_venta(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<Vec<ItemDevolvible>, String> {
+    requerir_sesion(&state).await?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+    repo::items_devolvibles(&mut conn, id_venta)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Devolución parcial o total. El reintegro sale de la caja abierta.
+#[tauri::command]
+pub async fn devolucion_crear(
+    state: State<'_, AppState>,
+    input: DevolucionCrearInput,
+) -> Result<DevolucionOut, String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let id_caja = caja_repo::ultima_caja_abierta_id(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No hay caja abierta".to_string())?;
+
+    let out = repo::crear_devolucion(&state.pool, id_caja, uid, input).await?;
+
+    // venta facturada: la NC queda en cola
+    fiscal::encolar_nota_credito(&state.pool, out.id_devolucion, uid).await;
+
+    Ok(out)
+}
+
+#[tauri::command]
+pub async fn devolucion_listar_por_venta(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<Vec<DevolucionRow>, String> {
+    requerir_sesion(&state).await?;
+
+    repo::devoluciones_por_venta(&state.pool, id_venta)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// INPUTS
+
+#[derive(Debug, Deserialize)]
+pub struct DevolucionItemInput {
+    pub id_item: i64, // venta_item original
+    pub cantidad: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DevolucionPagoInput {
+    pub medio: String,
+    pub monto: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DevolucionCrearInput {
+    pub id_venta: i64,
+    /// None => se devuelve todo lo que quede pendiente de la venta
+    pub items: Option<Vec<DevolucionItemInput>>,
+    /// Cómo se reintegra el dinero. Vacío => todo en efectivo.
+    #[serde(default)]
+    pub reintegros: Vec<DevolucionPagoInput>,
+    pub motivo: Option<String>,
+}
+
+// OUTPUTS
+
+#[derive(Debug, Serialize)]
+pub struct DevolucionOut {
+    pub id_devolucion: i64,
+    pub total: i64,
+}
+
+/// Línea de la venta con lo que todavía se puede devolver
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ItemDevolvible {
+    pub id_item: i64,
+    pub id_producto: i64,
+    pub nombre: String,
+    pub cantidad_vendida: i64,
+    pub cantidad_devuelta: i64,
+    pub cantidad_disponible: i64,
+    pub precio_unitario: i64,
+    pub subtotal: i64, // neto de descuentos
+    pub costo_unitario_en_venta: i64,
+    pub gramos: Option<i64>, // etiqueta de balanza: el stock va en gramos
+}
+
+impl ItemDevolvible {
+    /// Importe a reintegrar por `cantidad` unidades cuando ya se devolvieron
+    /// `ya_devuelta`. Prorratea el subtotal neto acumulado, así la suma de
+    /// devoluciones parciales nunca supera lo cobrado en la línea.
+    pub fn importe_a_devolver(&self, ya_devuelta: i64, cantidad: i64) -> i64 {
+        if self.cantidad_vendida <= 0 {
+            return 0;
+        }
+        let acumulado = |n: i64| self.subtotal * n / self.cantidad_vendida;
+        acumulado(ya_devuelta + cantidad) - acumulado(ya_devuelta)
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DevolucionRow {
+    pub id_devolucion: i64,
+    pub id_venta: i64,
+    pub id_caja: i64,
+    pub id_usuario: i64,
+    pub usuario: String,
+    pub fecha_hora: String,
+    pub motivo: Option<String>,
+    pub total: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linea(cantidad_vendida: i64, subtotal: i64) -> ItemDevolvible {
+        ItemDevolvible {
+            id_item: 1,
+            id_producto: 1,
+            nombre: "yerba".into(),
+            cantidad_vendida,
+            cantidad_devuelta: 0,
+            cantidad_disponible: cantidad_vendida,
+            precio_unitario: 0,
+            subtotal,
+            costo_unitario_en_venta: 0,
+            gramos: None,
+        }
+    }
+
+    #[test]
+    fn parciales_suman_lo_cobrado() {
+        // 3 unidades por 1000 neto: 333 + 333 + 334
+        let it = linea(3, 1000);
+        let partes: Vec<i64> = (0..3).map(|ya| it.importe_a_devolver(ya, 1)).collect();
+        assert_eq!(partes, vec![333, 333, 334]);
+        assert_eq!(it.importe_a_devolver(0, 3), 1000);
+        assert_eq!(it.importe_a_devolver(1, 2), 667);
+    }
+
+    #[test]
+    fn linea_sin_cantidad_no_devuelve() {
+        assert_eq!(linea(0, 500).importe_a_devolver(0, 1), 0);
+    }
+}
@@ -0,0 +1,416 @@
+use serde_json::json;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::audit::repo as audit_repo;
+use crate::stock::repo as stock_repo;
+
+use super::model::{
+    DevolucionCrearInput, DevolucionOut, DevolucionRow, ItemDevolvible,
+};
+
+pub async fn items_devolvibles(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<Vec<ItemDevolvible>, sqlx::Error> {
+    sqlx::query_as::<_, ItemDevolvible>(
+        r#"
+        SELECT
+          vi.id_item,
+          vi.id_producto,
+          p.nombre,
+          vi.cantidad AS cantidad_vendida,
+          COALESCE(d.devuelta, 0) AS cantidad_devuelta,
+          vi.cantidad - COALESCE(d.devuelta, 0) AS cantidad_disponible,
+          vi.precio_unitario,
+          vi.subtotal,
+          vi.costo_unitario_en_venta,
+          vi.gramos
+        FROM venta_item vi
+        JOIN producto p ON p.id_producto = vi.id_producto
+        LEFT JOIN (
+          SELECT id_item, SUM(cantidad) AS devuelta
+          FROM devolucion_item
+          GROUP BY id_item
+        ) d ON d.id_item = vi.id_item
+        WHERE vi.id_venta = ?1
+        ORDER BY vi.id_item ASC
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Registra la devolución completa en una transacción:
+/// devolucion + items + reintegros + stock_mov (motivo 'devolucion').
+pub async fn crear_devolucion(
+    pool: &SqlitePool,
+    id_caja: i64,
+    id_usuario: i64,
+    input: DevolucionCrearInput,
+) -> Result<DevolucionOut, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let estado: Option<String> = sqlx::query_scalar("SELECT estado FROM venta WHERE id_venta = ?1")
+        .bind(input.id_venta)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match estado.as_deref() {
+        None => return Err("Venta inexistente".into()),
+        Some("finalizada") => {}
+        Some(_) => return Err("Sólo se pueden devolver ventas finalizadas".into()),
+    }
+
+    let disponibles = items_devolvibles(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // (linea, cantidad a devolver)
+    let mut a_devolver: Vec<(&ItemDevolvible, i64)> = Vec::new();
+    match &input.items {
+        None => {
+            for it in disponibles.iter().filter(|it| it.cantidad_disponible > 0) {
+                a_devolver.push((it, it.cantidad_disponible));
+            }
+        }
+        Some(items) => {
+            for req in items {
+                if req.cantidad <= 0 {
+                    return Err("Cantidad a devolver inválida".into());
+                }
+                let it = disponibles
+                    .iter()
+                    .find(|d| d.id_item == req.id_item)
+                    .ok_or_else(|| format!("El item {} no pertenece a la venta", req.id_item))?;
+
+                let ya_pedida: i64 = a_devolver
+                    .iter()
+                    .filter(|(d, _)| d.id_item == it.id_item)
+                    .map(|(_, c)| *c)
+                    .sum();
+                if ya_pedida + req.cantidad > it.cantidad_disponible {
+                    return Err(format!(
+                        "No se pueden devolver {} de '{}': quedan {} sin devolver",
+                        ya_pedida + req.cantidad, it.nombre, it.cantidad_disponible
+                    ));
+                }
+                a_devolver.push((it, req.cantidad));
+            }
+        }
+    }
+
+    if a_devolver.is_empty() {
+        return Err("No hay nada para devolver en esta venta".into());
+    }
+
+    // Importe neto (con descuentos) de cada renglón a devolver
+    let mut devuelta_en_curso: std::collections::HashMap<i64, i64> = std::collections::HashMap::new();
+    let importes: Vec<i64> = a_devolver
+        .iter()
+        .map(|(it, cant)| {
+            let previa = devuelta_en_curso.entry(it.id_item).or_insert(0);
+            let importe = it.importe_a_devolver(it.cantidad_devuelta + *previa, *cant);
+            *previa += cant;
+            importe
+        })
+        .collect();
+    let total: i64 = importes.iter().sum();
+
+    // Reintegros: por defecto, todo en efectivo
+    let reintegros: Vec<(String, i64)> = if input.reintegros.is_empty() {
+        if total > 0 { vec![("efectivo".to_string(), total)] } else { vec![] }
+    } else {
+        input
+            .reintegros
+            .iter()
+            .map(|r| (r.medio.trim().to_lowercase(), r.monto))
+            .collect()
+    };
+    // los medios son los de medio_pago; 'cuenta_corriente' acredita el
+    // importe en la cuenta del cliente de la venta
+    for (medio, monto) in &reintegros {
+        let activo: Option<i64> = sqlx::query_scalar("SELECT activo FROM medio_pago WHERE codigo = ?1")
+            .bind(medio)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        match activo {
+            None => return Err(format!("Medio de pago inválido: {medio}")),
+            Some(a) if a != 1 => return Err(format!("El medio de pago '{medio}' está inactivo")),
+            Some(_) => {}
+        }
+        if *monto <= 0 {
+            return Err("Monto de reintegro inválido (> 0)".into());
+        }
+    }
+    if reintegros.iter().any(|(medio, _)| medio == "cuenta_corriente") {
+        let id_cliente: Option<i64> =
+            sqlx::query_scalar("SELECT id_cliente FROM venta WHERE id_venta = ?1")
+                .bind(input.id_venta)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        if id_cliente.is_none() {
+            return Err("La venta no tiene cliente: no se puede acreditar en cuenta corriente".into());
+        }
+    }
+    let suma_reintegros: i64 = reintegros.iter().map(|(_, m)| m).sum();
+    if suma_reintegros != total {
+        return Err(format!(
+            "La suma de los reintegros ({suma_reintegros}) no coincide con el total a devolver ({total})"
+        ));
+    }
+
+    let motivo = input
+        .motivo
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty());
+
+    let id_devolucion: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO devolucion (id_venta, id_caja, id_usuario, motivo, total)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        RETURNING id_devolucion
+        "#,
+    )
+    .bind(input.id_venta)
+    .bind(id_caja)
+    .bind(id_usuario)
+    .bind(&motivo)
+    .bind(total)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("insert devolucion: {e}"))?;
+
+    let referencia = format!("devolucion:{id_devolucion}");
+
+    for ((it, cant), importe) in a_devolver.iter().zip(&importes) {
+        sqlx::query(
+            r#"
+            INSERT INTO devolucion_item (
+              id_devolucion, id_item, id_producto, cantidad,
+              precio_unitario, costo_unitario, subtotal
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(id_devolucion)
+        .bind(it.id_item)
+        .bind(it.id_producto)
+        .bind(cant)
+        .bind(importe / cant)
+        .bind(it.costo_unitario_en_venta)
+        .bind(importe)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("insert devolucion_item: {e}"))?;
+
+        // vuelve al stock al costo con que salió
+        let gramos = it.gramos.unwrap_or(1);
+        let (costo_unitario, total_costo) =
+            stock_repo::costo_movimiento(it.costo_unitario_en_venta, gramos, cant * gramos, it.gramos.is_some());
+        sqlx::query(
+            r#"
+            INSERT INTO stock_mov (
+              id_producto, cantidad_delta, motivo, referencia,
+              costo_unitario, total_costo, fecha_hora
+            )
+            VALUES (?1, ?2, 'devolucion', ?3, ?4, ?5, DATETIME('now','localtime'))
+            "#,
+        )
+        .bind(it.id_producto)
+        .bind(cant * gramos)
+        .bind(&referencia)
+        .bind(costo_unitario)
+        .bind(total_costo)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("insert stock_mov: {e}"))?;
+    }
+
+    for (medio, monto) in &reintegros {
+        sqlx::query(
+            "INSERT INTO devolucion_pago (id_devolucion, medio, monto) VALUES (?1, ?2, ?3)",
+        )
+        .bind(id_devolucion)
+        .bind(medio)
+        .bind(monto)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("insert devolucion_pago: {e}"))?;
+    }
+
+    let despues = json!({
+        "id_venta": input.id_venta,
+        "id_caja": id_caja,
+        "motivo": motivo,
+        "total": total,
+        "items": a_devolver.iter()
+            .map(|(it, cant)| json!({ "id_item": it.id_item, "id_producto": it.id_producto, "cantidad": cant }))
+            .collect::<Vec<_>>(),
+        "reintegros": reintegros.iter()
+            .map(|(medio, monto)| json!({ "medio": medio, "monto": monto }))
+            .collect::<Vec<_>>(),
+    });
+    audit_repo::registrar(&mut *tx, Some(id_usuario), "devolucion", Some(id_devolucion), "crear", None, Some(&despues))
+        .await
+        .map_err(|e| format!("audit devolucion: {e}"))?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(DevolucionOut { id_devolucion, total })
+}
+
+pub async fn devoluciones_por_venta(
+    pool: &SqlitePool,
+    id_venta: i64,
+) -> Result<Vec<DevolucionRow>, sqlx::Error> {
+    sqlx::query_as::<_, DevolucionRow>(
+        r#"
+        SELECT d.id_devolucion, d.id_venta, d.id_caja, d.id_usuario,
+               u.nombre AS usuario, d.fecha_hora, d.motivo, d.total
+        FROM devolucion d
+        JOIN usuario u ON u.id_usuario = d.id_usuario
+        WHERE d.id_venta = ?1
+        ORDER BY d.fecha_hora ASC, d.id_devolucion ASC
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn venta_tiene_devoluciones(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<bool, sqlx::Error> {
+    let n: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM devolucion WHERE id_venta = ?1")
+        .bind(id_venta)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(n > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+    use crate::AppState;
+
+    fn pedido(v: serde_json::Value) -> DevolucionCrearInput {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[tokio::test]
+    async fn parcial_y_luego_el_resto() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let leche = prueba::producto(pool, "leche", 500, 300, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 3), (leche, 1)], json!([
+            { "medio": "efectivo", "monto": 1400 }
+        ]))
+        .await;
+
+        let mut conn = pool.acquire().await.unwrap();
+        let lineas = items_devolvibles(&mut conn, id_venta).await.unwrap();
+        drop(conn);
+        let item_pan = lineas.iter().find(|l| l.id_producto == pan).unwrap().id_item;
+
+        let out = crear_devolucion(pool, id_caja, 2, pedido(json!({
+            "id_venta": id_venta,
+            "items": [{ "id_item": item_pan, "cantidad": 2 }],
+            "reintegros": [{ "medio": "debito", "monto": 600 }]
+        })))
+        .await
+        .unwrap();
+        assert_eq!(out.total, 600);
+        assert_eq!(prueba::stock(pool, pan).await, 9);
+
+        // no se puede devolver más de lo que queda
+        let err = crear_devolucion(pool, id_caja, 2, pedido(json!({
+            "id_venta": id_venta, "items": [{ "id_item": item_pan, "cantidad": 2 }]
+        })))
+        .await
+        .unwrap_err();
+        assert!(err.contains("quedan 1"), "{err}");
+
+        // sin items: todo lo pendiente, reintegrado en efectivo
+        let resto = crear_devolucion(pool, id_caja, 2, pedido(json!({ "id_venta": id_venta })))
+            .await
+            .unwrap();
+        assert_eq!(resto.total, 800);
+        assert_eq!(prueba::stock(pool, pan).await, 10);
+        assert_eq!(prueba::stock(pool, leche).await, 10);
+        assert_eq!(devoluciones_por_venta(pool, id_venta).await.unwrap().len(), 2);
+
+        let err = crear_devolucion(pool, id_caja, 2, pedido(json!({ "id_venta": id_venta })))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "No hay nada para devolver en esta venta");
+    }
+
+    #[tokio::test]
+    async fn reintegros_validados() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 1)], json!([{ "medio": "efectivo", "monto": 300 }])).await;
+
+        let err = crear_devolucion(pool, id_caja, 2, pedido(json!({
+            "id_venta": id_venta, "reintegros": [{ "medio": "efectivo", "monto": 200 }]
+        })))
+        .await
+        .unwrap_err();
+        assert!(err.contains("no coincide"), "{err}");
+
+        // la venta no tiene cliente
+        let err = crear_devolucion(pool, id_caja, 2, pedido(json!({
+            "id_venta": id_venta, "reintegros": [{ "medio": "cuenta_corriente", "monto": 300 }]
+        })))
+        .await
+        .unwrap_err();
+        assert!(err.contains("no tiene cliente"), "{err}");
+
+        // los medios salen de la tabla
+        let err = crear_devolucion(pool, id_caja, 2, pedido(json!({
+            "id_venta": id_venta, "reintegros": [{ "medio": "cheque", "monto": 300 }]
+        })))
+        .await
+        .unwrap_err();
+        assert_eq!(err, "Medio de pago inválido: cheque");
+
+        // un carrito en curso no se devuelve
+        let carrito = prueba::carrito(&state, &[(pan, 1)]).await;
+        let err = crear_devolucion(pool, id_caja, 2, pedido(json!({ "id_venta": carrito })))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "Sólo se pueden devolver ventas finalizadas");
+        assert_eq!(prueba::stock(pool, pan).await, 9);
+
+        // un medio dado de alta después también sirve
+        sqlx::query("INSERT INTO medio_pago (codigo, nombre, orden) VALUES ('qr', 'QR', 6)")
+            .execute(pool)
+            .await
+            .unwrap();
+        crear_devolucion(pool, id_caja, 2, pedido(json!({
+            "id_venta": id_venta, "reintegros": [{ "medio": "qr", "monto": 300 }]
+        })))
+        .await
+        .unwrap();
+    }
+}
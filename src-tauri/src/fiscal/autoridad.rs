@@ -0,0 +1,209 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::model::{RespuestaCae, SolicitudCae};
+
+// Cliente de la autoridad fiscal. Es bloqueante: la cola lo llama desde
+// spawn_blocking. Cualquier implementación (servicio real, mock local) entra
+// detrás de este trait.
+
+#[derive(Debug, Clone)]
+pub enum ErrorAutoridad {
+    /// Red caída, timeout, servicio con error: se reintenta más tarde.
+    Transitorio(String),
+    /// La autoridad rechazó el comprobante: no tiene sentido reintentar.
+    Rechazo(String),
+}
+
+impl fmt::Display for ErrorAutoridad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorAutoridad::Transitorio(e) => write!(f, "{e}"),
+            ErrorAutoridad::Rechazo(e) => write!(f, "Rechazado: {e}"),
+        }
+    }
+}
+
+pub trait AutoridadFiscal: Send + Sync {
+    /// Último número autorizado para el punto de venta y tipo de comprobante.
+    fn ultimo_autorizado(&self, cuit: &str, punto_venta: i64, cbte_tipo: i64) -> Result<i64, ErrorAutoridad>;
+
+    /// Pide el CAE. El número de la solicitud tiene que ser el siguiente al último autorizado.
+    fn solicitar_cae(&self, solicitud: &SolicitudCae) -> Result<RespuestaCae, ErrorAutoridad>;
+
+    /// Busca un comprobante ya autorizado (para recuperar el CAE si la
+    /// respuesta anterior se perdió). None si no existe.
+    fn consultar(
+        &self,
+        cuit: &str,
+        punto_venta: i64,
+        cbte_tipo: i64,
+        numero: i64,
+    ) -> Result<Option<RespuestaCae>, ErrorAutoridad>;
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Servicio HTTP/JSON (puente local hacia la autoridad, o un mock para pruebas)
+//
+//   POST {url}/ultimo_autorizado  {cuit, punto_venta, cbte_tipo}      -> {numero}
+//   POST {url}/solicitar          SolicitudCae                        -> {resultado, cae, cae_vencimiento, errores}
+//   POST {url}/consultar          {cuit, punto_venta, cbte_tipo, numero} -> {encontrado, cae, cae_vencimiento}
+//
+// resultado 'A' = aprobado, 'R' = rechazado. Sólo http://; TLS lo resuelve el puente.
+
+pub struct ClienteHttp {
+    host: String,
+    puerto: u16,
+    prefijo: String,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct RespuestaUltimo {
+    numero: i64,
+}
+
+#[derive(Deserialize)]
+struct RespuestaSolicitar {
+    resultado: String,
+    cae: Option<String>,
+    cae_vencimiento: Option<String>,
+    #[serde(default)]
+    errores: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RespuestaConsultar {
+    encontrado: bool,
+    cae: Option<String>,
+    cae_vencimiento: Option<String>,
+}
+
+impl ClienteHttp {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let resto = url
+            .trim()
+            .strip_prefix("http://")
+            .ok_or("La URL del servicio fiscal debe empezar con http://")?;
+        let (autoridad, prefijo) = match resto.find('/') {
+            Some(i) => (&resto[..i], resto[i..].trim_end_matches('/')),
+            None => (resto, ""),
+        };
+        let (host, puerto) = match autoridad.rsplit_once(':') {
+            Some((h, p)) => (h, p.parse::<u16>().map_err(|_| format!("Puerto inválido en '{url}'"))?),
+            None => (autoridad, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("URL del servicio fiscal inválida: '{url}'"));
+        }
+
+        Ok(Self {
+            host: host.to_string(),
+            puerto,
+            prefijo: prefijo.to_string(),
+            timeout: Duration::from_secs(15),
+        })
+    }
+
+    fn post(&self, ruta: &str, cuerpo: &Value) -> Result<Value, ErrorAutoridad> {
+        let red = |e: std::io::Error| ErrorAutoridad::Transitorio(format!("Servicio fiscal: {e}"));
+
+        let addr = (self.host.as_str(), self.puerto)
+            .to_socket_addrs()
+            .map_err(red)?
+            .next()
+            .ok_or_else(|| ErrorAutoridad::Transitorio(format!("No se resuelve {}", self.host)))?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout).map_err(red)?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(red)?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(red)?;
+
+        // HTTP/1.0: sin chunked ni keep-alive, la respuesta termina al cerrar
+        let body = cuerpo.to_string();
+        let pedido = format!(
+            "POST {}/{ruta} HTTP/1.0\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            self.prefijo,
+            self.host,
+            self.puerto,
+            body.len()
+        );
+        stream.write_all(pedido.as_bytes()).map_err(red)?;
+
+        let mut crudo = Vec::new();
+        stream.read_to_end(&mut crudo).map_err(red)?;
+        let texto = String::from_utf8_lossy(&crudo);
+
+        let (cabecera, cuerpo) = texto
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| ErrorAutoridad::Transitorio("Respuesta HTTP inválida".into()))?;
+        let status: u16 = cabecera
+            .lines()
+            .next()
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        if status != 200 {
+            return Err(ErrorAutoridad::Transitorio(format!(
+                "Servicio fiscal respondió {status}: {}",
+                cuerpo.trim()
+            )));
+        }
+
+        serde_json::from_str(cuerpo)
+            .map_err(|e| ErrorAutoridad::Transitorio(format!("Respuesta del servicio fiscal inválida: {e}")))
+    }
+
+    fn parsear<T: for<'de> Deserialize<'de>>(v: Value) -> Result<T, ErrorAutoridad> {
+        serde_json::from_value(v)
+            .map_err(|e| ErrorAutoridad::Transitorio(format!("Respuesta del servicio fiscal inválida: {e}")))
+    }
+}
+
+impl AutoridadFiscal for ClienteHttp {
+    fn ultimo_autorizado(&self, cuit: &str, punto_venta: i64, cbte_tipo: i64) -> Result<i64, ErrorAutoridad> {
+        let v = self.post(
+            "ultimo_autorizado",
+            &json!({ "cuit": cuit, "punto_venta": punto_venta, "cbte_tipo": cbte_tipo }),
+        )?;
+        Ok(Self::parsear::<RespuestaUltimo>(v)?.numero)
+    }
+
+    fn solicitar_cae(&self, solicitud: &SolicitudCae) -> Result<RespuestaCae, ErrorAutoridad> {
+        let cuerpo = serde_json::to_value(solicitud)
+            .map_err(|e| ErrorAutoridad::Rechazo(e.to_string()))?;
+        let r: RespuestaSolicitar = Self::parsear(self.post("solicitar", &cuerpo)?)?;
+
+        match (r.resultado.as_str(), r.cae, r.cae_vencimiento) {
+            ("A", Some(cae), Some(cae_vencimiento)) => Ok(RespuestaCae { cae, cae_vencimiento }),
+            ("R", _, _) => Err(ErrorAutoridad::Rechazo(if r.errores.is_empty() {
+                "sin detalle".into()
+            } else {
+                r.errores.join("; ")
+            })),
+            _ => Err(ErrorAutoridad::Transitorio("Respuesta sin CAE".into())),
+        }
+    }
+
+    fn consultar(
+        &self,
+        cuit: &str,
+        punto_venta: i64,
+        cbte_tipo: i64,
+        numero: i64,
+    ) -> Result<Option<RespuestaCae>, ErrorAutoridad> {
+        let v = self.post(
+            "consultar",
+            &json!({ "cuit": cuit, "punto_venta": punto_venta, "cbte_tipo": cbte_tipo, "numero": numero }),
+        )?;
+        let r: RespuestaConsultar = Self::parsear(v)?;
+
+        Ok(match (r.encontrado, r.cae, r.cae_vencimiento) {
+            (true, Some(cae), Some(cae_vencimiento)) => Some(RespuestaCae { cae, cae_vencimiento }),
+            _ => None,
+        })
+    }
+}
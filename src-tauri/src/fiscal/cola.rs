@@ -0,0 +1,291 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+
+use super::autoridad::{AutoridadFiscal, ClienteHttp, ErrorAutoridad};
+use super::model::{ColaResultado, ComprobanteRow, FiscalConfig, RespuestaCae};
+use super::repo;
+
+// Cola de autorización. Las ventas y devoluciones sólo dejan el comprobante
+// 'pendiente'; el envío corre aparte y las fallas se reintentan solas.
+
+const INTERVALO: Duration = Duration::from_secs(60);
+
+// Una sola pasada a la vez: la numeración depende del último autorizado.
+static EN_PROCESO: AtomicBool = AtomicBool::new(false);
+
+struct Turno;
+
+impl Drop for Turno {
+    fn drop(&mut self) {
+        EN_PROCESO.store(false, Ordering::Release);
+    }
+}
+
+/// Pasada periódica sobre la cola mientras viva la app.
+pub async fn trabajador(pool: SqlitePool) {
+    loop {
+        if let Err(e) = procesar_pendientes(&pool).await {
+            eprintln!("[FISCAL] {e}");
+        }
+        tokio::time::sleep(INTERVALO).await;
+    }
+}
+
+/// Dispara una pasada sin esperarla (después de cobrar o devolver).
+pub fn despachar(pool: &SqlitePool) {
+    let pool = pool.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = procesar_pendientes(&pool).await {
+            eprintln!("[FISCAL] {e}");
+        }
+    });
+}
+
+/// Envía lo que esté en cola con el servicio configurado. Sin facturación
+/// habilitada o si ya hay una pasada en curso, no hace nada.
+pub async fn procesar_pendientes(pool: &SqlitePool) -> Result<ColaResultado, String> {
+    let config = {
+        let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+        repo::leer_config(&mut conn).await?
+    };
+    if !config.habilitado || config.url.trim().is_empty() {
+        return Ok(ColaResultado::default());
+    }
+
+    let autoridad: Arc<dyn AutoridadFiscal> = Arc::new(ClienteHttp::new(&config.url)?);
+    procesar_con(pool, &config, autoridad).await
+}
+
+/// Igual que `procesar_pendientes` pero con la autoridad que se le pase.
+pub async fn procesar_con(
+    pool: &SqlitePool,
+    config: &FiscalConfig,
+    autoridad: Arc<dyn AutoridadFiscal>,
+) -> Result<ColaResultado, String> {
+    if EN_PROCESO.swap(true, Ordering::AcqRel) {
+        return Ok(ColaResultado::default());
+    }
+    let _turno = Turno;
+
+    if config.cuit.is_empty() {
+        return Err("Falta el CUIT del negocio para facturar".into());
+    }
+
+    let mut res = ColaResultado::default();
+    while let Some(c) = repo::tomar_siguiente(pool).await? {
+        res.procesados += 1;
+        match enviar(pool, config, &autoridad, &c).await {
+            Ok(r) => {
+                repo::marcar_autorizado(pool, c.id_comprobante, &r).await?;
+                res.autorizados += 1;
+            }
+            Err(ErrorAutoridad::Rechazo(e)) => {
+                repo::marcar_rechazado(pool, c.id_comprobante, &e).await?;
+                res.rechazados += 1;
+            }
+            Err(ErrorAutoridad::Transitorio(e)) => {
+                repo::marcar_reintento(pool, c.id_comprobante, &e).await?;
+                res.reintentar += 1;
+            }
+        }
+    }
+    Ok(res)
+}
+
+async fn enviar(
+    pool: &SqlitePool,
+    config: &FiscalConfig,
+    autoridad: &Arc<dyn AutoridadFiscal>,
+    c: &ComprobanteRow,
+) -> Result<RespuestaCae, ErrorAutoridad> {
+    let cuit = config.cuit.clone();
+    let (pv, cbte) = (c.punto_venta, c.cbte_tipo);
+
+    // Ya se mandó con número: puede que la autoridad lo haya autorizado y se
+    // perdiera la respuesta. Se consulta antes de pedir otro.
+    if let Some(n) = c.numero {
+        let cuit = cuit.clone();
+        if let Some(r) = bloqueante(autoridad, move |a| a.consultar(&cuit, pv, cbte, n)).await? {
+            return Ok(r);
+        }
+    }
+
+    let ultimo = {
+        let cuit = cuit.clone();
+        bloqueante(autoridad, move |a| a.ultimo_autorizado(&cuit, pv, cbte)).await?
+    };
+    let numero = ultimo + 1;
+    if c.numero != Some(numero) {
+        repo::fijar_numero(pool, c.id_comprobante, numero)
+            .await
+            .map_err(ErrorAutoridad::Transitorio)?;
+    }
+
+    let solicitud = repo::armar_solicitud(pool, c, &cuit, numero)
+        .await
+        .map_err(ErrorAutoridad::Transitorio)?;
+
+    bloqueante(autoridad, move |a| a.solicitar_cae(&solicitud)).await
+}
+
+async fn bloqueante<T, F>(autoridad: &Arc<dyn AutoridadFiscal>, f: F) -> Result<T, ErrorAutoridad>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn AutoridadFiscal) -> Result<T, ErrorAutoridad> + Send + 'static,
+{
+    let a = Arc::clone(autoridad);
+    tokio::task::spawn_blocking(move || f(a.as_ref()))
+        .await
+        .map_err(|e| ErrorAutoridad::Transitorio(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+    use tauri::Manager;
+
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::commands::{venta_anular, VentaAnularInput};
+    use crate::ventas::prueba;
+    use crate::ventas_admin::editar::{venta_admin_editar_guardar, VentaEditarGuardarInput};
+    use crate::AppState;
+
+    /// Servicio fiscal de mentira en un puerto local: numera desde 7 y
+    /// aprueba todo. Devuelve la URL y las solicitudes recibidas.
+    fn servidor_mock() -> (String, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/fiscal", listener.local_addr().unwrap());
+        let recibidas = Arc::new(Mutex::new(Vec::new()));
+        let registro = Arc::clone(&recibidas);
+
+        std::thread::spawn(move || {
+            let mut ultimo = 7;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut crudo = Vec::new();
+                let mut buf = [0u8; 4096];
+                let (cabecera, mut cuerpo) = loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    crudo.extend_from_slice(&buf[..n]);
+                    let texto = String::from_utf8_lossy(&crudo).to_string();
+                    if let Some((c, b)) = texto.split_once("\r\n\r\n") {
+                        break (c.to_string(), b.to_string());
+                    }
+                };
+                let largo: usize = cabecera
+                    .lines()
+                    .find_map(|l| l.strip_prefix("Content-Length: "))
+                    .and_then(|v| v.trim().parse().ok())
+                    .unwrap_or(0);
+                while cuerpo.len() < largo {
+                    let n = stream.read(&mut buf).unwrap();
+                    cuerpo.push_str(&String::from_utf8_lossy(&buf[..n]));
+                }
+                let pedido: Value = serde_json::from_str(&cuerpo).unwrap();
+                let ruta = cabecera.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+                let respuesta = match ruta.as_str() {
+                    "/fiscal/ultimo_autorizado" => json!({ "numero": ultimo }),
+                    "/fiscal/consultar" => json!({ "encontrado": false }),
+                    "/fiscal/solicitar" => {
+                        ultimo = pedido["numero"].as_i64().unwrap();
+                        registro.lock().unwrap().push(pedido);
+                        json!({ "resultado": "A", "cae": format!("7400{ultimo:010}"), "cae_vencimiento": "2026-10-28" })
+                    }
+                    _ => json!({}),
+                };
+                let cuerpo = respuesta.to_string();
+                write!(
+                    stream,
+                    "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{cuerpo}",
+                    cuerpo.len()
+                )
+                .unwrap();
+            }
+        });
+
+        (url, recibidas)
+    }
+
+    #[tokio::test]
+    async fn la_factura_se_autoriza_con_los_renglones_congelados() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 500, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "efectivo", "monto": 1000 }])).await;
+
+        let (url, recibidas) = servidor_mock();
+        for (clave, valor) in [("negocio_cuit", "20-12345678-6"), ("fiscal_habilitado", "1"), ("fiscal_url", &url)] {
+            sqlx::query(
+                "INSERT INTO ajuste (clave, valor) VALUES (?1, ?2)
+                 ON CONFLICT(clave) DO UPDATE SET valor = excluded.valor",
+            )
+            .bind(clave)
+            .bind(valor)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        let config = {
+            let mut conn = pool.acquire().await.unwrap();
+            let config = repo::leer_config(&mut conn).await.unwrap();
+            repo::crear_factura(&mut conn, &config, id_venta, 1).await.unwrap();
+            config
+        };
+
+        // facturada no se anula ni se edita
+        let anular: VentaAnularInput =
+            serde_json::from_value(json!({ "id_venta": id_venta, "motivo": "error" })).unwrap();
+        let err = venta_anular(state.clone(), anular).await.unwrap_err();
+        assert!(err.contains("facturada"), "{err}");
+        let edicion: VentaEditarGuardarInput = serde_json::from_value(json!({
+            "id_venta": id_venta,
+            "items": [{ "id_producto": pan, "cantidad": 3, "precio_unitario": 500, "fuente_precio": "catalogo" }],
+            "pagos": [{ "medio": "efectivo", "monto": 1500 }],
+            "motivo": "faltó uno"
+        }))
+        .unwrap();
+        let err = venta_admin_editar_guardar(state.clone(), edicion).await.unwrap_err();
+        assert!(err.contains("facturada"), "{err}");
+
+        // aunque la venta cambie mientras espera, se manda lo que se facturó
+        sqlx::query("UPDATE venta_item SET precio_unitario = 900, subtotal = 1800 WHERE id_venta = ?1")
+            .bind(id_venta)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let autoridad: Arc<dyn AutoridadFiscal> = Arc::new(ClienteHttp::new(&config.url).unwrap());
+        let res = procesar_con(pool, &config, autoridad).await.unwrap();
+        assert_eq!((res.procesados, res.autorizados, res.reintentar), (1, 1, 0));
+
+        let recibidas = recibidas.lock().unwrap().clone();
+        assert_eq!(recibidas.len(), 1);
+        let s = &recibidas[0];
+        assert_eq!((s["cbte_tipo"].as_i64(), s["numero"].as_i64()), (Some(6), Some(8)));
+        assert_eq!(s["cuit_emisor"], "20123456786");
+        assert_eq!(s["importe_total"], 1000);
+        assert_eq!(s["items"], json!([{ "descripcion": "pan", "cantidad": 2, "precio_unitario": 500, "importe": 1000 }]));
+        assert_eq!(s["alicuotas"], json!([{ "id": 5, "base_imponible": 826, "importe": 174 }]));
+
+        let (estado, numero, cae): (String, Option<i64>, Option<String>) =
+            sqlx::query_as("SELECT estado, numero, cae FROM comprobante WHERE id_venta = ?1")
+                .bind(id_venta)
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        assert_eq!((estado.as_str(), numero, cae.as_deref()), ("autorizado", Some(8), Some("74000000000008")));
+    }
+}
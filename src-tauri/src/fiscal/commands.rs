@@ -0,0 +1,211 @@
+use serde_json::json;
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::{requerir_admin, requerir_sesion};
+
+use super::autoridad::ClienteHttp;
+use super::model::{id_alicuota, ColaResultado, ComprobanteListarInput, ComprobanteRow, FiscalConfig, CONDICIONES_EMISOR};
+use super::{cola, repo};
+
+async fn comprobante_o_error(pool: &SqlitePool, id_comprobante: i64) -> Result<ComprobanteRow, String> {
+    repo::obtener(pool, id_comprobante)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Comprobante inexistente".to_string())
+}
+
+async fn auditar_alta(pool: &SqlitePool, uid: i64, id_comprobante: i64) -> Result<ComprobanteRow, String> {
+    let c = comprobante_o_error(pool, id_comprobante).await?;
+    audit_repo::registrar(pool, Some(uid), "comprobante", Some(id_comprobante), "crear", None, Some(&json!(c)))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(c)
+}
+
+// Automáticos: con la facturación habilitada, cobrar deja la factura en cola
+// y devolver una venta facturada deja su NC. Si algo falla no se corta la
+// venta: se registra y se puede emitir a mano después.
+
+pub(crate) async fn encolar_factura(pool: &SqlitePool, id_venta: i64, uid: i64) -> Option<i64> {
+    let r = async {
+        let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+        let config = repo::leer_config(&mut conn).await?;
+        if !config.habilitado {
+            return Ok(None);
+        }
+        let id = repo::crear_factura(&mut conn, &config, id_venta, uid).await?;
+        drop(conn);
+        auditar_alta(pool, uid, id).await?;
+        Ok::<_, String>(Some(id))
+    }
+    .await;
+
+    match r {
+        Ok(id) => {
+            if id.is_some() {
+                cola::despachar(pool);
+            }
+            id
+        }
+        Err(e) => {
+            eprintln!("[FISCAL] venta {id_venta}: {e}");
+            None
+        }
+    }
+}
+
+pub(crate) async fn encolar_nota_credito(pool: &SqlitePool, id_devolucion: i64, uid: i64) -> Option<i64> {
+    let r = async {
+        let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+        let facturada: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT c.id_comprobante
+              FROM devolucion d
+              JOIN comprobante c ON c.id_venta = d.id_venta
+             WHERE d.id_devolucion = ?1 AND c.tipo = 'factura' AND c.estado <> 'rechazado'
+            "#,
+        )
+        .bind(id_devolucion)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        if facturada.is_none() {
+            return Ok(None);
+        }
+        let id = repo::crear_nota_credito(&mut conn, id_devolucion, uid).await?;
+        drop(conn);
+        auditar_alta(pool, uid, id).await?;
+        Ok::<_, String>(Some(id))
+    }
+    .await;
+
+    match r {
+        Ok(id) => {
+            if id.is_some() {
+                cola::despachar(pool);
+            }
+            id
+        }
+        Err(e) => {
+            eprintln!("[FISCAL] devolución {id_devolucion}: {e}");
+            None
+        }
+    }
+}
+
+// CONFIGURACIÓN
+
+#[tauri::command]
+pub async fn fiscal_config_obtener(state: State<'_, AppState>) -> Result<FiscalConfig, String> {
+    requerir_admin(&state).await?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+    repo::leer_config(&mut conn).await
+}
+
+#[tauri::command]
+pub async fn fiscal_config_fijar(state: State<'_, AppState>, input: FiscalConfig) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    if !(1..=99999).contains(&input.punto_venta) {
+        return Err("Punto de venta inválido (1 a 99999)".into());
+    }
+    if !CONDICIONES_EMISOR.contains(&input.condicion_iva.as_str()) {
+        return Err(format!("Condición frente al IVA inválida: {}", input.condicion_iva));
+    }
+    if id_alicuota(input.alicuota_iva).is_none() {
+        return Err(format!("Alícuota de IVA no admitida: {}%", input.alicuota_iva));
+    }
+    if !input.url.trim().is_empty() {
+        ClienteHttp::new(&input.url)?;
+    }
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    let antes = repo::leer_config(&mut tx).await?;
+    if input.habilitado && antes.cuit.is_empty() {
+        return Err("Cargá el CUIT en los datos del negocio antes de habilitar la facturación".into());
+    }
+    repo::guardar_config(&mut tx, &input).await?;
+    let despues = repo::leer_config(&mut tx).await?;
+
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "ajuste",
+        None,
+        "fiscal",
+        Some(&json!(antes)),
+        Some(&json!(despues)),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+// EMISIÓN MANUAL (ventas de antes de habilitar, o rechazadas ya corregidas)
+
+#[tauri::command]
+pub async fn factura_emitir(state: State<'_, AppState>, id_venta: i64) -> Result<ComprobanteRow, String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let id = {
+        let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+        let config = repo::leer_config(&mut conn).await?;
+        repo::crear_factura(&mut conn, &config, id_venta, uid).await?
+    };
+    let c = auditar_alta(&state.pool, uid, id).await?;
+
+    cola::despachar(&state.pool);
+    Ok(c)
+}
+
+#[tauri::command]
+pub async fn nota_credito_emitir(state: State<'_, AppState>, id_devolucion: i64) -> Result<ComprobanteRow, String> {
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let id = {
+        let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+        repo::crear_nota_credito(&mut conn, id_devolucion, uid).await?
+    };
+    let c = auditar_alta(&state.pool, uid, id).await?;
+
+    cola::despachar(&state.pool);
+    Ok(c)
+}
+
+// CONSULTAS / COLA
+
+#[tauri::command]
+pub async fn comprobante_listar(
+    state: State<'_, AppState>,
+    input: Option<ComprobanteListarInput>,
+) -> Result<Vec<ComprobanteRow>, String> {
+    requerir_admin(&state).await?;
+
+    repo::listar(&state.pool, &input.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn comprobante_obtener(state: State<'_, AppState>, id_comprobante: i64) -> Result<ComprobanteRow, String> {
+    requerir_sesion(&state).await?;
+
+    comprobante_o_error(&state.pool, id_comprobante).await
+}
+
+/// Reintenta ya lo pendiente (o un comprobante) y espera el resultado.
+#[tauri::command]
+pub async fn fiscal_cola_procesar(
+    state: State<'_, AppState>,
+    id_comprobante: Option<i64>,
+) -> Result<ColaResultado, String> {
+    requerir_admin(&state).await?;
+
+    repo::reencolar(&state.pool, id_comprobante).await?;
+    cola::procesar_pendientes(&state.pool).await
+}
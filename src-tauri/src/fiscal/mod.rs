@@ -0,0 +1,5 @@
+pub mod model;
+pub mod autoridad;
+pub mod repo;
+pub mod cola;
+pub mod commands;
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const CONDICIONES_EMISOR: [&str; 3] = ["responsable_inscripto", "monotributo", "exento"];
+
+// Documento del receptor según la autoridad
+pub const DOC_CUIT: i64 = 80;
+pub const DOC_CONSUMIDOR_FINAL: i64 = 99;
+
+/// Código de comprobante de la autoridad (factura / nota de crédito por letra).
+pub fn cbte_tipo(tipo: &str, letra: &str) -> Option<i64> {
+    match (tipo, letra) {
+        ("factura", "A") => Some(1),
+        ("nota_credito", "A") => Some(3),
+        ("factura", "B") => Some(6),
+        ("nota_credito", "B") => Some(8),
+        ("factura", "C") => Some(11),
+        ("nota_credito", "C") => Some(13),
+        _ => None,
+    }
+}
+
+/// Id de alícuota de IVA de la autoridad.
+pub fn id_alicuota(pct: f64) -> Option<i64> {
+    match (pct * 10.0).round() as i64 {
+        0 => Some(3),
+        25 => Some(9),
+        50 => Some(8),
+        105 => Some(4),
+        210 => Some(5),
+        270 => Some(6),
+        _ => None,
+    }
+}
+
+// CONFIGURACIÓN (tabla ajuste)
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiscalConfig {
+    pub habilitado: bool,
+    pub punto_venta: i64,
+    pub condicion_iva: String, // del emisor
+    pub alicuota_iva: f64,
+    pub url: String,
+    #[serde(default, skip_deserializing)]
+    pub cuit: String, // negocio_cuit, se edita en los datos del negocio
+}
+
+// INPUTS
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ComprobanteListarInput {
+    pub desde: Option<String>, // "YYYY-MM-DD"
+    pub hasta: Option<String>,
+    pub estado: Option<String>,
+    pub id_venta: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+// OUTPUTS
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ComprobanteRow {
+    pub id_comprobante: i64,
+    pub tipo: String,
+    pub letra: String,
+    pub cbte_tipo: i64,
+    pub punto_venta: i64,
+    pub numero: Option<i64>,
+    pub id_venta: i64,
+    pub id_devolucion: Option<i64>,
+    pub id_asociado: Option<i64>,
+    pub doc_tipo: i64,
+    pub doc_nro: String,
+    pub importe_neto: i64,
+    pub importe_iva: i64,
+    pub importe_total: i64,
+    pub alicuota_iva: f64,
+    pub estado: String,
+    pub cae: Option<String>,
+    pub cae_vencimiento: Option<String>,
+    pub intentos: i64,
+    pub ultimo_error: Option<String>,
+    pub proximo_intento: Option<String>,
+    pub creado_en: String,
+    pub autorizado_en: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ColaResultado {
+    pub procesados: i64,
+    pub autorizados: i64,
+    pub rechazados: i64,
+    pub reintentar: i64, // quedaron en cola
+}
+
+// PEDIDO / RESPUESTA DE LA AUTORIDAD
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlicuotaSolicitud {
+    pub id: i64,
+    pub base_imponible: i64,
+    pub importe: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemSolicitud {
+    pub descripcion: String,
+    pub cantidad: i64,
+    pub precio_unitario: i64,
+    pub importe: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AsociadoSolicitud {
+    pub cbte_tipo: i64,
+    pub punto_venta: i64,
+    pub numero: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SolicitudCae {
+    pub cuit_emisor: String,
+    pub punto_venta: i64,
+    pub cbte_tipo: i64,
+    pub numero: i64,
+    pub concepto: i64, // 1 = productos
+    pub fecha: String, // "YYYYMMDD"
+    pub doc_tipo: i64,
+    pub doc_nro: String,
+    pub importe_total: i64,
+    pub importe_neto: i64,    // gravado
+    pub importe_exento: i64,
+    pub importe_iva: i64,
+    pub alicuotas: Vec<AlicuotaSolicitud>,
+    pub items: Vec<ItemSolicitud>,
+    pub asociado: Option<AsociadoSolicitud>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RespuestaCae {
+    pub cae: String,
+    pub cae_vencimiento: String, // "YYYY-MM-DD"
+}
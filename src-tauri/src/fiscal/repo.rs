@@ -0,0 +1,662 @@
+use std::collections::HashMap;
+
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
+
+use super::model::{
+    cbte_tipo, id_alicuota, AlicuotaSolicitud, AsociadoSolicitud, ComprobanteListarInput, ComprobanteRow,
+    FiscalConfig, ItemSolicitud, RespuestaCae, SolicitudCae, DOC_CONSUMIDOR_FINAL, DOC_CUIT,
+};
+
+const SELECT_COMPROBANTE: &str = r#"
+    SELECT id_comprobante, tipo, letra, cbte_tipo, punto_venta, numero, id_venta, id_devolucion,
+           id_asociado, doc_tipo, doc_nro, importe_neto, importe_iva, importe_total, alicuota_iva,
+           estado, cae, cae_vencimiento, intentos, ultimo_error, proximo_intento, creado_en,
+           autorizado_en
+      FROM comprobante c
+"#;
+
+// CONFIGURACIÓN
+
+pub async fn leer_config(conn: &mut SqliteConnection) -> Result<FiscalConfig, String> {
+    let filas: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT clave, valor FROM ajuste
+        WHERE clave IN ('fiscal_habilitado','fiscal_punto_venta','fiscal_condicion_iva',
+                        'fiscal_alicuota_iva','fiscal_url','negocio_cuit')
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut m: HashMap<String, String> = filas.into_iter().collect();
+    let mut tomar = |k: &str| m.remove(k).unwrap_or_default();
+
+    Ok(FiscalConfig {
+        habilitado: tomar("fiscal_habilitado") == "1",
+        punto_venta: tomar("fiscal_punto_venta").parse().unwrap_or(1),
+        condicion_iva: tomar("fiscal_condicion_iva"),
+        alicuota_iva: tomar("fiscal_alicuota_iva").parse().unwrap_or(21.0),
+        url: tomar("fiscal_url"),
+        cuit: tomar("negocio_cuit").chars().filter(char::is_ascii_digit).collect(),
+    })
+}
+
+pub async fn guardar_config(conn: &mut SqliteConnection, config: &FiscalConfig) -> Result<(), String> {
+    let valores = [
+        ("fiscal_habilitado", if config.habilitado { "1".to_string() } else { "0".to_string() }),
+        ("fiscal_punto_venta", config.punto_venta.to_string()),
+        ("fiscal_condicion_iva", config.condicion_iva.clone()),
+        ("fiscal_alicuota_iva", config.alicuota_iva.to_string()),
+        ("fiscal_url", config.url.trim().to_string()),
+    ];
+    for (clave, valor) in &valores {
+        sqlx::query(
+            r#"
+            INSERT INTO ajuste (clave, valor) VALUES (?1, ?2)
+            ON CONFLICT(clave) DO UPDATE
+              SET valor = excluded.valor,
+                  actualizado_en = DATETIME('now','localtime')
+            "#,
+        )
+        .bind(clave)
+        .bind(valor)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Neto e IVA de un total con IVA incluido. La C no discrimina.
+fn separar_iva(total: i64, letra: &str, alicuota: f64) -> (i64, i64) {
+    if letra == "C" || alicuota <= 0.0 {
+        return (total, 0);
+    }
+    let neto = (total as f64 / (1.0 + alicuota / 100.0)).round() as i64;
+    (neto, total - neto)
+}
+
+/// Renglón del comprobante tal como se manda a autorizar.
+#[derive(FromRow)]
+struct Renglon {
+    descripcion: String,
+    cantidad: i64,
+    precio_unitario: i64,
+    importe: i64,
+    alicuota_iva: f64,
+}
+
+/// Renglones actuales de la venta (o de la devolución), con la alícuota
+/// congelada en cada línea vendida. Se leen una sola vez, al crear el comprobante.
+async fn renglones_de(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_devolucion: Option<i64>,
+) -> Result<Vec<Renglon>, String> {
+    match id_devolucion {
+        None => sqlx::query_as::<_, Renglon>(
+            r#"
+            SELECT p.nombre AS descripcion, vi.cantidad, vi.precio_unitario, vi.subtotal AS importe, vi.alicuota_iva
+              FROM venta_item vi
+              JOIN producto p ON p.id_producto = vi.id_producto
+             WHERE vi.id_venta = ?1
+             ORDER BY vi.id_item
+            "#,
+        )
+        .bind(id_venta),
+        Some(id) => sqlx::query_as::<_, Renglon>(
+            r#"
+            SELECT p.nombre AS descripcion, di.cantidad, di.precio_unitario, di.subtotal AS importe, vi.alicuota_iva
+              FROM devolucion_item di
+              JOIN venta_item vi ON vi.id_item = di.id_item
+              JOIN producto p ON p.id_producto = di.id_producto
+             WHERE di.id_devolucion = ?1
+             ORDER BY di.id_devolucion_item
+            "#,
+        )
+        .bind(id),
+    }
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+async fn renglones_guardados(conn: &mut SqliteConnection, id_comprobante: i64) -> Result<Vec<Renglon>, String> {
+    sqlx::query_as::<_, Renglon>(
+        r#"
+        SELECT descripcion, cantidad, precio_unitario, importe, alicuota_iva
+          FROM comprobante_item
+         WHERE id_comprobante = ?1
+         ORDER BY id_comprobante_item
+        "#,
+    )
+    .bind(id_comprobante)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Neto e IVA por alícuota de los renglones. Lo que los renglones no
+/// explican (recargo del medio de pago) va a la alícuota general. Devuelve
+/// (alícuota, neto, iva).
+fn desglose_iva(renglones: &[Renglon], letra: &str, total: i64, alicuota_general: f64) -> Vec<(f64, i64, i64)> {
+    let mut por_alicuota: Vec<(f64, i64)> = Vec::new();
+    for r in renglones {
+        match por_alicuota.iter_mut().find(|(a, _)| *a == r.alicuota_iva) {
+            Some((_, b)) => *b += r.importe,
+            None => por_alicuota.push((r.alicuota_iva, r.importe)),
+        }
+    }
+
+    let diferencia = total - renglones.iter().map(|r| r.importe).sum::<i64>();
+    if diferencia != 0 {
+        match por_alicuota.iter_mut().find(|(a, _)| *a == alicuota_general) {
+            Some((_, b)) => *b += diferencia,
+            None => por_alicuota.push((alicuota_general, diferencia)),
+        }
+    }
+
+    por_alicuota
+        .into_iter()
+        .filter(|(_, bruto)| *bruto != 0)
+        .map(|(a, bruto)| {
+            let (neto, iva) = separar_iva(bruto, letra, a);
+            (a, neto, iva)
+        })
+        .collect()
+}
+
+fn sumar_desglose(d: &[(f64, i64, i64)]) -> (i64, i64) {
+    d.iter().fold((0, 0), |(n, i), (_, neto, iva)| (n + neto, i + iva))
+}
+
+// ALTA DE COMPROBANTES
+
+pub async fn factura_de_venta(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<Option<ComprobanteRow>, sqlx::Error> {
+    let sql = format!(
+        "{SELECT_COMPROBANTE} WHERE c.id_venta = ?1 AND c.tipo = 'factura' AND c.estado <> 'rechazado'"
+    );
+    sqlx::query_as::<_, ComprobanteRow>(&sql)
+        .bind(id_venta)
+        .fetch_optional(&mut *conn)
+        .await
+}
+
+/// Una venta facturada (aunque la factura siga en cola) no se anula ni se
+/// edita: se corrige con una devolución, que emite la nota de crédito.
+pub async fn exigir_sin_factura(conn: &mut SqliteConnection, id_venta: i64) -> Result<(), String> {
+    match factura_de_venta(conn, id_venta).await.map_err(|e| e.to_string())? {
+        None => Ok(()),
+        Some(_) => Err("La venta está facturada: registrá una devolución para emitir la nota de crédito".into()),
+    }
+}
+
+/// Crea la factura 'pendiente' de una venta finalizada. La letra sale de la
+/// condición del emisor y del cliente asignado (A sólo a inscriptos con CUIT).
+pub async fn crear_factura(
+    conn: &mut SqliteConnection,
+    config: &FiscalConfig,
+    id_venta: i64,
+    id_usuario: i64,
+) -> Result<i64, String> {
+    let venta: Option<(String, i64, Option<i64>)> =
+        sqlx::query_as("SELECT estado, total, id_cliente FROM venta WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (estado, total, id_cliente) = venta.ok_or("Venta inexistente")?;
+    if estado != "finalizada" {
+        return Err("Sólo se facturan ventas finalizadas".into());
+    }
+    if factura_de_venta(conn, id_venta).await.map_err(|e| e.to_string())?.is_some() {
+        return Err("La venta ya tiene factura".into());
+    }
+
+    // el recargo del medio de pago también se factura
+    let recargos: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(recargo), 0) FROM venta_pago WHERE id_venta = ?1")
+        .bind(id_venta)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let total = total + recargos;
+
+    let cliente: Option<(Option<String>, String)> = match id_cliente {
+        Some(id) => sqlx::query_as("SELECT cuit, condicion_iva FROM cliente WHERE id_cliente = ?1")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => None,
+    };
+    let (cuit_cliente, condicion_cliente) = match cliente {
+        Some((cuit, condicion)) => (cuit, condicion),
+        None => (None, "consumidor_final".to_string()),
+    };
+
+    let letra = match (config.condicion_iva.as_str(), condicion_cliente.as_str(), &cuit_cliente) {
+        ("responsable_inscripto", "responsable_inscripto", Some(_)) => "A",
+        ("responsable_inscripto", _, _) => "B",
+        _ => "C",
+    };
+    let (doc_tipo, doc_nro) = match cuit_cliente {
+        Some(cuit) => (DOC_CUIT, cuit.chars().filter(char::is_ascii_digit).collect()),
+        None => (DOC_CONSUMIDOR_FINAL, "0".to_string()),
+    };
+    let renglones = renglones_de(conn, id_venta, None).await?;
+    let desglose = desglose_iva(&renglones, letra, total, config.alicuota_iva);
+    let (neto, iva) = sumar_desglose(&desglose);
+
+    insertar(
+        conn,
+        Nuevo {
+            tipo: "factura",
+            letra,
+            punto_venta: config.punto_venta,
+            id_venta,
+            id_devolucion: None,
+            id_asociado: None,
+            doc_tipo,
+            doc_nro: &doc_nro,
+            importe_neto: neto,
+            importe_iva: iva,
+            importe_total: total,
+            alicuota_iva: config.alicuota_iva,
+            id_usuario,
+        },
+        &renglones,
+    )
+    .await
+}
+
+/// Nota de crédito por una devolución de una venta facturada. Copia letra,
+/// receptor y alícuota de la factura.
+pub async fn crear_nota_credito(
+    conn: &mut SqliteConnection,
+    id_devolucion: i64,
+    id_usuario: i64,
+) -> Result<i64, String> {
+    let devolucion: Option<(i64, i64)> =
+        sqlx::query_as("SELECT id_venta, total FROM devolucion WHERE id_devolucion = ?1")
+            .bind(id_devolucion)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (id_venta, total) = devolucion.ok_or("Devolución inexistente")?;
+    if total <= 0 {
+        return Err("La devolución no tiene importe".into());
+    }
+
+    let factura = factura_de_venta(conn, id_venta)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("La venta de esta devolución no tiene factura")?;
+
+    let ya: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM comprobante WHERE id_devolucion = ?1 AND tipo = 'nota_credito' AND estado <> 'rechazado'",
+    )
+    .bind(id_devolucion)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    if ya > 0 {
+        return Err("La devolución ya tiene nota de crédito".into());
+    }
+
+    let renglones = renglones_de(conn, id_venta, Some(id_devolucion)).await?;
+    let desglose = desglose_iva(&renglones, &factura.letra, total, factura.alicuota_iva);
+    let (neto, iva) = sumar_desglose(&desglose);
+    insertar(
+        conn,
+        Nuevo {
+            tipo: "nota_credito",
+            letra: &factura.letra,
+            punto_venta: factura.punto_venta,
+            id_venta,
+            id_devolucion: Some(id_devolucion),
+            id_asociado: Some(factura.id_comprobante),
+            doc_tipo: factura.doc_tipo,
+            doc_nro: &factura.doc_nro,
+            importe_neto: neto,
+            importe_iva: iva,
+            importe_total: total,
+            alicuota_iva: factura.alicuota_iva,
+            id_usuario,
+        },
+        &renglones,
+    )
+    .await
+}
+
+struct Nuevo<'a> {
+    tipo: &'a str,
+    letra: &'a str,
+    punto_venta: i64,
+    id_venta: i64,
+    id_devolucion: Option<i64>,
+    id_asociado: Option<i64>,
+    doc_tipo: i64,
+    doc_nro: &'a str,
+    importe_neto: i64,
+    importe_iva: i64,
+    importe_total: i64,
+    alicuota_iva: f64,
+    id_usuario: i64,
+}
+
+/// Alta del comprobante con sus renglones congelados.
+async fn insertar(conn: &mut SqliteConnection, n: Nuevo<'_>, renglones: &[Renglon]) -> Result<i64, String> {
+    let cbte = cbte_tipo(n.tipo, n.letra).ok_or("Tipo de comprobante inválido")?;
+
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO comprobante (
+          tipo, letra, cbte_tipo, punto_venta, id_venta, id_devolucion, id_asociado,
+          doc_tipo, doc_nro, importe_neto, importe_iva, importe_total, alicuota_iva, id_usuario
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+        RETURNING id_comprobante
+        "#,
+    )
+    .bind(n.tipo)
+    .bind(n.letra)
+    .bind(cbte)
+    .bind(n.punto_venta)
+    .bind(n.id_venta)
+    .bind(n.id_devolucion)
+    .bind(n.id_asociado)
+    .bind(n.doc_tipo)
+    .bind(n.doc_nro)
+    .bind(n.importe_neto)
+    .bind(n.importe_iva)
+    .bind(n.importe_total)
+    .bind(n.alicuota_iva)
+    .bind(n.id_usuario)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for r in renglones {
+        sqlx::query(
+            r#"
+            INSERT INTO comprobante_item (id_comprobante, descripcion, cantidad, precio_unitario, importe, alicuota_iva)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(id)
+        .bind(&r.descripcion)
+        .bind(r.cantidad)
+        .bind(r.precio_unitario)
+        .bind(r.importe)
+        .bind(r.alicuota_iva)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(id)
+}
+
+// SOLICITUD
+
+/// Solicitud de CAE con los renglones congelados al crear el comprobante.
+pub async fn armar_solicitud(
+    pool: &SqlitePool,
+    c: &ComprobanteRow,
+    cuit_emisor: &str,
+    numero: i64,
+) -> Result<SolicitudCae, String> {
+    let renglones = {
+        let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+        renglones_guardados(&mut conn, c.id_comprobante).await?
+    };
+    let desglose = desglose_iva(&renglones, &c.letra, c.importe_total, c.alicuota_iva);
+
+    let mut items: Vec<ItemSolicitud> = renglones
+        .into_iter()
+        .map(|r| ItemSolicitud {
+            descripcion: r.descripcion,
+            cantidad: r.cantidad,
+            precio_unitario: r.precio_unitario,
+            importe: r.importe,
+        })
+        .collect();
+
+    // lo que no explican los renglones (recargo del medio de pago)
+    let diferencia = c.importe_total - items.iter().map(|i| i.importe).sum::<i64>();
+    if diferencia > 0 {
+        items.push(ItemSolicitud {
+            descripcion: "Recargo financiero".into(),
+            cantidad: 1,
+            precio_unitario: diferencia,
+            importe: diferencia,
+        });
+    }
+
+    let asociado = match c.id_asociado {
+        Some(id) => {
+            let (cbte_tipo, punto_venta, numero): (i64, i64, Option<i64>) = sqlx::query_as(
+                "SELECT cbte_tipo, punto_venta, numero FROM comprobante WHERE id_comprobante = ?1",
+            )
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            Some(AsociadoSolicitud {
+                cbte_tipo,
+                punto_venta,
+                numero: numero.ok_or("La factura asociada todavía no tiene número")?,
+            })
+        }
+        None => None,
+    };
+
+    // en la C no se discrimina; lo exento va aparte de las alícuotas
+    let mut alicuotas = Vec::new();
+    let mut exento = 0;
+    if c.letra != "C" {
+        for (a, neto, iva) in desglose {
+            if a <= 0.0 {
+                exento += neto;
+                continue;
+            }
+            alicuotas.push(AlicuotaSolicitud {
+                id: id_alicuota(a).ok_or_else(|| format!("Alícuota de IVA no admitida: {a}%"))?,
+                base_imponible: neto,
+                importe: iva,
+            });
+        }
+    }
+
+    Ok(SolicitudCae {
+        cuit_emisor: cuit_emisor.to_string(),
+        punto_venta: c.punto_venta,
+        cbte_tipo: c.cbte_tipo,
+        numero,
+        concepto: 1,
+        fecha: chrono::Local::now().format("%Y%m%d").to_string(),
+        doc_tipo: c.doc_tipo,
+        doc_nro: c.doc_nro.clone(),
+        importe_total: c.importe_total,
+        importe_neto: c.importe_neto - exento,
+        importe_exento: exento,
+        importe_iva: c.importe_iva,
+        alicuotas,
+        items,
+        asociado,
+    })
+}
+
+// COLA
+
+/// Toma el próximo comprobante a enviar y lo reserva unos minutos para que
+/// otra pasada no lo mande en paralelo. Primero los que ya tienen número;
+/// las NC esperan a que su factura esté autorizada.
+pub async fn tomar_siguiente(pool: &SqlitePool) -> Result<Option<ComprobanteRow>, String> {
+    loop {
+        let sql = format!(
+            r#"{SELECT_COMPROBANTE}
+             WHERE c.estado = 'pendiente'
+               AND (c.proximo_intento IS NULL OR c.proximo_intento <= DATETIME('now','localtime'))
+               AND (c.id_asociado IS NULL OR EXISTS (
+                     SELECT 1 FROM comprobante f
+                      WHERE f.id_comprobante = c.id_asociado AND f.estado = 'autorizado'))
+             ORDER BY c.numero IS NULL, c.id_comprobante
+             LIMIT 1"#
+        );
+        let Some(c) = sqlx::query_as::<_, ComprobanteRow>(&sql)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?
+        else {
+            return Ok(None);
+        };
+
+        let tomado = sqlx::query(
+            r#"
+            UPDATE comprobante
+               SET proximo_intento = DATETIME('now','localtime','+5 minutes')
+             WHERE id_comprobante = ?1
+               AND estado = 'pendiente'
+               AND (proximo_intento IS NULL OR proximo_intento <= DATETIME('now','localtime'))
+            "#,
+        )
+        .bind(c.id_comprobante)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+
+        if tomado == 1 {
+            return Ok(Some(c));
+        }
+    }
+}
+
+pub async fn fijar_numero(pool: &SqlitePool, id_comprobante: i64, numero: i64) -> Result<(), String> {
+    sqlx::query("UPDATE comprobante SET numero = ?1 WHERE id_comprobante = ?2")
+        .bind(numero)
+        .bind(id_comprobante)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn marcar_autorizado(pool: &SqlitePool, id_comprobante: i64, r: &RespuestaCae) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        UPDATE comprobante
+           SET estado = 'autorizado', cae = ?1, cae_vencimiento = ?2,
+               intentos = intentos + 1, ultimo_error = NULL, proximo_intento = NULL,
+               autorizado_en = DATETIME('now','localtime')
+         WHERE id_comprobante = ?3
+        "#,
+    )
+    .bind(&r.cae)
+    .bind(&r.cae_vencimiento)
+    .bind(id_comprobante)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Rechazo definitivo: libera el número y arrastra a las NC que dependían de él.
+pub async fn marcar_rechazado(pool: &SqlitePool, id_comprobante: i64, error: &str) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        UPDATE comprobante
+           SET estado = 'rechazado', numero = NULL, intentos = intentos + 1,
+               ultimo_error = ?1, proximo_intento = NULL
+         WHERE id_comprobante = ?2
+        "#,
+    )
+    .bind(error)
+    .bind(id_comprobante)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        UPDATE comprobante
+           SET estado = 'rechazado', numero = NULL, ultimo_error = 'Factura asociada rechazada'
+         WHERE id_asociado = ?1 AND estado = 'pendiente'
+        "#,
+    )
+    .bind(id_comprobante)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Falla transitoria: queda en cola con espera creciente (1, 2, 4... hasta 60 minutos).
+pub async fn marcar_reintento(pool: &SqlitePool, id_comprobante: i64, error: &str) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        UPDATE comprobante
+           SET intentos = intentos + 1,
+               ultimo_error = ?1,
+               proximo_intento = DATETIME('now','localtime',
+                 '+' || MIN(60, 1 << MIN(intentos, 6)) || ' minutes')
+         WHERE id_comprobante = ?2
+        "#,
+    )
+    .bind(error)
+    .bind(id_comprobante)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Vuelve a poner en cola ya (sin esperar el próximo intento).
+pub async fn reencolar(pool: &SqlitePool, id_comprobante: Option<i64>) -> Result<u64, String> {
+    let r = sqlx::query(
+        "UPDATE comprobante SET proximo_intento = NULL
+          WHERE estado = 'pendiente' AND (?1 IS NULL OR id_comprobante = ?1)",
+    )
+    .bind(id_comprobante)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(r.rows_affected())
+}
+
+// CONSULTAS
+
+pub async fn obtener(pool: &SqlitePool, id_comprobante: i64) -> Result<Option<ComprobanteRow>, sqlx::Error> {
+    let sql = format!("{SELECT_COMPROBANTE} WHERE c.id_comprobante = ?1");
+    sqlx::query_as::<_, ComprobanteRow>(&sql)
+        .bind(id_comprobante)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn listar(pool: &SqlitePool, input: &ComprobanteListarInput) -> Result<Vec<ComprobanteRow>, sqlx::Error> {
+    let sql = format!(
+        r#"{SELECT_COMPROBANTE}
+         WHERE (?1 IS NULL OR DATE(c.creado_en) >= DATE(?1))
+           AND (?2 IS NULL OR DATE(c.creado_en) <= DATE(?2))
+           AND (?3 IS NULL OR c.estado = ?3)
+           AND (?4 IS NULL OR c.id_venta = ?4)
+         ORDER BY c.id_comprobante DESC
+         LIMIT ?5"#
+    );
+    sqlx::query_as::<_, ComprobanteRow>(&sql)
+        .bind(&input.desde)
+        .bind(&input.hasta)
+        .bind(&input.estado)
+        .bind(input.id_venta)
+        .bind(input.limit.unwrap_or(200).clamp(1, 1000))
+        .fetch_all(pool)
+        .await
+}
@@ -1,37 +1,24 @@
 use tauri::State;
 
 use crate::app_state::AppState;
-use crate::users::auth::AuthState;
 
 use super::model::{SueldoRegistrarInput, GastoRegistrarInput, GastoListarPeriodoInput, GastoNegocioRow};
 use super::repo;
 use super::model::{TotalesPeriodoInput, TotalOut};
 use super::model::{SueldoListarPeriodoInput, SueldoPagoRow};
 use super::model::SueldoPagoRowView;
-
-fn read_uid(auth: &AuthState, state: &AppState) -> Option<i64> {
-    // Intentar desde AuthState
-    if let Ok(g) = auth.current_user_id.read() {
-        if g.is_some() { return *g; }
-    }
-    // Fallback al viejo session_user
-    if let Ok(g) = state.session_user.lock() {
-        *g
-    } else {
-        None
-    }
-}
+use crate::users::permisos::requerir_admin;
+use crate::audit::repo as audit_repo;
+use serde_json::json;
 
 // SUELDOS
 
 #[tauri::command(rename = "sueldo_registrar")]
 pub async fn sueldo_registrar(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     input: SueldoRegistrarInput,
 ) -> Result<i64, String> {
-    let uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión para registrar un sueldo".to_string())?;
+    let uid = requerir_admin(&state).await?.id_usuario;
 
     let desc = input.descripcion.trim();
     if desc.is_empty() { return Err("descripcion obligatoria".into()); }
@@ -41,7 +28,14 @@ pub async fn sueldo_registrar(
         .id_usuario_destino
         .ok_or_else(|| "Tenés que seleccionar el usuario destino del sueldo".to_string())?;
 
-    repo::sueldo_insert(
+    let despues = json!({
+        "descripcion": desc,
+        "monto": input.monto,
+        "id_usuario_destino": id_dest,
+        "fecha_hora": input.fecha_hora,
+    });
+
+    let id = repo::sueldo_insert(
         &state.pool,
         uid,
         input.fecha_hora,
@@ -50,19 +44,22 @@ pub async fn sueldo_registrar(
         Some(id_dest),
     )
     .await
-    .map_err(|e| e.to_string())
+    .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(&state.pool, Some(uid), "sueldo_pago", Some(id), "crear", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
 }
 // GASTOS
 
 #[tauri::command(rename = "gasto_registrar")]
 pub async fn gasto_registrar(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     input: GastoRegistrarInput,
 ) -> Result<i64, String> {
-
-    let uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión para registrar un gasto".to_string())?;
+    let uid = requerir_admin(&state).await?.id_usuario;
 
     let cat = input.categoria.trim();
     if cat.is_empty() {
@@ -76,7 +73,14 @@ pub async fn gasto_registrar(
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty());
 
-    repo::gasto_insert(
+    let despues = json!({
+        "categoria": cat,
+        "descripcion": desc,
+        "monto": input.monto,
+        "fecha_hora": input.fecha_hora,
+    });
+
+    let id = repo::gasto_insert(
         &state.pool,
         uid,
         input.fecha_hora,
@@ -85,19 +89,21 @@ pub async fn gasto_registrar(
         input.monto,
     )
     .await
-    .map_err(|e| e.to_string())
+    .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(&state.pool, Some(uid), "gasto_negocio", Some(id), "crear", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
 }
 
 #[tauri::command(rename = "gasto_listar_por_periodo")]
 pub async fn gasto_listar_por_periodo(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     filtro: GastoListarPeriodoInput,
 ) -> Result<Vec<GastoNegocioRow>, String> {
-
-    let _uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión.".to_string())?;
-
+    requerir_admin(&state).await?;
 
     repo::gasto_listar_por_periodo(&state.pool, filtro)
         .await
@@ -107,12 +113,9 @@ pub async fn gasto_listar_por_periodo(
 #[tauri::command(rename = "gasto_total_por_periodo")]
 pub async fn gasto_total_por_periodo(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     input: TotalesPeriodoInput,
 ) -> Result<TotalOut, String> {
-
-    let _uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión.".to_string())?;
+    requerir_admin(&state).await?;
 
     let cat = input.categoria.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
 
@@ -131,12 +134,9 @@ pub async fn gasto_total_por_periodo(
 #[tauri::command(rename = "sueldo_total_por_periodo")]
 pub async fn sueldo_total_por_periodo(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     input: TotalesPeriodoInput,
 ) -> Result<TotalOut, String> {
-
-    let _uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión.".to_string())?;
+    requerir_admin(&state).await?;
 
     let total = repo::sueldo_total_por_periodo(
         &state.pool,
@@ -154,11 +154,9 @@ pub async fn sueldo_total_por_periodo(
 #[tauri::command(rename = "sueldo_listar_por_periodo")]
 pub async fn sueldo_listar_por_periodo(
     state: State<'_, AppState>,
-    auth: State<'_, AuthState>,
     input: SueldoListarPeriodoInput,
 ) -> Result<Vec<SueldoPagoRowView>, String> {
-    let _uid = read_uid(&auth, &state)
-        .ok_or_else(|| "Tenés que iniciar sesión.".to_string())?;
+    requerir_admin(&state).await?;
 
     repo::sueldo_listar_por_periodo_view(
         &state.pool,
@@ -3,9 +3,12 @@ use tauri::State;
 use crate::AppState;
 
 use super::model::AdminHomeResumen;
+use crate::users::permisos::requerir_admin;
 
 #[tauri::command]
 pub async fn admin_home_resumen(state: State<'_, AppState>) -> Result<AdminHomeResumen, String> {
+    requerir_admin(&state).await?;
+
     super::repo::admin_home_resumen(&state.pool)
         .await
         .map_err(|e| e.to_string())
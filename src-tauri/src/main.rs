@@ -4,34 +4,45 @@ use std::sync::{Arc, Mutex};
 // módulos externos
 mod app_state;
 mod db;
-mod users { pub mod model; pub mod crypto; pub mod repo; pub mod commands; pub mod auth; }
+mod users { pub mod model; pub mod crypto; pub mod repo; pub mod commands; pub mod permisos; pub mod sesion; }
 mod ventas;
 mod caja;
+mod caja_movimiento;
+mod devoluciones;
 mod stock;
 mod reportes;
 mod compras;
 mod gastos;
 mod promos;
+mod promo_reglas;
 mod ventas_admin;
 mod PNL;
 mod home;
+mod audit;
+mod medios_pago;
+mod tickets;
+mod clientes;
+mod cuenta_corriente;
+mod fiscal;
 // === Imports de estructuras expuestas ===
 use app_state::AppState;
-use users::auth::AuthState;
+use users::sesion::SesionService;
 
 fn main() {
     tauri::Builder::default()
         .setup(|app| {
-            use std::sync::{Arc, Mutex};
             let handle = app.handle();
 
             let pool_res = tauri::async_runtime::block_on(db::init_db(&handle));
 
             match pool_res {
                 Ok(pool) => {
+                    // cola de facturación electrónica (reintentos)
+                    tauri::async_runtime::spawn(fiscal::cola::trabajador(pool.clone()));
+
                     app.manage(AppState {
                         pool,
-                        session_user: Arc::new(Mutex::new(None)),
+                        sesion: SesionService::default(),
                     });
                     Ok(())
                 }
@@ -43,8 +54,6 @@ fn main() {
                 }
             }
         })
-        // Registrar AuthState global (login)
-        .manage(AuthState::default())
         // Registrar comandos
         .invoke_handler(tauri::generate_handler![
             // === HOME ADMIN ===
@@ -56,6 +65,9 @@ fn main() {
             users::commands::login,
             users::commands::session_info,
             users::commands::usuario_actual,
+            users::commands::logout,
+            users::commands::sesion_bloquear,
+            users::commands::sesion_desbloquear,
             users::commands::usuario_listar_opciones,
             // === VENTAS ===
             ventas::commands::productos_disponibles,
@@ -66,8 +78,52 @@ fn main() {
             ventas::commands::venta_quitar_item,
             ventas::commands::venta_cancelar,
             ventas::commands::venta_finalizar,
+            ventas::commands::venta_anular,
             ventas::commands::historial_ventas_hoy,
             ventas::commands::venta_aplicar_promo_combo,
+            ventas::commands::venta_quitar_promo_grupo,
+            ventas::escaneo::venta_escanear,
+            stock::politica::venta_stock_verificar,
+            ventas::estacionadas::venta_estacionadas_listar,
+            ventas::estacionadas::venta_huerfanas_listar,
+            ventas::estacionadas::venta_estacionar,
+            ventas::estacionadas::venta_etiquetar,
+            ventas::estacionadas::venta_retomar,
+            ventas::estacionadas::venta_descartar,
+            ventas::descuentos::venta_descuento_aplicar,
+            ventas::descuentos::venta_descuento_quitar,
+            ventas::descuentos::venta_descuento_listar,
+            ventas::descuentos::descuento_limite_listar,
+            ventas::descuentos::descuento_limite_fijar,
+            // === CLIENTES ===
+            clientes::commands::cliente_crear,
+            clientes::commands::cliente_actualizar,
+            clientes::commands::cliente_set_activo,
+            clientes::commands::cliente_obtener,
+            clientes::commands::cliente_buscar,
+            clientes::commands::cliente_historial,
+            clientes::commands::venta_asignar_cliente,
+            // === CUENTA CORRIENTE ===
+            cuenta_corriente::commands::cliente_limite_credito_fijar,
+            cuenta_corriente::commands::cc_estado_cuenta,
+            cuenta_corriente::commands::cc_cobro_registrar,
+            cuenta_corriente::commands::cc_antiguedad_saldos,
+            // === TICKETS ===
+            tickets::commands::ticket_emitir,
+            tickets::commands::ticket_negocio_obtener,
+            tickets::commands::ticket_negocio_fijar,
+            // === FACTURACIÓN ===
+            fiscal::commands::fiscal_config_obtener,
+            fiscal::commands::fiscal_config_fijar,
+            fiscal::commands::factura_emitir,
+            fiscal::commands::nota_credito_emitir,
+            fiscal::commands::comprobante_listar,
+            fiscal::commands::comprobante_obtener,
+            fiscal::commands::fiscal_cola_procesar,
+            // === DEVOLUCIONES ===
+            devoluciones::commands::devolucion_items_venta,
+            devoluciones::commands::devolucion_crear,
+            devoluciones::commands::devolucion_listar_por_venta,
             // === CAJA ===
             caja::commands::caja_esta_abierta,
             caja::commands::caja_abrir,
@@ -77,6 +133,14 @@ fn main() {
             caja::commands::caja_estado,
             caja::commands::caja_resumen_diario,
             caja::commands::caja_cerrar_diario,
+            caja::commands::caja_reporte_diferencias,
+            caja::commands::caja_cierre_z_listar,
+            caja::commands::caja_cierre_z_detalle,
+            caja_movimiento::commands::caja_movimiento_registrar,
+            caja_movimiento::commands::caja_movimiento_listar,
+            // === MEDIOS DE PAGO ===
+            medios_pago::commands::medio_pago_listar,
+            medios_pago::commands::medio_pago_guardar,
             // ==== STOCK ===
             stock::commands::stock_listar,
             stock::commands::producto_crear,
@@ -92,10 +156,21 @@ fn main() {
             stock::commands::stock_compra,
             stock::commands::reporte_stock_reposicion,
             stock::commands::producto_actualizar_reposicion,
+            stock::commands::producto_alicuota_iva_fijar,
+            stock::commands::producto_unidad_venta_fijar,
+            stock::codigos::producto_codigo_barra_listar,
+            stock::codigos::producto_codigo_barra_agregar,
+            stock::codigos::producto_codigo_barra_quitar,
+            stock::codigos::producto_plu_fijar,
+            stock::politica::politica_stock_global_obtener,
+            stock::politica::politica_stock_global_fijar,
+            stock::politica::producto_politica_stock_fijar,
             // === REPORTES ===
             reportes::commands::admin_historial_dia,
             reportes::rentabilidad::reporte_rentabilidad,
             reportes::rentabilidad::reporte_rentabilidad_negocio,
+            reportes::libro_iva::libro_iva_ventas,
+            reportes::libro_iva::libro_iva_ventas_exportar,
             // === COMPRAS ===
             compras::commands::registrar_compra,
             // === GASTOS ===
@@ -109,15 +184,26 @@ fn main() {
             promos::commands::promo_combo_crear,
             promos::commands::promo_combo_listar,
             promos::commands::promo_combo_detalle,
+            promos::commands::promo_combo_vigencia_fijar,
             promos::commands::promo_combo_eliminar,
+            promos::commands::venta_combos_detectar,
+            promos::commands::promo_combo_auto_obtener,
+            promos::commands::promo_combo_auto_fijar,
+            promo_reglas::commands::promo_regla_crear,
+            promo_reglas::commands::promo_regla_listar,
+            promo_reglas::commands::promo_regla_eliminar,
             // === VENTAS ADMIN ===
             ventas_admin::commands::ventas_admin_listar,
             ventas_admin::commands::venta_admin_detalle,
             ventas_admin::editar::venta_admin_editar_guardar,
             ventas_admin::editar::producto_listar_basico,
+            ventas_admin::revision::venta_revision_listar,
+            ventas_admin::revision::venta_revision_diff,
             ventas_admin::commands::usuarios_listar_operadores,
             // === PNL ===
             PNL::commands::pnl_reporte,
+            // === AUDITORÍA ===
+            audit::commands::audit_evento_listar,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,129 @@
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::{requerir_admin, requerir_sesion};
+
+use super::model::{MedioPagoGuardarInput, MedioPagoRow};
+use super::repo;
+
+// LISTAR (el POS pide solo los activos)
+
+#[tauri::command]
+pub async fn medio_pago_listar(
+    state: State<'_, AppState>,
+    solo_activos: Option<bool>,
+) -> Result<Vec<MedioPagoRow>, String> {
+    requerir_sesion(&state).await?;
+
+    repo::listar(&state.pool, solo_activos.unwrap_or(true))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// ALTA / MODIFICACIÓN (solo admin). No se borran: se desactivan.
+
+#[tauri::command]
+pub async fn medio_pago_guardar(
+    state: State<'_, AppState>,
+    input: MedioPagoGuardarInput,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let codigo = input.codigo.trim().to_lowercase();
+    let nombre = input.nombre.trim();
+    if codigo.is_empty() || nombre.is_empty() {
+        return Err("Código y nombre son obligatorios".into());
+    }
+    let pct_ok = |p: f64| p.is_finite() && (0.0..=100.0).contains(&p);
+    if !pct_ok(input.recargo_pct) || !pct_ok(input.comision_pct) {
+        return Err("Porcentajes inválidos (0 a 100)".into());
+    }
+    for p in &input.planes {
+        if p.cuotas <= 1 {
+            return Err("Un plan tiene que ser de 2 cuotas o más".into());
+        }
+        if !pct_ok(p.recargo_pct) || !pct_ok(p.comision_pct) {
+            return Err(format!("Porcentajes inválidos en el plan de {} cuotas", p.cuotas));
+        }
+    }
+    if codigo == "efectivo" && !input.activo {
+        return Err("El efectivo no se puede desactivar".into());
+    }
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let antes: Option<(String, f64, f64, i64)> = sqlx::query_as(
+        "SELECT nombre, recargo_pct, comision_pct, activo FROM medio_pago WHERE codigo = ?1",
+    )
+    .bind(&codigo)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO medio_pago (codigo, nombre, recargo_pct, comision_pct, activo, orden)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ON CONFLICT(codigo) DO UPDATE SET
+          nombre = excluded.nombre,
+          recargo_pct = excluded.recargo_pct,
+          comision_pct = excluded.comision_pct,
+          activo = excluded.activo,
+          orden = excluded.orden
+        "#,
+    )
+    .bind(&codigo)
+    .bind(nombre)
+    .bind(input.recargo_pct)
+    .bind(input.comision_pct)
+    .bind(input.activo)
+    .bind(input.orden)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM medio_pago_plan WHERE codigo_medio = ?1")
+        .bind(&codigo)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for p in &input.planes {
+        sqlx::query(
+            "INSERT INTO medio_pago_plan (codigo_medio, cuotas, recargo_pct, comision_pct) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(&codigo)
+        .bind(p.cuotas)
+        .bind(p.recargo_pct)
+        .bind(p.comision_pct)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("plan {} cuotas: {e}", p.cuotas))?;
+    }
+
+    let antes = antes.map(|(nombre, recargo_pct, comision_pct, activo)| {
+        json!({ "nombre": nombre, "recargo_pct": recargo_pct, "comision_pct": comision_pct, "activo": activo })
+    });
+    let despues = json!({
+        "nombre": nombre,
+        "recargo_pct": input.recargo_pct,
+        "comision_pct": input.comision_pct,
+        "activo": input.activo,
+        "planes": input.planes,
+    });
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "medio_pago",
+        None,
+        if antes.is_some() { "actualizar" } else { "crear" },
+        antes.as_ref(),
+        Some(&json!({ "codigo": codigo, "datos": despues })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// INPUTS
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MedioPagoPlanInput {
+    pub cuotas: i64, // > 1
+    pub recargo_pct: f64,
+    pub comision_pct: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MedioPagoGuardarInput {
+    pub codigo: String, // ej: 'qr', 'cheque', 'cuenta_corriente'
+    pub nombre: String,
+    #[serde(default)]
+    pub recargo_pct: f64,
+    #[serde(default)]
+    pub comision_pct: f64,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+    #[serde(default)]
+    pub orden: i64,
+    /// Reemplaza todos los planes del medio
+    #[serde(default)]
+    pub planes: Vec<MedioPagoPlanInput>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// OUTPUTS
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MedioPagoPlanRow {
+    pub codigo_medio: String,
+    pub cuotas: i64,
+    pub recargo_pct: f64,
+    pub comision_pct: f64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MedioPagoRow {
+    pub codigo: String,
+    pub nombre: String,
+    pub recargo_pct: f64,
+    pub comision_pct: f64,
+    pub activo: i64,
+    pub orden: i64,
+    #[sqlx(skip)]
+    pub planes: Vec<MedioPagoPlanRow>,
+}
+
+/// Lo que resulta de cobrar `monto` con un medio / plan
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CargosPago {
+    pub cuotas: i64,
+    pub recargo: i64,  // ARS que paga el cliente además del monto
+    pub comision: i64, // ARS que retiene la procesadora
+}
@@ -0,0 +1,81 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::model::{CargosPago, MedioPagoPlanRow, MedioPagoRow};
+
+// redondeo al peso
+fn pct_de(monto: i64, pct: f64) -> i64 {
+    ((monto as f64) * pct / 100.0).round() as i64
+}
+
+pub async fn listar(pool: &SqlitePool, solo_activos: bool) -> Result<Vec<MedioPagoRow>, sqlx::Error> {
+    let mut medios = sqlx::query_as::<_, MedioPagoRow>(
+        r#"
+        SELECT codigo, nombre, recargo_pct, comision_pct, activo, orden
+        FROM medio_pago
+        WHERE (?1 = 0 OR activo = 1)
+        ORDER BY orden ASC, nombre ASC
+        "#,
+    )
+    .bind(solo_activos)
+    .fetch_all(pool)
+    .await?;
+
+    let planes = sqlx::query_as::<_, MedioPagoPlanRow>(
+        "SELECT codigo_medio, cuotas, recargo_pct, comision_pct FROM medio_pago_plan ORDER BY codigo_medio, cuotas",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for m in medios.iter_mut() {
+        m.planes = planes.iter().filter(|p| p.codigo_medio == m.codigo).cloned().collect();
+    }
+    Ok(medios)
+}
+
+/// Recargo y comisión de un pago. Sin plan (1 cuota) se usan los % del medio;
+/// con cuotas, los del plan. El medio tiene que existir y estar activo.
+pub async fn cargos_de_pago(
+    conn: &mut SqliteConnection,
+    medio: &str,
+    cuotas: Option<i64>,
+    monto: i64,
+) -> Result<CargosPago, String> {
+    let fila: Option<(f64, f64, i64)> = sqlx::query_as(
+        "SELECT recargo_pct, comision_pct, activo FROM medio_pago WHERE codigo = ?1",
+    )
+    .bind(medio)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (mut recargo_pct, mut comision_pct, activo) =
+        fila.ok_or_else(|| format!("Medio de pago inexistente: '{medio}'"))?;
+    if activo != 1 {
+        return Err(format!("El medio de pago '{medio}' está inactivo"));
+    }
+
+    let cuotas = cuotas.unwrap_or(1);
+    if cuotas < 1 {
+        return Err("Cantidad de cuotas inválida".into());
+    }
+    if cuotas > 1 {
+        let plan: Option<(f64, f64)> = sqlx::query_as(
+            "SELECT recargo_pct, comision_pct FROM medio_pago_plan WHERE codigo_medio = ?1 AND cuotas = ?2",
+        )
+        .bind(medio)
+        .bind(cuotas)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        (recargo_pct, comision_pct) =
+            plan.ok_or_else(|| format!("'{medio}' no tiene plan en {cuotas} cuotas"))?;
+    }
+
+    let recargo = pct_de(monto, recargo_pct);
+    Ok(CargosPago {
+        cuotas,
+        recargo,
+        comision: pct_de(monto + recargo, comision_pct),
+    })
+}
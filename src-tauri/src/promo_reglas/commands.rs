@@ -0,0 +1,75 @@
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::{requerir_admin, requerir_sesion};
+
+use super::model::{PromoReglaCrearInput, PromoReglaRow};
+use super::repo;
+
+#[tauri::command]
+pub async fn promo_regla_crear(
+    state: State<'_, AppState>,
+    input: PromoReglaCrearInput,
+) -> Result<PromoReglaRow, String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let id_regla = {
+        let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+        let id = repo::crear(&mut tx, &input).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+        id
+    };
+
+    let regla = repo::obtener(&state.pool, id_regla)
+        .await?
+        .ok_or_else(|| "Regla inexistente".to_string())?;
+
+    audit_repo::registrar(&state.pool, Some(uid), "promo_regla", Some(id_regla), "crear", None, Some(&json!(regla)))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(regla)
+}
+
+#[tauri::command]
+pub async fn promo_regla_listar(
+    state: State<'_, AppState>,
+    id_producto: Option<i64>,
+) -> Result<Vec<PromoReglaRow>, String> {
+    requerir_sesion(&state).await?;
+
+    repo::listar(&state.pool, id_producto).await
+}
+
+/// Da de baja la regla. Los carritos en curso la pierden en el próximo
+/// cambio de líneas; las ventas cerradas conservan su descuento.
+#[tauri::command]
+pub async fn promo_regla_eliminar(state: State<'_, AppState>, id_regla: i64) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let antes = repo::obtener(&state.pool, id_regla)
+        .await?
+        .ok_or_else(|| "Regla inexistente".to_string())?;
+
+    sqlx::query("UPDATE promo_regla SET activo = 0 WHERE id_regla = ?1")
+        .bind(id_regla)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &state.pool,
+        Some(uid),
+        "promo_regla",
+        Some(id_regla),
+        "eliminar",
+        Some(&json!(antes)),
+        Some(&json!({ "activo": 0 })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
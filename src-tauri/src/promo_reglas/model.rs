@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const TIPOS_REGLA: [&str; 3] = ["nxm", "segunda_unidad", "escala"];
+
+// INPUTS
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EscalaInput {
+    pub cantidad_min: i64,
+    pub precio_unitario: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromoReglaCrearInput {
+    pub nombre: String,
+    pub tipo: String, // 'nxm' | 'segunda_unidad' | 'escala'
+    pub id_producto: i64,
+    pub lleva: Option<i64>,         // nxm: N
+    pub paga: Option<i64>,          // nxm: M
+    pub pct_descuento: Option<i64>, // segunda_unidad
+    pub escalas: Option<Vec<EscalaInput>>,
+}
+
+// OUTPUTS
+
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct EscalaRow {
+    pub cantidad_min: i64,
+    pub precio_unitario: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PromoReglaRow {
+    pub id_regla: i64,
+    pub nombre: String,
+    pub tipo: String,
+    pub id_producto: i64,
+    pub producto: String,
+    pub lleva: Option<i64>,
+    pub paga: Option<i64>,
+    pub pct_descuento: Option<i64>,
+    pub activo: i64,
+    pub creado_en: String,
+    #[sqlx(skip)]
+    pub escalas: Vec<EscalaRow>,
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use sqlx::{FromRow, SqliteConnection};
+
+use super::model::EscalaRow;
+
+// Motor de reglas por cantidad. Mira las líneas de catálogo (fuera de combos)
+// de un carrito en curso y deja, por línea, a lo sumo un descuento 'monto' en
+// venta_descuento con el id_regla que lo generó: el de la regla que más
+// descuenta. Lo llama recalcular_descuentos, o sea, ante cualquier cambio en
+// las líneas.
+
+#[derive(FromRow)]
+struct Regla {
+    id_regla: i64,
+    nombre: String,
+    tipo: String,
+    id_producto: i64,
+    lleva: Option<i64>,
+    paga: Option<i64>,
+    pct_descuento: Option<i64>,
+    #[sqlx(skip)]
+    escalas: Vec<EscalaRow>,
+}
+
+/// Cuánto descuenta la regla sobre `cantidad` unidades a `precio`.
+fn descuento(r: &Regla, cantidad: i64, precio: i64) -> i64 {
+    match r.tipo.as_str() {
+        "nxm" => match (r.lleva, r.paga) {
+            (Some(n), Some(m)) if n > m && m >= 1 => (cantidad / n) * (n - m) * precio,
+            _ => 0,
+        },
+        "segunda_unidad" => (cantidad / 2) * precio * r.pct_descuento.unwrap_or(0) / 100,
+        "escala" => r
+            .escalas
+            .iter()
+            .filter(|e| e.cantidad_min <= cantidad)
+            .max_by_key(|e| e.cantidad_min)
+            .map(|e| cantidad * (precio - e.precio_unitario).max(0))
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+pub(crate) async fn evaluar(conn: &mut SqliteConnection, id_venta: i64) -> Result<(), String> {
+    let venta: Option<(String, i64)> = sqlx::query_as("SELECT estado, id_usuario FROM venta WHERE id_venta = ?1")
+        .bind(id_venta)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("reglas (venta): {e}"))?;
+    let id_usuario = match venta {
+        Some((estado, id_usuario)) if estado == "en_curso" => id_usuario,
+        _ => return Ok(()),
+    };
+
+    let lineas: Vec<(i64, i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_item, id_producto, cantidad, precio_unitario
+          FROM venta_item
+         WHERE id_venta = ?1
+           AND fuente_precio = 'catalogo'
+           AND promo_combo_id IS NULL
+           AND promo_grupo_id IS NULL
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("reglas (items): {e}"))?;
+
+    let filas: Vec<Regla> = sqlx::query_as(
+        r#"
+        SELECT r.id_regla, r.nombre, r.tipo, r.id_producto, r.lleva, r.paga, r.pct_descuento
+          FROM promo_regla r
+         WHERE r.activo = 1
+           AND r.id_producto IN (SELECT id_producto FROM venta_item WHERE id_venta = ?1)
+         ORDER BY r.id_regla
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("reglas (definiciones): {e}"))?;
+
+    let mut reglas: HashMap<i64, Vec<Regla>> = HashMap::new();
+    for mut r in filas {
+        if r.tipo == "escala" {
+            r.escalas = sqlx::query_as::<_, EscalaRow>(
+                "SELECT cantidad_min, precio_unitario FROM promo_regla_escala WHERE id_regla = ?1",
+            )
+            .bind(r.id_regla)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| format!("reglas (escalas): {e}"))?;
+        }
+        reglas.entry(r.id_producto).or_default().push(r);
+    }
+
+    // lo que ya había generado el motor: id_item -> (id_descuento, id_regla, valor)
+    let previos: Vec<(i64, i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_descuento, id_item, id_regla, valor
+          FROM venta_descuento
+         WHERE id_venta = ?1 AND id_regla IS NOT NULL
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("reglas (previos): {e}"))?;
+    let mut previos: HashMap<i64, (i64, i64, i64)> =
+        previos.into_iter().map(|(d, i, r, v)| (i, (d, r, v))).collect();
+
+    for (id_item, id_producto, cantidad, precio) in lineas {
+        let mejor = reglas
+            .get(&id_producto)
+            .into_iter()
+            .flatten()
+            .map(|r| (r, descuento(r, cantidad, precio).min(cantidad * precio)))
+            .filter(|(_, monto)| *monto > 0)
+            .max_by_key(|(_, monto)| *monto);
+
+        match (mejor, previos.remove(&id_item)) {
+            (Some((r, monto)), Some((_, id_regla, valor))) if r.id_regla == id_regla && monto == valor => {}
+            (Some((r, monto)), Some((id_descuento, _, _))) => {
+                sqlx::query("UPDATE venta_descuento SET id_regla = ?1, valor = ?2, motivo = ?3 WHERE id_descuento = ?4")
+                    .bind(r.id_regla)
+                    .bind(monto)
+                    .bind(format!("Promo: {}", r.nombre))
+                    .bind(id_descuento)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|e| format!("reglas (actualizar): {e}"))?;
+            }
+            (Some((r, monto)), None) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO venta_descuento (id_venta, id_item, tipo, valor, motivo, id_usuario, id_regla)
+                    VALUES (?1, ?2, 'monto', ?3, ?4, ?5, ?6)
+                    "#,
+                )
+                .bind(id_venta)
+                .bind(id_item)
+                .bind(monto)
+                .bind(format!("Promo: {}", r.nombre))
+                .bind(id_usuario)
+                .bind(r.id_regla)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| format!("reglas (insertar): {e}"))?;
+            }
+            (None, Some((id_descuento, _, _))) => {
+                sqlx::query("DELETE FROM venta_descuento WHERE id_descuento = ?1")
+                    .bind(id_descuento)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|e| format!("reglas (quitar): {e}"))?;
+            }
+            (None, None) => {}
+        }
+    }
+
+    // líneas que ya no califican (pasaron a un combo, precio manual...)
+    for (id_descuento, _, _) in previos.into_values() {
+        sqlx::query("DELETE FROM venta_descuento WHERE id_descuento = ?1")
+            .bind(id_descuento)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("reglas (quitar): {e}"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::commands::{venta_set_cantidad, SetCantidadInput};
+    use crate::ventas::prueba;
+    use crate::AppState;
+
+    fn regla(tipo: &str, lleva: Option<i64>, paga: Option<i64>, pct: Option<i64>, escalas: &[(i64, i64)]) -> Regla {
+        Regla {
+            id_regla: 1,
+            nombre: tipo.to_string(),
+            tipo: tipo.to_string(),
+            id_producto: 1,
+            lleva,
+            paga,
+            pct_descuento: pct,
+            escalas: escalas
+                .iter()
+                .map(|(cantidad_min, precio_unitario)| EscalaRow {
+                    cantidad_min: *cantidad_min,
+                    precio_unitario: *precio_unitario,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn nxm_descuenta_solo_los_grupos_completos() {
+        let dos_por_uno = regla("nxm", Some(2), Some(1), None, &[]);
+        let tres_por_dos = regla("nxm", Some(3), Some(2), None, &[]);
+
+        let por_cantidad = |r: &Regla| (1..=7).map(|q| descuento(r, q, 100)).collect::<Vec<_>>();
+        assert_eq!(por_cantidad(&dos_por_uno), [0, 100, 100, 200, 200, 300, 300]);
+        assert_eq!(por_cantidad(&tres_por_dos), [0, 0, 100, 100, 100, 200, 200]);
+
+        // mal definida no descuenta
+        assert_eq!(descuento(&regla("nxm", Some(2), Some(2), None, &[]), 4, 100), 0);
+        assert_eq!(descuento(&regla("nxm", None, Some(1), None, &[]), 4, 100), 0);
+    }
+
+    #[test]
+    fn segunda_unidad_redondea_para_abajo() {
+        let mitad = regla("segunda_unidad", None, None, Some(50), &[]);
+        assert_eq!(descuento(&mitad, 1, 333), 0);
+        assert_eq!(descuento(&mitad, 2, 333), 166); // 166,5
+        assert_eq!(descuento(&mitad, 3, 333), 166);
+        assert_eq!(descuento(&mitad, 4, 333), 333);
+
+        let setenta = regla("segunda_unidad", None, None, Some(70), &[]);
+        assert_eq!(descuento(&setenta, 2, 999), 699); // 699,3
+        assert_eq!(descuento(&setenta, 5, 999), 1398); // 2 * 999 * 0,7 = 1398,6
+    }
+
+    #[test]
+    fn escala_toma_el_mayor_tramo_alcanzado() {
+        // desordenadas a propósito
+        let r = regla("escala", None, None, None, &[(10, 70), (3, 90), (6, 80)]);
+        let por_cantidad = |q| descuento(&r, q, 100);
+        assert_eq!(por_cantidad(2), 0);
+        assert_eq!(por_cantidad(3), 3 * 10);
+        assert_eq!(por_cantidad(5), 5 * 10);
+        assert_eq!(por_cantidad(6), 6 * 20);
+        assert_eq!(por_cantidad(12), 12 * 30);
+
+        // un tramo más caro que el precio del carrito no recarga
+        assert_eq!(descuento(&r, 4, 85), 0);
+        assert_eq!(descuento(&regla("escala", None, None, None, &[]), 50, 100), 0);
+    }
+
+    #[tokio::test]
+    async fn evaluar_sigue_los_cambios_del_carrito() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 20).await;
+        let nxm: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_regla (nombre, tipo, id_producto, lleva, paga)
+             VALUES ('2x1 pan', 'nxm', ?1, 2, 1) RETURNING id_regla",
+        )
+        .bind(pan)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let escala: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_regla (nombre, tipo, id_producto) VALUES ('pan por mayor', 'escala', ?1) RETURNING id_regla",
+        )
+        .bind(pan)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO promo_regla_escala (id_regla, cantidad_min, precio_unitario) VALUES (?1, 10, 100)")
+            .bind(escala)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let id_venta = prueba::carrito(&state, &[(pan, 1)]).await;
+        let id_item: i64 = sqlx::query_scalar("SELECT id_item FROM venta_item WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        // (id_regla, monto) de los descuentos del motor
+        let descuentos = || {
+            sqlx::query_as::<_, (i64, i64)>(
+                "SELECT id_regla, valor FROM venta_descuento WHERE id_venta = ?1 AND id_regla IS NOT NULL",
+            )
+            .bind(id_venta)
+            .fetch_all(pool)
+        };
+        let cantidad = |cantidad| {
+            let input: SetCantidadInput =
+                serde_json::from_value(json!({ "id_item": id_item, "cantidad": cantidad })).unwrap();
+            venta_set_cantidad(state.clone(), input)
+        };
+
+        assert_eq!(descuentos().await.unwrap(), []);
+
+        cantidad(3).await.unwrap();
+        assert_eq!(descuentos().await.unwrap(), [(nxm, 300)]);
+
+        cantidad(4).await.unwrap();
+        assert_eq!(descuentos().await.unwrap(), [(nxm, 600)]);
+
+        // con 10 conviene la escala (10 * 200) sobre el 2x1 (5 * 300)
+        cantidad(10).await.unwrap();
+        assert_eq!(descuentos().await.unwrap(), [(escala, 2000)]);
+
+        cantidad(1).await.unwrap();
+        assert_eq!(descuentos().await.unwrap(), []);
+    }
+}
@@ -0,0 +1,136 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::model::{EscalaRow, PromoReglaCrearInput, PromoReglaRow, TIPOS_REGLA};
+
+const SELECT_REGLA: &str = r#"
+    SELECT r.id_regla, r.nombre, r.tipo, r.id_producto, p.nombre AS producto,
+           r.lleva, r.paga, r.pct_descuento, r.activo, r.creado_en
+      FROM promo_regla r
+      JOIN producto p ON p.id_producto = r.id_producto
+"#;
+
+fn validar(input: &PromoReglaCrearInput) -> Result<(), String> {
+    if input.nombre.trim().is_empty() {
+        return Err("nombre obligatorio".into());
+    }
+    if !TIPOS_REGLA.contains(&input.tipo.as_str()) {
+        return Err(format!("tipo inválido: {} (nxm, segunda_unidad, escala)", input.tipo));
+    }
+
+    match input.tipo.as_str() {
+        "nxm" => match (input.lleva, input.paga) {
+            (Some(n), Some(m)) if m >= 1 && n > m => Ok(()),
+            _ => Err("NxM: 'lleva' tiene que ser mayor que 'paga' (y paga al menos 1)".into()),
+        },
+        "segunda_unidad" => match input.pct_descuento {
+            Some(p) if (1..=100).contains(&p) => Ok(()),
+            _ => Err("Segunda unidad: el porcentaje va de 1 a 100".into()),
+        },
+        _ => {
+            let escalas = input.escalas.as_deref().unwrap_or_default();
+            if escalas.is_empty() {
+                return Err("Escala: cargá al menos un tramo".into());
+            }
+            if escalas.iter().any(|e| e.cantidad_min < 1 || e.precio_unitario < 0) {
+                return Err("Escala: cantidad mínima >= 1 y precio >= 0".into());
+            }
+            let mut mins: Vec<i64> = escalas.iter().map(|e| e.cantidad_min).collect();
+            mins.sort_unstable();
+            mins.dedup();
+            if mins.len() != escalas.len() {
+                return Err("Escala: hay cantidades mínimas repetidas".into());
+            }
+            Ok(())
+        }
+    }
+}
+
+pub async fn crear(conn: &mut SqliteConnection, input: &PromoReglaCrearInput) -> Result<i64, String> {
+    validar(input)?;
+
+    let existe: Option<i64> = sqlx::query_scalar("SELECT 1 FROM producto WHERE id_producto = ?1")
+        .bind(input.id_producto)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if existe.is_none() {
+        return Err("Producto inexistente".into());
+    }
+
+    let es = |t: &str| input.tipo == t;
+    let id_regla: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO promo_regla (nombre, tipo, id_producto, lleva, paga, pct_descuento)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        RETURNING id_regla
+        "#,
+    )
+    .bind(input.nombre.trim())
+    .bind(&input.tipo)
+    .bind(input.id_producto)
+    .bind(input.lleva.filter(|_| es("nxm")))
+    .bind(input.paga.filter(|_| es("nxm")))
+    .bind(input.pct_descuento.filter(|_| es("segunda_unidad")))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if es("escala") {
+        for e in input.escalas.as_deref().unwrap_or_default() {
+            sqlx::query(
+                "INSERT INTO promo_regla_escala (id_regla, cantidad_min, precio_unitario) VALUES (?1, ?2, ?3)",
+            )
+            .bind(id_regla)
+            .bind(e.cantidad_min)
+            .bind(e.precio_unitario)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(id_regla)
+}
+
+async fn con_escalas(pool: &SqlitePool, mut r: PromoReglaRow) -> Result<PromoReglaRow, String> {
+    if r.tipo == "escala" {
+        r.escalas = sqlx::query_as::<_, EscalaRow>(
+            "SELECT cantidad_min, precio_unitario FROM promo_regla_escala WHERE id_regla = ?1 ORDER BY cantidad_min",
+        )
+        .bind(r.id_regla)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(r)
+}
+
+pub async fn obtener(pool: &SqlitePool, id_regla: i64) -> Result<Option<PromoReglaRow>, String> {
+    let sql = format!("{SELECT_REGLA} WHERE r.id_regla = ?1");
+    let r = sqlx::query_as::<_, PromoReglaRow>(&sql)
+        .bind(id_regla)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    match r {
+        Some(r) => Ok(Some(con_escalas(pool, r).await?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn listar(pool: &SqlitePool, id_producto: Option<i64>) -> Result<Vec<PromoReglaRow>, String> {
+    let sql = format!(
+        "{SELECT_REGLA} WHERE r.activo = 1 AND (?1 IS NULL OR r.id_producto = ?1) ORDER BY p.nombre, r.id_regla"
+    );
+    let filas = sqlx::query_as::<_, PromoReglaRow>(&sql)
+        .bind(id_producto)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::with_capacity(filas.len());
+    for r in filas {
+        out.push(con_escalas(pool, r).await?);
+    }
+    Ok(out)
+}
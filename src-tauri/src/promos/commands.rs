@@ -1,20 +1,46 @@
 use tauri::State;
 
 use crate::AppState;
-use crate::promos::{model::*, repo};
+use crate::promos::{deteccion, model::*, repo};
+use crate::ventas::commands::auditar_venta;
+use crate::ventas::descuentos;
+use crate::ventas::estacionadas::carrito_activo;
+use crate::users::permisos::{requerir_admin, requerir_sesion};
+use crate::audit::repo as audit_repo;
+use serde_json::json;
 
 #[tauri::command(rename = "promo_combo_crear")]
 pub async fn promo_combo_crear(
     state: State<'_, AppState>,
     input: PromoComboCrearInput,
 ) -> Result<i64, String> {
-    repo::promo_combo_crear_db(&state.pool, input).await
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let despues = json!({
+        "nombre": input.nombre.trim(),
+        "precio_pack": input.precio_pack,
+        "precio_min_total": input.precio_min_total,
+        "items": input.items.iter()
+            .map(|it| json!({ "id_producto": it.id_producto, "cantidad": it.cantidad }))
+            .collect::<Vec<_>>(),
+        "vigencia": input.vigencia,
+    });
+
+    let id_combo = repo::promo_combo_crear_db(&state.pool, input).await?;
+
+    audit_repo::registrar(&state.pool, Some(uid), "promo_combo", Some(id_combo), "crear", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id_combo)
 }
 
 #[tauri::command(rename = "promo_combo_listar")]
 pub async fn promo_combo_listar(
     state: State<'_, AppState>,
-) -> Result<Vec<PromoComboRow>, String> {
+) -> Result<PromoComboListado, String> {
+    requerir_sesion(&state).await?;
+
     repo::promo_combo_listar_db(&state.pool).await
 }
 
@@ -23,17 +49,57 @@ pub async fn promo_combo_detalle(
     state: State<'_, AppState>,
     id_combo: i64,
 ) -> Result<PromoComboDetalle, String> {
+    requerir_sesion(&state).await?;
+
     repo::promo_combo_detalle_db(&state.pool, id_combo).await
 }
 
+#[tauri::command(rename = "promo_combo_vigencia_fijar")]
+pub async fn promo_combo_vigencia_fijar(
+    state: State<'_, AppState>,
+    id_combo: i64,
+    vigencia: PromoVigenciaInput,
+) -> Result<PromoComboDetalle, String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let antes = repo::promo_combo_detalle_db(&state.pool, id_combo).await?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    repo::promo_combo_vigencia_fijar_db(&mut tx, id_combo, &vigencia).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let despues = repo::promo_combo_detalle_db(&state.pool, id_combo).await?;
+
+    audit_repo::registrar(
+        &state.pool,
+        Some(uid),
+        "promo_combo",
+        Some(id_combo),
+        "vigencia",
+        Some(&json!(antes.combo)),
+        Some(&json!(despues.combo)),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(despues)
+}
 
 #[tauri::command(rename = "promo_combo_eliminar")]
 pub async fn promo_combo_eliminar(
     state: tauri::State<'_, crate::AppState>,
     id_combo: i64,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     let pool = &state.pool;
 
+    let antes: Option<i64> = sqlx::query_scalar("SELECT activo FROM promo_combo WHERE id_combo = ?")
+        .bind(id_combo)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
     let res = sqlx::query(
         "UPDATE promo_combo SET activo = 0 WHERE id_combo = ?"
     )
@@ -46,5 +112,99 @@ pub async fn promo_combo_eliminar(
         return Err("Combo no encontrado.".to_string());
     }
 
+    audit_repo::registrar(
+        pool,
+        Some(uid),
+        "promo_combo",
+        Some(id_combo),
+        "eliminar",
+        antes.map(|a| json!({ "activo": a })).as_ref(),
+        Some(&json!({ "activo": 0 })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
     Ok(())
-}
\ No newline at end of file
+}
+// DETECCIÓN EN EL CARRITO
+
+/// Combos que entran con lo que hay en el carrito. Con `aplicar`, además
+/// aplica la mejor combinación.
+#[tauri::command(rename = "venta_combos_detectar")]
+pub async fn venta_combos_detectar(
+    state: State<'_, AppState>,
+    id_venta: i64,
+    aplicar: Option<bool>,
+) -> Result<ComboDeteccion, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let (coincidencias, mejor) = deteccion::detectar(&mut tx, id_venta).await?;
+    let ahorro_total = mejor.iter().map(|s| s.ahorro_total).sum();
+
+    let aplicado = aplicar.unwrap_or(false) && !mejor.is_empty();
+    if aplicado {
+        carrito_activo(&mut tx, &sesion, id_venta).await?;
+
+        let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
+            .await
+            .map_err(|e| e.to_string())?;
+        deteccion::aplicar_mejor(&mut tx, id_venta).await?;
+        descuentos::recalcular_descuentos(&mut tx, id_venta).await?;
+        auditar_venta(&mut tx, uid, id_venta, "aplicar_combos_auto", antes).await?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(ComboDeteccion { coincidencias, mejor, ahorro_total, aplicado })
+}
+
+#[tauri::command(rename = "promo_combo_auto_obtener")]
+pub async fn promo_combo_auto_obtener(state: State<'_, AppState>) -> Result<bool, String> {
+    requerir_sesion(&state).await?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+    deteccion::auto_habilitado(&mut conn).await
+}
+
+/// Aplicar combos solos en cada venta_agregar_item.
+#[tauri::command(rename = "promo_combo_auto_fijar")]
+pub async fn promo_combo_auto_fijar(
+    state: State<'_, AppState>,
+    activo: bool,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    let antes = deteccion::auto_habilitado(&mut tx).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO ajuste (clave, valor) VALUES (?1, ?2)
+        ON CONFLICT(clave) DO UPDATE
+          SET valor = excluded.valor,
+              actualizado_en = DATETIME('now','localtime')
+        "#,
+    )
+    .bind(deteccion::AJUSTE_COMBO_AUTO)
+    .bind(if activo { "1" } else { "0" })
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "ajuste",
+        None,
+        deteccion::AJUSTE_COMBO_AUTO,
+        Some(&json!({ "activo": antes })),
+        Some(&json!({ "activo": activo })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use sqlx::SqliteConnection;
+
+use crate::promos::model::{ComboCoincidencia, ComboSeleccion};
+use crate::ventas::repo as ventas_repo;
+
+// Detección de combos: cruza lo que hay libre en el carrito (líneas de
+// catálogo fuera de cualquier combo) con los combos vigentes ahora y con precio.
+
+pub const AJUSTE_COMBO_AUTO: &str = "promo_combo_auto";
+
+// Tope de nodos al buscar la mejor combinación. Si se agota, queda la mejor
+// encontrada hasta ahí (no necesariamente la óptima); como la primera hoja
+// que se visita es la golosa (más ahorro primero), nunca es peor que esa.
+const PRESUPUESTO_BUSQUEDA: u32 = 20_000;
+
+struct Combo {
+    coincidencia: ComboCoincidencia,
+    items: Vec<(i64, i64)>, // (id_producto, cantidad)
+}
+
+async fn libres_por_producto(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<HashMap<i64, (i64, i64)>, String> {
+    // id_producto -> (cantidad libre, precio de la línea más nueva)
+    let mut libres: HashMap<i64, (i64, i64)> = HashMap::new();
+    for l in ventas_repo::lineas_libres(conn, id_venta, None).await? {
+        libres
+            .entry(l.id_producto)
+            .and_modify(|(c, _)| *c += l.cantidad)
+            .or_insert((l.cantidad, l.precio_unitario));
+    }
+    Ok(libres)
+}
+
+async fn combos_en_carrito(
+    conn: &mut SqliteConnection,
+    libres: &HashMap<i64, (i64, i64)>,
+) -> Result<Vec<Combo>, String> {
+    let combos: Vec<(i64, String, i64)> = sqlx::query_as(
+        r#"
+        SELECT c.id_combo, c.nombre, c.precio_pack
+          FROM promo_combo c
+          JOIN v_promo_combo_estado e ON e.id_combo = c.id_combo
+         WHERE e.estado = 'vigente'
+           AND e.en_horario = 1
+           AND c.precio_pack > 0
+           AND c.precio_pack >= c.precio_min_total
+         ORDER BY c.id_combo
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let items: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT pci.id_combo, pci.id_producto, pci.cantidad
+          FROM promo_combo_item pci
+          JOIN promo_combo c ON c.id_combo = pci.id_combo
+         WHERE c.activo = 1
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for (id_combo, nombre, precio_pack) in combos {
+        let del_combo: Vec<(i64, i64)> = items
+            .iter()
+            .filter(|(c, _, _)| *c == id_combo)
+            .map(|(_, p, q)| (*p, *q))
+            .collect();
+        if del_combo.is_empty() {
+            continue;
+        }
+
+        let veces = del_combo
+            .iter()
+            .map(|(p, q)| libres.get(p).map(|(c, _)| c / q).unwrap_or(0))
+            .min()
+            .unwrap_or(0);
+        if veces == 0 {
+            continue;
+        }
+
+        let precio_lista: i64 = del_combo
+            .iter()
+            .map(|(p, q)| libres.get(p).map(|(_, precio)| precio * q).unwrap_or(0))
+            .sum();
+
+        out.push(Combo {
+            coincidencia: ComboCoincidencia {
+                id_combo,
+                nombre,
+                precio_pack,
+                precio_lista,
+                ahorro: precio_lista - precio_pack,
+                veces,
+            },
+            items: del_combo,
+        });
+    }
+    Ok(out)
+}
+
+/// Mejor combinación sin solaparse (cada unidad del carrito entra en un solo
+/// combo), dentro del presupuesto de búsqueda. Sólo cuenta combos que ahorran algo.
+fn mejor_conjunto(combos: &[Combo], libres: &HashMap<i64, (i64, i64)>) -> Vec<ComboSeleccion> {
+    let mut candidatos: Vec<&Combo> = combos.iter().filter(|c| c.coincidencia.ahorro > 0).collect();
+    candidatos.sort_by_key(|c| std::cmp::Reverse(c.coincidencia.ahorro));
+
+    let mut disponible: HashMap<i64, i64> = libres.iter().map(|(p, (c, _))| (*p, *c)).collect();
+    let mut actual = vec![0i64; candidatos.len()];
+    let mut mejor = (0i64, actual.clone());
+    let mut presupuesto = PRESUPUESTO_BUSQUEDA;
+
+    fn buscar(
+        i: usize,
+        candidatos: &[&Combo],
+        disponible: &mut HashMap<i64, i64>,
+        actual: &mut [i64],
+        ahorro: i64,
+        mejor: &mut (i64, Vec<i64>),
+        presupuesto: &mut u32,
+    ) {
+        if *presupuesto == 0 {
+            return;
+        }
+        *presupuesto -= 1;
+
+        if i == candidatos.len() {
+            if ahorro > mejor.0 {
+                *mejor = (ahorro, actual.to_vec());
+            }
+            return;
+        }
+
+        let c = candidatos[i];
+        let max = c
+            .items
+            .iter()
+            .map(|(p, q)| disponible.get(p).copied().unwrap_or(0) / q)
+            .min()
+            .unwrap_or(0);
+
+        for k in (0..=max).rev() {
+            for (p, q) in &c.items {
+                *disponible.entry(*p).or_insert(0) -= q * k;
+            }
+            actual[i] = k;
+            buscar(i + 1, candidatos, disponible, actual, ahorro + k * c.coincidencia.ahorro, mejor, presupuesto);
+            for (p, q) in &c.items {
+                *disponible.entry(*p).or_insert(0) += q * k;
+            }
+        }
+        actual[i] = 0;
+    }
+
+    buscar(0, &candidatos, &mut disponible, &mut actual, 0, &mut mejor, &mut presupuesto);
+
+    candidatos
+        .iter()
+        .zip(mejor.1)
+        .filter(|(_, veces)| *veces > 0)
+        .map(|(c, veces)| ComboSeleccion {
+            id_combo: c.coincidencia.id_combo,
+            nombre: c.coincidencia.nombre.clone(),
+            veces,
+            ahorro_total: veces * c.coincidencia.ahorro,
+        })
+        .collect()
+}
+
+/// Combos que entran en el carrito y la mejor combinación para aplicar.
+pub(crate) async fn detectar(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<(Vec<ComboCoincidencia>, Vec<ComboSeleccion>), String> {
+    let libres = libres_por_producto(conn, id_venta).await?;
+    let combos = combos_en_carrito(conn, &libres).await?;
+    let mejor = mejor_conjunto(&combos, &libres);
+    Ok((combos.into_iter().map(|c| c.coincidencia).collect(), mejor))
+}
+
+/// Aplica la mejor combinación (cada vez con el precio del pack). El
+/// llamador recalcula descuentos y audita.
+pub(crate) async fn aplicar_mejor(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<Vec<ComboSeleccion>, String> {
+    let (_, mejor) = detectar(conn, id_venta).await?;
+
+    for s in &mejor {
+        let precio_pack: i64 = sqlx::query_scalar("SELECT precio_pack FROM promo_combo WHERE id_combo = ?1")
+            .bind(s.id_combo)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        for _ in 0..s.veces {
+            ventas_repo::venta_aplicar_promo_combo_db(conn, id_venta, s.id_combo, precio_pack).await?;
+        }
+    }
+    Ok(mejor)
+}
+
+pub(crate) async fn auto_habilitado(conn: &mut SqliteConnection) -> Result<bool, String> {
+    let valor: Option<String> = sqlx::query_scalar("SELECT valor FROM ajuste WHERE clave = ?1")
+        .bind(AJUSTE_COMBO_AUTO)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(valor.as_deref() == Some("1"))
+}
+
+/// Si el ajuste está activo, aplica solo lo que convenga (al agregar ítems).
+pub(crate) async fn autoaplicar(conn: &mut SqliteConnection, id_venta: i64) -> Result<(), String> {
+    if auto_habilitado(conn).await? {
+        aplicar_mejor(conn, id_venta).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(id_combo: i64, ahorro: i64, items: &[(i64, i64)]) -> Combo {
+        Combo {
+            coincidencia: ComboCoincidencia {
+                id_combo,
+                nombre: format!("combo {id_combo}"),
+                precio_pack: 1000,
+                precio_lista: 1000 + ahorro,
+                ahorro,
+                veces: 1,
+            },
+            items: items.to_vec(),
+        }
+    }
+
+    fn libres(cantidades: &[(i64, i64)]) -> HashMap<i64, (i64, i64)> {
+        cantidades.iter().map(|(p, c)| (*p, (*c, 100))).collect()
+    }
+
+    fn elegidos(s: &[ComboSeleccion]) -> Vec<(i64, i64, i64)> {
+        s.iter().map(|s| (s.id_combo, s.veces, s.ahorro_total)).collect()
+    }
+
+    #[test]
+    fn no_solapa_unidades_y_supera_a_la_golosa() {
+        // el 1 ahorra más por vez, pero usar dos veces el 2 rinde más
+        let combos = [combo(1, 300, &[(10, 2)]), combo(2, 200, &[(10, 1), (20, 1)])];
+        let s = mejor_conjunto(&combos, &libres(&[(10, 2), (20, 2)]));
+        assert_eq!(elegidos(&s), [(2, 2, 400)]);
+
+        // con unidades de sobra entran los dos
+        let s = mejor_conjunto(&combos, &libres(&[(10, 4), (20, 2)]));
+        assert_eq!(elegidos(&s), [(1, 1, 300), (2, 2, 400)]);
+    }
+
+    #[test]
+    fn ignora_combos_sin_ahorro_o_sin_unidades() {
+        let combos = [combo(1, 0, &[(10, 1)]), combo(2, -50, &[(10, 1)]), combo(3, 100, &[(30, 1)])];
+        assert!(mejor_conjunto(&combos, &libres(&[(10, 5)])).is_empty());
+        assert!(mejor_conjunto(&[], &libres(&[(10, 5)])).is_empty());
+    }
+
+    #[test]
+    fn aplica_el_mismo_combo_varias_veces() {
+        let combos = [combo(1, 150, &[(10, 2), (20, 1)])];
+        let s = mejor_conjunto(&combos, &libres(&[(10, 7), (20, 5)]));
+        assert_eq!(elegidos(&s), [(1, 3, 450)]);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod model;
 pub mod commands;   
-pub mod repo;
\ No newline at end of file
+pub mod repo;
+pub mod deteccion;
@@ -6,12 +6,24 @@ pub struct PromoComboItemInput {
     pub cantidad: i64,
 }
 
+/// Vigencia de un combo. Todo opcional: sin datos, rige siempre.
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct PromoVigenciaInput {
+    pub vigente_desde: Option<String>, // "YYYY-MM-DD", inclusive
+    pub vigente_hasta: Option<String>, // "YYYY-MM-DD", inclusive
+    pub dias_semana: Option<i64>,      // bit 0 = domingo ... bit 6 = sábado
+    pub hora_desde: Option<String>,    // "HH:MM"
+    pub hora_hasta: Option<String>,    // "HH:MM" (excluida)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PromoComboCrearInput {
     pub nombre: String,
     pub precio_pack: i64,
     pub precio_min_total: i64,
     pub items: Vec<PromoComboItemInput>,
+    #[serde(default)]
+    pub vigencia: PromoVigenciaInput,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -22,6 +34,21 @@ pub struct PromoComboRow {
     pub precio_min_total: i64,
     pub activo: i64,
     pub creado_en: String,
+    pub vigente_desde: Option<String>,
+    pub vigente_hasta: Option<String>,
+    pub dias_semana: i64,
+    pub hora_desde: Option<String>,
+    pub hora_hasta: Option<String>,
+    pub estado: String,   // 'vigente' | 'proxima' | 'vencida' | 'inactiva'
+    pub en_horario: bool, // el día y la hora actuales entran en la franja
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct PromoComboListado {
+    pub vigentes: Vec<PromoComboRow>,         // se pueden aplicar ahora
+    pub fuera_de_horario: Vec<PromoComboRow>, // vigentes, pero no en este día/hora
+    pub proximas: Vec<PromoComboRow>,
+    pub vencidas: Vec<PromoComboRow>,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -49,3 +76,31 @@ pub struct PromoComboListadoRow {
     pub activo: i64,
     pub resumen: String,
 }
+
+// Detección de combos en el carrito
+
+#[derive(Debug, Serialize)]
+pub struct ComboCoincidencia {
+    pub id_combo: i64,
+    pub nombre: String,
+    pub precio_pack: i64,
+    pub precio_lista: i64, // lo que sale suelto con los precios del carrito
+    pub ahorro: i64,       // por cada vez que se aplica
+    pub veces: i64,        // cuántas veces entra con lo que hay libre
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComboSeleccion {
+    pub id_combo: i64,
+    pub nombre: String,
+    pub veces: i64,
+    pub ahorro_total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComboDeteccion {
+    pub coincidencias: Vec<ComboCoincidencia>,
+    pub mejor: Vec<ComboSeleccion>, // combinación sin solaparse que más ahorra
+    pub ahorro_total: i64,
+    pub aplicado: bool,
+}
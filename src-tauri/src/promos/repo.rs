@@ -1,9 +1,29 @@
-use sqlx::{SqlitePool, Sqlite, Transaction};
+use sqlx::{SqliteConnection, SqlitePool, Sqlite, Transaction};
 
 use crate::promos::model::{
-    PromoComboCrearInput, PromoComboRow, PromoComboItemRow, PromoComboDetalle,
+    PromoComboCrearInput, PromoComboRow, PromoComboItemRow, PromoComboDetalle, PromoComboListado,
+    PromoVigenciaInput,
 };
 
+const SELECT_COMBO: &str = r#"
+    SELECT
+        c.id_combo,
+        c.nombre,
+        c.precio_pack,
+        c.precio_min_total,
+        c.activo,
+        c.creado_en,
+        c.vigente_desde,
+        c.vigente_hasta,
+        c.dias_semana,
+        c.hora_desde,
+        c.hora_hasta,
+        e.estado,
+        e.en_horario
+    FROM promo_combo c
+    JOIN v_promo_combo_estado e ON e.id_combo = c.id_combo
+"#;
+
 /// Prorratea total_pack proporcional al "peso" (precio_normal * cantidad), exacto sin floats
 /// Devuelve (id_producto, total_asignado_al_producto)
 pub(crate) fn repartir_total_proporcional(
@@ -58,6 +78,74 @@ pub(crate) fn dividir_total_en_unitarios(total: i64, qty: i64) -> Result<(i64, i
     Ok((total / qty, total % qty))
 }
 
+/// Valida y normaliza la vigencia (fechas y horas con ceros a la izquierda,
+/// para que se comparen bien como texto en SQLite).
+pub(crate) fn validar_vigencia(v: &PromoVigenciaInput) -> Result<PromoVigenciaInput, String> {
+    let fecha = |f: &Option<String>| -> Result<Option<chrono::NaiveDate>, String> {
+        match f.as_deref().map(str::trim).filter(|x| !x.is_empty()) {
+            Some(x) => chrono::NaiveDate::parse_from_str(x, "%Y-%m-%d")
+                .map(Some)
+                .map_err(|_| format!("Fecha inválida: {x} (YYYY-MM-DD)")),
+            None => Ok(None),
+        }
+    };
+    let hora = |h: &Option<String>| -> Result<Option<chrono::NaiveTime>, String> {
+        match h.as_deref().map(str::trim).filter(|x| !x.is_empty()) {
+            Some(x) => chrono::NaiveTime::parse_from_str(x, "%H:%M")
+                .map(Some)
+                .map_err(|_| format!("Hora inválida: {x} (HH:MM)")),
+            None => Ok(None),
+        }
+    };
+
+    let (desde, hasta) = (fecha(&v.vigente_desde)?, fecha(&v.vigente_hasta)?);
+    if let (Some(d), Some(h)) = (desde, hasta) {
+        if d > h {
+            return Err("La vigencia termina antes de empezar".into());
+        }
+    }
+
+    let dias = v.dias_semana.unwrap_or(127);
+    if !(1..=127).contains(&dias) {
+        return Err("Días de la semana inválidos (al menos uno)".into());
+    }
+
+    let (hd, hh) = (hora(&v.hora_desde)?, hora(&v.hora_hasta)?);
+    match (hd, hh) {
+        (Some(a), Some(b)) if a == b => return Err("La franja horaria está vacía".into()),
+        (Some(_), None) | (None, Some(_)) => return Err("La franja horaria necesita hora desde y hasta".into()),
+        _ => {}
+    }
+
+    Ok(PromoVigenciaInput {
+        vigente_desde: desde.map(|d| d.format("%Y-%m-%d").to_string()),
+        vigente_hasta: hasta.map(|d| d.format("%Y-%m-%d").to_string()),
+        dias_semana: Some(dias),
+        hora_desde: hd.map(|t| t.format("%H:%M").to_string()),
+        hora_hasta: hh.map(|t| t.format("%H:%M").to_string()),
+    })
+}
+
+/// Error si el combo no se puede aplicar ahora (inactivo, fuera de fechas o
+/// fuera de su franja).
+pub(crate) async fn verificar_aplicable(conn: &mut SqliteConnection, id_combo: i64) -> Result<(), String> {
+    let estado: Option<(String, bool)> =
+        sqlx::query_as("SELECT estado, en_horario FROM v_promo_combo_estado WHERE id_combo = ?1")
+            .bind(id_combo)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    match estado {
+        None => Err("Combo no encontrado".into()),
+        Some((e, _)) if e == "inactiva" => Err("La promoción no está activa".into()),
+        Some((e, _)) if e == "proxima" => Err("La promoción todavía no empezó".into()),
+        Some((e, _)) if e == "vencida" => Err("La promoción está vencida".into()),
+        Some((_, false)) => Err("La promoción no rige en este día u horario".into()),
+        Some(_) => Ok(()),
+    }
+}
+
 pub async fn promo_combo_crear_db(
     pool: &SqlitePool,
     input: PromoComboCrearInput,
@@ -74,18 +162,27 @@ pub async fn promo_combo_crear_db(
     if input.items.iter().any(|it| it.cantidad <= 0) {
         return Err("cantidad debe ser > 0".into());
     }
+    let vigencia = validar_vigencia(&input.vigencia)?;
 
     let mut tx: Transaction<'_, Sqlite> = pool.begin().await.map_err(|e| e.to_string())?;
 
     let res = sqlx::query(
     r#"
-    INSERT INTO promo_combo (nombre, precio_pack, precio_min_total, activo)
-    VALUES (?, ?, ?, 1)
+    INSERT INTO promo_combo (
+      nombre, precio_pack, precio_min_total, activo,
+      vigente_desde, vigente_hasta, dias_semana, hora_desde, hora_hasta
+    )
+    VALUES (?, ?, ?, 1, ?, ?, ?, ?, ?)
     "#
     )
     .bind(input.nombre.trim())
     .bind(input.precio_pack)
     .bind(input.precio_min_total)
+    .bind(&vigencia.vigente_desde)
+    .bind(&vigencia.vigente_hasta)
+    .bind(vigencia.dias_semana)
+    .bind(&vigencia.hora_desde)
+    .bind(&vigencia.hora_hasta)
     .execute(&mut *tx)
     .await
     .map_err(|e| e.to_string())?;
@@ -111,52 +208,71 @@ pub async fn promo_combo_crear_db(
     Ok(id_combo)
 }
 
+/// Combos activos separados por su vigencia de hoy.
 pub async fn promo_combo_listar_db(
     pool: &SqlitePool,
-) -> Result<Vec<PromoComboRow>, String> {
-    let rows = sqlx::query_as::<_, PromoComboRow>(
+) -> Result<PromoComboListado, String> {
+    let sql = format!("{SELECT_COMBO} WHERE c.activo = 1 ORDER BY c.id_combo DESC");
+    let rows = sqlx::query_as::<_, PromoComboRow>(&sql)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut out = PromoComboListado::default();
+    for r in rows {
+        match (r.estado.as_str(), r.en_horario) {
+            ("proxima", _) => out.proximas.push(r),
+            ("vencida", _) => out.vencidas.push(r),
+            (_, true) => out.vigentes.push(r),
+            (_, false) => out.fuera_de_horario.push(r),
+        }
+    }
+    Ok(out)
+}
+
+pub async fn promo_combo_vigencia_fijar_db(
+    conn: &mut SqliteConnection,
+    id_combo: i64,
+    vigencia: &PromoVigenciaInput,
+) -> Result<(), String> {
+    let v = validar_vigencia(vigencia)?;
+
+    let res = sqlx::query(
         r#"
-        SELECT
-            id_combo,
-            nombre,
-            precio_pack,
-            precio_min_total,
-            activo,
-            creado_en
-        FROM promo_combo
-        WHERE activo = 1
-        ORDER BY id_combo DESC
-        "#
+        UPDATE promo_combo
+           SET vigente_desde = ?1, vigente_hasta = ?2, dias_semana = ?3,
+               hora_desde = ?4, hora_hasta = ?5
+         WHERE id_combo = ?6
+        "#,
     )
-    .fetch_all(pool)
+    .bind(&v.vigente_desde)
+    .bind(&v.vigente_hasta)
+    .bind(v.dias_semana)
+    .bind(&v.hora_desde)
+    .bind(&v.hora_hasta)
+    .bind(id_combo)
+    .execute(&mut *conn)
     .await
     .map_err(|e| e.to_string())?;
 
-    Ok(rows)
+    if res.rows_affected() == 0 {
+        return Err("Combo no encontrado.".into());
+    }
+    Ok(())
 }
+
 pub async fn promo_combo_detalle_db(
     pool: &SqlitePool,
     id_combo: i64,
 ) -> Result<PromoComboDetalle, String> {
 
     // ── combo ─────────────────────────────
-    let combo = sqlx::query_as::<_, PromoComboRow>(
-        r#"
-        SELECT
-            id_combo,
-            nombre,
-            precio_pack,
-            precio_min_total,
-            activo,
-            creado_en
-        FROM promo_combo
-        WHERE id_combo = ?
-        "#
-    )
-    .bind(id_combo)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    let sql = format!("{SELECT_COMBO} WHERE c.id_combo = ?");
+    let combo = sqlx::query_as::<_, PromoComboRow>(&sql)
+        .bind(id_combo)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
 
     // ── items ─────────────────────────────
     let items = sqlx::query_as::<_, PromoComboItemRow>(
@@ -185,4 +301,129 @@ pub async fn promo_combo_detalle_db(
         items,
         total_sugerido,
     })
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn vigencia(v: serde_json::Value) -> PromoVigenciaInput {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn vigencia_normalizada() {
+        let v = validar_vigencia(&vigencia(json!({
+            "vigente_desde": " 2026-3-1 ", "vigente_hasta": "2026-03-01",
+            "hora_desde": "9:05", "hora_hasta": "22:00"
+        })))
+        .unwrap();
+        assert_eq!(v.vigente_desde.as_deref(), Some("2026-03-01"));
+        assert_eq!(v.vigente_hasta.as_deref(), Some("2026-03-01"));
+        assert_eq!((v.hora_desde.as_deref(), v.hora_hasta.as_deref()), (Some("09:05"), Some("22:00")));
+        assert_eq!(v.dias_semana, Some(127));
+
+        // vacío = rige siempre; la franja puede cruzar la medianoche
+        let v = validar_vigencia(&vigencia(json!({ "vigente_desde": "", "hora_desde": "22:00", "hora_hasta": "02:00" })))
+            .unwrap();
+        assert_eq!(v.vigente_desde, None);
+        assert_eq!((v.hora_desde.as_deref(), v.hora_hasta.as_deref()), (Some("22:00"), Some("02:00")));
+    }
+
+    #[test]
+    fn vigencia_invalida() {
+        let err = |v| validar_vigencia(&vigencia(v)).unwrap_err();
+        assert_eq!(err(json!({ "vigente_desde": "01/03/2026" })), "Fecha inválida: 01/03/2026 (YYYY-MM-DD)");
+        assert_eq!(
+            err(json!({ "vigente_desde": "2026-03-02", "vigente_hasta": "2026-03-01" })),
+            "La vigencia termina antes de empezar"
+        );
+        assert_eq!(err(json!({ "dias_semana": 0 })), "Días de la semana inválidos (al menos uno)");
+        assert_eq!(err(json!({ "dias_semana": 128 })), "Días de la semana inválidos (al menos uno)");
+        assert_eq!(err(json!({ "hora_desde": "25:00", "hora_hasta": "02:00" })), "Hora inválida: 25:00 (HH:MM)");
+        assert_eq!(err(json!({ "hora_desde": "10:00", "hora_hasta": "10:00" })), "La franja horaria está vacía");
+        assert_eq!(
+            err(json!({ "hora_desde": "10:00" })),
+            "La franja horaria necesita hora desde y hasta"
+        );
+    }
+
+    async fn ahora(pool: &SqlitePool, expr: &str) -> String {
+        sqlx::query_scalar(&format!("SELECT {expr}")).fetch_one(pool).await.unwrap()
+    }
+
+    // Las franjas se arman alrededor de la hora actual con un par de minutos
+    // de margen, para que el test no dependa de cuándo corre.
+    #[tokio::test]
+    async fn estado_segun_fechas_dias_y_franja() {
+        let pool = crate::db::pool_prueba().await;
+
+        let hoy = ahora(&pool, "DATE('now','localtime')").await;
+        let ayer = ahora(&pool, "DATE('now','localtime','-1 day')").await;
+        let manana = ahora(&pool, "DATE('now','localtime','+1 day')").await;
+        let hace_1 = ahora(&pool, "strftime('%H:%M','now','localtime','-1 minutes')").await;
+        let hace_2 = ahora(&pool, "strftime('%H:%M','now','localtime','-2 minutes')").await;
+        let en_2 = ahora(&pool, "strftime('%H:%M','now','localtime','+2 minutes')").await;
+        let dia: i64 = sqlx::query_scalar("SELECT CAST(strftime('%w','now','localtime') AS INTEGER)")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let hoy_solo = 1 << dia;
+
+        let casos = [
+            ("hasta hoy", json!({ "vigente_hasta": hoy })),
+            ("vencida ayer", json!({ "vigente_hasta": ayer })),
+            ("empieza mañana", json!({ "vigente_desde": manana })),
+            ("sólo hoy", json!({ "dias_semana": hoy_solo })),
+            ("menos hoy", json!({ "dias_semana": 127 & !hoy_solo })),
+            // cruzan la medianoche: de hace un minuto hasta hace dos (casi todo
+            // el día) y de dentro de dos hasta hace uno (deja afuera ahora)
+            ("nocturna adentro", json!({ "hora_desde": hace_1, "hora_hasta": hace_2 })),
+            ("nocturna afuera", json!({ "hora_desde": en_2, "hora_hasta": hace_1 })),
+        ];
+        for (nombre, v) in casos {
+            let v = validar_vigencia(&vigencia(v)).unwrap();
+            sqlx::query(
+                "INSERT INTO promo_combo (nombre, precio_pack, vigente_desde, vigente_hasta, dias_semana, hora_desde, hora_hasta)
+                 VALUES (?1, 1000, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .bind(nombre)
+            .bind(&v.vigente_desde)
+            .bind(&v.vigente_hasta)
+            .bind(v.dias_semana)
+            .bind(&v.hora_desde)
+            .bind(&v.hora_hasta)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let estados: Vec<(String, String, bool)> = sqlx::query_as(
+            "SELECT c.nombre, e.estado, e.en_horario
+               FROM promo_combo c JOIN v_promo_combo_estado e ON e.id_combo = c.id_combo
+              ORDER BY c.id_combo",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let esperado = [
+            ("hasta hoy", "vigente", true),
+            ("vencida ayer", "vencida", true),
+            ("empieza mañana", "proxima", true),
+            ("sólo hoy", "vigente", true),
+            ("menos hoy", "vigente", false),
+            ("nocturna adentro", "vigente", true),
+            ("nocturna afuera", "vigente", false),
+        ];
+        let estados: Vec<(&str, &str, bool)> = estados.iter().map(|(n, e, h)| (n.as_str(), e.as_str(), *h)).collect();
+        assert_eq!(estados, esperado);
+
+        let listado = promo_combo_listar_db(&pool).await.unwrap();
+        let nombres = |l: &[PromoComboRow]| l.iter().map(|c| c.nombre.clone()).collect::<Vec<_>>();
+        assert_eq!(nombres(&listado.vigentes), ["nocturna adentro", "sólo hoy", "hasta hoy"]);
+        assert_eq!(nombres(&listado.fuera_de_horario), ["nocturna afuera", "menos hoy"]);
+        assert_eq!(nombres(&listado.proximas), ["empieza mañana"]);
+        assert_eq!(nombres(&listado.vencidas), ["vencida ayer"]);
+    }
+}
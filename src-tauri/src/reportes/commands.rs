@@ -5,6 +5,7 @@ use crate::AppState;
 
 // Necesario para query_as::<_, T>
 use sqlx::FromRow;
+use crate::users::permisos::requerir_admin;
 
 #[derive(Serialize, FromRow)]
 pub struct VentaAdminRow {
@@ -47,6 +48,8 @@ pub async fn admin_historial_dia(
     fecha: Option<String>,   
     id_usuario: Option<i64>, 
 ) -> Result<AdminHistorialDia, String> {
+    requerir_admin(&state).await?;
+
     use sqlx::Row;
 
     let pool = &state.pool;
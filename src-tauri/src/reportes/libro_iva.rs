@@ -0,0 +1,261 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use tauri::State;
+
+use crate::fiscal::repo as fiscal_repo;
+use crate::reportes::rentabilidad::month_bounds_from_ym;
+use crate::users::permisos::requerir_admin;
+use crate::AppState;
+
+// Libro IVA Ventas: un renglón por venta finalizada del mes y uno negativo
+// por cada devolución. Los importes salen de la alícuota congelada en cada
+// línea; el recargo del medio de pago va a la alícuota general fiscal.
+
+#[derive(Serialize, FromRow)]
+pub struct LibroIvaRow {
+    pub fecha: String,
+    pub tipo: String, // 'venta' | 'devolucion'
+    pub id_venta: i64,
+    pub id_devolucion: Option<i64>,
+    pub letra: Option<String>,
+    pub punto_venta: Option<i64>,
+    pub numero: Option<i64>,
+    pub cae: Option<String>,
+    pub cliente: Option<String>,
+    pub cuit: Option<String>,
+    pub condicion_iva: String,
+    pub neto_21: i64,
+    pub iva_21: i64,
+    pub neto_105: i64,
+    pub iva_105: i64,
+    pub exento: i64,
+    pub recargo: i64,
+    pub total: i64,
+}
+
+#[derive(Serialize, Default)]
+pub struct LibroIvaTotales {
+    pub neto_21: i64,
+    pub iva_21: i64,
+    pub neto_105: i64,
+    pub iva_105: i64,
+    pub exento: i64,
+    pub total: i64,
+}
+
+#[derive(Serialize)]
+pub struct LibroIva {
+    pub mes: String,
+    pub fecha_desde: String,
+    pub fecha_hasta: String,
+    pub filas: Vec<LibroIvaRow>,
+    pub totales: LibroIvaTotales,
+}
+
+const SQL_LIBRO: &str = r#"
+    SELECT * FROM (
+      SELECT
+        v.fecha_hora AS fecha,
+        'venta'      AS tipo,
+        v.id_venta   AS id_venta,
+        NULL         AS id_devolucion,
+        c.letra, c.punto_venta, c.numero, c.cae,
+        cl.nombre    AS cliente,
+        cl.cuit      AS cuit,
+        COALESCE(cl.condicion_iva, 'consumidor_final') AS condicion_iva,
+        SUM(CASE WHEN x.alicuota_iva = 21   THEN x.neto  ELSE 0 END) AS neto_21,
+        SUM(CASE WHEN x.alicuota_iva = 21   THEN x.iva   ELSE 0 END) AS iva_21,
+        SUM(CASE WHEN x.alicuota_iva = 10.5 THEN x.neto  ELSE 0 END) AS neto_105,
+        SUM(CASE WHEN x.alicuota_iva = 10.5 THEN x.iva   ELSE 0 END) AS iva_105,
+        SUM(CASE WHEN x.alicuota_iva = 0    THEN x.bruto ELSE 0 END) AS exento,
+        (SELECT COALESCE(SUM(vp.recargo), 0) FROM venta_pago vp WHERE vp.id_venta = v.id_venta) AS recargo,
+        SUM(x.bruto) AS total
+      FROM venta v
+      JOIN v_venta_item_iva x ON x.id_venta = v.id_venta
+      LEFT JOIN cliente cl    ON cl.id_cliente = v.id_cliente
+      LEFT JOIN comprobante c ON c.id_venta = v.id_venta AND c.tipo = 'factura' AND c.estado <> 'rechazado'
+      WHERE v.estado = 'finalizada'
+        AND DATE(v.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+      GROUP BY v.id_venta
+
+      UNION ALL
+
+      SELECT
+        d.fecha_hora,
+        'devolucion',
+        d.id_venta,
+        d.id_devolucion,
+        c.letra, c.punto_venta, c.numero, c.cae,
+        cl.nombre,
+        cl.cuit,
+        COALESCE(cl.condicion_iva, 'consumidor_final'),
+        -SUM(CASE WHEN x.alicuota_iva = 21   THEN x.neto  ELSE 0 END),
+        -SUM(CASE WHEN x.alicuota_iva = 21   THEN x.iva   ELSE 0 END),
+        -SUM(CASE WHEN x.alicuota_iva = 10.5 THEN x.neto  ELSE 0 END),
+        -SUM(CASE WHEN x.alicuota_iva = 10.5 THEN x.iva   ELSE 0 END),
+        -SUM(CASE WHEN x.alicuota_iva = 0    THEN x.bruto ELSE 0 END),
+        0,
+        -SUM(x.bruto)
+      FROM devolucion d
+      JOIN v_devolucion_item_iva x ON x.id_devolucion = d.id_devolucion
+      JOIN venta v                 ON v.id_venta = d.id_venta
+      LEFT JOIN cliente cl         ON cl.id_cliente = v.id_cliente
+      LEFT JOIN comprobante c      ON c.id_devolucion = d.id_devolucion AND c.tipo = 'nota_credito' AND c.estado <> 'rechazado'
+      WHERE DATE(d.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+      GROUP BY d.id_devolucion
+    )
+    ORDER BY fecha, id_venta
+"#;
+
+pub async fn calcular_libro_iva(state: &AppState, mes: &str) -> Result<LibroIva, String> {
+    let (desde, hasta) = month_bounds_from_ym(mes)?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+    let alicuota_general = fiscal_repo::leer_config(&mut conn).await?.alicuota_iva;
+
+    let mut filas: Vec<LibroIvaRow> = sqlx::query_as(SQL_LIBRO)
+        .bind(&desde)
+        .bind(&hasta)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut totales = LibroIvaTotales::default();
+    for f in &mut filas {
+        if f.recargo != 0 {
+            let neto = (f.recargo as f64 / (1.0 + alicuota_general / 100.0)).round() as i64;
+            let iva = f.recargo - neto;
+            if alicuota_general == 21.0 {
+                f.neto_21 += neto;
+                f.iva_21 += iva;
+            } else if alicuota_general == 10.5 {
+                f.neto_105 += neto;
+                f.iva_105 += iva;
+            } else {
+                f.exento += f.recargo;
+            }
+            f.total += f.recargo;
+        }
+
+        totales.neto_21 += f.neto_21;
+        totales.iva_21 += f.iva_21;
+        totales.neto_105 += f.neto_105;
+        totales.iva_105 += f.iva_105;
+        totales.exento += f.exento;
+        totales.total += f.total;
+    }
+
+    Ok(LibroIva {
+        mes: mes.to_string(),
+        fecha_desde: desde,
+        fecha_hasta: hasta,
+        filas,
+        totales,
+    })
+}
+
+#[tauri::command]
+pub async fn libro_iva_ventas(state: State<'_, AppState>, mes: String) -> Result<LibroIva, String> {
+    requerir_admin(&state).await?;
+    calcular_libro_iva(&state, &mes).await
+}
+
+/// CSV separado por ';' (lo abre Excel en configuración regional argentina).
+#[tauri::command]
+pub async fn libro_iva_ventas_exportar(state: State<'_, AppState>, mes: String) -> Result<String, String> {
+    requerir_admin(&state).await?;
+    let libro = calcular_libro_iva(&state, &mes).await?;
+
+    let texto = |s: &Option<String>| s.as_deref().unwrap_or("").replace(';', ",");
+    let num = |n: Option<i64>| n.map(|n| n.to_string()).unwrap_or_default();
+
+    let mut csv = String::from(
+        "Fecha;Tipo;Venta;Devolucion;Letra;PtoVta;Numero;CAE;Cliente;CUIT;Condicion IVA;\
+         Neto 21;IVA 21;Neto 10.5;IVA 10.5;Exento;Total\n",
+    );
+    for f in &libro.filas {
+        csv.push_str(&format!(
+            "{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{}\n",
+            f.fecha,
+            f.tipo,
+            f.id_venta,
+            num(f.id_devolucion),
+            texto(&f.letra),
+            num(f.punto_venta),
+            num(f.numero),
+            texto(&f.cae),
+            texto(&f.cliente),
+            texto(&f.cuit),
+            f.condicion_iva,
+            f.neto_21,
+            f.iva_21,
+            f.neto_105,
+            f.iva_105,
+            f.exento,
+            f.total,
+        ));
+    }
+    let t = &libro.totales;
+    csv.push_str(&format!(
+        "TOTAL;;;;;;;;;;;{};{};{};{};{};{}\n",
+        t.neto_21, t.iva_21, t.neto_105, t.iva_105, t.exento, t.total
+    ));
+
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::devoluciones::repo::{crear_devolucion, items_devolvibles};
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+
+    #[tokio::test]
+    async fn venta_y_devolucion_del_mismo_momento_caen_en_el_mismo_mes() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 1210, 500, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "efectivo", "monto": 2420 }])).await;
+
+        let mut conn = pool.acquire().await.unwrap();
+        let id_item = items_devolvibles(&mut conn, id_venta).await.unwrap()[0].id_item;
+        drop(conn);
+        let input = serde_json::from_value(json!({
+            "id_venta": id_venta,
+            "items": [{ "id_item": id_item, "cantidad": 1 }],
+            "reintegros": [{ "medio": "efectivo", "monto": 1210 }]
+        }))
+        .unwrap();
+        let id_devolucion = crear_devolucion(pool, id_caja, 1, input).await.unwrap().id_devolucion;
+
+        // las dos justo en el borde del mes
+        let borde = "2026-04-01 00:30:00";
+        for (sql, id) in [
+            ("UPDATE venta SET fecha_hora = ?1 WHERE id_venta = ?2", id_venta),
+            ("UPDATE devolucion SET fecha_hora = ?1 WHERE id_devolucion = ?2", id_devolucion),
+        ] {
+            sqlx::query(sql).bind(borde).bind(id).execute(pool).await.unwrap();
+        }
+        let mes: String = sqlx::query_scalar("SELECT strftime('%Y-%m', ?1, 'localtime')")
+            .bind(borde)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let otro = if mes == "2026-04" { "2026-03" } else { "2026-04" };
+
+        let libro = calcular_libro_iva(&state, &mes).await.unwrap();
+        let tipos: Vec<&str> = libro.filas.iter().map(|f| f.tipo.as_str()).collect();
+        assert_eq!(tipos, ["venta", "devolucion"]);
+        assert_eq!((libro.totales.neto_21, libro.totales.iva_21, libro.totales.total), (1000, 210, 1210));
+
+        assert!(calcular_libro_iva(&state, otro).await.unwrap().filas.is_empty());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod commands;
-pub mod rentabilidad;
\ No newline at end of file
+pub mod rentabilidad;
+pub mod libro_iva;
@@ -4,6 +4,7 @@ use tauri::State;
 use sqlx::{FromRow, Row, SqlitePool};
 
 use crate::AppState;
+use crate::users::permisos::requerir_admin;
 
 
 #[derive(Serialize, FromRow)]
@@ -14,6 +15,8 @@ pub struct RentabilidadProductoRow {
     pub ingreso_total: i64,
     pub costo_total: i64,
     pub ganancia: i64, // ganancia BRUTA (sin gastos del negocio)
+    pub ingreso_neto: i64, // ingreso_total sin IVA
+    pub iva: i64,
 }
 
 #[derive(Serialize)]
@@ -21,6 +24,8 @@ pub struct RentabilidadReporte {
     pub fecha_desde: String,
     pub fecha_hasta: String,
     pub total_ventas: i64,
+    pub total_ventas_neto: i64, // sin IVA
+    pub total_iva: i64,
     pub total_costos: i64,
     pub ganancia_bruta: i64,
     pub margen_pct: f64,
@@ -53,19 +58,19 @@ pub async fn calcular_rentabilidad(
         sqlx::query_as::<_, RentabilidadProductoRow>(
             r#"
             SELECT 
-                vi.id_producto AS id_producto,
-                p.nombre       AS nombre,
-                SUM(vi.cantidad) AS cantidad_vendida,
-                SUM(vi.subtotal) AS ingreso_total,
-                SUM(vi.costo_unitario_en_venta * vi.cantidad) AS costo_total,
-                SUM(vi.subtotal) - SUM(vi.costo_unitario_en_venta * vi.cantidad) AS ganancia
-            FROM venta v
-            JOIN venta_item vi ON vi.id_venta = v.id_venta
-            JOIN producto p    ON p.id_producto = vi.id_producto
+                l.id_producto AS id_producto,
+                p.nombre      AS nombre,
+                SUM(l.cantidad) AS cantidad_vendida,
+                SUM(l.subtotal) AS ingreso_total,
+                SUM(l.costo) AS costo_total,
+                SUM(l.subtotal) - SUM(l.costo) AS ganancia,
+                SUM(l.neto) AS ingreso_neto,
+                SUM(l.iva) AS iva
+            FROM v_linea_rentabilidad l
+            JOIN producto p ON p.id_producto = l.id_producto
             WHERE 
-                v.estado = 'finalizada'
-                AND DATE(v.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
-            GROUP BY vi.id_producto, p.nombre
+                DATE(l.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+            GROUP BY l.id_producto, p.nombre
             ORDER BY ganancia DESC
             "#
         )
@@ -76,10 +81,14 @@ pub async fn calcular_rentabilidad(
         .map_err(|e| e.to_string())?;
 
     let mut total_ventas: i64 = 0;
+    let mut total_ventas_neto: i64 = 0;
+    let mut total_iva: i64 = 0;
     let mut total_costos: i64 = 0;
 
     for p in &productos {
         total_ventas += p.ingreso_total;
+        total_ventas_neto += p.ingreso_neto;
+        total_iva += p.iva;
         total_costos += p.costo_total;
     }
 
@@ -94,6 +103,8 @@ pub async fn calcular_rentabilidad(
         fecha_desde: desde_str,
         fecha_hasta: hasta_str,
         total_ventas,
+        total_ventas_neto,
+        total_iva,
         total_costos,
         ganancia_bruta,
         margen_pct,
@@ -107,6 +118,8 @@ pub async fn reporte_rentabilidad(
     desde: Option<String>,
     hasta: Option<String>,
 ) -> Result<RentabilidadReporte, String> {
+    requerir_admin(&state).await?;
+
     let pool = &state.pool;
     calcular_rentabilidad(pool, desde, hasta).await
 }
@@ -166,7 +179,7 @@ pub struct RentabilidadResumenMesReporte {
     pub top_por_ganancia: Vec<TopProductoRow>,
 }
 
-fn month_bounds_from_ym(ym: &str) -> Result<(String, String), String> {
+pub(crate) fn month_bounds_from_ym(ym: &str) -> Result<(String, String), String> {
     // ym: "YYYY-MM"
     let parts: Vec<&str> = ym.split('-').collect();
     if parts.len() != 2 {
@@ -223,12 +236,10 @@ async fn resumen_ventas_mes(
     let row = sqlx::query_as::<_, (i64, i64)>(
         r#"
         SELECT
-          COALESCE(SUM(vi.subtotal), 0) AS total_ventas,
-          COALESCE(SUM(vi.costo_unitario_en_venta * vi.cantidad), 0) AS total_costos
-        FROM venta v
-        JOIN venta_item vi ON vi.id_venta = v.id_venta
-        WHERE v.estado = 'finalizada'
-          AND DATE(v.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+          COALESCE(SUM(l.subtotal), 0) AS total_ventas,
+          COALESCE(SUM(l.costo), 0) AS total_costos
+        FROM v_linea_rentabilidad l
+        WHERE DATE(l.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
         "#,
     )
     .bind(desde)
@@ -264,17 +275,15 @@ async fn top_productos(
     let sql = format!(
         r#"
         SELECT
-          vi.id_producto AS id_producto,
-          p.nombre       AS nombre,
-          COALESCE(SUM(vi.cantidad), 0) AS cantidad_vendida,
-          COALESCE(SUM(vi.subtotal), 0) AS ingreso_total,
-          COALESCE(SUM(vi.subtotal) - SUM(vi.costo_unitario_en_venta * vi.cantidad), 0) AS ganancia_bruta
-        FROM venta v
-        JOIN venta_item vi ON vi.id_venta = v.id_venta
-        JOIN producto p    ON p.id_producto = vi.id_producto
-        WHERE v.estado = 'finalizada'
-          AND DATE(v.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
-        GROUP BY vi.id_producto, p.nombre
+          l.id_producto AS id_producto,
+          p.nombre      AS nombre,
+          COALESCE(SUM(l.cantidad), 0) AS cantidad_vendida,
+          COALESCE(SUM(l.subtotal), 0) AS ingreso_total,
+          COALESCE(SUM(l.subtotal) - SUM(l.costo), 0) AS ganancia_bruta
+        FROM v_linea_rentabilidad l
+        JOIN producto p ON p.id_producto = l.id_producto
+        WHERE DATE(l.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+        GROUP BY l.id_producto, p.nombre
         ORDER BY {order_by} DESC
         LIMIT ?3
         "#
@@ -294,6 +303,8 @@ pub async fn reporte_rentabilidad_resumen_mes(
     state: State<'_, AppState>,
     mes: Option<String>, // "YYYY-MM" o None => mes actual
 ) -> Result<RentabilidadResumenMesReporte, String> {
+    requerir_admin(&state).await?;
+
     let pool = &state.pool;
 
     let ym = mes.unwrap_or_else(ym_of_local_now);
@@ -431,12 +442,10 @@ async fn totales_periodo(pool: &SqlitePool, desde: &str, hasta: &str) -> Result<
     let row = sqlx::query(
         r#"
         SELECT
-          COALESCE(SUM(vi.subtotal), 0) AS ventas,
-          COALESCE(SUM(vi.costo_unitario_en_venta * vi.cantidad), 0) AS cogs
-        FROM venta v
-        JOIN venta_item vi ON vi.id_venta = v.id_venta
-        WHERE v.estado = 'finalizada'
-          AND DATE(v.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+          COALESCE(SUM(l.subtotal), 0) AS ventas,
+          COALESCE(SUM(l.costo), 0) AS cogs
+        FROM v_linea_rentabilidad l
+        WHERE DATE(l.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
         "#
     )
     .bind(desde)
@@ -504,13 +513,11 @@ async fn tendencia_mensual_12m(pool: &SqlitePool, end_ym: &str) -> Result<Vec<Ga
     let ventas_cogs = sqlx::query_as::<_, (String, i64, i64)>(
         r#"
         SELECT
-          STRFTIME('%Y-%m', DATE(v.fecha_hora, 'localtime')) AS mes,
-          COALESCE(SUM(vi.subtotal), 0) AS ventas,
-          COALESCE(SUM(vi.costo_unitario_en_venta * vi.cantidad), 0) AS cogs
-        FROM venta v
-        JOIN venta_item vi ON vi.id_venta = v.id_venta
-        WHERE v.estado = 'finalizada'
-          AND DATE(v.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
+          STRFTIME('%Y-%m', DATE(l.fecha_hora, 'localtime')) AS mes,
+          COALESCE(SUM(l.subtotal), 0) AS ventas,
+          COALESCE(SUM(l.costo), 0) AS cogs
+        FROM v_linea_rentabilidad l
+        WHERE DATE(l.fecha_hora, 'localtime') BETWEEN DATE(?1) AND DATE(?2)
         GROUP BY mes
         ORDER BY mes ASC
         "#
@@ -615,6 +622,8 @@ pub async fn reporte_rentabilidad_negocio(
     desde: Option<String>,
     hasta: Option<String>,
 ) -> Result<RentabilidadNegocioReporte, String> {
+    requerir_admin(&state).await?;
+
     let pool = &state.pool;
     calcular_rentabilidad_negocio(pool, desde, hasta).await
 }
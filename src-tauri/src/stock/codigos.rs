@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection};
+use tauri::State;
+
+use crate::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::requerir_admin;
+
+// Resolución de lo que entra por el lector: codigo_producto, EAN cargados en
+// producto_codigo_barra, EAN-13 de balanza (precio o peso embebido) y PLU.
+
+pub const ERR_PRODUCTO_NO_ENCONTRADO: &str = "Producto no encontrado";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Embebido {
+    Precio(i64), // ARS
+    Peso(i64),   // gramos
+}
+
+#[derive(Debug, Clone)]
+pub struct CodigoResuelto {
+    pub id_producto: i64,
+    pub origen: &'static str, // 'codigo' | 'ean' | 'plu' | 'balanza'
+    pub embebido: Option<Embebido>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct CodigoBarraRow {
+    pub id_codigo: i64,
+    pub id_producto: i64,
+    pub codigo: String,
+    pub creado_en: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CodigoBarraInput {
+    pub id_producto: i64,
+    pub codigo: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductoPluInput {
+    pub id_producto: i64,
+    pub plu: Option<i64>, // None = quitar
+}
+
+/// Dígito verificador EAN-13 (sobre los 12 primeros dígitos)
+fn ean13_valido(d: &[u8]) -> bool {
+    if d.len() != 13 {
+        return false;
+    }
+    let suma: u32 = d[..12]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c - b'0') as u32 * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    (10 - suma % 10) % 10 == (d[12] - b'0') as u32
+}
+
+async fn producto_activo_por(
+    conn: &mut SqliteConnection,
+    sql: &str,
+    valor: &str,
+) -> Result<Option<i64>, String> {
+    sqlx::query_scalar::<_, i64>(sql)
+        .bind(valor)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Resuelve un código escaneado o tipeado a un producto activo
+pub async fn resolver(conn: &mut SqliteConnection, codigo: &str) -> Result<CodigoResuelto, String> {
+    let codigo = codigo.trim();
+    if codigo.is_empty() {
+        return Err("Código vacío".into());
+    }
+
+    // 1) codigo_producto interno
+    if let Some(id) = producto_activo_por(
+        conn,
+        "SELECT id_producto FROM producto WHERE codigo_producto = ?1 AND activo = 1",
+        codigo,
+    )
+    .await?
+    {
+        return Ok(CodigoResuelto { id_producto: id, origen: "codigo", embebido: None });
+    }
+
+    // 2) EAN cargado para el producto
+    if let Some(id) = producto_activo_por(
+        conn,
+        r#"
+        SELECT p.id_producto
+        FROM producto_codigo_barra cb
+        JOIN producto p ON p.id_producto = cb.id_producto
+        WHERE cb.codigo = ?1 AND p.activo = 1
+        "#,
+        codigo,
+    )
+    .await?
+    {
+        return Ok(CodigoResuelto { id_producto: id, origen: "ean", embebido: None });
+    }
+
+    let digitos = codigo.as_bytes();
+    let numerico = digitos.iter().all(u8::is_ascii_digit);
+
+    // 3) EAN-13 de balanza: PP + PLU(5) + VALOR(5) + verificador
+    if numerico && ean13_valido(digitos) {
+        let tipo: Option<String> =
+            sqlx::query_scalar("SELECT tipo FROM codigo_embebido WHERE prefijo = ?1")
+                .bind(&codigo[..2])
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+
+        if let Some(tipo) = tipo {
+            let plu = &codigo[2..7];
+            let valor: i64 = codigo[7..12].parse().map_err(|_| "Código de balanza inválido")?;
+            let id = producto_activo_por(
+                conn,
+                "SELECT id_producto FROM producto WHERE plu = CAST(?1 AS INTEGER) AND activo = 1",
+                plu,
+            )
+            .await?
+            .ok_or_else(|| format!("{ERR_PRODUCTO_NO_ENCONTRADO}: PLU {} (código {codigo})", plu.trim_start_matches('0')))?;
+
+            let embebido = match tipo.as_str() {
+                "peso" => Embebido::Peso(valor),
+                _ => Embebido::Precio(valor),
+            };
+            return Ok(CodigoResuelto { id_producto: id, origen: "balanza", embebido: Some(embebido) });
+        }
+    }
+
+    // 4) PLU tipeado
+    if numerico && digitos.len() <= 5 {
+        if let Some(id) = producto_activo_por(
+            conn,
+            "SELECT id_producto FROM producto WHERE plu = CAST(?1 AS INTEGER) AND activo = 1",
+            codigo,
+        )
+        .await?
+        {
+            return Ok(CodigoResuelto { id_producto: id, origen: "plu", embebido: None });
+        }
+    }
+
+    Err(format!("{ERR_PRODUCTO_NO_ENCONTRADO}: '{codigo}'"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Administración de códigos (solo admin)
+
+#[tauri::command]
+pub async fn producto_codigo_barra_listar(
+    state: State<'_, AppState>,
+    id_producto: i64,
+) -> Result<Vec<CodigoBarraRow>, String> {
+    requerir_admin(&state).await?;
+
+    sqlx::query_as::<_, CodigoBarraRow>(
+        r#"
+        SELECT id_codigo, id_producto, codigo, creado_en
+        FROM producto_codigo_barra
+        WHERE id_producto = ?1
+        ORDER BY id_codigo
+        "#,
+    )
+    .bind(id_producto)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn producto_codigo_barra_agregar(
+    state: State<'_, AppState>,
+    input: CodigoBarraInput,
+) -> Result<i64, String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let codigo = input.codigo.trim();
+    if codigo.is_empty() {
+        return Err("Código vacío".into());
+    }
+
+    let pool = &state.pool;
+
+    // no puede pisar el código interno de otro producto
+    let choca: Option<i64> = sqlx::query_scalar(
+        "SELECT id_producto FROM producto WHERE codigo_producto = ?1 AND id_producto <> ?2",
+    )
+    .bind(codigo)
+    .bind(input.id_producto)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if choca.is_some() {
+        return Err("Ese código ya es el código interno de otro producto".into());
+    }
+
+    let res = sqlx::query(
+        "INSERT INTO producto_codigo_barra (id_producto, codigo) VALUES (?1, ?2)",
+    )
+    .bind(input.id_producto)
+    .bind(codigo)
+    .execute(pool)
+    .await;
+
+    let id_codigo = match res {
+        Ok(r) => r.last_insert_rowid(),
+        Err(sqlx::Error::Database(db)) if db.message().contains("UNIQUE") => {
+            return Err("Ese código de barras ya está asignado a un producto".into());
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    audit_repo::registrar(
+        pool,
+        Some(uid),
+        "producto",
+        Some(input.id_producto),
+        "codigo_barra_agregar",
+        None,
+        Some(&serde_json::json!({ "codigo": codigo })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(id_codigo)
+}
+
+#[tauri::command]
+pub async fn producto_codigo_barra_quitar(
+    state: State<'_, AppState>,
+    id_codigo: i64,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let pool = &state.pool;
+    let (id_producto, codigo): (i64, String) = sqlx::query_as(
+        "SELECT id_producto, codigo FROM producto_codigo_barra WHERE id_codigo = ?1",
+    )
+    .bind(id_codigo)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Código inexistente".to_string())?;
+
+    sqlx::query("DELETE FROM producto_codigo_barra WHERE id_codigo = ?1")
+        .bind(id_codigo)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        pool,
+        Some(uid),
+        "producto",
+        Some(id_producto),
+        "codigo_barra_quitar",
+        Some(&serde_json::json!({ "codigo": codigo })),
+        None,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn producto_plu_fijar(
+    state: State<'_, AppState>,
+    input: ProductoPluInput,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    if matches!(input.plu, Some(p) if !(1..=99999).contains(&p)) {
+        return Err("PLU inválido (1 a 99999)".into());
+    }
+
+    let pool = &state.pool;
+    let antes: Option<Option<i64>> = sqlx::query_scalar("SELECT plu FROM producto WHERE id_producto = ?1")
+        .bind(input.id_producto)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let antes = antes.ok_or_else(|| "Producto inexistente".to_string())?;
+
+    let res = sqlx::query("UPDATE producto SET plu = ?1 WHERE id_producto = ?2")
+        .bind(input.plu)
+        .bind(input.id_producto)
+        .execute(pool)
+        .await;
+
+    match res {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(db)) if db.message().contains("UNIQUE") => {
+            return Err("Ese PLU ya está asignado a otro producto".into());
+        }
+        Err(e) => return Err(e.to_string()),
+    }
+
+    audit_repo::registrar(
+        pool,
+        Some(uid),
+        "producto",
+        Some(input.id_producto),
+        "plu",
+        Some(&serde_json::json!({ "plu": antes })),
+        Some(&serde_json::json!({ "plu": input.plu })),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verificador_ean13() {
+        assert!(ean13_valido(b"4006381333931"));
+        assert!(ean13_valido(b"2100042005006"));
+        // verificador 0: (10 - suma % 10) % 10
+        assert!(ean13_valido(b"7790000000010"));
+        assert!(!ean13_valido(b"4006381333932"));
+        assert!(!ean13_valido(b"400638133393"));
+        assert!(!ean13_valido(b"40063813339310"));
+    }
+}
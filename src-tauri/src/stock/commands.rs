@@ -6,8 +6,10 @@ use crate::stock::repo::{self, TipoPrecio};
 
 use sqlx::Row;
 use sqlx::Error as SqlxError;
-use crate::stock::model::ReposicionModo;
-use super::model::{StockMermaInput, CompraStockInput};
+use crate::stock::model::{ALICUOTAS_IVA, UNIDADES_VENTA};
+use super::model::{StockMermaInput, CompraStockInput, ProductoCrearIn};
+use crate::users::permisos::requerir_admin;
+use crate::audit::repo as audit_repo;
 /*  Listar / Buscar  */
 #[derive(Serialize)]
 pub struct StockResumen {
@@ -18,6 +20,8 @@ pub struct StockResumen {
     pub precio_venta_actual: i64,
     pub costo_actual: i64,
     pub activo: i64,
+    pub alicuota_iva: f64,
+    pub unidad_venta: String, // 'unidad' | 'peso'
 }
 
 #[derive(Debug, Serialize)]
@@ -54,41 +58,43 @@ pub struct FixAbsInput {
 }
 
 #[tauri::command]
-pub fn stock_listar(
+pub async fn stock_listar(
     state: tauri::State<'_, crate::app_state::AppState>,
     q: Option<String>,
     solo_activos: Option<bool>,
     limit: Option<i64>,
     offset: Option<i64>,
 ) -> Result<Vec<crate::stock::commands::StockResumen>, String> {
+    requerir_admin(&state).await?;
+
     let q = q.unwrap_or_default();
     let solo_activos = solo_activos.unwrap_or(true);
     let limit = limit.unwrap_or(200).max(1);
     let offset = offset.unwrap_or(0).max(0);
 
-    let rows = tauri::async_runtime::block_on(async move {
-        sqlx::query(
-            r#"
-            SELECT p.id_producto,
-                p.codigo_producto,
-                p.nombre,
-                COALESCE(ps.stock_actual, 0) AS stock_actual,
-                p.precio_venta_actual,
-                p.costo_actual,
-                p.activo
-            FROM producto p
-            LEFT JOIN producto_stock ps USING(id_producto)
-            WHERE (?1 = '' OR p.nombre LIKE '%'||?1||'%' OR p.codigo_producto LIKE '%'||?1||'%')
-            ORDER BY p.id_producto ASC
-            LIMIT ?2 OFFSET ?3
-            "#
-        )
-        .bind(&q)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&state.pool)
-        .await
-    })
+    let rows = sqlx::query(
+        r#"
+        SELECT p.id_producto,
+            p.codigo_producto,
+            p.nombre,
+            COALESCE(ps.stock_actual, 0) AS stock_actual,
+            p.precio_venta_actual,
+            p.costo_actual,
+            p.activo,
+            p.alicuota_iva,
+            p.unidad_venta
+        FROM producto p
+        LEFT JOIN producto_stock ps USING(id_producto)
+        WHERE (?1 = '' OR p.nombre LIKE '%'||?1||'%' OR p.codigo_producto LIKE '%'||?1||'%')
+        ORDER BY p.id_producto ASC
+        LIMIT ?2 OFFSET ?3
+        "#
+    )
+    .bind(&q)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await
     .map_err(|e| e.to_string())?;
 
     let out = rows
@@ -101,21 +107,14 @@ pub fn stock_listar(
             precio_venta_actual: r.get::<i64, _>(4),
             costo_actual: r.get::<i64, _>(5),
             activo: r.get::<i64, _>(6), // <-- nuevo
+            alicuota_iva: r.get::<f64, _>(7),
+            unidad_venta: r.get::<String, _>(8),
         })
         .collect();
 
     Ok(out)
 }
 /* Crear producto  */
-#[derive(Deserialize)]
-pub struct ProductoCrearIn {
-    pub codigo: String,
-    pub nombre: String,
-    pub precio_venta: i64,
-    pub costo: i64,
-    pub reposicion_modo: ReposicionModo,
-    pub reposicion_factor: i64,
-}
 
 #[derive(Serialize)]
 pub struct ProductoIdOut { pub id_producto: i64 }
@@ -125,18 +124,19 @@ pub async fn producto_crear(
     state: State<'_, AppState>,
     input: ProductoCrearIn
 ) -> Result<ProductoIdOut, String> {
-    let res = crate::stock::repo::producto_crear(
-    &state.pool,
-    &input.codigo,
-    &input.nombre,
-    input.precio_venta,
-    input.costo,
-    input.reposicion_modo,
-    input.reposicion_factor,
-).await;
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let res = crate::stock::repo::producto_crear(&state.pool, &input).await;
 
     match res {
-        Ok(id) => Ok(ProductoIdOut { id_producto: id }),
+        Ok(id) => {
+            let despues = audit_repo::producto_snapshot(&state.pool, id)
+                .await.map_err(|e| e.to_string())?;
+            audit_repo::registrar(
+                &state.pool, Some(uid), "producto", Some(id), "crear", None, despues.as_ref()
+            ).await.map_err(|e| e.to_string())?;
+            Ok(ProductoIdOut { id_producto: id })
+        }
         Err(e) => {
             // e es anyhow::Error → intento obtener referencia a sqlx::Error
             if let Some(db_err) = e.downcast_ref::<SqlxError>() {
@@ -170,14 +170,80 @@ pub struct ProductoActualizarIn {
 #[tauri::command]
 pub async fn producto_actualizar(state: State<'_, AppState>, input: ProductoActualizarIn)
 -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.id_producto <= 0 { return Err("id_producto inválido".into()); }
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     repo::producto_actualizar(
         &state.pool,
         input.id_producto,
         input.codigo.as_deref(),
         input.nombre.as_deref(),
         input.activo
-    ).await.map_err(|e| e.to_string())
+    ).await.map_err(|e| e.to_string())?;
+
+    auditar_producto(&state, uid, input.id_producto, "actualizar", antes).await
+}
+
+/*  Alícuota de IVA (21, 10.5 o 0 = exento)  */
+
+#[derive(Deserialize)]
+pub struct ProductoAlicuotaIvaIn { pub id_producto: i64, pub alicuota_iva: f64 }
+
+#[tauri::command]
+pub async fn producto_alicuota_iva_fijar(state: State<'_, AppState>, input: ProductoAlicuotaIvaIn)
+-> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    if input.id_producto <= 0 { return Err("id_producto inválido".into()); }
+    if !ALICUOTAS_IVA.contains(&input.alicuota_iva) {
+        return Err(format!("Alícuota de IVA inválida: {} (21, 10.5 o 0 = exento)", input.alicuota_iva));
+    }
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+    if antes.is_none() { return Err("Producto inexistente".into()); }
+
+    // las líneas ya cargadas conservan la alícuota con la que se agregaron
+    sqlx::query("UPDATE producto SET alicuota_iva = ?1 WHERE id_producto = ?2")
+        .bind(input.alicuota_iva)
+        .bind(input.id_producto)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    auditar_producto(&state, uid, input.id_producto, "alicuota_iva", antes).await
+}
+
+/*  Unidad de venta ('unidad' o 'peso' por balanza)  */
+
+#[derive(Deserialize)]
+pub struct ProductoUnidadVentaIn { pub id_producto: i64, pub unidad_venta: String }
+
+#[tauri::command]
+pub async fn producto_unidad_venta_fijar(state: State<'_, AppState>, input: ProductoUnidadVentaIn)
+-> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    if input.id_producto <= 0 { return Err("id_producto inválido".into()); }
+    let unidad = input.unidad_venta.trim().to_lowercase();
+    if !UNIDADES_VENTA.contains(&unidad.as_str()) {
+        return Err(format!("Unidad de venta inválida: {} (unidad o peso)", input.unidad_venta));
+    }
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+    if antes.is_none() { return Err("Producto inexistente".into()); }
+
+    // el stock cargado no se convierte (unidades <-> gramos): se ajusta aparte
+    sqlx::query("UPDATE producto SET unidad_venta = ?1 WHERE id_producto = ?2")
+        .bind(&unidad)
+        .bind(input.id_producto)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    auditar_producto(&state, uid, input.id_producto, "unidad_venta", antes).await
 }
 
 /*  Eliminar/Restaurar (soft delete)  */
@@ -188,9 +254,33 @@ pub struct ProductoSetActivoIn { pub id_producto: i64, pub activo: bool }
 #[tauri::command]
 pub async fn producto_set_activo(state: State<'_, AppState>, input: ProductoSetActivoIn)
 -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.id_producto <= 0 { return Err("id_producto inválido".into()); }
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     repo::producto_set_activo(&state.pool, input.id_producto, input.activo)
-        .await.map_err(|e| e.to_string())
+        .await.map_err(|e| e.to_string())?;
+
+    let accion = if input.activo { "restaurar" } else { "desactivar" };
+    auditar_producto(&state, uid, input.id_producto, accion, antes).await
+}
+
+/// Auditoría de cambios sobre producto: `antes` se toma antes de tocar la
+/// base y el `después` se relee acá.
+async fn auditar_producto(
+    state: &AppState,
+    uid: i64,
+    id_producto: i64,
+    accion: &str,
+    antes: Option<serde_json::Value>,
+) -> Result<(), String> {
+    let despues = audit_repo::producto_snapshot(&state.pool, id_producto)
+        .await.map_err(|e| e.to_string())?;
+    audit_repo::registrar(
+        &state.pool, Some(uid), "producto", Some(id_producto), accion, antes.as_ref(), despues.as_ref()
+    ).await.map_err(|e| e.to_string())
 }
 
 /*  Ajustar stock  */
@@ -201,10 +291,15 @@ pub struct StockAjusteIn { pub id_producto: i64, pub delta: i64, pub motivo: Str
 #[tauri::command]
 pub async fn stock_ajustar(state: State<'_, AppState>, input: StockAjusteIn)
 -> Result<StockAjusteOut, String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.id_producto <= 0 { return Err("id_producto inválido".into()); }
     if input.delta == 0 { return Err("delta no puede ser 0".into()); }
     if input.motivo.trim().is_empty() { return Err("motivo requerido".into()); }
 
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     let id_mov = repo::stock_ajustar(
         &state.pool, input.id_producto, input.delta, &input.motivo, input.referencia.as_deref()
     ).await.map_err(|e| e.to_string())?;
@@ -218,6 +313,8 @@ pub async fn stock_ajustar(state: State<'_, AppState>, input: StockAjusteIn)
     .await
     .map_err(|e| e.to_string())?;
 
+    auditar_producto(&state, uid, input.id_producto, "stock_ajustar", antes).await?;
+
     Ok(StockAjusteOut { id_movimiento: id_mov, stock_nuevo })
 }
 
@@ -230,6 +327,8 @@ pub async fn precio_actualizar(
     state: tauri::State<'_, crate::app_state::AppState>,
     input: PrecioActualizarIn,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.id_producto <= 0 { return Err("id_producto inválido".into()); }
     if input.nuevo < 0 { return Err("precio negativo".into()); }
 
@@ -239,11 +338,17 @@ pub async fn precio_actualizar(
         _ => return Err("tipo inválido (usa 'venta' o 'costo')".into())
     };
 
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     // descartamos el i64
     repo::precio_actualizar(&state.pool, input.id_producto, t, input.nuevo)
         .await
         .map(|_| ())                 // <--- convierte Ok(i64) a Ok(())
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    let accion = format!("precio_{}", input.tipo);
+    auditar_producto(&state, uid, input.id_producto, &accion, antes).await
 }
 
 /*  Historiales  */
@@ -260,6 +365,8 @@ pub struct PrecioHistOut { pub id_precio: i64, pub tipo: String, pub precio: i64
 #[tauri::command]
 pub async fn stock_mov_listar(state: State<'_, AppState>, input: HistStockIn)
 -> Result<Vec<StockMovOut>, String> {
+    requerir_admin(&state).await?;
+
     let rows = repo::stock_mov_listar(&state.pool, input.id_producto, input.limit)
         .await.map_err(|e| e.to_string())?;
     Ok(rows.into_iter().map(|r| StockMovOut {
@@ -271,6 +378,8 @@ pub async fn stock_mov_listar(state: State<'_, AppState>, input: HistStockIn)
 #[tauri::command]
 pub async fn precio_hist_listar(state: State<'_, AppState>, input: HistPrecioIn)
 -> Result<Vec<PrecioHistOut>, String> {
+    requerir_admin(&state).await?;
+
     let rows = repo::precio_hist_listar(
         &state.pool, input.id_producto, input.tipo.as_deref(), input.limit
     ).await.map_err(|e| e.to_string())?;
@@ -286,6 +395,8 @@ pub async fn stock_fijar_absoluto(
     state: tauri::State<'_, AppState>,
     input: FixAbsInput,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.nuevo < 0 {
         return Err("Stock objetivo inválido (< 0)".into());
     }
@@ -307,6 +418,9 @@ pub async fn stock_fijar_absoluto(
 
     // si no hay cambio, no hacemos nada
     if delta != 0 {
+        let antes = audit_repo::producto_snapshot(&mut *tx, input.id_producto)
+            .await.map_err(|e| e.to_string())?;
+
         //  obtener costo_actual del producto
         let costo_actual: i64 = sqlx::query_scalar(
             "SELECT costo_actual FROM producto WHERE id_producto = ?1"
@@ -348,6 +462,13 @@ pub async fn stock_fijar_absoluto(
         .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
+
+        let despues = audit_repo::producto_snapshot(&mut *tx, input.id_producto)
+            .await.map_err(|e| e.to_string())?;
+        audit_repo::registrar(
+            &mut *tx, Some(uid), "producto", Some(input.id_producto), "stock_fijar_absoluto",
+            antes.as_ref(), despues.as_ref()
+        ).await.map_err(|e| e.to_string())?;
     }
 
     tx.commit().await.map_err(|e| e.to_string())?;
@@ -356,10 +477,12 @@ pub async fn stock_fijar_absoluto(
 
 
 #[tauri::command]
-pub fn reporte_stock_general(
+pub async fn reporte_stock_general(
     state: State<'_, AppState>,
     solo_activos: Option<bool>,
 ) -> Result<StockReporteResultado, String> {
+    requerir_admin(&state).await?;
+
     use std::collections::HashMap;
 
     let pool = &state.pool;
@@ -367,57 +490,55 @@ pub fn reporte_stock_general(
     let solo_activos = solo_activos.unwrap_or(true);
 
     //  Productos + stock + costo
-    let filas = tauri::async_runtime::block_on(async {
-        sqlx::query(
-            r#"
-            SELECT
-                p.id_producto,
-                p.codigo_producto,
-                p.nombre,
-                COALESCE(ps.stock_actual, 0) AS stock_actual,
-                p.costo_actual               AS costo_unitario
-            FROM producto p
-            LEFT JOIN producto_stock ps
-                ON ps.id_producto = p.id_producto
-            WHERE (p.activo = 1 OR ?1 = 0)
-            ORDER BY p.nombre ASC
-            "#
-        )
-        .bind(if solo_activos { 1 } else { 0 })
-        .fetch_all(pool)
-        .await
-    }).map_err(|e| e.to_string())?;
+    let filas = sqlx::query(
+        r#"
+        SELECT
+            p.id_producto,
+            p.codigo_producto,
+            p.nombre,
+            COALESCE(ps.stock_actual, 0) AS stock_actual,
+            p.costo_actual               AS costo_unitario
+        FROM producto p
+        LEFT JOIN producto_stock ps
+            ON ps.id_producto = p.id_producto
+        WHERE (p.activo = 1 OR ?1 = 0)
+        ORDER BY p.nombre ASC
+        "#
+    )
+    .bind(if solo_activos { 1 } else { 0 })
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
 
     //  Ventas por producto: últimos 30 días y 30 previos
-    let ventas_rows = tauri::async_runtime::block_on(async {
-        sqlx::query(
-            r#"
-            SELECT
-                vi.id_producto                           AS id_producto,
-                SUM(
-                  CASE
-                    WHEN v.fecha_hora >= DATETIME('now','localtime','-30 day')
-                    THEN vi.cantidad
-                    ELSE 0
-                  END
-                ) AS cant_30,
-                SUM(
-                  CASE
-                    WHEN v.fecha_hora >= DATETIME('now','localtime','-60 day')
-                     AND v.fecha_hora < DATETIME('now','localtime','-30 day')
-                    THEN vi.cantidad
-                    ELSE 0
-                  END
-                ) AS cant_prev_30
-            FROM venta v
-            JOIN venta_item vi ON vi.id_venta = v.id_venta
-            WHERE v.estado = 'finalizada'
-            GROUP BY vi.id_producto
-            "#
-        )
-        .fetch_all(pool)
-        .await
-    }).map_err(|e| e.to_string())?;
+    let ventas_rows = sqlx::query(
+        r#"
+        SELECT
+            vi.id_producto                           AS id_producto,
+            SUM(
+              CASE
+                WHEN v.fecha_hora >= DATETIME('now','localtime','-30 day')
+                THEN vi.cantidad
+                ELSE 0
+              END
+            ) AS cant_30,
+            SUM(
+              CASE
+                WHEN v.fecha_hora >= DATETIME('now','localtime','-60 day')
+                 AND v.fecha_hora < DATETIME('now','localtime','-30 day')
+                THEN vi.cantidad
+                ELSE 0
+              END
+            ) AS cant_prev_30
+        FROM venta v
+        JOIN venta_item vi ON vi.id_venta = v.id_venta
+        WHERE v.estado = 'finalizada'
+        GROUP BY vi.id_producto
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
 
     // mapa id_producto -> (ventas últimos 30 días, ventas 30 días previos)
     let mut ventas_map: HashMap<i64, (i64, i64)> = HashMap::new();
@@ -557,13 +678,21 @@ pub async fn stock_registrar_merma(
     state: State<'_, AppState>,
     input: StockMermaInput,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.cantidad <= 0 {
         return Err("La cantidad debe ser mayor a cero".to_string());
     }
 
+    let id_producto = input.id_producto;
+    let antes = audit_repo::producto_snapshot(&state.pool, id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     repo::registrar_merma(&state.pool, input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    auditar_producto(&state, uid, id_producto, "merma", antes).await
 }
 
 #[tauri::command(rename = "stock_compra")]
@@ -571,6 +700,8 @@ pub async fn stock_compra(
     state: State<'_, AppState>,
     input: CompraStockInput,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     if input.cantidad <= 0 {
         return Err("Cantidad inválida".into());
     }
@@ -596,6 +727,9 @@ pub async fn stock_compra(
     let pool = &state.pool;
     let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
 
+    let antes = audit_repo::producto_snapshot(&mut *tx, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     repo::stock_compra_tx(
         &mut tx,
         input.id_producto,
@@ -605,6 +739,13 @@ pub async fn stock_compra(
         referencia,
     )
     .await?;
+
+    let despues = audit_repo::producto_snapshot(&mut *tx, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+    audit_repo::registrar(
+        &mut *tx, Some(uid), "producto", Some(input.id_producto), "stock_compra",
+        antes.as_ref(), despues.as_ref()
+    ).await.map_err(|e| e.to_string())?;
     tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -630,6 +771,8 @@ pub async fn reporte_stock_reposicion(
     state: State<'_, AppState>,
     input: ReposicionRangoIn,
 ) -> Result<Vec<StockReposicionRowOut>, String> {
+    requerir_admin(&state).await?;
+
     let solo_activos = input.solo_activos.unwrap_or(true);
 
     let rows = repo::reporte_stock_reposicion_rango(
@@ -666,6 +809,11 @@ pub async fn producto_actualizar_reposicion(
     state: State<'_, AppState>,
     input: ProductoActualizarReposicionIn,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let antes = audit_repo::producto_snapshot(&state.pool, input.id_producto)
+        .await.map_err(|e| e.to_string())?;
+
     repo::producto_actualizar_reposicion(
         &state.pool,
         input.id_producto,
@@ -673,6 +821,8 @@ pub async fn producto_actualizar_reposicion(
         input.reposicion_factor,
     )
     .await
-    .map_err(|e| e.to_string())
+    .map_err(|e| e.to_string())?;
+
+    auditar_producto(&state, uid, input.id_producto, "reposicion", antes).await
 }
 
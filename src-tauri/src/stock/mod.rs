@@ -1,5 +1,7 @@
 pub mod model;
 pub mod repo;
 pub mod commands;
+pub mod codigos;
+pub mod politica;
 
 pub use commands::stock_registrar_merma;
\ No newline at end of file
@@ -1,5 +1,11 @@
 use serde::{Serialize, Deserialize};
 
+// IVA de producto: 0 = exento
+pub const ALICUOTAS_IVA: [f64; 3] = [21.0, 10.5, 0.0];
+
+// 'peso': stock en gramos, precio y costo de lista por kg
+pub const UNIDADES_VENTA: [&str; 2] = ["unidad", "peso"];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TipoPrecio { Venta, Costo }
 
@@ -61,6 +67,17 @@ pub struct Producto {
     pub activo: i64,
 }
 
+/* Crear producto  */
+#[derive(Deserialize)]
+pub struct ProductoCrearIn {
+    pub codigo: String,
+    pub nombre: String,
+    pub precio_venta: i64,
+    pub costo: i64,
+    pub reposicion_modo: ReposicionModo,
+    pub reposicion_factor: i64,
+    pub alicuota_iva: Option<f64>, // default 21
+}
 
 //Perdidas
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection};
+use tauri::State;
+
+use crate::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::{requerir_admin, requerir_admin_o_aprobacion, requerir_sesion, AprobacionInput};
+
+// Política ante stock negativo. Se evalúa al agregar ítems y al finalizar:
+// 'permitir' no dice nada, 'advertir' devuelve el faltante, 'bloquear' corta
+// salvo que un admin lo autorice.
+
+pub const AJUSTE_STOCK_NEGATIVO: &str = "stock_negativo";
+const POLITICAS: [&str; 3] = ["permitir", "advertir", "bloquear"];
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StockAdvertencia {
+    pub id_producto: i64,
+    pub nombre: String,
+    pub stock_actual: i64,
+    pub requerido: i64, // suma de la venta para ese producto
+    pub faltante: i64,
+    pub politica: String, // 'advertir' | 'bloquear'
+    pub autorizado_por: Option<i64>, // admin que levantó el bloqueo
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoliticaStockGlobal {
+    pub politica: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductoPoliticaInput {
+    pub id_producto: i64,
+    pub politica: Option<String>, // None = usar la global
+}
+
+fn validar_politica(p: &str) -> Result<String, String> {
+    let p = p.trim().to_lowercase();
+    if POLITICAS.contains(&p.as_str()) {
+        Ok(p)
+    } else {
+        Err("Política inválida (permitir | advertir | bloquear)".into())
+    }
+}
+
+/// Productos de la venta cuyo pedido supera el stock y cuya política no es 'permitir'.
+/// Con `id_producto` se limita a ese producto (lo que se acaba de agregar).
+pub async fn evaluar(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_producto: Option<i64>,
+) -> Result<Vec<StockAdvertencia>, String> {
+    let faltantes = sqlx::query_as::<_, StockAdvertencia>(
+        r#"
+        SELECT
+          p.id_producto,
+          p.nombre,
+          COALESCE(ps.stock_actual, 0)                    AS stock_actual,
+          SUM(vi.cantidad * COALESCE(vi.gramos, 1))       AS requerido,
+          SUM(vi.cantidad * COALESCE(vi.gramos, 1))
+            - COALESCE(ps.stock_actual, 0)                AS faltante,
+          COALESCE(
+            p.politica_stock,
+            (SELECT valor FROM ajuste WHERE clave = ?3),
+            'advertir'
+          )                                               AS politica,
+          NULL                                            AS autorizado_por
+        FROM venta_item vi
+        JOIN producto p ON p.id_producto = vi.id_producto
+        LEFT JOIN producto_stock ps ON ps.id_producto = p.id_producto
+        WHERE vi.id_venta = ?1
+          AND (?2 IS NULL OR vi.id_producto = ?2)
+        GROUP BY p.id_producto
+        HAVING SUM(vi.cantidad * COALESCE(vi.gramos, 1)) > COALESCE(ps.stock_actual, 0)
+        ORDER BY p.nombre
+        "#,
+    )
+    .bind(id_venta)
+    .bind(id_producto)
+    .bind(AJUSTE_STOCK_NEGATIVO)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(faltantes.into_iter().filter(|f| f.politica != "permitir").collect())
+}
+
+/// Evalúa la política dentro de la transacción del llamador. Un bloqueo se
+/// levanta con `forzar` (admin) o con la aprobación de un admin; queda auditado.
+pub(crate) async fn controlar(
+    state: &AppState,
+    conn: &mut SqliteConnection,
+    uid: i64,
+    id_venta: i64,
+    id_producto: Option<i64>,
+    forzar: bool,
+    aprobacion: Option<&AprobacionInput>,
+) -> Result<Vec<StockAdvertencia>, String> {
+    let mut faltantes = evaluar(conn, id_venta, id_producto).await?;
+
+    let bloqueados: Vec<String> = faltantes
+        .iter()
+        .filter(|f| f.politica == "bloquear")
+        .map(|f| format!("{} (stock {}, pedido {})", f.nombre, f.stock_actual, f.requerido))
+        .collect();
+    if bloqueados.is_empty() {
+        return Ok(faltantes);
+    }
+
+    let detalle = format!("Stock insuficiente: {}", bloqueados.join(", "));
+    if !forzar && aprobacion.is_none() {
+        return Err(detalle);
+    }
+    let (_, aprobado_por) = requerir_admin_o_aprobacion(state, aprobacion)
+        .await
+        .map_err(|e| format!("{detalle}. {e}"))?;
+
+    for f in faltantes.iter_mut().filter(|f| f.politica == "bloquear") {
+        f.autorizado_por = Some(aprobado_por);
+    }
+
+    let despues = serde_json::json!({
+        "aprobado_por": aprobado_por,
+        "faltantes": faltantes.iter().filter(|f| f.autorizado_por.is_some()).map(|f| serde_json::json!({
+            "id_producto": f.id_producto,
+            "stock_actual": f.stock_actual,
+            "requerido": f.requerido,
+        })).collect::<Vec<_>>(),
+    });
+    audit_repo::registrar(&mut *conn, Some(uid), "venta", Some(id_venta), "stock_forzado", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(faltantes)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Faltantes de la venta completa, para mostrar antes de cobrar
+#[tauri::command]
+pub async fn venta_stock_verificar(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<Vec<StockAdvertencia>, String> {
+    requerir_sesion(&state).await?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+    evaluar(&mut conn, id_venta, None).await
+}
+
+#[tauri::command]
+pub async fn politica_stock_global_obtener(
+    state: State<'_, AppState>,
+) -> Result<PoliticaStockGlobal, String> {
+    requerir_sesion(&state).await?;
+
+    let politica: Option<String> = sqlx::query_scalar("SELECT valor FROM ajuste WHERE clave = ?1")
+        .bind(AJUSTE_STOCK_NEGATIVO)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(PoliticaStockGlobal { politica: politica.unwrap_or_else(|| "advertir".into()) })
+}
+
+#[tauri::command]
+pub async fn politica_stock_global_fijar(
+    state: State<'_, AppState>,
+    politica: String,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+    let politica = validar_politica(&politica)?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let antes: Option<String> = sqlx::query_scalar("SELECT valor FROM ajuste WHERE clave = ?1")
+        .bind(AJUSTE_STOCK_NEGATIVO)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO ajuste (clave, valor) VALUES (?1, ?2)
+        ON CONFLICT(clave) DO UPDATE
+          SET valor = excluded.valor,
+              actualizado_en = DATETIME('now','localtime')
+        "#,
+    )
+    .bind(AJUSTE_STOCK_NEGATIVO)
+    .bind(&politica)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "ajuste",
+        None,
+        AJUSTE_STOCK_NEGATIVO,
+        Some(&serde_json::json!({ "valor": antes })),
+        Some(&serde_json::json!({ "valor": politica })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn producto_politica_stock_fijar(
+    state: State<'_, AppState>,
+    input: ProductoPoliticaInput,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+    let politica = input.politica.as_deref().map(validar_politica).transpose()?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let antes: Option<Option<String>> =
+        sqlx::query_scalar("SELECT politica_stock FROM producto WHERE id_producto = ?1")
+            .bind(input.id_producto)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    let antes = antes.ok_or_else(|| "Producto inexistente".to_string())?;
+
+    sqlx::query("UPDATE producto SET politica_stock = ?1 WHERE id_producto = ?2")
+        .bind(&politica)
+        .bind(input.id_producto)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "producto",
+        Some(input.id_producto),
+        "politica_stock",
+        Some(&serde_json::json!({ "politica_stock": antes })),
+        Some(&serde_json::json!({ "politica_stock": politica })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
@@ -1,8 +1,7 @@
 use sqlx::{SqlitePool, Row, sqlite::SqliteQueryResult};
 use time::OffsetDateTime;
-use super::model::StockMermaInput;
+use super::model::{ProductoCrearIn, StockMermaInput};
 use sqlx::{Sqlite, Transaction};
-use crate::stock::model::ReposicionModo;
 #[derive(Copy, Clone, Debug)]
 pub enum TipoPrecio { Venta, Costo }
 
@@ -17,17 +16,14 @@ impl TipoPrecio {
 
 /*  Crear producto  */
 
-pub async fn producto_crear(
-    pool: &SqlitePool,
-    codigo: &str,
-    nombre: &str,
-    precio_venta: i64,
-    costo: i64,
-    reposicion_modo: ReposicionModo,
-    reposicion_factor: i64,
-) -> anyhow::Result<i64> {
+pub async fn producto_crear(pool: &SqlitePool, input: &ProductoCrearIn) -> anyhow::Result<i64> {
+    let (precio_venta, costo, reposicion_factor) = (input.precio_venta, input.costo, input.reposicion_factor);
+    let alicuota_iva = input.alicuota_iva.unwrap_or(21.0);
     if precio_venta < 0 || costo < 0 { anyhow::bail!("precio/costo negativos"); }
     if reposicion_factor <= 0 { anyhow::bail!("reposicion_factor debe ser > 0"); }
+    if !crate::stock::model::ALICUOTAS_IVA.contains(&alicuota_iva) {
+        anyhow::bail!("alícuota de IVA inválida (21, 10.5 o 0)");
+    }
 
     let mut tx = pool.begin().await?;
 
@@ -39,16 +35,18 @@ pub async fn producto_crear(
             costo_actual,
             activo,
             reposicion_modo,
-            reposicion_factor
+            reposicion_factor,
+            alicuota_iva
          )
-         VALUES (?1,?2,?3,?4,1,?5,?6)"
+         VALUES (?1,?2,?3,?4,1,?5,?6,?7)"
     )
-    .bind(codigo)
-    .bind(nombre)
+    .bind(&input.codigo)
+    .bind(&input.nombre)
     .bind(precio_venta)
     .bind(costo)
-    .bind(reposicion_modo.as_str())
+    .bind(input.reposicion_modo.as_str())
     .bind(reposicion_factor)
+    .bind(alicuota_iva)
     .execute(&mut *tx)
     .await?;
 
@@ -358,6 +356,19 @@ pub async fn registrar_merma(
 }
 
 
+/// Costo de mover `unidades` de stock si `unidades_base` costaron
+/// `costo_base` (en los productos por peso, gramos): (costo_unitario,
+/// total_costo). El unitario de los de peso va por kg, como su costo de
+/// lista; por gramo redondearía a 0 en casi cualquier etiqueta.
+pub fn costo_movimiento(costo_base: i64, unidades_base: i64, unidades: i64, por_peso: bool) -> (i64, i64) {
+    if unidades_base <= 0 {
+        return (0, 0);
+    }
+    let redondeo = |num: i64| (num + unidades_base / 2) / unidades_base;
+    let escala = if por_peso { 1000 } else { 1 };
+    (redondeo(costo_base * escala), redondeo(unidades.abs() * costo_base))
+}
+
 pub fn factor_por_unidad(unidad: &str) -> Result<i64, String> {
     match unidad {
         "MAPLE" => Ok(1),
@@ -0,0 +1,316 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::repo as audit_repo;
+use crate::users::permisos::{requerir_admin, requerir_sesion, Rol};
+
+use super::model::{TicketEmitido, TicketEmitirInput, TicketNegocio, TicketNegocioInput};
+use super::{render, repo};
+
+/// A dónde sale un ticket
+#[derive(Debug)]
+enum Salida {
+    /// Dispositivo configurado: se abre sin crear ni truncar
+    Impresora(String),
+    /// Ruta pedida por un admin: tiene que ser un archivo nuevo
+    Archivo(PathBuf),
+    /// Archivo con nombre generado en la carpeta de tickets
+    Exportar(PathBuf),
+}
+
+/// Un operador solo imprime en las impresoras configuradas; otra ruta la pide un admin.
+fn elegir_salida(destino: Option<&str>, negocio: &TicketNegocio, rol: Rol) -> Result<Salida, String> {
+    let Some(destino) = destino else {
+        return Ok(Salida::Exportar(carpeta_tickets(&negocio.carpeta)?));
+    };
+    if negocio.impresoras.iter().any(|i| i == destino) {
+        return Ok(Salida::Impresora(destino.to_string()));
+    }
+    if rol != Rol::Admin {
+        return Err(format!(
+            "'{destino}' no es una impresora configurada: elegí una de la lista o exportá el ticket sin destino"
+        ));
+    }
+    Ok(Salida::Archivo(PathBuf::from(destino)))
+}
+
+fn carpeta_tickets(configurada: &str) -> Result<PathBuf, String> {
+    if !configurada.is_empty() {
+        return Ok(PathBuf::from(configurada));
+    }
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .map(|d| d.join("tickets"))
+        .ok_or_else(|| "No hay carpeta de tickets: configurala en los datos del negocio".into())
+}
+
+fn crear_nuevo(ruta: &Path) -> std::io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(ruta)
+}
+
+/// Escribe el ticket y devuelve la ruta usada. Nunca pisa un archivo existente.
+fn escribir(salida: Salida, id_venta: i64, extension: &str, bytes: &[u8]) -> Result<String, String> {
+    let (mut f, ruta) = match salida {
+        Salida::Impresora(dispositivo) => {
+            let f = OpenOptions::new()
+                .write(true)
+                .open(&dispositivo)
+                .map_err(|e| format!("No se pudo abrir la impresora '{dispositivo}': {e}"))?;
+            (f, PathBuf::from(dispositivo))
+        }
+        Salida::Archivo(ruta) => {
+            let f = crear_nuevo(&ruta).map_err(|e| match e.kind() {
+                ErrorKind::AlreadyExists => format!("'{}' ya existe: no se sobrescribe", ruta.display()),
+                _ => format!("No se pudo crear '{}': {e}", ruta.display()),
+            })?;
+            (f, ruta)
+        }
+        Salida::Exportar(carpeta) => {
+            fs::create_dir_all(&carpeta)
+                .map_err(|e| format!("No se pudo crear la carpeta '{}': {e}", carpeta.display()))?;
+            let mut n = 1;
+            loop {
+                let ruta = carpeta.join(format!("ticket-{id_venta}-{n}.{extension}"));
+                match crear_nuevo(&ruta) {
+                    Ok(f) => break (f, ruta),
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+                    Err(e) => return Err(format!("No se pudo crear '{}': {e}", ruta.display())),
+                }
+            }
+        }
+    };
+
+    let destino = ruta.display().to_string();
+    f.write_all(bytes)
+        .and_then(|_| f.flush())
+        .map_err(|e| format!("No se pudo escribir en '{destino}': {e}"))?;
+    Ok(destino)
+}
+
+// EMITIR: la primera emisión es el original, las siguientes salen como COPIA.
+// 'texto' sin destino es solo vista previa y no cuenta como emisión.
+// 'pdf' / 'escpos' sin destino se exportan a la carpeta de tickets.
+
+#[tauri::command]
+pub async fn ticket_emitir(
+    state: State<'_, AppState>,
+    input: TicketEmitirInput,
+) -> Result<TicketEmitido, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+    let pool = &state.pool;
+
+    let formato = input.formato.trim().to_lowercase();
+    if !matches!(formato.as_str(), "texto" | "pdf" | "escpos") {
+        return Err("Formato inválido (texto | pdf | escpos)".into());
+    }
+    let destino = input
+        .destino
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(str::to_string);
+
+    let copia = repo::ya_emitido(pool, input.id_venta).await?;
+    let ticket = repo::armar_ticket(pool, input.id_venta, copia).await?;
+    let ancho = input.ancho.unwrap_or(ticket.negocio.ancho).clamp(24, 64);
+
+    let renglones = render::renglones(&ticket, ancho);
+    let texto = render::texto(&renglones, ancho);
+
+    if destino.is_none() && formato == "texto" {
+        return Ok(TicketEmitido { copia, formato, destino: None, bytes: 0, texto });
+    }
+    let salida = elegir_salida(destino.as_deref(), &ticket.negocio, sesion.rol)?;
+
+    let (bytes, extension) = match formato.as_str() {
+        "pdf" => (render::pdf(&renglones, ancho), "pdf"),
+        "escpos" => (render::escpos(&renglones, ancho), "bin"),
+        _ => (texto.clone().into_bytes(), "txt"),
+    };
+    let destino = escribir(salida, input.id_venta, extension, &bytes)?;
+
+    repo::registrar_impresion(pool, input.id_venta, copia, &formato, Some(&destino), uid).await?;
+    if copia {
+        audit_repo::registrar(
+            pool,
+            Some(uid),
+            "venta",
+            Some(input.id_venta),
+            "ticket_copia",
+            None,
+            Some(&json!({ "formato": formato, "destino": destino })),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(TicketEmitido { copia, formato, destino: Some(destino), bytes: bytes.len(), texto })
+}
+
+// DATOS DEL NEGOCIO (encabezado / pie)
+
+#[tauri::command]
+pub async fn ticket_negocio_obtener(state: State<'_, AppState>) -> Result<TicketNegocio, String> {
+    requerir_sesion(&state).await?;
+    repo::leer_negocio(&state.pool).await
+}
+
+#[tauri::command]
+pub async fn ticket_negocio_fijar(
+    state: State<'_, AppState>,
+    input: TicketNegocioInput,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let nombre = input.nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre del negocio es obligatorio".into());
+    }
+    if matches!(input.ancho, Some(a) if !(24..=64).contains(&a)) {
+        return Err("Ancho inválido (24 a 64 columnas)".into());
+    }
+
+    let antes = repo::leer_negocio(&state.pool).await?;
+    let limpio = |v: &Option<String>| v.as_deref().unwrap_or("").trim().to_string();
+    let valores = [
+        ("negocio_nombre", nombre.to_string()),
+        ("negocio_direccion", limpio(&input.direccion)),
+        ("negocio_cuit", limpio(&input.cuit)),
+        ("negocio_telefono", limpio(&input.telefono)),
+        ("ticket_pie", limpio(&input.pie)),
+        ("ticket_ancho", input.ancho.unwrap_or(antes.ancho).to_string()),
+        (
+            "ticket_impresoras",
+            input
+                .impresoras
+                .as_ref()
+                .map(|v| v.iter().map(|i| i.trim()).filter(|i| !i.is_empty()).collect::<Vec<_>>().join("\n"))
+                .unwrap_or_else(|| antes.impresoras.join("\n")),
+        ),
+        (
+            "ticket_carpeta",
+            input.carpeta.as_deref().map(str::trim).unwrap_or(&antes.carpeta).to_string(),
+        ),
+    ];
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    for (clave, valor) in &valores {
+        sqlx::query(
+            r#"
+            INSERT INTO ajuste (clave, valor) VALUES (?1, ?2)
+            ON CONFLICT(clave) DO UPDATE
+              SET valor = excluded.valor,
+                  actualizado_en = DATETIME('now','localtime')
+            "#,
+        )
+        .bind(clave)
+        .bind(valor)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    let despues: serde_json::Map<String, serde_json::Value> =
+        valores.iter().map(|(k, v)| (k.to_string(), json!(v))).collect();
+    audit_repo::registrar(
+        &mut *tx,
+        Some(uid),
+        "ajuste",
+        None,
+        "ticket_negocio",
+        Some(&json!(antes)),
+        Some(&serde_json::Value::Object(despues)),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::ventas::prueba;
+
+    fn emitir(id_venta: i64, formato: &str, destino: Option<&str>) -> TicketEmitirInput {
+        serde_json::from_value(json!({ "id_venta": id_venta, "formato": formato, "destino": destino }))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn el_ticket_sale_a_impresoras_configuradas_o_a_un_archivo_nuevo() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let carpeta = std::env::temp_dir().join(format!("tickets-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&carpeta).unwrap();
+        let impresora = carpeta.join("lp0");
+        File::create(&impresora).unwrap();
+        let impresora = impresora.display().to_string();
+        let ajustes = [
+            ("ticket_carpeta", carpeta.display().to_string()),
+            ("ticket_impresoras", impresora.clone()),
+        ];
+        for (clave, valor) in ajustes {
+            sqlx::query("INSERT INTO ajuste (clave, valor) VALUES (?1, ?2)")
+                .bind(clave)
+                .bind(valor)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+
+        prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "efectivo", "monto": 600 }])).await;
+
+        // sin destino: archivo nuevo en la carpeta; la copia no pisa el original
+        let original = ticket_emitir(state.clone(), emitir(id_venta, "pdf", None)).await.unwrap();
+        let primero = carpeta.join(format!("ticket-{id_venta}-1.pdf"));
+        assert_eq!(original.destino, Some(primero.display().to_string()));
+        let copia = ticket_emitir(state.clone(), emitir(id_venta, "pdf", None)).await.unwrap();
+        assert!(copia.copia);
+        assert_eq!(copia.destino, Some(carpeta.join(format!("ticket-{id_venta}-2.pdf")).display().to_string()));
+        assert_eq!(fs::metadata(&primero).unwrap().len(), original.bytes as u64);
+
+        // impresora configurada
+        let out = ticket_emitir(state.clone(), emitir(id_venta, "escpos", Some(&impresora))).await.unwrap();
+        assert_eq!(fs::metadata(&impresora).unwrap().len(), out.bytes as u64);
+
+        // un operador no escribe en cualquier ruta
+        let otra = carpeta.join("otra.txt").display().to_string();
+        let err = ticket_emitir(state.clone(), emitir(id_venta, "texto", Some(&otra))).await.unwrap_err();
+        assert!(err.contains("no es una impresora configurada"), "{err}");
+        assert!(!carpeta.join("otra.txt").exists());
+
+        // un admin sí, pero nunca sobre un archivo existente
+        state.sesion.iniciar(1, Rol::Admin).unwrap();
+        let err = ticket_emitir(state.clone(), emitir(id_venta, "texto", Some(&primero.display().to_string())))
+            .await
+            .unwrap_err();
+        assert!(err.contains("no se sobrescribe"), "{err}");
+        assert_eq!(fs::metadata(&primero).unwrap().len(), original.bytes as u64);
+        let out = ticket_emitir(state.clone(), emitir(id_venta, "texto", Some(&otra))).await.unwrap();
+        assert_eq!(fs::read_to_string(&otra).unwrap(), out.texto);
+
+        let emisiones: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ticket_impresion WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(emisiones, 4);
+
+        fs::remove_dir_all(&carpeta).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+// INPUTS
+
+#[derive(Debug, Deserialize)]
+pub struct TicketEmitirInput {
+    pub id_venta: i64,
+    pub formato: String, // 'texto' | 'pdf' | 'escpos'
+    /// Impresora configurada (ej: /dev/usb/lp0, \\.\COM3). Sin destino, 'pdf' y
+    /// 'escpos' van a un archivo nuevo en la carpeta de tickets y 'texto' es
+    /// vista previa. Otra ruta solo la puede pedir un admin.
+    pub destino: Option<String>,
+    /// Columnas del papel (default: ajuste ticket_ancho)
+    pub ancho: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TicketNegocioInput {
+    pub nombre: String,
+    pub direccion: Option<String>,
+    pub cuit: Option<String>,
+    pub telefono: Option<String>,
+    pub pie: Option<String>,
+    pub ancho: Option<usize>,
+    /// Dispositivos a los que puede imprimir cualquier operador (None = no cambia)
+    pub impresoras: Option<Vec<String>>,
+    /// Carpeta de exportación (None = no cambia; vacía = Documentos/tickets)
+    pub carpeta: Option<String>,
+}
+
+// LAYOUT (lo que sale en papel, sin formato)
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TicketNegocio {
+    pub nombre: String,
+    pub direccion: String,
+    pub cuit: String,
+    pub telefono: String,
+    pub pie: String,
+    pub ancho: usize,
+    pub impresoras: Vec<String>,
+    pub carpeta: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TicketItem {
+    pub descripcion: String,
+    pub cantidad: i64,
+    pub precio_unitario: i64,
+    pub bruto: i64,
+    pub promo_grupo_id: Option<String>,
+    pub promo_combo: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TicketDescuento {
+    pub descripcion: String, // "Desc. Leche (cliente frecuente)"
+    pub monto: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TicketPago {
+    pub medio: String,
+    pub monto: i64,
+    pub cuotas: i64,
+    pub recargo: i64,
+    pub entregado: Option<i64>,
+    pub vuelto: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub negocio: TicketNegocio,
+    pub id_venta: i64,
+    pub fecha_hora: String,
+    pub operador: String,
+    pub id_caja: i64,
+    pub items: Vec<TicketItem>,
+    pub descuentos: Vec<TicketDescuento>,
+    pub total: i64,
+    pub pagos: Vec<TicketPago>,
+    pub anulada: bool,
+    pub copia: bool,
+}
+
+/// Un renglón ya armado; los renderers deciden cómo alinear / resaltar
+#[derive(Debug, Clone)]
+pub struct Renglon {
+    pub texto: String,
+    pub centrado: bool,
+    pub resaltado: bool,
+}
+
+// OUTPUT
+
+#[derive(Debug, Serialize)]
+pub struct TicketEmitido {
+    pub copia: bool,
+    pub formato: String,
+    pub destino: Option<String>,
+    pub bytes: usize,
+    pub texto: String, // siempre: sirve de vista previa
+}
@@ -0,0 +1,262 @@
+use super::model::{Renglon, Ticket};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Layout: el ticket en renglones de `ancho` columnas
+
+/// $ 12.345
+pub fn pesos(monto: i64) -> String {
+    let digitos = monto.abs().to_string();
+    let mut out = String::new();
+    for (i, c) in digitos.chars().enumerate() {
+        if i > 0 && (digitos.len() - i).is_multiple_of(3) {
+            out.push('.');
+        }
+        out.push(c);
+    }
+    if monto < 0 {
+        format!("-$ {out}")
+    } else {
+        format!("$ {out}")
+    }
+}
+
+fn recortar(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
+
+/// Texto a la izquierda, importe a la derecha
+fn columnas(izq: &str, der: &str, ancho: usize) -> String {
+    let largo_der = der.chars().count();
+    let izq = recortar(izq, ancho.saturating_sub(largo_der + 1));
+    let relleno = ancho.saturating_sub(izq.chars().count() + largo_der);
+    format!("{izq}{}{der}", " ".repeat(relleno))
+}
+
+fn normal(texto: String) -> Renglon {
+    Renglon { texto, centrado: false, resaltado: false }
+}
+
+fn centrado(texto: &str, resaltado: bool) -> Renglon {
+    Renglon { texto: texto.to_string(), centrado: true, resaltado }
+}
+
+pub fn renglones(t: &Ticket, ancho: usize) -> Vec<Renglon> {
+    let ancho = ancho.clamp(24, 64);
+    let sep = || normal("-".repeat(ancho));
+    let mut r = Vec::new();
+
+    // Encabezado
+    r.push(centrado(&t.negocio.nombre, true));
+    for extra in [&t.negocio.direccion, &t.negocio.telefono] {
+        if !extra.trim().is_empty() {
+            r.push(centrado(extra, false));
+        }
+    }
+    if !t.negocio.cuit.trim().is_empty() {
+        r.push(centrado(&format!("CUIT {}", t.negocio.cuit), false));
+    }
+    if t.copia {
+        r.push(centrado("*** COPIA ***", true));
+    }
+    if t.anulada {
+        r.push(centrado("*** VENTA ANULADA ***", true));
+    }
+    r.push(sep());
+    r.push(normal(columnas(&format!("Venta #{}", t.id_venta), &t.fecha_hora, ancho)));
+    r.push(sep());
+
+    // Ítems: los de un mismo combo van juntos bajo el nombre del combo
+    let mut grupos_vistos: Vec<&str> = Vec::new();
+    for it in &t.items {
+        match it.promo_grupo_id.as_deref() {
+            Some(g) if grupos_vistos.contains(&g) => continue,
+            Some(g) => {
+                grupos_vistos.push(g);
+                let del_grupo: Vec<_> = t
+                    .items
+                    .iter()
+                    .filter(|x| x.promo_grupo_id.as_deref() == Some(g))
+                    .collect();
+                let total_grupo: i64 = del_grupo.iter().map(|x| x.bruto).sum();
+                let nombre = it.promo_combo.as_deref().unwrap_or("Promo");
+                r.push(normal(columnas(&format!("PROMO {nombre}"), &pesos(total_grupo), ancho)));
+                for x in del_grupo {
+                    r.push(normal(recortar(&format!("  {} x {}", x.cantidad, x.descripcion), ancho)));
+                }
+            }
+            None => {
+                r.push(normal(recortar(&it.descripcion, ancho)));
+                r.push(normal(columnas(
+                    &format!("  {} x {}", it.cantidad, pesos(it.precio_unitario)),
+                    &pesos(it.bruto),
+                    ancho,
+                )));
+            }
+        }
+    }
+
+    // Descuentos
+    if !t.descuentos.is_empty() {
+        r.push(sep());
+        let subtotal: i64 = t.items.iter().map(|x| x.bruto).sum();
+        r.push(normal(columnas("Subtotal", &pesos(subtotal), ancho)));
+        for d in &t.descuentos {
+            r.push(normal(columnas(&d.descripcion, &pesos(-d.monto), ancho)));
+        }
+    }
+
+    r.push(sep());
+    let recargos: i64 = t.pagos.iter().map(|p| p.recargo).sum();
+    r.push(Renglon {
+        texto: columnas("TOTAL", &pesos(t.total + recargos), ancho),
+        centrado: false,
+        resaltado: true,
+    });
+    r.push(sep());
+
+    // Pagos
+    for p in &t.pagos {
+        let medio = if p.cuotas > 1 {
+            format!("{} ({} cuotas)", p.medio, p.cuotas)
+        } else {
+            p.medio.clone()
+        };
+        r.push(normal(columnas(&medio, &pesos(p.monto + p.recargo), ancho)));
+        if p.recargo > 0 {
+            r.push(normal(columnas("  incluye recargo", &pesos(p.recargo), ancho)));
+        }
+        if let Some(entregado) = p.entregado {
+            r.push(normal(columnas("  Entregado", &pesos(entregado), ancho)));
+            r.push(normal(columnas("  Vuelto", &pesos(p.vuelto), ancho)));
+        }
+    }
+
+    r.push(sep());
+    r.push(normal(recortar(&format!("Le atendió: {}  Caja #{}", t.operador, t.id_caja), ancho)));
+    if !t.negocio.pie.trim().is_empty() {
+        r.push(centrado(&t.negocio.pie, false));
+    }
+    if t.copia {
+        r.push(centrado("*** COPIA ***", false));
+    }
+    r
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Texto plano
+
+pub fn texto(renglones: &[Renglon], ancho: usize) -> String {
+    let mut out = String::new();
+    for r in renglones {
+        let linea = recortar(&r.texto, ancho);
+        if r.centrado {
+            let margen = ancho.saturating_sub(linea.chars().count()) / 2;
+            out.push_str(&" ".repeat(margen));
+        }
+        out.push_str(&linea);
+        out.push('\n');
+    }
+    out
+}
+
+// Impresoras y PDF (Type1 estándar) trabajan en Latin-1 / WinAnsi:
+// los acentos del castellano entran, el resto sale como '?'
+fn latin1(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| if (c as u32) < 256 { c as u32 as u8 } else { b'?' })
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PDF: una página del ancho del rollo (80 mm), Courier
+
+pub fn pdf(renglones: &[Renglon], ancho: usize) -> Vec<u8> {
+    const ANCHO_PT: f64 = 226.8; // 80 mm
+    const MARGEN: f64 = 10.0;
+
+    let cuerpo = ((ANCHO_PT - 2.0 * MARGEN) / (ancho as f64 * 0.6)).min(10.0);
+    let interlineado = cuerpo * 1.25;
+    let alto = 2.0 * MARGEN + interlineado * renglones.len().max(1) as f64;
+
+    // contenido: mismo texto que la versión plana, resaltados en negrita
+    let plano = texto(renglones, ancho);
+    let mut contenido: Vec<u8> = Vec::new();
+    contenido.extend_from_slice(
+        format!("BT {interlineado:.2} TL {MARGEN:.2} {:.2} Td\n", alto - MARGEN - cuerpo).as_bytes(),
+    );
+    for (linea, r) in plano.lines().zip(renglones) {
+        let fuente = if r.resaltado { "F2" } else { "F1" };
+        contenido.extend_from_slice(format!("/{fuente} {cuerpo:.2} Tf (").as_bytes());
+        for b in latin1(linea) {
+            if matches!(b, b'(' | b')' | b'\\') {
+                contenido.push(b'\\');
+            }
+            contenido.push(b);
+        }
+        contenido.extend_from_slice(b") Tj T*\n");
+    }
+    contenido.extend_from_slice(b"ET\n");
+
+    let objetos: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {ANCHO_PT:.2} {alto:.2}] \
+             /Contents 4 0 R /Resources << /Font << /F1 5 0 R /F2 6 0 R >> >> >>"
+        )
+        .into_bytes(),
+        {
+            let mut s = format!("<< /Length {} >>\nstream\n", contenido.len()).into_bytes();
+            s.extend_from_slice(&contenido);
+            s.extend_from_slice(b"endstream");
+            s
+        },
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+
+    let mut out: Vec<u8> = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objetos.len());
+    for (i, obj) in objetos.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(obj);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objetos.len() + 1).as_bytes());
+    for o in offsets {
+        out.extend_from_slice(format!("{o:010} 00000 n \n").as_bytes());
+    }
+    out.extend_from_slice(
+        format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objetos.len() + 1)
+            .as_bytes(),
+    );
+    out
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ESC/POS
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+pub fn escpos(renglones: &[Renglon], ancho: usize) -> Vec<u8> {
+    let mut out = vec![
+        ESC, b'@', // init
+        ESC, b't', 16, // tabla WPC1252
+    ];
+
+    for r in renglones {
+        out.extend_from_slice(&[ESC, b'a', if r.centrado { 1 } else { 0 }]);
+        out.extend_from_slice(&[ESC, b'E', if r.resaltado { 1 } else { 0 }]);
+        out.extend(latin1(&recortar(&r.texto, ancho)));
+        out.push(b'\n');
+    }
+
+    out.extend_from_slice(&[ESC, b'a', 0, ESC, b'E', 0]);
+    // avanzar y corte parcial
+    out.extend_from_slice(&[GS, b'V', 66, 3]);
+    out
+}
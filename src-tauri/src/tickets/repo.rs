@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use sqlx::SqlitePool;
+
+use crate::ventas_admin::commands::detalle_venta;
+
+use super::model::{Ticket, TicketDescuento, TicketItem, TicketNegocio, TicketPago};
+
+pub const ANCHO_DEFAULT: usize = 42;
+
+pub async fn leer_negocio(pool: &SqlitePool) -> Result<TicketNegocio, String> {
+    let filas: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT clave, valor FROM ajuste
+        WHERE clave IN ('negocio_nombre','negocio_direccion','negocio_cuit',
+                        'negocio_telefono','ticket_pie','ticket_ancho',
+                        'ticket_impresoras','ticket_carpeta')
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut m: HashMap<String, String> = filas.into_iter().collect();
+    let mut tomar = |k: &str| m.remove(k).unwrap_or_default();
+
+    Ok(TicketNegocio {
+        nombre: tomar("negocio_nombre"),
+        direccion: tomar("negocio_direccion"),
+        cuit: tomar("negocio_cuit"),
+        telefono: tomar("negocio_telefono"),
+        pie: tomar("ticket_pie"),
+        ancho: tomar("ticket_ancho").parse().unwrap_or(ANCHO_DEFAULT),
+        // una por renglón
+        impresoras: tomar("ticket_impresoras")
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect(),
+        carpeta: tomar("ticket_carpeta"),
+    })
+}
+
+/// true si la venta ya tiene un ticket emitido (lo que sigue es copia)
+pub async fn ya_emitido(pool: &SqlitePool, id_venta: i64) -> Result<bool, String> {
+    let n: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ticket_impresion WHERE id_venta = ?1")
+        .bind(id_venta)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(n > 0)
+}
+
+pub async fn registrar_impresion(
+    pool: &SqlitePool,
+    id_venta: i64,
+    copia: bool,
+    formato: &str,
+    destino: Option<&str>,
+    id_usuario: i64,
+) -> Result<i64, String> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO ticket_impresion (id_venta, copia, formato, destino, id_usuario)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        RETURNING id_impresion
+        "#,
+    )
+    .bind(id_venta)
+    .bind(copia)
+    .bind(formato)
+    .bind(destino)
+    .bind(id_usuario)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Arma el ticket con los mismos datos que venta_admin_detalle
+pub async fn armar_ticket(pool: &SqlitePool, id_venta: i64, copia: bool) -> Result<Ticket, String> {
+    let det = detalle_venta(pool, id_venta).await?;
+
+    let anulada = det.anulacion.is_some();
+    if det.resumen.estado != "finalizada" && !anulada {
+        return Err("Solo se emiten tickets de ventas finalizadas".into());
+    }
+
+    let items = det
+        .items
+        .iter()
+        .map(|it| TicketItem {
+            descripcion: it.producto.clone(),
+            cantidad: it.cantidad,
+            precio_unitario: it.precio_unitario,
+            bruto: it.bruto,
+            promo_grupo_id: it.promo_grupo_id.clone(),
+            promo_combo: it.promo_combo.clone(),
+        })
+        .collect();
+
+    let descuentos = det
+        .descuentos
+        .iter()
+        .map(|d| TicketDescuento {
+            descripcion: match &d.producto {
+                Some(p) => format!("Desc. {p} ({})", d.motivo),
+                None => format!("Desc. ticket ({})", d.motivo),
+            },
+            monto: d.monto,
+        })
+        .collect();
+
+    let pagos = det
+        .pagos
+        .iter()
+        .map(|p| TicketPago {
+            medio: p.medio.clone(),
+            monto: p.monto,
+            cuotas: p.cuotas,
+            recargo: p.recargo,
+            entregado: p.monto_entregado,
+            vuelto: p.vuelto,
+        })
+        .collect();
+
+    Ok(Ticket {
+        negocio: leer_negocio(pool).await?,
+        id_venta,
+        fecha_hora: det.resumen.fecha_hora.clone(),
+        operador: det.resumen.usuario.clone(),
+        id_caja: det.resumen.id_caja,
+        items,
+        descuentos,
+        total: det.resumen.total,
+        pagos,
+        anulada,
+        copia,
+    })
+}
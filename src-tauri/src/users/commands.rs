@@ -10,6 +10,9 @@ use crate::{
         repo,
     },
 };
+use crate::users::permisos::{requerir_admin, rol_de_usuario, ERR_SIN_SESION, ERR_USUARIO_INACTIVO};
+use crate::audit::repo as audit_repo;
+use serde_json::json;
 
 fn norm_username(s: &str) -> String {
     s.trim().to_lowercase()
@@ -19,10 +22,23 @@ fn norm_username(s: &str) -> String {
 // Crear usuario (usa repo::crear + normaliza nombre_usuario)
 #[tauri::command(rename = "usuario_crear")]
 pub async fn usuario_crear(state: State<'_, AppState>, input: UsuarioCrear) -> Result<i64, String> {
+    let actor = requerir_admin(&state).await?.id_usuario;
+
     // Rol fijo en este command
-    repo::crear(&state.pool, &input, "operador")
+    let id = repo::crear(&state.pool, &input, "operador")
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    let despues = json!({
+        "nombre": input.nombre.trim(),
+        "nombre_usuario": norm_username(&input.nombre_usuario),
+        "rol_tipo": "operador",
+    });
+    audit_repo::registrar(&state.pool, Some(actor), "usuario", Some(id), "crear", None, Some(&despues))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -32,6 +48,8 @@ pub async fn usuario_obtener(
     state: State<'_, AppState>,
     id_usuario: i64,
 ) -> Result<Usuario, String> {
+    requerir_admin(&state).await?;
+
     repo::obtener(&state.pool, id_usuario)
         .await
         .map_err(|e| e.to_string())
@@ -42,6 +60,8 @@ pub async fn usuario_listar(
     state: State<'_, AppState>,
     params: ListarUsuariosParams,
 ) -> Result<Vec<Usuario>, String> {
+    requerir_admin(&state).await?;
+
     repo::listar(&state.pool, params)
         .await
         .map_err(|e| e.to_string())
@@ -74,9 +94,15 @@ pub async fn login(state: State<'_, AppState>, input: LoginInput) -> Result<bool
         return Err("Contraseña incorrecta".into());
     }
 
-    if let Ok(mut guard) = state.session_user.lock() {
-        *guard = Some(id_usuario);
-    }
+    let (rol, _) = rol_de_usuario(&state.pool, id_usuario)
+        .await?
+        .ok_or_else(|| "Usuario inexistente".to_string())?;
+
+    state.sesion.iniciar(id_usuario, rol)?;
+
+    audit_repo::registrar(&state.pool, Some(id_usuario), "sesion", Some(id_usuario), "login", None, None)
+        .await
+        .map_err(|e| e.to_string())?;
 
     Ok(true)
 }
@@ -88,6 +114,9 @@ pub struct SessionOut {
     pub usuarioId: i64,
     pub rol: String,      // normalizado a minúsculas
     pub rol_tipo: String, // crudo en BD
+    pub login_en: String,
+    pub ultima_actividad: String,
+    pub bloqueada: bool,  // true => pedir contraseña (sesion_desbloquear)
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -128,21 +157,22 @@ async fn get_rol_raw(pool: &sqlx::SqlitePool, id_usuario: i64) -> Result<String,
 // session_info
 #[tauri::command]
 pub async fn session_info(state: State<'_, AppState>) -> Result<SessionOut, String> {
-    let maybe_id = state
-        .session_user
-        .lock()
-        .map_err(|_| "lock".to_string())?
-        .clone();
+    // snapshot: consultar la sesión no cuenta como actividad
+    let snap = state
+        .sesion
+        .snapshot()?
+        .ok_or_else(|| ERR_SIN_SESION.to_string())?;
 
-    let id = maybe_id.ok_or_else(|| "No hay sesión".to_string())?;
-
-    let rol_tipo_str = get_rol_raw(&state.pool, id).await?;
+    let rol_tipo_str = get_rol_raw(&state.pool, snap.id_usuario).await?;
     let rol_norm = rol_tipo_str.trim().to_lowercase();
 
     Ok(SessionOut {
-        usuarioId: id,
+        usuarioId: snap.id_usuario,
         rol: rol_norm,
         rol_tipo: rol_tipo_str,
+        login_en: snap.login_en,
+        ultima_actividad: snap.ultima_actividad,
+        bloqueada: snap.bloqueada,
     })
 }
 
@@ -150,13 +180,7 @@ pub async fn session_info(state: State<'_, AppState>) -> Result<SessionOut, Stri
 // usuario_actual
 #[tauri::command]
 pub async fn usuario_actual(state: State<'_, AppState>) -> Result<UsuarioActualOut, String> {
-    let maybe_id = state
-        .session_user
-        .lock()
-        .map_err(|_| "lock".to_string())?
-        .clone();
-
-    let id = maybe_id.ok_or_else(|| "No hay sesión".to_string())?;
+    let (id, _) = state.sesion.usuario_activo()?;
 
     if has_rol_tipo(&state.pool).await? {
         let row = sqlx::query("SELECT nombre, rol_tipo FROM usuario WHERE id_usuario = ?1")
@@ -195,14 +219,51 @@ pub async fn usuario_actual(state: State<'_, AppState>) -> Result<UsuarioActualO
 // Logout
 #[tauri::command]
 pub async fn logout(state: State<'_, AppState>) -> Result<(), String> {
-    if let Ok(mut guard) = state.session_user.lock() {
-        *guard = None;
+    if let Some(id_usuario) = state.sesion.cerrar()? {
+        audit_repo::registrar(&state.pool, Some(id_usuario), "sesion", Some(id_usuario), "logout", None, None)
+            .await
+            .map_err(|e| e.to_string())?;
     }
     Ok(())
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Bloqueo manual (el operador se aleja de la caja) y desbloqueo con contraseña
+#[tauri::command]
+pub async fn sesion_bloquear(state: State<'_, AppState>) -> Result<(), String> {
+    state.sesion.bloquear()
+}
+
+#[tauri::command]
+pub async fn sesion_desbloquear(state: State<'_, AppState>, password: String) -> Result<(), String> {
+    let id_usuario = state
+        .sesion
+        .usuario_id()?
+        .ok_or_else(|| ERR_SIN_SESION.to_string())?;
+
+    let hash: String = sqlx::query_scalar(
+        "SELECT clave_hash FROM usuario WHERE id_usuario = ?1 AND activo = 1",
+    )
+    .bind(id_usuario)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| ERR_USUARIO_INACTIVO.to_string())?;
+
+    let ok = verify_password(&password, &hash)
+        .map_err(|_| "Error al verificar contraseña".to_string())?;
+
+    if !ok {
+        return Err("Contraseña incorrecta".into());
+    }
+
+    state.sesion.desbloquear(id_usuario)
+}
+
 #[tauri::command]
 pub async fn usuario_listar_opciones(state: State<'_, AppState>) -> Result<Vec<UsuarioOpcion>, String> {
+    requerir_admin(&state).await?;
+
     repo::listar_opciones(&state.pool)
         .await
         .map_err(|e| e.to_string())
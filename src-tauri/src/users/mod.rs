@@ -1,5 +1,6 @@
-pub mod auth;      
 pub mod crypto;
 pub mod model;
+pub mod permisos;
 pub mod repo;
+pub mod sesion;
 pub mod commands;
\ No newline at end of file
@@ -0,0 +1,359 @@
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::app_state::AppState;
+use crate::users::crypto::verify_password;
+
+// Autorización del lado de Rust: cada command resuelve el usuario de la sesión
+// y su usuario.rol_tipo antes de tocar la base. El front (RequireAuth) sólo
+// oculta pantallas; la decisión real se toma acá.
+
+pub const ERR_SIN_SESION: &str = "No hay sesión";
+pub const ERR_USUARIO_INACTIVO: &str = "Usuario inactivo";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rol {
+    Admin,
+    Operador,
+}
+
+impl Rol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rol::Admin => "admin",
+            Rol::Operador => "operador",
+        }
+    }
+
+    /// rol_tipo crudo de la BD (se normaliza trim + minúsculas)
+    pub fn desde_db(s: &str) -> Option<Rol> {
+        match s.trim().to_lowercase().as_str() {
+            "admin" => Some(Rol::Admin),
+            "operador" => Some(Rol::Operador),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SesionActiva {
+    pub id_usuario: i64,
+    pub rol: Rol,
+}
+
+/// Mensaje único para rechazos por rol (el front lo muestra tal cual)
+pub fn err_sin_permiso(permitidos: &[Rol]) -> String {
+    let roles: Vec<&str> = permitidos.iter().map(|r| r.as_str()).collect();
+    format!("No autorizado: esta acción requiere rol {}", roles.join(" o "))
+}
+
+/// Rol vigente del usuario (None si no existe)
+pub async fn rol_de_usuario(pool: &SqlitePool, id_usuario: i64) -> Result<Option<(Rol, i64)>, String> {
+    let row: Option<(String, i64)> = sqlx::query_as(
+        "SELECT rol_tipo, activo FROM usuario WHERE id_usuario = ?1",
+    )
+    .bind(id_usuario)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match row {
+        None => Ok(None),
+        Some((rol_tipo, activo)) => {
+            let rol = Rol::desde_db(&rol_tipo)
+                .ok_or_else(|| format!("Rol desconocido: {rol_tipo}"))?;
+            Ok(Some((rol, activo)))
+        }
+    }
+}
+
+/// Usuario logueado + rol. Falla si no hay sesión, si está bloqueada por
+/// inactividad o si el usuario fue desactivado. El rol se relee de la BD.
+pub async fn requerir_sesion(state: &AppState) -> Result<SesionActiva, String> {
+    let (id_usuario, _) = state.sesion.usuario_activo()?;
+
+    let (rol, activo) = rol_de_usuario(&state.pool, id_usuario)
+        .await?
+        .ok_or_else(|| ERR_SIN_SESION.to_string())?;
+
+    if activo != 1 {
+        return Err(ERR_USUARIO_INACTIVO.into());
+    }
+
+    Ok(SesionActiva { id_usuario, rol })
+}
+
+pub async fn requerir_rol(state: &AppState, permitidos: &[Rol]) -> Result<SesionActiva, String> {
+    let sesion = requerir_sesion(state).await?;
+    if permitidos.contains(&sesion.rol) {
+        Ok(sesion)
+    } else {
+        Err(err_sin_permiso(permitidos))
+    }
+}
+
+pub async fn requerir_admin(state: &AppState) -> Result<SesionActiva, String> {
+    requerir_rol(state, &[Rol::Admin]).await
+}
+
+/// Credenciales de un admin presente que autoriza la operación
+#[derive(Debug, Clone, Deserialize)]
+pub struct AprobacionInput {
+    pub nombre_usuario: String,
+    pub password: String,
+}
+
+/// Operaciones sensibles: las hace un admin, o un operador con la aprobación
+/// (usuario + contraseña) de un admin. Devuelve la sesión y quién aprobó.
+pub async fn requerir_admin_o_aprobacion(
+    state: &AppState,
+    aprobacion: Option<&AprobacionInput>,
+) -> Result<(SesionActiva, i64), String> {
+    let sesion = requerir_sesion(state).await?;
+    if sesion.rol == Rol::Admin {
+        return Ok((sesion, sesion.id_usuario));
+    }
+
+    let ap = aprobacion.ok_or_else(|| {
+        "Se requiere la aprobación de un administrador".to_string()
+    })?;
+
+    let nombre_usuario = ap.nombre_usuario.trim().to_lowercase();
+    let row: Option<(i64, String, i64)> = sqlx::query_as(
+        "SELECT id_usuario, clave_hash, activo FROM usuario WHERE nombre_usuario = ?1",
+    )
+    .bind(&nombre_usuario)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (id_aprobador, hash, activo) = row.ok_or_else(|| "Aprobador inexistente".to_string())?;
+    if activo != 1 {
+        return Err("Aprobador inactivo".into());
+    }
+
+    let ok = verify_password(&ap.password, &hash)
+        .map_err(|_| "Error al verificar contraseña".to_string())?;
+    if !ok {
+        return Err("Contraseña del aprobador incorrecta".into());
+    }
+
+    match rol_de_usuario(&state.pool, id_aprobador).await? {
+        Some((Rol::Admin, _)) => Ok((sesion, id_aprobador)),
+        _ => Err("El aprobador debe tener rol admin".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri::Manager;
+
+    use crate::users::model::UsuarioCrear;
+    use crate::users::repo as usuarios_repo;
+    use crate::users::sesion::ERR_SESION_BLOQUEADA;
+
+    // Seed (0004): 1 es admin, 2 es operador.
+    const ADMIN: Option<(i64, Rol)> = Some((1, Rol::Admin));
+    const OPERADOR: Option<(i64, Rol)> = Some((2, Rol::Operador));
+
+    async fn alta(pool: &SqlitePool, nombre_usuario: &str, rol: &str) -> i64 {
+        let input = UsuarioCrear {
+            nombre: nombre_usuario.to_string(),
+            nombre_usuario: nombre_usuario.to_string(),
+            password: "clave".to_string(),
+        };
+        usuarios_repo::crear(pool, &input, rol).await.expect("alta usuario")
+    }
+
+    fn aprobacion(nombre_usuario: &str, password: &str) -> AprobacionInput {
+        AprobacionInput {
+            nombre_usuario: nombre_usuario.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn rol_desde_db_normaliza() {
+        assert_eq!(Rol::desde_db(" Admin "), Some(Rol::Admin));
+        assert_eq!(Rol::desde_db("OPERADOR"), Some(Rol::Operador));
+        assert_eq!(Rol::desde_db("empleado"), None);
+    }
+
+    #[tokio::test]
+    async fn sin_sesion_rechaza_todo() {
+        let state = AppState::prueba(None).await;
+        assert_eq!(requerir_sesion(&state).await.unwrap_err(), ERR_SIN_SESION);
+        assert_eq!(requerir_admin(&state).await.unwrap_err(), ERR_SIN_SESION);
+        assert_eq!(requerir_admin_o_aprobacion(&state, None).await.unwrap_err(), ERR_SIN_SESION);
+    }
+
+    #[tokio::test]
+    async fn operador_tiene_sesion_pero_no_admin() {
+        let state = AppState::prueba(OPERADOR).await;
+        let s = requerir_sesion(&state).await.unwrap();
+        assert_eq!((s.id_usuario, s.rol), (2, Rol::Operador));
+        assert_eq!(requerir_admin(&state).await.unwrap_err(), err_sin_permiso(&[Rol::Admin]));
+        assert!(requerir_rol(&state, &[Rol::Admin, Rol::Operador]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn admin_pasa_sin_aprobacion() {
+        let state = AppState::prueba(ADMIN).await;
+        assert_eq!(requerir_admin(&state).await.unwrap().rol, Rol::Admin);
+        let (s, aprobador) = requerir_admin_o_aprobacion(&state, None).await.unwrap();
+        assert_eq!((s.id_usuario, aprobador), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn el_rol_se_relee_de_la_base() {
+        let state = AppState::prueba(OPERADOR).await;
+        sqlx::query("UPDATE usuario SET rol_tipo = 'admin' WHERE id_usuario = 2")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        assert!(requerir_admin(&state).await.is_ok());
+
+        sqlx::query("UPDATE usuario SET activo = 0 WHERE id_usuario = 2")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(requerir_sesion(&state).await.unwrap_err(), ERR_USUARIO_INACTIVO);
+    }
+
+    #[tokio::test]
+    async fn sesion_bloqueada_rechaza_ambos_roles() {
+        for usuario in [ADMIN, OPERADOR] {
+            let state = AppState::prueba(usuario).await;
+            state.sesion.bloquear().unwrap();
+            assert_eq!(requerir_sesion(&state).await.unwrap_err(), ERR_SESION_BLOQUEADA);
+            assert_eq!(requerir_admin(&state).await.unwrap_err(), ERR_SESION_BLOQUEADA);
+        }
+    }
+
+    #[tokio::test]
+    async fn operador_necesita_aprobacion_de_un_admin() {
+        let state = AppState::prueba(OPERADOR).await;
+        let id_jefe = alta(&state.pool, "jefe", "admin").await;
+        alta(&state.pool, "cajero", "operador").await;
+
+        assert!(requerir_admin_o_aprobacion(&state, None).await.is_err());
+
+        let (s, aprobador) = requerir_admin_o_aprobacion(&state, Some(&aprobacion(" Jefe ", "clave")))
+            .await
+            .unwrap();
+        assert_eq!((s.id_usuario, aprobador), (2, id_jefe));
+
+        let mal = requerir_admin_o_aprobacion(&state, Some(&aprobacion("jefe", "otra"))).await;
+        assert_eq!(mal.unwrap_err(), "Contraseña del aprobador incorrecta");
+
+        let operador = requerir_admin_o_aprobacion(&state, Some(&aprobacion("cajero", "clave"))).await;
+        assert_eq!(operador.unwrap_err(), "El aprobador debe tener rol admin");
+
+        let nadie = requerir_admin_o_aprobacion(&state, Some(&aprobacion("nadie", "clave"))).await;
+        assert_eq!(nadie.unwrap_err(), "Aprobador inexistente");
+    }
+
+    // Commands registrados en main.rs, llamados con cada rol. Los de admin
+    // tienen que rechazar al operador con el mensaje común; los de sesión
+    // sólo piden estar logueado. Con el rol correcto puede fallar por otra
+    // cosa (datos), pero nunca por permisos.
+    macro_rules! con_rol {
+        ($usuario:expr, |$state:ident| $llamada:expr) => {{
+            let app = tauri::test::mock_app();
+            app.manage(AppState::prueba($usuario).await);
+            let $state = app.state::<AppState>();
+            $llamada.await.map(|_| ())
+        }};
+    }
+
+    fn es_rechazo(e: &str) -> bool {
+        e == ERR_SIN_SESION || e.starts_with("No autorizado")
+    }
+
+    macro_rules! solo_admin {
+        ($nombre:literal, |$state:ident| $llamada:expr) => {{
+            let r = con_rol!(None, |$state| $llamada);
+            assert_eq!(r.unwrap_err(), ERR_SIN_SESION, "{} sin sesión", $nombre);
+            let r = con_rol!(OPERADOR, |$state| $llamada);
+            assert_eq!(r.unwrap_err(), err_sin_permiso(&[Rol::Admin]), "{} operador", $nombre);
+            if let Err(e) = con_rol!(ADMIN, |$state| $llamada) {
+                assert!(!es_rechazo(&e), "{} admin: {e}", $nombre);
+            }
+        }};
+    }
+
+    macro_rules! con_sesion {
+        ($nombre:literal, |$state:ident| $llamada:expr) => {{
+            let r = con_rol!(None, |$state| $llamada);
+            assert_eq!(r.unwrap_err(), ERR_SIN_SESION, "{} sin sesión", $nombre);
+            for usuario in [OPERADOR, ADMIN] {
+                if let Err(e) = con_rol!(usuario, |$state| $llamada) {
+                    assert!(!es_rechazo(&e), "{} {:?}: {e}", $nombre, usuario);
+                }
+            }
+        }};
+    }
+
+    fn input<T: serde::de::DeserializeOwned>(v: serde_json::Value) -> T {
+        serde_json::from_value(v).expect("input de prueba")
+    }
+
+    #[tokio::test]
+    async fn commands_de_admin_por_rol() {
+        use crate::{audit, gastos, promos, reportes, stock, users, ventas_admin, PNL};
+        use serde_json::json;
+
+        solo_admin!("pnl_reporte", |s| PNL::commands::pnl_reporte(
+            s,
+            input(json!({ "desde": "2025-01-01", "hasta": "2025-01-31", "group_by": "total" }))
+        ));
+        solo_admin!("venta_admin_editar_guardar", |s| ventas_admin::editar::venta_admin_editar_guardar(
+            s,
+            input(json!({ "id_venta": 1, "items": [], "pagos": [], "motivo": "prueba" }))
+        ));
+        solo_admin!("precio_actualizar", |s| stock::commands::precio_actualizar(
+            s,
+            input(json!({ "id_producto": 1, "tipo": "venta", "nuevo": 100 }))
+        ));
+        solo_admin!("stock_fijar_absoluto", |s| stock::commands::stock_fijar_absoluto(
+            s,
+            input(json!({ "id_producto": 1, "nuevo": 5 }))
+        ));
+        solo_admin!("sueldo_registrar", |s| gastos::commands::sueldo_registrar(
+            s,
+            input(json!({ "descripcion": "Sueldo", "monto": 1000 }))
+        ));
+
+        solo_admin!("usuario_crear", |s| users::commands::usuario_crear(
+            s,
+            UsuarioCrear {
+                nombre: "Nuevo".into(),
+                nombre_usuario: "nuevo".into(),
+                password: "clave".into(),
+            }
+        ));
+        solo_admin!("usuario_listar_opciones", |s| users::commands::usuario_listar_opciones(s));
+        solo_admin!("stock_listar", |s| stock::commands::stock_listar(s, None, None, None, None));
+        solo_admin!("reporte_stock_general", |s| stock::commands::reporte_stock_general(s, None));
+        solo_admin!("politica_stock_global_fijar", |s| stock::politica::politica_stock_global_fijar(
+            s,
+            "advertir".into()
+        ));
+        solo_admin!("reporte_rentabilidad", |s| reportes::rentabilidad::reporte_rentabilidad(s, None, None));
+        solo_admin!("libro_iva_ventas", |s| reportes::libro_iva::libro_iva_ventas(s, "2025-01".into()));
+        solo_admin!("promo_combo_auto_fijar", |s| promos::commands::promo_combo_auto_fijar(s, true));
+        solo_admin!("audit_evento_listar", |s| audit::commands::audit_evento_listar(s, None));
+    }
+
+    #[tokio::test]
+    async fn commands_de_sesion_por_rol() {
+        use crate::{clientes, medios_pago, ventas};
+
+        con_sesion!("productos_disponibles", |s| ventas::commands::productos_disponibles(s));
+        con_sesion!("historial_ventas_hoy", |s| ventas::commands::historial_ventas_hoy(s));
+        con_sesion!("venta_estacionadas_listar", |s| ventas::estacionadas::venta_estacionadas_listar(s, None));
+        con_sesion!("medio_pago_listar", |s| medios_pago::commands::medio_pago_listar(s, None));
+        con_sesion!("cliente_buscar", |s| clientes::commands::cliente_buscar(s, None));
+    }
+}
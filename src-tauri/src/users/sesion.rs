@@ -0,0 +1,190 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::users::permisos::{Rol, ERR_SIN_SESION};
+
+// Sesión única de la app (reemplaza AuthState + AppState.session_user).
+// Guarda quién está logueado, con qué rol, desde cuándo y su última actividad.
+// Si pasa IDLE_TIMEOUT sin actividad la sesión queda bloqueada: el usuario
+// sigue siendo el mismo, pero hay que reingresar la contraseña para seguir.
+
+pub const IDLE_TIMEOUT_DEFAULT: Duration = Duration::from_secs(15 * 60);
+
+pub const ERR_SESION_BLOQUEADA: &str = "Sesión bloqueada por inactividad";
+
+#[derive(Debug, Clone)]
+struct SesionDatos {
+    id_usuario: i64,
+    rol: Rol,
+    login_en: DateTime<Local>,
+    ultima_actividad: DateTime<Local>,
+    ultima_actividad_mono: Instant, // reloj monotónico para el timeout
+    bloqueada: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SesionSnapshot {
+    pub id_usuario: i64,
+    pub rol: String,
+    pub login_en: String,         // "YYYY-MM-DD HH:MM:SS" (local)
+    pub ultima_actividad: String, // "YYYY-MM-DD HH:MM:SS" (local)
+    pub bloqueada: bool,
+}
+
+pub struct SesionService {
+    actual: Mutex<Option<SesionDatos>>,
+    idle_timeout: Duration,
+}
+
+impl Default for SesionService {
+    fn default() -> Self {
+        Self::new(IDLE_TIMEOUT_DEFAULT)
+    }
+}
+
+fn fmt_local(dt: &DateTime<Local>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+impl SesionService {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            actual: Mutex::new(None),
+            idle_timeout,
+        }
+    }
+
+    /// Login OK: reemplaza cualquier sesión previa
+    pub fn iniciar(&self, id_usuario: i64, rol: Rol) -> Result<(), String> {
+        let ahora = Local::now();
+        let mut g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        *g = Some(SesionDatos {
+            id_usuario,
+            rol,
+            login_en: ahora,
+            ultima_actividad: ahora,
+            ultima_actividad_mono: Instant::now(),
+            bloqueada: false,
+        });
+        Ok(())
+    }
+
+    /// Logout. Devuelve el usuario que estaba logueado (si había)
+    pub fn cerrar(&self) -> Result<Option<i64>, String> {
+        let mut g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        Ok(g.take().map(|s| s.id_usuario))
+    }
+
+    pub fn bloquear(&self) -> Result<(), String> {
+        let mut g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        let s = g.as_mut().ok_or_else(|| ERR_SIN_SESION.to_string())?;
+        s.bloqueada = true;
+        Ok(())
+    }
+
+    /// Desbloquea sólo si quien reingresa es el mismo usuario de la sesión
+    pub fn desbloquear(&self, id_usuario: i64) -> Result<(), String> {
+        let mut g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        let s = g.as_mut().ok_or_else(|| ERR_SIN_SESION.to_string())?;
+        if s.id_usuario != id_usuario {
+            return Err("La sesión bloqueada pertenece a otro usuario".into());
+        }
+        s.bloqueada = false;
+        s.ultima_actividad = Local::now();
+        s.ultima_actividad_mono = Instant::now();
+        Ok(())
+    }
+
+    /// Usuario de la sesión aunque esté bloqueada (para desbloquear)
+    pub fn usuario_id(&self) -> Result<Option<i64>, String> {
+        let g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        Ok(g.as_ref().map(|s| s.id_usuario))
+    }
+
+    /// Usuario + rol de la sesión, registrando actividad.
+    /// Falla si no hay sesión o si está bloqueada (o venció el idle timeout).
+    pub fn usuario_activo(&self) -> Result<(i64, Rol), String> {
+        let mut g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        let s = g.as_mut().ok_or_else(|| ERR_SIN_SESION.to_string())?;
+
+        if !s.bloqueada && s.ultima_actividad_mono.elapsed() >= self.idle_timeout {
+            s.bloqueada = true;
+        }
+        if s.bloqueada {
+            return Err(ERR_SESION_BLOQUEADA.into());
+        }
+
+        s.ultima_actividad = Local::now();
+        s.ultima_actividad_mono = Instant::now();
+        Ok((s.id_usuario, s.rol))
+    }
+
+    /// Estado de la sesión sin contar como actividad (para polling del front)
+    pub fn snapshot(&self) -> Result<Option<SesionSnapshot>, String> {
+        let mut g = self.actual.lock().map_err(|_| "lock".to_string())?;
+        let Some(s) = g.as_mut() else { return Ok(None) };
+
+        if !s.bloqueada && s.ultima_actividad_mono.elapsed() >= self.idle_timeout {
+            s.bloqueada = true;
+        }
+
+        Ok(Some(SesionSnapshot {
+            id_usuario: s.id_usuario,
+            rol: s.rol.as_str().to_string(),
+            login_en: fmt_local(&s.login_en),
+            ultima_actividad: fmt_local(&s.ultima_actividad),
+            bloqueada: s.bloqueada,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sin_login_no_hay_usuario() {
+        let s = SesionService::default();
+        assert_eq!(s.usuario_activo().unwrap_err(), ERR_SIN_SESION);
+        assert!(s.snapshot().unwrap().is_none());
+        assert_eq!(s.cerrar().unwrap(), None);
+    }
+
+    #[test]
+    fn login_reemplaza_y_logout_devuelve_usuario() {
+        let s = SesionService::default();
+        s.iniciar(2, Rol::Operador).unwrap();
+        s.iniciar(1, Rol::Admin).unwrap();
+        assert_eq!(s.usuario_activo().unwrap(), (1, Rol::Admin));
+        assert_eq!(s.cerrar().unwrap(), Some(1));
+        assert!(s.usuario_id().unwrap().is_none());
+    }
+
+    #[test]
+    fn inactividad_bloquea_y_desbloquea_el_mismo_usuario() {
+        let s = SesionService::new(Duration::ZERO);
+        s.iniciar(2, Rol::Operador).unwrap();
+
+        let snap = s.snapshot().unwrap().unwrap();
+        assert!(snap.bloqueada);
+        assert_eq!(s.usuario_activo().unwrap_err(), ERR_SESION_BLOQUEADA);
+        // bloqueada, pero sigue siendo del mismo usuario
+        assert_eq!(s.usuario_id().unwrap(), Some(2));
+
+        assert!(s.desbloquear(3).is_err());
+        s.desbloquear(2).unwrap();
+    }
+
+    #[test]
+    fn bloqueo_manual() {
+        let s = SesionService::default();
+        s.iniciar(2, Rol::Operador).unwrap();
+        s.bloquear().unwrap();
+        assert_eq!(s.usuario_activo().unwrap_err(), ERR_SESION_BLOQUEADA);
+        s.desbloquear(2).unwrap();
+        assert_eq!(s.usuario_activo().unwrap(), (2, Rol::Operador));
+    }
+}
@@ -2,7 +2,19 @@ use tauri::State;
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
 use crate::AppState;
-use super::{repo, model::PromoComboAplicarInput};
+use super::{descuentos, estacionadas, repo, model::PromoComboAplicarInput};
+use crate::users::permisos::{requerir_admin_o_aprobacion, requerir_sesion, AprobacionInput};
+use crate::caja::cierre_z;
+use crate::devoluciones::repo as devoluciones_repo;
+use crate::audit::repo as audit_repo;
+use crate::medios_pago::repo as medios_pago_repo;
+use crate::cuenta_corriente::commands as cuenta_corriente;
+use crate::fiscal::commands as fiscal;
+use crate::fiscal::repo as fiscal_repo;
+use crate::stock::politica::{self as politica_stock, StockAdvertencia};
+use crate::promos::deteccion as promos_deteccion;
+use serde_json::{json, Value};
+use sqlx::SqliteConnection;
 
 #[derive(sqlx::FromRow, serde::Serialize)]
 pub struct ProductoDisponible {
@@ -12,6 +24,23 @@ pub struct ProductoDisponible {
     pub stock_disponible: i64,
 }
 
+/// Auditoría de la venta: `antes` se toma al entrar y el después se relee
+/// dentro de la misma transacción.
+pub(crate) async fn auditar_venta(
+    conn: &mut SqliteConnection,
+    uid: i64,
+    id_venta: i64,
+    accion: &str,
+    antes: Option<Value>,
+) -> Result<(), String> {
+    let despues = audit_repo::venta_snapshot(&mut *conn, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit_repo::registrar(&mut *conn, Some(uid), "venta", Some(id_venta), accion, antes.as_ref(), despues.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[derive(sqlx::FromRow, serde::Serialize)]
 pub struct VentaItemDto {
     pub id_item: i64,
@@ -19,6 +48,7 @@ pub struct VentaItemDto {
     pub nombre: String,
     pub cantidad: i64,
     pub precio_unitario: i64,
+    pub descuento: i64,
     pub subtotal: i64,
 }
 
@@ -27,12 +57,20 @@ pub struct AgregarItemInput {
     pub id_venta: i64,
     pub id_producto: i64,
     pub cantidad: i64,
+    /// Admin: vender igual un producto con política 'bloquear'
+    #[serde(default)]
+    pub forzar_stock: bool,
+    /// Operador: aprobación de un admin para levantar el bloqueo
+    pub aprobacion: Option<AprobacionInput>,
 }
 
 #[derive(serde::Deserialize)]
 pub struct SetCantidadInput {
     pub id_item: i64,
     pub cantidad: i64,
+    #[serde(default)]
+    pub forzar_stock: bool,
+    pub aprobacion: Option<AprobacionInput>,
 }
 
 #[derive(serde::Deserialize)]
@@ -50,17 +88,97 @@ pub struct VentaCancelarInput {
     pub id_venta: i64,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VentaAnularInput {
+    pub id_venta: i64,
+    pub motivo: String,
+    /// Obligatoria si quien anula no es admin
+    pub aprobacion: Option<AprobacionInput>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PagoInput {
     pub medio: String,
     pub monto: i64,
     pub referencia: Option<String>,
+    /// Efectivo: lo que entregó el cliente (el vuelto sale de acá)
+    #[serde(default)]
+    pub monto_entregado: Option<i64>,
+    /// Tarjeta en cuotas (None = 1 pago)
+    #[serde(default)]
+    pub cuotas: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VentaFinalizarOut {
+    pub total: i64,
+    pub recargos: i64, // lo que se cobró de más por medio / cuotas
+    pub entregado: i64,
+    pub vuelto: i64,
+    pub advertencias: Vec<StockAdvertencia>,
+    pub id_comprobante: Option<i64>, // factura en cola (facturación habilitada)
+}
+
+struct PagoNeto<'a> {
+    medio: &'a str,
+    monto: i64,
+    monto_entregado: Option<i64>,
+    vuelto: i64,
+    referencia: Option<&'a str>,
+    cuotas: Option<i64>,
+}
+
+/// Lleva los pagos a neto. Si el efectivo suma de más respecto del total,
+/// el excedente se toma como vuelto (el cajero cargó lo que le dieron).
+fn pagos_netos(pagos: &[PagoInput], total: i64) -> Result<Vec<PagoNeto<'_>>, String> {
+    let mut netos = Vec::with_capacity(pagos.len());
+    for p in pagos {
+        if p.monto <= 0 {
+            return Err("Monto de pago inválido".into());
+        }
+        let vuelto = repo::vuelto_de_pago(&p.medio, p.monto, p.monto_entregado)?;
+        netos.push(PagoNeto {
+            medio: &p.medio,
+            monto: p.monto,
+            monto_entregado: p.monto_entregado,
+            vuelto,
+            referencia: p.referencia.as_deref(),
+            cuotas: p.cuotas,
+        });
+    }
+
+    let suma: i64 = netos.iter().map(|p| p.monto).sum();
+    let mut exceso = suma - total;
+    for p in netos.iter_mut().rev().filter(|p| p.medio == "efectivo") {
+        if exceso <= 0 {
+            break;
+        }
+        // el pago tiene que quedar > 0
+        let tomar = exceso.min(p.monto - 1);
+        p.monto_entregado = Some(p.monto_entregado.unwrap_or(p.monto));
+        p.monto -= tomar;
+        p.vuelto += tomar;
+        exceso -= tomar;
+    }
+
+    let suma_neta: i64 = netos.iter().map(|p| p.monto).sum();
+    if suma_neta != total {
+        return Err(format!(
+            "La suma de los pagos ({}) no coincide con el total de la venta ({})",
+            suma, total
+        ));
+    }
+    Ok(netos)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct VentaFinalizarInput {
     pub id_venta: i64,
     pub pagos: Vec<PagoInput>,
+    #[serde(default)]
+    pub forzar_stock: bool,
+    #[serde(skip_serializing)]
+    pub aprobacion: Option<AprobacionInput>,
 }
 
 #[derive(sqlx::FromRow, serde::Serialize)]
@@ -82,6 +200,8 @@ pub struct HistorialItem {
 pub async fn productos_disponibles(
     state: State<'_, AppState>,
 ) -> Result<Vec<ProductoDisponible>, String> {
+    requerir_sesion(&state).await?;
+
     let rows = sqlx::query_as::<_, ProductoDisponible>(
         r#"
         SELECT
@@ -113,34 +233,44 @@ pub async fn productos_disponibles(
 
 
 
+/// Nuevo carrito activo. Si el operador ya tenía uno activo en la caja,
+/// queda estacionado (igual que al retomar otro).
 #[tauri::command]
 pub async fn venta_iniciar(state: State<'_, AppState>) -> Result<i64, String> {
-    let uid = *state
-        .session_user
-        .lock()
-        .map_err(|_| "lock")?
-        .as_ref()
-        .ok_or("No hay sesión")?;
+    let uid = requerir_sesion(&state).await?.id_usuario;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
 
     let id_caja = sqlx::query_scalar::<_, i64>(
         "SELECT id_caja FROM caja WHERE estado='abierta' LIMIT 1",
     )
-    .fetch_optional(&state.pool)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| e.to_string())?
     .ok_or("No hay caja abierta")?;
 
+    estacionadas::estacionar_activo(&mut tx, uid, id_caja, None).await?;
+
     let res = sqlx::query(
         "INSERT INTO venta(id_usuario, id_caja, fecha_hora, total, estado)
          VALUES(?, ?, DATETIME('now','localtime'), 0, 'en_curso')"
     )
     .bind(uid)
     .bind(id_caja)
-    .execute(&state.pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| e.to_string())?;
 
-    Ok(res.last_insert_rowid())
+    let id_venta = res.last_insert_rowid();
+    audit_repo::registrar(
+        &mut *tx, Some(uid), "venta", Some(id_venta), "iniciar",
+        None, Some(&json!({ "id_caja": id_caja, "estado": "en_curso" })),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(id_venta)
 }
 
 // Agregar ítem al carrito. El stock se impacta al finalizar la venta.
@@ -148,25 +278,80 @@ pub async fn venta_iniciar(state: State<'_, AppState>) -> Result<i64, String> {
 pub async fn venta_agregar_item(
     state: State<'_, AppState>,
     input: AgregarItemInput,
-) -> Result<(), String> {
+) -> Result<Vec<StockAdvertencia>, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
     if input.cantidad <= 0 {
         return Err("Cantidad inválida".into());
     }
 
     let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    estacionadas::carrito_activo(&mut tx, &sesion, input.id_venta).await?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    agregar_linea_catalogo(&mut tx, input.id_venta, input.id_producto, input.cantidad).await?;
+    promos_deteccion::autoaplicar(&mut tx, input.id_venta).await?;
+
+    let advertencias = politica_stock::controlar(
+        &state,
+        &mut tx,
+        uid,
+        input.id_venta,
+        Some(input.id_producto),
+        input.forzar_stock,
+        input.aprobacion.as_ref(),
+    )
+    .await?;
+
+    descuentos::recalcular_descuentos(&mut tx, input.id_venta).await?;
+
+    auditar_venta(&mut tx, uid, input.id_venta, "agregar_item", antes).await?;
 
-    // ya existe una línea para este producto en esta venta?
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(advertencias)
+}
+
+/// Suma `cantidad` a la línea de catálogo del producto (o la crea con el
+/// precio y costo vigentes) y recalcula el total de la venta.
+pub(crate) async fn agregar_linea_catalogo(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_producto: i64,
+    cantidad: i64,
+) -> Result<(), String> {
+    // los de peso llevan el stock en gramos: entran sólo por la balanza
+    let unidad_venta: Option<String> =
+        sqlx::query_scalar("SELECT unidad_venta FROM producto WHERE id_producto = ?")
+            .bind(id_producto)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    match unidad_venta.as_deref() {
+        None => return Err("Producto inexistente".into()),
+        Some("peso") => return Err("El producto se vende por peso: pasalo por la balanza".into()),
+        Some(_) => {}
+    }
+
+    // ya existe una línea libre para este producto en esta venta? (una con
+    // descuento manual no se agranda: el descuento se aprobó por esas unidades)
     let existente = sqlx::query(
         "SELECT id_item, cantidad, precio_unitario
-        FROM venta_item
-        WHERE id_venta = ? 
+        FROM venta_item vi
+        WHERE id_venta = ?
             AND id_producto = ?
             AND fuente_precio = 'catalogo'
+            AND promo_combo_id IS NULL
+            AND NOT EXISTS (SELECT 1 FROM venta_descuento d WHERE d.id_item = vi.id_item AND d.id_regla IS NULL)
+        ORDER BY id_item DESC
         LIMIT 1",
     )
-    .bind(input.id_venta)
-    .bind(input.id_producto)
-    .fetch_optional(&mut *tx)
+    .bind(id_venta)
+    .bind(id_producto)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|e| e.to_string())?;
 
@@ -176,7 +361,7 @@ pub async fn venta_agregar_item(
         let cantidad_actual: i64 = row.get("cantidad");
         let precio_unitario: i64 = row.get("precio_unitario");
 
-        let nueva_cantidad = cantidad_actual + input.cantidad;
+        let nueva_cantidad = cantidad_actual + cantidad;
         let nuevo_subtotal = nueva_cantidad * precio_unitario;
 
         sqlx::query(
@@ -188,7 +373,7 @@ pub async fn venta_agregar_item(
         .bind(nueva_cantidad)
         .bind(nuevo_subtotal)
         .bind(id_item)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await
         .map_err(|e| e.to_string())?;
     } else {
@@ -219,33 +404,37 @@ pub async fn venta_agregar_item(
                 LIMIT 1
                 ),
                 (SELECT p.costo_actual FROM producto p WHERE p.id_producto = ?)
-            ) AS costo_unitario_en_venta
+            ) AS costo_unitario_en_venta,
+            (SELECT p.alicuota_iva FROM producto p WHERE p.id_producto = ?) AS alicuota_iva
             "#
         )
-        .bind(input.id_producto)
-        .bind(input.id_producto)
-        .bind(input.id_producto)
-        .bind(input.id_producto)
-        .fetch_one(&mut *tx)
+        .bind(id_producto)
+        .bind(id_producto)
+        .bind(id_producto)
+        .bind(id_producto)
+        .bind(id_producto)
+        .fetch_one(&mut *conn)
         .await
         .map_err(|e| e.to_string())?;
 
         let precio_unitario: i64 = row.get("precio_unitario");
         let costo_unitario_en_venta: i64 = row.get("costo_unitario_en_venta");
-        let subtotal = precio_unitario * input.cantidad;
+        let alicuota_iva: f64 = row.get("alicuota_iva");
+        let subtotal = precio_unitario * cantidad;
 
         sqlx::query(
             "INSERT INTO venta_item
-                (id_venta, id_producto, cantidad, precio_unitario, costo_unitario_en_venta, fuente_precio, subtotal)
-             VALUES(?, ?, ?, ?, ?, 'catalogo', ?)",
+                (id_venta, id_producto, cantidad, precio_unitario, costo_unitario_en_venta, fuente_precio, subtotal, alicuota_iva)
+             VALUES(?, ?, ?, ?, ?, 'catalogo', ?, ?)",
         )
-        .bind(input.id_venta)
-        .bind(input.id_producto)
-        .bind(input.cantidad)
+        .bind(id_venta)
+        .bind(id_producto)
+        .bind(cantidad)
         .bind(precio_unitario)
         .bind(costo_unitario_en_venta)
         .bind(subtotal)
-        .execute(&mut *tx)
+        .bind(alicuota_iva)
+        .execute(&mut *conn)
         .await
         .map_err(|e| e.to_string())?;
     }
@@ -258,13 +447,12 @@ pub async fn venta_agregar_item(
                           WHERE id_venta = ?)
           WHERE id_venta = ?",
     )
-    .bind(input.id_venta)
-    .bind(input.id_venta)
-    .execute(&mut *tx)
+    .bind(id_venta)
+    .bind(id_venta)
+    .execute(&mut *conn)
     .await
     .map_err(|e| e.to_string())?;
 
-    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -274,6 +462,8 @@ pub async fn venta_listar(
     state: State<'_, AppState>,
     input: VentaListarInput,
 ) -> Result<(Vec<VentaItemDto>, i64), String> {
+    requerir_sesion(&state).await?;
+
     let id_venta = input.id_venta;
 
     let items = sqlx::query_as::<_, VentaItemDto>(
@@ -284,6 +474,7 @@ pub async fn venta_listar(
           p.nombre        AS nombre,
           vi.cantidad     AS cantidad,
           vi.precio_unitario AS precio_unitario,
+          vi.descuento    AS descuento,
           vi.subtotal     AS subtotal
         FROM venta_item vi
         JOIN producto p ON p.id_producto = vi.id_producto
@@ -307,18 +498,37 @@ pub async fn venta_listar(
     Ok((items, total))
 }
 
+async fn venta_de_item(conn: &mut SqliteConnection, id_item: i64) -> Result<i64, String> {
+    sqlx::query_scalar("SELECT id_venta FROM venta_item WHERE id_item=?")
+        .bind(id_item)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Ítem inexistente".to_string())
+}
+
 // Cambiar cantidad (NO toca stock)
 #[tauri::command]
 pub async fn venta_set_cantidad(
     state: State<'_, AppState>,
     input: SetCantidadInput,
-) -> Result<(), String> {
+) -> Result<Vec<StockAdvertencia>, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
     if input.cantidad <= 0 {
         return Err("Cantidad inválida".into());
     }
 
     let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
 
+    let id_venta = venta_de_item(&mut tx, input.id_item).await?;
+    estacionadas::carrito_activo(&mut tx, &sesion, id_venta).await?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
     // actualizar cantidad y subtotal
     sqlx::query(
         "UPDATE venta_item
@@ -347,8 +557,27 @@ pub async fn venta_set_cantidad(
     .await
     .map_err(|e| e.to_string())?;
 
+    let id_producto: i64 = sqlx::query_scalar("SELECT id_producto FROM venta_item WHERE id_item=?")
+        .bind(input.id_item)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let advertencias = politica_stock::controlar(
+        &state,
+        &mut tx,
+        uid,
+        id_venta,
+        Some(id_producto),
+        input.forzar_stock,
+        input.aprobacion.as_ref(),
+    )
+    .await?;
+
+    descuentos::recalcular_descuentos(&mut tx, id_venta).await?;
+    auditar_venta(&mut tx, uid, id_venta, "set_cantidad", antes).await?;
+
     tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(())
+    Ok(advertencias)
 }
 
 // Quitar ítem (NO toca stock)
@@ -357,35 +586,38 @@ pub async fn venta_quitar_item(
     state: State<'_, AppState>,
     input: QuitarItemInput,
 ) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
     let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
 
     // Guardar id_venta para recalcular total
-    let id_venta: Option<i64> = sqlx::query_scalar(
-        "SELECT id_venta FROM venta_item WHERE id_item=?",
-    )
-    .bind(input.id_item)
-    .fetch_optional(&mut *tx)
-    .await
-    .map_err(|e| e.to_string())?;
+    let id_venta = venta_de_item(&mut tx, input.id_item).await?;
+    estacionadas::carrito_activo(&mut tx, &sesion, id_venta).await?;
 
-    sqlx::query("DELETE FROM venta_item WHERE id_item=?")
-        .bind(input.id_item)
-        .execute(&mut *tx)
+    let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
         .await
         .map_err(|e| e.to_string())?;
 
-    if let Some(v) = id_venta {
-        sqlx::query(
-            "UPDATE venta
-                SET total = (SELECT COALESCE(SUM(subtotal),0) FROM venta_item WHERE id_venta=?)
-              WHERE id_venta=?",
-        )
-        .bind(v)
-        .bind(v)
+    sqlx::query("DELETE FROM venta_item WHERE id_item=?")
+        .bind(input.id_item)
         .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
-    }
+
+    sqlx::query(
+        "UPDATE venta
+            SET total = (SELECT COALESCE(SUM(subtotal),0) FROM venta_item WHERE id_venta=?)
+          WHERE id_venta=?",
+    )
+    .bind(id_venta)
+    .bind(id_venta)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    descuentos::recalcular_descuentos(&mut tx, id_venta).await?;
+    auditar_venta(&mut tx, uid, id_venta, "quitar_item", antes).await?;
 
     tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
@@ -396,22 +628,162 @@ pub async fn venta_cancelar(
     state: State<'_, AppState>,
     input: VentaCancelarInput,
 ) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
     let id_venta = input.id_venta;
 
     let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
 
-    sqlx::query("DELETE FROM venta_item WHERE id_venta=?")
+    // Sólo carritos: una venta finalizada ya movió stock y cobró
+    let estado: Option<String> = sqlx::query_scalar("SELECT estado FROM venta WHERE id_venta=?")
         .bind(id_venta)
-        .execute(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
 
-    sqlx::query("UPDATE venta SET estado='anulada', total=0 WHERE id_venta=?")
+    match estado.as_deref() {
+        None => return Err("Venta inexistente".into()),
+        Some("en_curso") => {}
+        Some("finalizada") => {
+            return Err("La venta ya está finalizada: usá la anulación de venta".into())
+        }
+        Some(_) => return Err("La venta ya está anulada".into()),
+    }
+    // del operador (o admin); con la caja cerrada igual se puede descartar
+    estacionadas::carrito_de_sesion(&mut tx, &sesion, id_venta).await?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    repo::carrito_descartar(&mut tx, id_venta).await?;
+
+    auditar_venta(&mut tx, uid, id_venta, "cancelar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Anula una venta finalizada: revierte stock, conserva items y pagos para
+/// auditoría y registra quién anuló, quién aprobó y por qué.
+#[tauri::command]
+pub async fn venta_anular(
+    state: State<'_, AppState>,
+    input: VentaAnularInput,
+) -> Result<(), String> {
+    let (sesion, aprobada_por) =
+        requerir_admin_o_aprobacion(&state, input.aprobacion.as_ref()).await?;
+
+    let motivo = input.motivo.trim();
+    if motivo.is_empty() {
+        return Err("motivo obligatorio".into());
+    }
+
+    let id_venta = input.id_venta;
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let estado: Option<String> = sqlx::query_scalar("SELECT estado FROM venta WHERE id_venta=?")
         .bind(id_venta)
-        .execute(&mut *tx)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match estado.as_deref() {
+        None => return Err("Venta inexistente".into()),
+        Some("finalizada") => {}
+        Some("en_curso") => return Err("La venta está en curso: usá cancelar".into()),
+        Some(_) => return Err("La venta ya está anulada".into()),
+    }
+
+    if devoluciones_repo::venta_tiene_devoluciones(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("La venta tiene devoluciones: devolvé el resto en lugar de anular".into());
+    }
+
+    fiscal_repo::exigir_sin_factura(&mut tx, id_venta).await?;
+
+    let pagos_antes = cierre_z::pagos_por_medio(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Contramovimiento del neto que quedó en stock_mov para esta venta
+    let referencia = format!("venta:{}", id_venta);
+    sqlx::query(
+        r#"
+        INSERT INTO stock_mov (
+          id_producto,
+          cantidad_delta,
+          motivo,
+          referencia,
+          costo_unitario,
+          total_costo,
+          fecha_hora
+        )
+        SELECT
+          sm.id_producto,
+          -SUM(sm.cantidad_delta),
+          'anulacion',
+          ?1,
+          COALESCE(CAST(ROUND(vi.costo * CASE p.unidad_venta WHEN 'peso' THEN 1000 ELSE 1 END
+                              * 1.0 / vi.unidades) AS INTEGER), 0),
+          COALESCE(CAST(ROUND(ABS(SUM(sm.cantidad_delta)) * vi.costo * 1.0 / vi.unidades) AS INTEGER), 0),
+          DATETIME('now','localtime')
+        FROM stock_mov sm
+        JOIN producto p ON p.id_producto = sm.id_producto
+        -- costo de lo vendido por unidad de stock (gramos en los de peso),
+        -- sumando todas las líneas del producto
+        LEFT JOIN (
+          SELECT id_producto,
+                 SUM(cantidad * costo_unitario_en_venta) AS costo,
+                 NULLIF(SUM(cantidad * COALESCE(gramos, 1)), 0) AS unidades
+          FROM venta_item
+          WHERE id_venta = ?2
+          GROUP BY id_producto
+        ) vi ON vi.id_producto = sm.id_producto
+        WHERE sm.referencia = ?1
+        GROUP BY sm.id_producto
+        HAVING SUM(sm.cantidad_delta) <> 0
+        "#
+    )
+    .bind(&referencia)
+    .bind(id_venta)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        UPDATE venta
+           SET estado = 'anulada',
+               anulada_en = DATETIME('now','localtime'),
+               anulada_por = ?,
+               anulacion_aprobada_por = ?,
+               anulacion_motivo = ?
+         WHERE id_venta = ? AND estado = 'finalizada'
+        "#
+    )
+    .bind(sesion.id_usuario)
+    .bind(aprobada_por)
+    .bind(motivo)
+    .bind(id_venta)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Si la caja de la venta ya cerró, el reverso queda como ajuste del Z
+    cierre_z::registrar_ajustes(&mut tx, id_venta, &pagos_antes, "anulacion", sesion.id_usuario)
         .await
         .map_err(|e| e.to_string())?;
 
+    auditar_venta(&mut tx, sesion.id_usuario, id_venta, "anular", antes).await?;
+
     tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -420,7 +792,10 @@ pub async fn venta_cancelar(
 pub async fn venta_finalizar(
     state: State<'_, AppState>,
     input: VentaFinalizarInput,
-) -> Result<(), String> {
+) -> Result<VentaFinalizarOut, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
     use sqlx::Row;
 
     let id_venta = input.id_venta;
@@ -442,6 +817,12 @@ pub async fn venta_finalizar(
     if estado != "en_curso" {
         return Err("La venta no está en curso o ya fue finalizada".into());
     }
+    // se cobra sólo el carrito activo de la sesión, con la caja abierta
+    estacionadas::carrito_activo(&mut tx, &sesion, id_venta).await?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
 
     let items = sqlx::query(
         r#"
@@ -462,6 +843,9 @@ pub async fn venta_finalizar(
         return Err("No se puede finalizar una venta sin items".into());
     }
 
+    // un monto fijo pudo pasar el tope si después bajaron las cantidades
+    descuentos::controlar_topes(&mut tx, id_venta).await?;
+
     let total: i64 = sqlx::query_scalar(
         "SELECT COALESCE(SUM(subtotal), 0) FROM venta_item WHERE id_venta = ?",
     )
@@ -474,13 +858,19 @@ pub async fn venta_finalizar(
         return Err("No se puede finalizar una venta con total 0".into());
     }
 
-    let suma_pagos: i64 = input.pagos.iter().map(|p| p.monto).sum();
-    if suma_pagos != total {
-        return Err(format!(
-            "La suma de los pagos ({}) no coincide con el total de la venta ({})",
-            suma_pagos, total
-        ));
-    }
+    let pagos = pagos_netos(&input.pagos, total)?;
+
+    // se vuelve a mirar el stock: pudo moverse desde que se armó el carrito
+    let advertencias = politica_stock::controlar(
+        &state,
+        &mut tx,
+        uid,
+        id_venta,
+        None,
+        input.forzar_stock,
+        input.aprobacion.as_ref(),
+    )
+    .await?;
 
     sqlx::query(
         r#"
@@ -495,10 +885,12 @@ pub async fn venta_finalizar(
         )
         SELECT
           vi.id_producto,
-          -vi.cantidad,
+          -vi.cantidad * COALESCE(vi.gramos, 1),
           'venta',
           ?,
-          vi.costo_unitario_en_venta,
+          -- el costo de una etiqueta de balanza es el de sus gramos: va por kg
+          CASE WHEN vi.gramos IS NULL THEN vi.costo_unitario_en_venta
+               ELSE CAST(ROUND(vi.costo_unitario_en_venta * 1000.0 / vi.gramos) AS INTEGER) END,
           vi.cantidad * vi.costo_unitario_en_venta,
           DATETIME('now','localtime')
         FROM venta_item vi
@@ -517,20 +909,33 @@ pub async fn venta_finalizar(
         .await
         .map_err(|e| e.to_string())?;
 
-    for pago in &input.pagos {
+    let mut recargos = 0i64;
+    for pago in &pagos {
+        // recargo al cliente y comisión de la procesadora según medio / cuotas
+        let cargos = medios_pago_repo::cargos_de_pago(&mut tx, pago.medio, pago.cuotas, pago.monto).await?;
+        recargos += cargos.recargo;
+
         sqlx::query(
-            "INSERT INTO venta_pago (id_venta, medio, monto, referencia)
-             VALUES (?, ?, ?, ?)",
+            "INSERT INTO venta_pago (id_venta, medio, monto, referencia, monto_entregado, vuelto, cuotas, recargo, comision)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(id_venta)
-        .bind(&pago.medio)
+        .bind(pago.medio)
         .bind(pago.monto)
-        .bind(&pago.referencia)
+        .bind(pago.referencia)
+        .bind(pago.monto_entregado)
+        .bind(pago.vuelto)
+        .bind(cargos.cuotas)
+        .bind(cargos.recargo)
+        .bind(cargos.comision)
         .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     }
 
+    // fiado: cliente con cuenta habilitada y dentro del límite (o autorizado)
+    cuenta_corriente::controlar(&state, &mut tx, uid, id_venta, input.aprobacion.as_ref()).await?;
+
     sqlx::query(
         "UPDATE venta
          SET estado = 'finalizada', total = ?
@@ -542,8 +947,28 @@ pub async fn venta_finalizar(
     .await
     .map_err(|e| e.to_string())?;
 
+    auditar_venta(&mut tx, uid, id_venta, "finalizar", antes).await?;
+
     tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(())
+
+    // la autorización fiscal corre aparte: no demora ni corta el cobro
+    let id_comprobante = fiscal::encolar_factura(&state.pool, id_venta, uid).await;
+
+    // lo que se recibió en efectivo (el vuelto sale de ahí)
+    let entregado: i64 = pagos
+        .iter()
+        .filter(|p| p.medio == "efectivo")
+        .map(|p| p.monto_entregado.unwrap_or(p.monto))
+        .sum();
+    let vuelto: i64 = pagos.iter().map(|p| p.vuelto).sum();
+    Ok(VentaFinalizarOut {
+        total,
+        recargos,
+        entregado,
+        vuelto,
+        advertencias,
+        id_comprobante,
+    })
 }
 
 
@@ -553,12 +978,7 @@ pub async fn venta_finalizar(
 pub async fn historial_ventas_hoy(
     state: State<'_, AppState>
 ) -> Result<Vec<HistorialItem>, String> {
-    let uid = *state
-        .session_user
-        .lock()
-        .map_err(|_| "lock".to_string())?
-        .as_ref()
-        .ok_or("No hay sesión")?;
+    let uid = requerir_sesion(&state).await?.id_usuario;
 
     let items = sqlx::query_as::<_, HistorialItem>(
         r#"
@@ -572,7 +992,12 @@ pub async fn historial_ventas_hoy(
             vi.subtotal AS subtotal,
             v.total AS total_venta,
             (
-                SELECT GROUP_CONCAT(medio || ' $' || monto, ' + ')
+                SELECT GROUP_CONCAT(
+                    medio || ' $' || monto
+                        || CASE WHEN vuelto > 0
+                                THEN ' (entregó $' || monto_entregado || ', vuelto $' || vuelto || ')'
+                                ELSE '' END,
+                    ' + ')
                 FROM venta_pago
                 WHERE id_venta = v.id_venta
             ) AS pagos_detalle
@@ -600,36 +1025,25 @@ pub async fn historial_ventas_hoy(
 pub async fn venta_aplicar_promo_combo(
     state: State<'_, AppState>,
     input: PromoComboAplicarInput,
-) -> Result<(), String> {
-    use sqlx::Row;
-
-    let pool = &state.pool;
+) -> Result<String, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
 
     if input.precio_total_pack < 0 {
         return Err("El precio del pack no puede ser negativo.".to_string());
     }
 
-    // 0) Traer combo (incluye precio_pack)
-    let combo = sqlx::query(
-        r#"
-        SELECT id_combo, activo, precio_min_total, precio_pack
-        FROM promo_combo
-        WHERE id_combo = ?
-        "#,
-    )
-    .bind(input.id_combo)
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| e.to_string())?
-    .ok_or_else(|| "Combo no encontrado.".to_string())?;
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
 
-    let activo: i64 = combo.try_get("activo").map_err(|e| e.to_string())?;
-    if activo != 1 {
-        return Err("El combo está inactivo.".to_string());
-    }
+    estacionadas::carrito_activo(&mut tx, &sesion, input.id_venta).await?;
 
-    let precio_min_total: i64 = combo.try_get("precio_min_total").map_err(|e| e.to_string())?;
-    let precio_pack_db: i64 = combo.try_get("precio_pack").map_err(|e| e.to_string())?;
+    // activo y vigencia los controla venta_aplicar_promo_combo_db
+    let precio_pack_db: i64 = sqlx::query_scalar("SELECT precio_pack FROM promo_combo WHERE id_combo = ?")
+        .bind(input.id_combo)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Combo no encontrado.".to_string())?;
 
     //  Determinar precio a aplicar (input > 0, si no DB)
     let precio_pack_aplicar = if input.precio_total_pack > 0 {
@@ -637,171 +1051,378 @@ pub async fn venta_aplicar_promo_combo(
     } else {
         precio_pack_db
     };
-
     if precio_pack_aplicar <= 0 {
         return Err("El precio del pack no está definido (0). Definilo al crear el combo o ingresalo manualmente.".to_string());
     }
-    if precio_pack_aplicar < precio_min_total {
-        return Err(format!(
-            "El precio del pack (${precio_pack_aplicar}) no puede ser menor al mínimo (${precio_min_total})."
-        ));
+
+    let antes = audit_repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    //  Lo que falte en el carrito se agrega de catálogo (en una línea libre,
+    //  aparte de las que tienen descuento manual) y después se consume
+    let items: Vec<(i64, i64)> = sqlx::query_as("SELECT id_producto, cantidad FROM promo_combo_item WHERE id_combo = ?")
+        .bind(input.id_combo)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    for (id_producto, cantidad) in items {
+        let libre: i64 = repo::lineas_libres(&mut tx, input.id_venta, Some(id_producto))
+            .await?
+            .iter()
+            .map(|l| l.cantidad)
+            .sum();
+        if libre < cantidad {
+            agregar_linea_catalogo(&mut tx, input.id_venta, id_producto, cantidad - libre).await?;
+        }
     }
 
-    //  Items del combo
-    let items = sqlx::query(
-        r#"
-        SELECT id_producto, cantidad
-        FROM promo_combo_item
-        WHERE id_combo = ?
-        "#,
+    let promo_grupo_id =
+        repo::venta_aplicar_promo_combo_db(&mut tx, input.id_venta, input.id_combo, precio_pack_aplicar).await?;
+
+    descuentos::recalcular_descuentos(&mut tx, input.id_venta).await?;
+
+    // el después lleva además el combo aplicado
+    let mut despues = audit_repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(Value::Object(m)) = despues.as_mut() {
+        m.insert("id_combo".into(), json!(input.id_combo));
+        m.insert("promo_grupo_id".into(), json!(promo_grupo_id));
+    }
+    audit_repo::registrar(
+        &mut *tx, Some(uid), "venta", Some(input.id_venta), "aplicar_promo_combo",
+        antes.as_ref(), despues.as_ref(),
     )
-    .bind(input.id_combo)
-    .fetch_all(pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    if items.is_empty() {
-        return Err("El combo no tiene productos.".to_string());
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(promo_grupo_id)
+}
+
+/// Saca del carrito todas las líneas de un combo aplicado.
+#[tauri::command(rename = "venta_quitar_promo_grupo")]
+pub async fn venta_quitar_promo_grupo(
+    state: State<'_, AppState>,
+    id_venta: i64,
+    promo_grupo_id: String,
+) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    estacionadas::carrito_activo(&mut tx, &sesion, id_venta).await?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let res = sqlx::query("DELETE FROM venta_item WHERE id_venta = ? AND promo_grupo_id = ?")
+        .bind(id_venta)
+        .bind(&promo_grupo_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if res.rows_affected() == 0 {
+        return Err("El combo no está en el carrito".into());
     }
 
-    //  Traer precio/costo actual por producto y preparar prorrateo
-    #[derive(Clone)]
-    struct ItemCalc {
-        id_producto: i64,
-        cant: i64,
-        costo_unit: i64,
-        base_total: i64,   // precio_catalogo * cant (solo para ponderar)
-        asign_total: i64,  // total asignado exacto para este producto dentro del pack
-        resto: i64,
+    descuentos::recalcular_descuentos(&mut tx, id_venta).await?;
+    auditar_venta(&mut tx, uid, id_venta, "quitar_promo_combo", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::caja::repo as caja_repo;
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+
+    fn anular(v: serde_json::Value) -> VentaAnularInput {
+        serde_json::from_value(v).unwrap()
     }
 
-    let mut calc: Vec<ItemCalc> = Vec::with_capacity(items.len());
-    for r in items {
-        let id_producto: i64 = r.try_get("id_producto").map_err(|e| e.to_string())?;
-        let cant: i64 = r.try_get("cantidad").map_err(|e| e.to_string())?;
+    fn pagos(v: serde_json::Value) -> Vec<PagoInput> {
+        serde_json::from_value(v).unwrap()
+    }
 
-        let prod = sqlx::query(
-            r#"
-            SELECT precio_venta_actual, costo_actual
-            FROM producto
-            WHERE id_producto = ? AND activo = 1
-            "#,
+    #[test]
+    fn pagos_netos_toma_el_exceso_de_efectivo_como_vuelto() {
+        // el cajero cargó lo que le dieron: $1000 en efectivo para $600
+        let entrada = pagos(json!([
+            { "medio": "debito", "monto": 400 },
+            { "medio": "efectivo", "monto": 1000 },
+        ]));
+        let netos = pagos_netos(&entrada, 1000).unwrap();
+        let efectivo = &netos[1];
+        assert_eq!((efectivo.monto, efectivo.monto_entregado, efectivo.vuelto), (600, Some(1000), 400));
+        assert_eq!((netos[0].monto, netos[0].vuelto), (400, 0));
+
+        // con lo entregado informado aparte el monto ya es neto
+        let entrada = pagos(json!([{ "medio": "efectivo", "monto": 1000, "monto_entregado": 1500 }]));
+        let netos = pagos_netos(&entrada, 1000).unwrap();
+        assert_eq!((netos[0].monto, netos[0].monto_entregado, netos[0].vuelto), (1000, Some(1500), 500));
+    }
+
+    #[test]
+    fn pagos_netos_rechaza_lo_que_no_cierra() {
+        // sin efectivo no hay de dónde sacar vuelto
+        let entrada = pagos(json!([{ "medio": "debito", "monto": 1200 }]));
+        assert_eq!(
+            pagos_netos(&entrada, 1000).err().unwrap(),
+            "La suma de los pagos (1200) no coincide con el total de la venta (1000)"
+        );
+
+        let entrada = pagos(json!([{ "medio": "efectivo", "monto": 500 }]));
+        assert!(pagos_netos(&entrada, 1000).is_err());
+
+        let entrada = pagos(json!([{ "medio": "efectivo", "monto": 0 }]));
+        assert_eq!(pagos_netos(&entrada, 0).err().unwrap(), "Monto de pago inválido");
+    }
+
+    #[tokio::test]
+    async fn finalizar_informa_lo_entregado_en_efectivo() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 500, 100, 10).await;
+
+        let id_venta = prueba::carrito(&state, &[(pan, 2)]).await;
+        let out = prueba::finalizar(
+            &state,
+            id_venta,
+            json!([
+                { "medio": "debito", "monto": 400 },
+                { "medio": "efectivo", "monto": 600, "monto_entregado": 1000 },
+            ]),
         )
-        .bind(id_producto)
-        .fetch_optional(pool)
         .await
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| format!("Producto {id_producto} no existe o está inactivo."))?;
-
-        let precio_catalogo: i64 = prod.try_get("precio_venta_actual").map_err(|e| e.to_string())?;
-        let costo_unit: i64 = prod.try_get("costo_actual").map_err(|e| e.to_string())?;
-
-        let base_total = precio_catalogo
-            .checked_mul(cant)
-            .ok_or_else(|| "Overflow calculando total base.".to_string())?;
-
-        calc.push(ItemCalc {
-            id_producto,
-            cant,
-            costo_unit,
-            base_total,
-            asign_total: 0,
-            resto: 0,
-        });
-    }
+        .unwrap();
+        assert_eq!((out.total, out.entregado, out.vuelto), (1000, 1000, 400));
 
-    let base_sum: i64 = calc.iter().map(|x| x.base_total).sum();
-    if base_sum <= 0 {
-        return Err("No se puede prorratear: suma base (catálogo) es 0.".to_string());
+        // sin efectivo no se entregó nada en mano
+        let id_venta = prueba::carrito(&state, &[(pan, 1)]).await;
+        let out = prueba::finalizar(&state, id_venta, json!([{ "medio": "debito", "monto": 500 }]))
+            .await
+            .unwrap();
+        assert_eq!((out.entregado, out.vuelto), (0, 0));
     }
 
-    //  Prorrateo exacto por "resto mayor"
-    let mut asign_sum: i64 = 0;
-    for it in calc.iter_mut() {
-        let num = it
-            .base_total
-            .checked_mul(precio_pack_aplicar)
-            .ok_or_else(|| "Overflow en prorrateo.".to_string())?;
+    #[tokio::test]
+    async fn un_monto_fijo_que_pasa_el_tope_no_se_cobra() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let vino = prueba::producto(pool, "vino", 1000, 600, 20).await;
+        let id_venta = prueba::carrito(&state, &[(vino, 10)]).await;
+        let id_item: i64 = sqlx::query_scalar("SELECT id_item FROM venta_item WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        // 1000 sobre 10000 es el 10% del operador
+        let descuento = serde_json::from_value(json!({
+            "id_venta": id_venta, "id_item": id_item, "tipo": "monto", "valor": 1000, "motivo": "caja rota"
+        }))
+        .unwrap();
+        descuentos::venta_descuento_aplicar(state.clone(), descuento).await.unwrap();
+
+        let cantidad = |cantidad| SetCantidadInput { id_item, cantidad, forzar_stock: false, aprobacion: None };
+        venta_set_cantidad(state.clone(), cantidad(1)).await.unwrap();
+        let err = prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 1 }]))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "El descuento de vino quedó en 100% y supera el tope (10%): quitalo o volvé a aplicarlo");
 
-        it.asign_total = num / base_sum;
-        it.resto = num % base_sum;
-        asign_sum += it.asign_total;
+        venta_set_cantidad(state.clone(), cantidad(10)).await.unwrap();
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 9000 }])).await.unwrap();
     }
 
-    let mut faltante = precio_pack_aplicar - asign_sum;
-    if faltante > 0 {
-        calc.sort_by(|a, b| b.resto.cmp(&a.resto));
-        for it in calc.iter_mut() {
-            if faltante == 0 {
-                break;
-            }
-            it.asign_total += 1;
-            faltante -= 1;
-        }
+    #[tokio::test]
+    async fn anular_repone_stock_y_pide_aprobacion() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        prueba::usuario(pool, "jefe", "admin").await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 4)], json!([{ "medio": "efectivo", "monto": 1200 }])).await;
+        assert_eq!(prueba::stock(pool, pan).await, 6);
+
+        let err = venta_anular(state.clone(), anular(json!({ "id_venta": id_venta, "motivo": "error" })))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "Se requiere la aprobación de un administrador");
+        assert_eq!(prueba::stock(pool, pan).await, 6);
+
+        let con_ok = json!({
+            "id_venta": id_venta, "motivo": "cobro duplicado",
+            "aprobacion": { "nombre_usuario": "jefe", "password": "clave" }
+        });
+        venta_anular(state.clone(), anular(con_ok.clone())).await.unwrap();
+        assert_eq!(prueba::stock(pool, pan).await, 10);
+
+        let (estado, anulada_por): (String, i64) =
+            sqlx::query_as("SELECT estado, anulada_por FROM venta WHERE id_venta = ?1")
+                .bind(id_venta)
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        assert_eq!((estado.as_str(), anulada_por), ("anulada", 2));
+
+        // dos veces no: el stock no vuelve a entrar
+        let err = venta_anular(state.clone(), anular(con_ok)).await.unwrap_err();
+        assert_eq!(err, "La venta ya está anulada");
+        assert_eq!(prueba::stock(pool, pan).await, 10);
     }
 
-    //  Insertar líneas promo SIN pisar lo existente (transacción)
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
-
-    for it in calc {
-        // Queremos: subtotal == cantidad * precio_unitario SIEMPRE.
-        // Repartimos el total asignado en (base) y (base+1) si hay resto por unidad.
-        let base_unit = it.asign_total / it.cant;
-        let rem_units = it.asign_total % it.cant; // cantidad que debe ir a (base+1)
-
-        let cant_base = it.cant - rem_units;
-        if cant_base > 0 {
-            let subtotal_base = cant_base
-                .checked_mul(base_unit)
-                .ok_or_else(|| "Overflow subtotal_base.".to_string())?;
-
-            sqlx::query(
-                r#"
-                INSERT INTO venta_item (
-                    id_venta, id_producto, cantidad,
-                    precio_unitario, costo_unitario_en_venta,
-                    fuente_precio, subtotal
-                ) VALUES (?, ?, ?, ?, ?, 'promo', ?)
-                "#,
-            )
-            .bind(input.id_venta)
-            .bind(it.id_producto)
-            .bind(cant_base)
-            .bind(base_unit)
-            .bind(it.costo_unit)
-            .bind(subtotal_base)
-            .execute(&mut *tx)
+    #[tokio::test]
+    async fn anular_con_caja_cerrada_ajusta_el_z() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "debito", "monto": 600 }])).await;
+        caja_repo::cerrar_caja(pool, id_caja, 1, None).await.unwrap();
+
+        venta_anular(state.clone(), anular(json!({ "id_venta": id_venta, "motivo": "mal cobrada" })))
             .await
-            .map_err(|e| e.to_string())?;
+            .unwrap();
+
+        let ajuste: (String, i64, i64, String) = sqlx::query_as(
+            "SELECT medio, monto_anterior, diferencia, origen FROM cierre_z_ajuste WHERE id_venta = ?1",
+        )
+        .bind(id_venta)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(ajuste, ("debito".to_string(), 600, -600, "anulacion".to_string()));
+        assert_eq!(prueba::stock(pool, pan).await, 10);
+    }
+
+    async fn combo(pool: &sqlx::SqlitePool, nombre: &str, precio_pack: i64, activo: i64, items: &[(i64, i64)]) -> i64 {
+        let id_combo: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_combo (nombre, precio_pack, activo) VALUES (?1, ?2, ?3) RETURNING id_combo",
+        )
+        .bind(nombre)
+        .bind(precio_pack)
+        .bind(activo)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        for (id_producto, cantidad) in items {
+            sqlx::query("INSERT INTO promo_combo_item (id_combo, id_producto, cantidad) VALUES (?1, ?2, ?3)")
+                .bind(id_combo)
+                .bind(id_producto)
+                .bind(cantidad)
+                .execute(pool)
+                .await
+                .unwrap();
         }
+        id_combo
+    }
+
+    // (id_producto, cantidad, fuente_precio, en combo)
+    async fn lineas(pool: &sqlx::SqlitePool, id_venta: i64) -> Vec<(i64, i64, String, bool)> {
+        sqlx::query_as(
+            "SELECT id_producto, cantidad, fuente_precio, promo_combo_id IS NOT NULL
+               FROM venta_item WHERE id_venta = ?1 ORDER BY id_item",
+        )
+        .bind(id_venta)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
 
-        if rem_units > 0 {
-            let unit_plus = base_unit + 1;
-            let subtotal_plus = rem_units
-                .checked_mul(unit_plus)
-                .ok_or_else(|| "Overflow subtotal_plus.".to_string())?;
-
-            sqlx::query(
-                r#"
-                INSERT INTO venta_item (
-                    id_venta, id_producto, cantidad,
-                    precio_unitario, costo_unitario_en_venta,
-                    fuente_precio, subtotal
-                ) VALUES (?, ?, ?, ?, ?, 'promo', ?)
-                "#,
-            )
-            .bind(input.id_venta)
-            .bind(it.id_producto)
-            .bind(rem_units)
-            .bind(unit_plus)
-            .bind(it.costo_unit)
-            .bind(subtotal_plus)
-            .execute(&mut *tx)
+    #[tokio::test]
+    async fn combo_con_linea_descontada_agrega_una_libre() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let cafe = prueba::producto(pool, "cafe", 800, 300, 10).await;
+        let medialuna = prueba::producto(pool, "medialuna", 400, 100, 10).await;
+        let desayuno = combo(pool, "desayuno", 1000, 1, &[(cafe, 1), (medialuna, 1)]).await;
+
+        // el café del carrito tiene un descuento manual: no entra en el combo
+        let id_venta = prueba::carrito(&state, &[(cafe, 1)]).await;
+        let id_item: i64 = sqlx::query_scalar("SELECT id_item FROM venta_item WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(pool)
             .await
-            .map_err(|e| e.to_string())?;
-        }
+            .unwrap();
+        let descuento = serde_json::from_value(json!({
+            "id_venta": id_venta, "id_item": id_item, "tipo": "porcentaje", "valor": 10, "motivo": "taza propia"
+        }))
+        .unwrap();
+        descuentos::venta_descuento_aplicar(state.clone(), descuento).await.unwrap();
+
+        let aplicar = |id_combo| PromoComboAplicarInput { id_venta, id_combo, precio_total_pack: 0 };
+        venta_aplicar_promo_combo(state.clone(), aplicar(desayuno)).await.unwrap();
+
+        let catalogo = "catalogo".to_string();
+        let promo = "promo".to_string();
+        assert_eq!(
+            lineas(pool, id_venta).await,
+            vec![(cafe, 1, catalogo.clone(), false), (cafe, 1, promo.clone(), true), (medialuna, 1, promo, true)]
+        );
+        let total: i64 = sqlx::query_scalar("SELECT total FROM venta WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(total, 720 + 1000);
+
+        // agregar más café tampoco agranda la línea descontada
+        let input: AgregarItemInput =
+            serde_json::from_value(json!({ "id_venta": id_venta, "id_producto": cafe, "cantidad": 2 })).unwrap();
+        venta_agregar_item(state.clone(), input).await.unwrap();
+        let filas = lineas(pool, id_venta).await;
+        assert_eq!((filas[0].1, &filas[3]), (1, &(cafe, 2, catalogo, false)));
     }
 
-    tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(())
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn combo_inactivo_no_toca_el_carrito() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let cafe = prueba::producto(pool, "cafe", 800, 300, 10).await;
+        let medialuna = prueba::producto(pool, "medialuna", 400, 100, 10).await;
+        let viejo = combo(pool, "viejo", 1000, 0, &[(cafe, 1), (medialuna, 1)]).await;
+
+        let id_venta = prueba::carrito(&state, &[(cafe, 1)]).await;
+        let input = PromoComboAplicarInput { id_venta, id_combo: viejo, precio_total_pack: 0 };
+        let err = venta_aplicar_promo_combo(state.clone(), input).await.unwrap_err();
+        assert_eq!(err, "La promoción no está activa");
+        assert_eq!(lineas(pool, id_venta).await, vec![(cafe, 1, "catalogo".to_string(), false)]);
+
+        let input = PromoComboAplicarInput { id_venta, id_combo: 999, precio_total_pack: 0 };
+        let err = venta_aplicar_promo_combo(state.clone(), input).await.unwrap_err();
+        assert_eq!(err, "Combo no encontrado.");
+    }
+}
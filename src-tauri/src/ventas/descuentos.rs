@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
+use tauri::State;
+
+use crate::AppState;
+use crate::promo_reglas;
+use crate::promos::repo::repartir_total_proporcional;
+use crate::users::permisos::{
+    requerir_admin, requerir_admin_o_aprobacion, requerir_sesion, AprobacionInput, Rol,
+};
+
+use super::commands::auditar_venta;
+use super::estacionadas::carrito_activo;
+
+// Descuentos manuales sobre un carrito en_curso. La definición (porcentaje o
+// monto, motivo, quién) vive en venta_descuento; lo aplicado en cada línea se
+// guarda en venta_item.descuento y el trigger deja subtotal = bruto - descuento.
+// El descuento de ticket se prorratea entre las líneas según su neto.
+
+#[derive(Debug, Deserialize)]
+pub struct DescuentoAplicarInput {
+    pub id_venta: i64,
+    pub id_item: Option<i64>, // None = todo el ticket
+    pub tipo: String,         // 'porcentaje' | 'monto'
+    pub valor: i64,           // % entero o ARS
+    pub motivo: String,
+    pub aprobacion: Option<AprobacionInput>, // si supera el tope del rol
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DescuentoLimiteInput {
+    pub rol_tipo: String,
+    pub max_pct: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct VentaDescuentoRow {
+    pub id_descuento: i64,
+    pub id_venta: i64,
+    pub id_item: Option<i64>,
+    pub producto: Option<String>,
+    pub tipo: String,
+    pub valor: i64,
+    pub monto: i64,
+    pub motivo: String,
+    pub id_usuario: i64,
+    pub usuario: String,
+    pub aprobado_por: Option<String>,
+    pub fecha_hora: String,
+    pub id_regla: Option<i64>, // generado por una regla de promoción
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DescuentoLimiteRow {
+    pub rol_tipo: String,
+    pub max_pct: i64,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Cálculo
+
+fn monto_definido(tipo: &str, valor: i64, base: i64) -> i64 {
+    match tipo {
+        "porcentaje" => base * valor / 100,
+        _ => valor,
+    }
+}
+
+/// Recalcula venta_item.descuento y venta_descuento.monto a partir de las
+/// definiciones. Se llama después de cualquier cambio en las líneas, así que
+/// primero reevalúa las reglas de promoción por cantidad.
+pub(crate) async fn recalcular_descuentos(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<(), String> {
+    promo_reglas::motor::evaluar(conn, id_venta).await?;
+
+    let lineas: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT id_item, cantidad * precio_unitario FROM venta_item WHERE id_venta = ?1 ORDER BY id_item",
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("descuentos (items): {e}"))?;
+
+    let defs: Vec<(i64, Option<i64>, String, i64)> = sqlx::query_as(
+        "SELECT id_descuento, id_item, tipo, valor FROM venta_descuento WHERE id_venta = ?1 ORDER BY id_descuento",
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("descuentos (definiciones): {e}"))?;
+
+    let brutos: HashMap<i64, i64> = lineas.iter().copied().collect();
+    let mut por_linea: HashMap<i64, i64> = HashMap::new();
+    let mut montos: Vec<(i64, i64)> = Vec::with_capacity(defs.len());
+
+    // 1) descuentos de línea, en orden de carga, sin pasar el bruto
+    for (id_descuento, id_item, tipo, valor) in defs.iter().filter(|d| d.1.is_some()) {
+        let id_item = id_item.unwrap_or_default();
+        let bruto = brutos.get(&id_item).copied().unwrap_or(0);
+        let acumulado = por_linea.entry(id_item).or_insert(0);
+        let monto = monto_definido(tipo, *valor, bruto).clamp(0, bruto - *acumulado);
+        *acumulado += monto;
+        montos.push((*id_descuento, monto));
+    }
+
+    // 2) descuentos de ticket sobre el neto de las líneas
+    let netos: Vec<(i64, i64)> = lineas
+        .iter()
+        .map(|(id, bruto)| (*id, bruto - por_linea.get(id).copied().unwrap_or(0)))
+        .collect();
+    let base_ticket: i64 = netos.iter().map(|(_, n)| n).sum();
+
+    let mut total_ticket = 0i64;
+    for (id_descuento, _, tipo, valor) in defs.iter().filter(|d| d.1.is_none()) {
+        let monto = monto_definido(tipo, *valor, base_ticket).clamp(0, base_ticket - total_ticket);
+        total_ticket += monto;
+        montos.push((*id_descuento, monto));
+    }
+
+    if total_ticket > 0 {
+        let pesos: Vec<(i64, i64)> = netos.iter().copied().filter(|(_, n)| *n > 0).collect();
+        for (id_item, parte) in repartir_total_proporcional(total_ticket, &pesos)? {
+            *por_linea.entry(id_item).or_insert(0) += parte;
+        }
+    }
+
+    for (id_item, _) in &lineas {
+        sqlx::query("UPDATE venta_item SET descuento = ?1 WHERE id_item = ?2 AND descuento <> ?1")
+            .bind(por_linea.get(id_item).copied().unwrap_or(0))
+            .bind(id_item)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("descuentos (aplicar): {e}"))?;
+    }
+
+    for (id_descuento, monto) in montos {
+        sqlx::query("UPDATE venta_descuento SET monto = ?1 WHERE id_descuento = ?2")
+            .bind(monto)
+            .bind(id_descuento)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("descuentos (montos): {e}"))?;
+    }
+
+    Ok(())
+}
+
+async fn max_pct_rol(pool: &SqlitePool, rol: Rol) -> Result<i64, String> {
+    let max: Option<i64> = sqlx::query_scalar("SELECT max_pct FROM descuento_limite WHERE rol_tipo = ?1")
+        .bind(rol.as_str())
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(max.unwrap_or(0))
+}
+
+/// % descontado (redondeado para arriba) en el alcance del descuento:
+/// la línea contra su bruto, o el ticket contra el neto de las líneas. Lo
+/// que generan las reglas de promoción no cuenta para el tope.
+async fn pct_en_alcance(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_item: Option<i64>,
+) -> Result<i64, String> {
+    let (descontado, base): (i64, i64) = match id_item {
+        Some(id) => sqlx::query_as(
+            r#"
+            SELECT
+              (SELECT COALESCE(SUM(monto), 0) FROM venta_descuento WHERE id_item = ?1 AND id_regla IS NULL),
+              (SELECT cantidad * precio_unitario FROM venta_item WHERE id_item = ?1)
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await,
+        None => sqlx::query_as(
+            r#"
+            SELECT
+              (SELECT COALESCE(SUM(monto), 0) FROM venta_descuento
+                WHERE id_venta = ?1 AND id_item IS NULL),
+              (SELECT COALESCE(SUM(vi.cantidad * vi.precio_unitario), 0)
+                    - COALESCE((SELECT SUM(monto) FROM venta_descuento
+                                 WHERE id_venta = ?1 AND id_item IS NOT NULL), 0)
+                 FROM venta_item vi WHERE vi.id_venta = ?1)
+            "#,
+        )
+        .bind(id_venta)
+        .fetch_one(&mut *conn)
+        .await,
+    }
+    .map_err(|e| e.to_string())?;
+
+    if base <= 0 {
+        return Ok(0);
+    }
+    Ok((descontado * 100 + base - 1) / base)
+}
+
+/// Los descuentos de monto fijo se recalculan con las cantidades: uno que
+/// entró en el tope puede pasarlo si después la línea bajó. Antes de cobrar
+/// se vuelve a medir cada alcance contra el tope de quien lo cargó (el del
+/// admin si estaba aprobado).
+pub(crate) async fn controlar_topes(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+) -> Result<(), String> {
+    let alcances: Vec<(Option<i64>, Option<String>, i64)> = sqlx::query_as(
+        r#"
+        SELECT d.id_item, p.nombre, MAX(COALESCE(l.max_pct, 0))
+        FROM venta_descuento d
+        JOIN usuario u             ON u.id_usuario = d.id_usuario
+        LEFT JOIN descuento_limite l
+               ON l.rol_tipo = CASE WHEN d.aprobado_por IS NULL
+                                    THEN LOWER(TRIM(u.rol_tipo)) ELSE 'admin' END
+        LEFT JOIN venta_item vi    ON vi.id_item = d.id_item
+        LEFT JOIN producto p       ON p.id_producto = vi.id_producto
+        WHERE d.id_venta = ?1 AND d.id_regla IS NULL
+        GROUP BY d.id_item
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (id_item, producto, tope) in alcances {
+        let pct = pct_en_alcance(conn, id_venta, id_item).await?;
+        if pct > tope {
+            let alcance = match producto {
+                Some(nombre) => format!("de {nombre}"),
+                None => "del ticket".to_string(),
+            };
+            return Err(format!(
+                "El descuento {alcance} quedó en {pct}% y supera el tope ({tope}%): quitalo o volvé a aplicarlo"
+            ));
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Commands
+
+#[tauri::command]
+pub async fn venta_descuento_aplicar(
+    state: State<'_, AppState>,
+    input: DescuentoAplicarInput,
+) -> Result<i64, String> {
+    let sesion = requerir_sesion(&state).await?;
+
+    let tipo = input.tipo.trim().to_lowercase();
+    if tipo != "porcentaje" && tipo != "monto" {
+        return Err("tipo inválido (usa 'porcentaje' o 'monto')".into());
+    }
+    if input.valor <= 0 {
+        return Err("El descuento debe ser mayor a cero".into());
+    }
+    if tipo == "porcentaje" && input.valor > 100 {
+        return Err("El porcentaje no puede superar 100".into());
+    }
+    let motivo = input.motivo.trim();
+    if motivo.is_empty() {
+        return Err("motivo obligatorio".into());
+    }
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    carrito_activo(&mut tx, &sesion, input.id_venta).await?;
+
+    if let Some(id_item) = input.id_item {
+        let es_de_la_venta: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM venta_item WHERE id_item = ?1 AND id_venta = ?2",
+        )
+        .bind(id_item)
+        .bind(input.id_venta)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        if es_de_la_venta.is_none() {
+            return Err("La línea no pertenece a la venta".into());
+        }
+    }
+
+    let antes = crate::audit::repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let id_descuento: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO venta_descuento (id_venta, id_item, tipo, valor, motivo, id_usuario)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        RETURNING id_descuento
+        "#,
+    )
+    .bind(input.id_venta)
+    .bind(input.id_item)
+    .bind(&tipo)
+    .bind(input.valor)
+    .bind(motivo)
+    .bind(sesion.id_usuario)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    recalcular_descuentos(&mut tx, input.id_venta).await?;
+
+    // Tope por rol: se mide todo lo descontado en la línea / ticket
+    let pct = pct_en_alcance(&mut tx, input.id_venta, input.id_item).await?;
+    if pct > max_pct_rol(&state.pool, sesion.rol).await? {
+        let (_, aprobado_por) =
+            requerir_admin_o_aprobacion(&state, input.aprobacion.as_ref()).await?;
+        let max_admin = max_pct_rol(&state.pool, Rol::Admin).await?;
+        if pct > max_admin {
+            return Err(format!("El descuento ({pct}%) supera el máximo permitido ({max_admin}%)"));
+        }
+
+        sqlx::query("UPDATE venta_descuento SET aprobado_por = ?1 WHERE id_descuento = ?2")
+            .bind(aprobado_por)
+            .bind(id_descuento)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    auditar_venta(&mut tx, sesion.id_usuario, input.id_venta, "descuento_aplicar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(id_descuento)
+}
+
+#[tauri::command]
+pub async fn venta_descuento_quitar(
+    state: State<'_, AppState>,
+    id_descuento: i64,
+) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let (id_venta, id_regla): (i64, Option<i64>) =
+        sqlx::query_as("SELECT id_venta, id_regla FROM venta_descuento WHERE id_descuento = ?1")
+            .bind(id_descuento)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Descuento inexistente".to_string())?;
+    if id_regla.is_some() {
+        return Err("Ese descuento lo genera una promoción: se recalcula solo con las cantidades".into());
+    }
+
+    carrito_activo(&mut tx, &sesion, id_venta).await?;
+
+    let antes = crate::audit::repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM venta_descuento WHERE id_descuento = ?1")
+        .bind(id_descuento)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    recalcular_descuentos(&mut tx, id_venta).await?;
+
+    auditar_venta(&mut tx, sesion.id_usuario, id_venta, "descuento_quitar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub(crate) async fn descuentos_por_venta(
+    pool: &SqlitePool,
+    id_venta: i64,
+) -> Result<Vec<VentaDescuentoRow>, String> {
+    sqlx::query_as::<_, VentaDescuentoRow>(
+        r#"
+        SELECT d.id_descuento, d.id_venta, d.id_item, p.nombre AS producto,
+               d.tipo, d.valor, d.monto, d.motivo,
+               d.id_usuario, u.nombre AS usuario, ua.nombre AS aprobado_por,
+               d.fecha_hora, d.id_regla
+        FROM venta_descuento d
+        JOIN usuario u        ON u.id_usuario = d.id_usuario
+        LEFT JOIN usuario ua  ON ua.id_usuario = d.aprobado_por
+        LEFT JOIN venta_item vi ON vi.id_item = d.id_item
+        LEFT JOIN producto p  ON p.id_producto = vi.id_producto
+        WHERE d.id_venta = ?1
+        ORDER BY d.id_descuento ASC
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn venta_descuento_listar(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<Vec<VentaDescuentoRow>, String> {
+    requerir_sesion(&state).await?;
+    descuentos_por_venta(&state.pool, id_venta).await
+}
+
+#[tauri::command]
+pub async fn descuento_limite_listar(
+    state: State<'_, AppState>,
+) -> Result<Vec<DescuentoLimiteRow>, String> {
+    requerir_sesion(&state).await?;
+
+    sqlx::query_as::<_, DescuentoLimiteRow>(
+        "SELECT rol_tipo, max_pct FROM descuento_limite ORDER BY rol_tipo",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn descuento_limite_fijar(
+    state: State<'_, AppState>,
+    input: DescuentoLimiteInput,
+) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
+    let rol = Rol::desde_db(&input.rol_tipo)
+        .ok_or_else(|| format!("Rol desconocido: {}", input.rol_tipo))?;
+    if !(0..=100).contains(&input.max_pct) {
+        return Err("max_pct debe estar entre 0 y 100".into());
+    }
+
+    let antes = max_pct_rol(&state.pool, rol).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO descuento_limite (rol_tipo, max_pct) VALUES (?1, ?2)
+        ON CONFLICT(rol_tipo) DO UPDATE SET max_pct = excluded.max_pct
+        "#,
+    )
+    .bind(rol.as_str())
+    .bind(input.max_pct)
+    .execute(&state.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    crate::audit::repo::registrar(
+        &state.pool,
+        Some(uid),
+        "descuento_limite",
+        None,
+        "fijar",
+        Some(&serde_json::json!({ "rol_tipo": rol.as_str(), "max_pct": antes })),
+        Some(&serde_json::json!({ "rol_tipo": rol.as_str(), "max_pct": input.max_pct })),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqliteConnection};
+use tauri::State;
+
+use super::commands::{agregar_linea_catalogo, auditar_venta};
+use super::{descuentos, estacionadas};
+use crate::AppState;
+use crate::audit::repo as audit_repo;
+use crate::promos::deteccion as promos_deteccion;
+use crate::stock::codigos::{self, Embebido};
+use crate::stock::politica::{self as politica_stock, StockAdvertencia};
+use crate::users::permisos::{requerir_sesion, AprobacionInput};
+
+#[derive(Debug, Deserialize)]
+pub struct VentaEscanearInput {
+    pub id_venta: i64,
+    pub codigo: String,
+    pub cantidad: Option<i64>, // default 1; se ignora en códigos de balanza
+    #[serde(default)]
+    pub forzar_stock: bool,
+    pub aprobacion: Option<AprobacionInput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EscaneoOut {
+    pub id_producto: i64,
+    pub nombre: String,
+    pub cantidad: i64,
+    pub precio_unitario: i64,
+    pub origen: String, // 'codigo' | 'ean' | 'plu' | 'balanza'
+    pub gramos: Option<i64>,
+    pub advertencias: Vec<StockAdvertencia>,
+}
+
+// redondeo al peso más cercano
+fn proporcional(valor: i64, num: i64, den: i64) -> i64 {
+    if den <= 0 {
+        return 0;
+    }
+    (valor * num + den / 2) / den
+}
+
+/// Línea de balanza: cantidad 1 con el importe de la etiqueta y el peso
+/// (dato de la etiqueta o deducido del precio por kg), que es lo que mueve
+/// el stock. El costo se lleva en la misma proporción que el precio.
+async fn agregar_linea_balanza(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_producto: i64,
+    embebido: Embebido,
+) -> Result<(i64, i64), String> {
+    let row = sqlx::query(
+        "SELECT precio_venta_actual, costo_actual, alicuota_iva, unidad_venta FROM producto WHERE id_producto = ?",
+    )
+    .bind(id_producto)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    // el peso mueve el stock en gramos: un producto por unidad no lo admite
+    let unidad_venta: String = row.get("unidad_venta");
+    if unidad_venta != "peso" {
+        return Err("El producto no se vende por peso: escaneá su código de barras".into());
+    }
+    let precio_lista: i64 = row.get("precio_venta_actual");
+    let costo_lista: i64 = row.get("costo_actual");
+    let alicuota_iva: f64 = row.get("alicuota_iva");
+
+    let (precio, costo, gramos) = match embebido {
+        // precio de lista por kg
+        Embebido::Peso(gramos) => (
+            proporcional(precio_lista, gramos, 1000),
+            proporcional(costo_lista, gramos, 1000),
+            gramos,
+        ),
+        Embebido::Precio(importe) => (
+            importe,
+            proporcional(costo_lista, importe, precio_lista),
+            proporcional(1000, importe, precio_lista),
+        ),
+    };
+
+    if precio <= 0 {
+        return Err("El código de balanza no tiene importe".into());
+    }
+    if gramos <= 0 {
+        return Err("No se puede calcular el peso de la etiqueta (producto sin precio por kg)".into());
+    }
+
+    sqlx::query(
+        "INSERT INTO venta_item
+            (id_venta, id_producto, cantidad, precio_unitario, costo_unitario_en_venta, fuente_precio, subtotal, alicuota_iva, gramos)
+         VALUES(?, ?, 1, ?, ?, 'balanza', ?, ?, ?)",
+    )
+    .bind(id_venta)
+    .bind(id_producto)
+    .bind(precio)
+    .bind(costo)
+    .bind(precio)
+    .bind(alicuota_iva)
+    .bind(gramos)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok((precio, gramos))
+}
+
+// Lector de códigos: resuelve el código y suma el producto a la venta en curso
+#[tauri::command]
+pub async fn venta_escanear(
+    state: State<'_, AppState>,
+    input: VentaEscanearInput,
+) -> Result<EscaneoOut, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let uid = sesion.id_usuario;
+
+    let cantidad = input.cantidad.unwrap_or(1);
+    if cantidad <= 0 {
+        return Err("Cantidad inválida".into());
+    }
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    estacionadas::carrito_activo(&mut tx, &sesion, input.id_venta).await?;
+
+    let resuelto = codigos::resolver(&mut tx, &input.codigo).await?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let (cantidad, gramos) = match resuelto.embebido {
+        Some(emb) => {
+            let (_, gramos) = agregar_linea_balanza(&mut tx, input.id_venta, resuelto.id_producto, emb).await?;
+            (1, Some(gramos))
+        }
+        None => {
+            agregar_linea_catalogo(&mut tx, input.id_venta, resuelto.id_producto, cantidad).await?;
+            (cantidad, None)
+        }
+    };
+    promos_deteccion::autoaplicar(&mut tx, input.id_venta).await?;
+
+    let advertencias = politica_stock::controlar(
+        &state,
+        &mut tx,
+        uid,
+        input.id_venta,
+        Some(resuelto.id_producto),
+        input.forzar_stock,
+        input.aprobacion.as_ref(),
+    )
+    .await?;
+
+    descuentos::recalcular_descuentos(&mut tx, input.id_venta).await?;
+
+    auditar_venta(&mut tx, uid, input.id_venta, "escanear", antes).await?;
+
+    // línea afectada, para mostrar en el POS
+    let row = sqlx::query(
+        "SELECT p.nombre, vi.precio_unitario
+           FROM venta_item vi
+           JOIN producto p ON p.id_producto = vi.id_producto
+          WHERE vi.id_venta = ? AND vi.id_producto = ?
+          ORDER BY vi.id_item DESC
+          LIMIT 1",
+    )
+    .bind(input.id_venta)
+    .bind(resuelto.id_producto)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(EscaneoOut {
+        id_producto: resuelto.id_producto,
+        nombre: row.get("nombre"),
+        cantidad,
+        precio_unitario: row.get("precio_unitario"),
+        origen: resuelto.origen.to_string(),
+        gramos,
+        advertencias,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+
+    fn escanear(id_venta: i64, codigo: &str) -> VentaEscanearInput {
+        serde_json::from_value(json!({ "id_venta": id_venta, "codigo": codigo })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn la_balanza_descuenta_el_stock_en_gramos() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        // $2000 el kg, 5 kg en stock
+        let queso = prueba::producto(pool, "queso", 2000, 1000, 5000).await;
+        sqlx::query("UPDATE producto SET plu = 42, unidad_venta = 'peso' WHERE id_producto = ?1")
+            .bind(queso)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let id_venta = prueba::carrito(&state, &[]).await;
+
+        // etiqueta de peso: 500 g
+        let out = venta_escanear(state.clone(), escanear(id_venta, "2100042005006")).await.unwrap();
+        assert_eq!((out.cantidad, out.precio_unitario, out.gramos), (1, 1000, Some(500)));
+
+        // etiqueta de precio: $1250 => 625 g
+        let out = venta_escanear(state.clone(), escanear(id_venta, "2000042012502")).await.unwrap();
+        assert_eq!((out.cantidad, out.precio_unitario, out.gramos), (1, 1250, Some(625)));
+
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 2250 }]))
+            .await
+            .unwrap();
+        assert_eq!(prueba::stock(pool, queso).await, 5000 - 500 - 625);
+
+        // el costo unitario del movimiento va por kg, el total es el de la etiqueta
+        let costos: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT costo_unitario, total_costo FROM stock_mov WHERE referencia = ?1 ORDER BY id_movimiento",
+        )
+        .bind(format!("venta:{id_venta}"))
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        assert_eq!(costos, vec![(1000, 500), (1000, 625)]);
+    }
+
+    async fn costos_de(pool: &sqlx::SqlitePool, referencia: &str) -> Vec<(i64, i64, i64)> {
+        sqlx::query_as(
+            "SELECT cantidad_delta, costo_unitario, total_costo FROM stock_mov WHERE referencia = ?1",
+        )
+        .bind(referencia)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn anular_y_devolver_la_balanza_conservan_el_costo() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        // 450 g cuestan 300: por gramo daría 0
+        let queso = prueba::producto(pool, "queso", 2000, 667, 5000).await;
+        sqlx::query("UPDATE producto SET plu = 42, unidad_venta = 'peso' WHERE id_producto = ?1")
+            .bind(queso)
+            .execute(pool)
+            .await
+            .unwrap();
+        let pago = json!([{ "medio": "efectivo", "monto": 900 }]);
+
+        let devuelta = prueba::carrito(&state, &[]).await;
+        venta_escanear(state.clone(), escanear(devuelta, "2100042004504")).await.unwrap();
+        prueba::finalizar(&state, devuelta, pago.clone()).await.unwrap();
+        assert_eq!(costos_de(pool, &format!("venta:{devuelta}")).await, vec![(-450, 667, 300)]);
+
+        let input = serde_json::from_value(json!({ "id_venta": devuelta })).unwrap();
+        let out = crate::devoluciones::commands::devolucion_crear(state.clone(), input).await.unwrap();
+        let referencia = format!("devolucion:{}", out.id_devolucion);
+        assert_eq!(costos_de(pool, &referencia).await, vec![(450, 667, 300)]);
+
+        let anulada = prueba::carrito(&state, &[]).await;
+        venta_escanear(state.clone(), escanear(anulada, "2100042004504")).await.unwrap();
+        prueba::finalizar(&state, anulada, pago).await.unwrap();
+        let input = serde_json::from_value(json!({ "id_venta": anulada, "motivo": "error" })).unwrap();
+        crate::ventas::commands::venta_anular(state.clone(), input).await.unwrap();
+
+        let mut movs = costos_de(pool, &format!("venta:{anulada}")).await;
+        movs.sort();
+        assert_eq!(movs, vec![(-450, 667, 300), (450, 667, 300)]);
+        assert_eq!(prueba::stock(pool, queso).await, 5000);
+    }
+
+    #[tokio::test]
+    async fn peso_y_unidad_no_se_mezclan() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let gaseosa = prueba::producto(pool, "gaseosa", 2000, 1000, 10).await;
+        let queso = prueba::producto(pool, "queso", 2000, 1000, 5000).await;
+        sqlx::query(
+            "UPDATE producto SET plu = CASE id_producto WHEN ?1 THEN 41 ELSE 42 END,
+                    unidad_venta = CASE id_producto WHEN ?1 THEN 'unidad' ELSE 'peso' END
+              WHERE id_producto IN (?1, ?2)",
+        )
+        .bind(gaseosa)
+        .bind(queso)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let id_venta = prueba::carrito(&state, &[]).await;
+
+        // etiqueta de balanza de un producto por unidad
+        let err = venta_escanear(state.clone(), escanear(id_venta, "2100041005007")).await.unwrap_err();
+        assert_eq!(err, "El producto no se vende por peso: escaneá su código de barras");
+
+        // el de peso no entra por código ni por PLU
+        let err = venta_escanear(state.clone(), escanear(id_venta, "queso")).await.unwrap_err();
+        assert_eq!(err, "El producto se vende por peso: pasalo por la balanza");
+        assert!(venta_escanear(state.clone(), escanear(id_venta, "42")).await.is_err());
+
+        // la gaseosa por PLU sí, y mueve una unidad
+        venta_escanear(state.clone(), escanear(id_venta, "41")).await.unwrap();
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 2000 }]))
+            .await
+            .unwrap();
+        assert_eq!(prueba::stock(pool, gaseosa).await, 9);
+        assert_eq!(prueba::stock(pool, queso).await, 5000);
+    }
+
+    #[tokio::test]
+    async fn escanear_aplica_el_combo_automatico() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let cafe = prueba::producto(pool, "cafe", 800, 300, 10).await;
+        let medialuna = prueba::producto(pool, "medialuna", 400, 100, 10).await;
+        let id_combo: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_combo (nombre, precio_pack) VALUES ('desayuno', 1000) RETURNING id_combo",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO promo_combo_item (id_combo, id_producto, cantidad) VALUES (?1, ?2, 1), (?1, ?3, 1)")
+            .bind(id_combo)
+            .bind(cafe)
+            .bind(medialuna)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO ajuste (clave, valor) VALUES (?1, '1')
+             ON CONFLICT(clave) DO UPDATE SET valor = excluded.valor",
+        )
+        .bind(promos_deteccion::AJUSTE_COMBO_AUTO)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let id_venta = prueba::carrito(&state, &[]).await;
+        venta_escanear(state.clone(), escanear(id_venta, "cafe")).await.unwrap();
+        let out = venta_escanear(state.clone(), escanear(id_venta, "medialuna")).await.unwrap();
+        assert_eq!(out.cantidad, 1);
+
+        let (total, en_combo): (i64, i64) = sqlx::query_as(
+            "SELECT SUM(subtotal), SUM(promo_combo_id IS NOT NULL) FROM venta_item WHERE id_venta = ?1",
+        )
+        .bind(id_venta)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!((total, en_combo), (1000, 2));
+    }
+}
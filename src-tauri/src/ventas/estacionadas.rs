@@ -0,0 +1,503 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection};
+use tauri::State;
+
+use crate::AppState;
+use crate::users::permisos::{requerir_admin, requerir_sesion, Rol, SesionActiva};
+
+use super::commands::auditar_venta;
+use super::repo;
+
+// Carritos estacionados: un operador puede tener varias ventas en_curso en
+// la caja. La activa tiene estacionada_en NULL; el resto espera a retomarse.
+// Huérfano = carrito que nadie va a retomar (caja cerrada, sesión de otro
+// usuario o sin actividad hace más de HUERFANO_MINUTOS).
+
+pub const HUERFANO_MINUTOS: i64 = 120;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct VentaEnCursoRow {
+    pub id_venta: i64,
+    pub id_usuario: i64,
+    pub usuario: String,
+    pub id_caja: i64,
+    pub etiqueta: Option<String>,
+    pub fecha_hora: String,
+    pub estacionada_en: Option<String>,
+    pub actualizada_en: String,
+    pub total: i64,
+    pub cantidad_items: i64,
+    /// 'caja_cerrada' | 'sesion_terminada' | 'inactivo' (None = no es huérfano)
+    pub huerfano_motivo: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VentaEtiquetaInput {
+    pub id_venta: i64,
+    pub etiqueta: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct VentaEstacionadasFiltro {
+    pub id_caja: Option<i64>,    // default: caja abierta
+    pub id_usuario: Option<i64>, // sólo admin; el operador ve los suyos
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Consultas
+
+const SELECT_EN_CURSO: &str = r#"
+    SELECT v.id_venta, v.id_usuario, u.nombre AS usuario, v.id_caja, v.etiqueta,
+           v.fecha_hora, v.estacionada_en,
+           COALESCE(v.actualizada_en, v.fecha_hora) AS actualizada_en,
+           v.total,
+           (SELECT COUNT(*) FROM venta_item vi WHERE vi.id_venta = v.id_venta) AS cantidad_items,
+           CASE
+             WHEN c.estado <> 'abierta' THEN 'caja_cerrada'
+             WHEN v.estacionada_en IS NULL AND v.id_usuario <> ?1 THEN 'sesion_terminada'
+             WHEN v.estacionada_en IS NULL
+              AND COALESCE(v.actualizada_en, v.fecha_hora)
+                  < DATETIME('now','localtime', '-' || ?2 || ' minutes') THEN 'inactivo'
+             ELSE NULL
+           END AS huerfano_motivo
+    FROM venta v
+    JOIN usuario u ON u.id_usuario = v.id_usuario
+    JOIN caja c    ON c.id_caja = v.id_caja
+    WHERE v.estado = 'en_curso'
+"#;
+
+async fn listar_en_curso(
+    conn: &mut SqliteConnection,
+    id_sesion: i64,
+    id_caja: Option<i64>,
+    id_usuario: Option<i64>,
+    solo_huerfanos: bool,
+) -> Result<Vec<VentaEnCursoRow>, String> {
+    let sql = format!(
+        "SELECT * FROM ({SELECT_EN_CURSO}) t
+         WHERE (?3 IS NULL OR t.id_caja = ?3)
+           AND (?4 IS NULL OR t.id_usuario = ?4)
+           AND (?5 = 0 OR t.huerfano_motivo IS NOT NULL)
+         ORDER BY t.estacionada_en IS NOT NULL, t.fecha_hora ASC, t.id_venta ASC"
+    );
+
+    sqlx::query_as::<_, VentaEnCursoRow>(&sql)
+        .bind(id_sesion)
+        .bind(HUERFANO_MINUTOS)
+        .bind(id_caja)
+        .bind(id_usuario)
+        .bind(solo_huerfanos as i64)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Carrito en_curso sobre el que opera la sesión. El operador sólo toca los
+/// suyos; el admin puede tocar cualquiera (p. ej. para limpiar huérfanos).
+pub(crate) async fn carrito_de_sesion(
+    conn: &mut SqliteConnection,
+    sesion: &SesionActiva,
+    id_venta: i64,
+) -> Result<(i64, String), String> {
+    let row: Option<(i64, String, i64, String)> = sqlx::query_as(
+        r#"
+        SELECT v.id_usuario, v.estado, v.id_caja, c.estado
+        FROM venta v
+        JOIN caja c ON c.id_caja = v.id_caja
+        WHERE v.id_venta = ?1
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (id_usuario, estado, id_caja, estado_caja) =
+        row.ok_or_else(|| "Venta inexistente".to_string())?;
+
+    if estado != "en_curso" {
+        return Err("La venta no está en curso".into());
+    }
+    if id_usuario != sesion.id_usuario && sesion.rol != Rol::Admin {
+        return Err("El carrito pertenece a otro operador".into());
+    }
+
+    Ok((id_caja, estado_caja))
+}
+
+/// Carrito sobre el que se cargan líneas: de la sesión, activo (no
+/// estacionado) y con la caja abierta.
+pub(crate) async fn carrito_activo(
+    conn: &mut SqliteConnection,
+    sesion: &SesionActiva,
+    id_venta: i64,
+) -> Result<(), String> {
+    let (_, estado_caja) = carrito_de_sesion(conn, sesion, id_venta).await?;
+    if estado_caja != "abierta" {
+        return Err("La caja del carrito está cerrada: sólo se puede descartar".into());
+    }
+
+    let estacionada: Option<String> =
+        sqlx::query_scalar("SELECT estacionada_en FROM venta WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    if estacionada.is_some() {
+        return Err("El carrito está estacionado: retomalo para modificarlo".into());
+    }
+    Ok(())
+}
+
+/// Estaciona el carrito activo del operador en la caja (salvo `excepto`):
+/// al retomar otro o al iniciar uno nuevo, el que estaba queda esperando.
+pub(crate) async fn estacionar_activo(
+    conn: &mut SqliteConnection,
+    id_usuario: i64,
+    id_caja: i64,
+    excepto: Option<i64>,
+) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        UPDATE venta
+           SET estacionada_en = DATETIME('now','localtime')
+         WHERE estado = 'en_curso'
+           AND estacionada_en IS NULL
+           AND id_usuario = ?1
+           AND id_caja = ?2
+           AND (?3 IS NULL OR id_venta <> ?3)
+        "#,
+    )
+    .bind(id_usuario)
+    .bind(id_caja)
+    .bind(excepto)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn normalizar_etiqueta(etiqueta: Option<String>) -> Option<String> {
+    etiqueta.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Commands
+
+#[tauri::command]
+pub async fn venta_estacionadas_listar(
+    state: State<'_, AppState>,
+    filtro: Option<VentaEstacionadasFiltro>,
+) -> Result<Vec<VentaEnCursoRow>, String> {
+    let sesion = requerir_sesion(&state).await?;
+    let filtro = filtro.unwrap_or_default();
+
+    let id_usuario = match sesion.rol {
+        Rol::Admin => filtro.id_usuario,
+        Rol::Operador => Some(sesion.id_usuario),
+    };
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    let id_caja = match filtro.id_caja {
+        Some(id) => id,
+        None => crate::caja::repo::ultima_caja_abierta_id(&state.pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "No hay caja abierta".to_string())?,
+    };
+
+    listar_en_curso(&mut conn, sesion.id_usuario, Some(id_caja), id_usuario, false).await
+}
+
+/// Carritos huérfanos de todas las cajas (para descartarlos o retomarlos)
+#[tauri::command]
+pub async fn venta_huerfanas_listar(
+    state: State<'_, AppState>,
+) -> Result<Vec<VentaEnCursoRow>, String> {
+    let sesion = requerir_admin(&state).await?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+    listar_en_curso(&mut conn, sesion.id_usuario, None, None, true).await
+}
+
+#[tauri::command]
+pub async fn venta_estacionar(
+    state: State<'_, AppState>,
+    input: VentaEtiquetaInput,
+) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    carrito_de_sesion(&mut tx, &sesion, input.id_venta).await?;
+
+    let antes = crate::audit::repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        UPDATE venta
+           SET estacionada_en = DATETIME('now','localtime'),
+               etiqueta = COALESCE(?1, etiqueta)
+         WHERE id_venta = ?2
+        "#,
+    )
+    .bind(normalizar_etiqueta(input.etiqueta))
+    .bind(input.id_venta)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    auditar_venta(&mut tx, sesion.id_usuario, input.id_venta, "estacionar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn venta_etiquetar(
+    state: State<'_, AppState>,
+    input: VentaEtiquetaInput,
+) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    carrito_de_sesion(&mut tx, &sesion, input.id_venta).await?;
+
+    let antes = crate::audit::repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("UPDATE venta SET etiqueta = ?1 WHERE id_venta = ?2")
+        .bind(normalizar_etiqueta(input.etiqueta))
+        .bind(input.id_venta)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    auditar_venta(&mut tx, sesion.id_usuario, input.id_venta, "etiquetar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Retoma un carrito: pasa a ser el activo y estaciona el que estaba activo
+/// del mismo operador en esa caja.
+#[tauri::command]
+pub async fn venta_retomar(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    let (id_caja, estado_caja) = carrito_de_sesion(&mut tx, &sesion, id_venta).await?;
+
+    if estado_caja != "abierta" {
+        return Err("La caja del carrito está cerrada: sólo se puede descartar".into());
+    }
+
+    estacionar_activo(&mut tx, sesion.id_usuario, id_caja, Some(id_venta)).await?;
+
+    let antes = crate::audit::repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        UPDATE venta
+           SET estacionada_en = NULL,
+               actualizada_en = DATETIME('now','localtime')
+         WHERE id_venta = ?1
+        "#,
+    )
+    .bind(id_venta)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    auditar_venta(&mut tx, sesion.id_usuario, id_venta, "retomar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn venta_descartar(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<(), String> {
+    let sesion = requerir_sesion(&state).await?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+    carrito_de_sesion(&mut tx, &sesion, id_venta).await?;
+
+    let antes = crate::audit::repo::venta_snapshot(&mut tx, id_venta)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    repo::carrito_descartar(&mut tx, id_venta).await?;
+
+    auditar_venta(&mut tx, sesion.id_usuario, id_venta, "descartar", antes).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::ventas::commands::{self, AgregarItemInput, QuitarItemInput, VentaCancelarInput};
+    use crate::ventas::descuentos::{self, DescuentoAplicarInput};
+    use crate::ventas::escaneo::{self, VentaEscanearInput};
+    use crate::ventas::prueba;
+
+    fn agregar(id_venta: i64, id_producto: i64) -> AgregarItemInput {
+        serde_json::from_value(json!({ "id_venta": id_venta, "id_producto": id_producto, "cantidad": 1 }))
+            .unwrap()
+    }
+
+    async fn estacionada(pool: &sqlx::SqlitePool, id_venta: i64) -> bool {
+        sqlx::query_scalar::<_, Option<String>>("SELECT estacionada_en FROM venta WHERE id_venta = ?1")
+            .bind(id_venta)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn iniciar_estaciona_el_activo_y_no_se_carga_en_estacionados() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let primero = prueba::carrito(&state, &[(pan, 1)]).await;
+        let segundo = commands::venta_iniciar(state.clone()).await.unwrap();
+        assert!(estacionada(pool, primero).await);
+        assert!(!estacionada(pool, segundo).await);
+
+        let err = commands::venta_agregar_item(state.clone(), agregar(primero, pan)).await.unwrap_err();
+        assert_eq!(err, "El carrito está estacionado: retomalo para modificarlo");
+
+        let id_item: i64 = sqlx::query_scalar("SELECT id_item FROM venta_item WHERE id_venta = ?1")
+            .bind(primero)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let quitar = QuitarItemInput { id_item };
+        assert!(commands::venta_quitar_item(state.clone(), quitar).await.is_err());
+
+        let escanear = |id_venta: i64| -> VentaEscanearInput {
+            serde_json::from_value(json!({ "id_venta": id_venta, "codigo": "pan" })).unwrap()
+        };
+        assert!(escaneo::venta_escanear(state.clone(), escanear(primero)).await.is_err());
+
+        // al retomar el primero, el segundo queda estacionado
+        venta_retomar(state.clone(), primero).await.unwrap();
+        assert!(estacionada(pool, segundo).await);
+        escaneo::venta_escanear(state.clone(), escanear(primero)).await.unwrap();
+        let en_curso = venta_estacionadas_listar(state.clone(), None).await.unwrap();
+        let activos: Vec<i64> =
+            en_curso.iter().filter(|v| v.estacionada_en.is_none()).map(|v| v.id_venta).collect();
+        assert_eq!(activos, vec![primero]);
+    }
+
+    #[tokio::test]
+    async fn el_carrito_de_otro_operador_no_se_toca() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::carrito(&state, &[(pan, 1)]).await;
+
+        state.sesion.iniciar(3, Rol::Operador).unwrap();
+        let err = commands::venta_agregar_item(state.clone(), agregar(id_venta, pan)).await.unwrap_err();
+        assert_eq!(err, "El carrito pertenece a otro operador");
+
+        // el admin sí (p. ej. para limpiar huérfanos)
+        state.sesion.iniciar(1, Rol::Admin).unwrap();
+        commands::venta_agregar_item(state.clone(), agregar(id_venta, pan)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cobrar_o_descontar_exige_el_carrito_activo_propio() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let estacionado = prueba::carrito(&state, &[(pan, 1)]).await;
+        let activo = prueba::carrito(&state, &[(pan, 1)]).await;
+        assert!(estacionada(pool, estacionado).await);
+
+        let descuento = |id_venta: i64| -> DescuentoAplicarInput {
+            serde_json::from_value(json!({
+                "id_venta": id_venta, "tipo": "porcentaje", "valor": 5, "motivo": "cliente"
+            }))
+            .unwrap()
+        };
+        let pago = json!([{ "medio": "efectivo", "monto": 300 }]);
+        let parado = "El carrito está estacionado: retomalo para modificarlo";
+
+        let err = prueba::finalizar(&state, estacionado, pago.clone()).await.unwrap_err();
+        assert_eq!(err, parado);
+        let err = descuentos::venta_descuento_aplicar(state.clone(), descuento(estacionado)).await.unwrap_err();
+        assert_eq!(err, parado);
+        let err = commands::venta_quitar_promo_grupo(state.clone(), estacionado, "x".into()).await.unwrap_err();
+        assert_eq!(err, parado);
+
+        // otro operador no cobra, descuenta ni cancela el carrito ajeno
+        state.sesion.iniciar(3, Rol::Operador).unwrap();
+        let ajeno = "El carrito pertenece a otro operador";
+        let err = prueba::finalizar(&state, activo, pago.clone()).await.unwrap_err();
+        assert_eq!(err, ajeno);
+        let err = descuentos::venta_descuento_aplicar(state.clone(), descuento(activo)).await.unwrap_err();
+        assert_eq!(err, ajeno);
+        let cancelar = VentaCancelarInput { id_venta: activo };
+        let err = commands::venta_cancelar(state.clone(), cancelar).await.unwrap_err();
+        assert_eq!(err, ajeno);
+
+        // el dueño cobra el activo
+        state.sesion.iniciar(2, Rol::Operador).unwrap();
+        prueba::finalizar(&state, activo, pago).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn etiquetar_queda_auditado() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((2, Rol::Operador))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 2, 0).await;
+        let id_venta = commands::venta_iniciar(state.clone()).await.unwrap();
+        let input = VentaEtiquetaInput { id_venta, etiqueta: Some(" señora del perro ".into()) };
+        venta_etiquetar(state.clone(), input).await.unwrap();
+
+        let (antes, despues): (String, String) = sqlx::query_as(
+            "SELECT antes_json, despues_json FROM audit_evento
+              WHERE entidad = 'venta' AND id_entidad = ?1 AND accion = 'etiquetar'",
+        )
+        .bind(id_venta)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let antes: serde_json::Value = serde_json::from_str(&antes).unwrap();
+        let despues: serde_json::Value = serde_json::from_str(&despues).unwrap();
+        assert_eq!(antes["etiqueta"], serde_json::Value::Null);
+        assert_eq!(despues["etiqueta"], "señora del perro");
+    }
+}
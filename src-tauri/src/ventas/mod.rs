@@ -1,3 +1,8 @@
 pub mod commands;
+pub mod descuentos;
+pub mod escaneo;
+pub mod estacionadas;
 pub mod model;
-pub mod repo;
\ No newline at end of file
+pub mod repo;
+#[cfg(test)]
+pub mod prueba;
@@ -0,0 +1,93 @@
+// Armado de datos para tests: productos con stock, caja abierta y ventas
+// hechas con los mismos commands que usa el front.
+
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::caja::repo as caja_repo;
+use crate::users::model::UsuarioCrear;
+use crate::users::repo as usuarios_repo;
+use crate::AppState;
+
+use super::commands::{self, AgregarItemInput, VentaFinalizarInput};
+
+/// Producto de catálogo (IVA 21) con `stock` unidades cargadas.
+pub async fn producto(pool: &SqlitePool, codigo: &str, precio: i64, costo: i64, stock: i64) -> i64 {
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO producto (codigo_producto, nombre, precio_venta_actual, costo_actual)
+         VALUES (?1, ?1, ?2, ?3) RETURNING id_producto",
+    )
+    .bind(codigo)
+    .bind(precio)
+    .bind(costo)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    if stock != 0 {
+        sqlx::query(
+            "INSERT INTO stock_mov (id_producto, cantidad_delta, motivo, costo_unitario, total_costo)
+             VALUES (?1, ?2, 'ajuste', ?3, ?2 * ?3)",
+        )
+        .bind(id)
+        .bind(stock)
+        .bind(costo)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+    id
+}
+
+pub async fn stock(pool: &SqlitePool, id_producto: i64) -> i64 {
+    sqlx::query_scalar("SELECT stock_actual FROM producto_stock WHERE id_producto = ?1")
+        .bind(id_producto)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Usuario con contraseña "clave" (para aprobaciones).
+pub async fn usuario(pool: &SqlitePool, nombre_usuario: &str, rol: &str) -> i64 {
+    let input = UsuarioCrear {
+        nombre: nombre_usuario.to_string(),
+        nombre_usuario: nombre_usuario.to_string(),
+        password: "clave".to_string(),
+    };
+    usuarios_repo::crear(pool, &input, rol).await.unwrap()
+}
+
+pub async fn caja(pool: &SqlitePool, id_usuario: i64, fondo: i64) -> i64 {
+    caja_repo::abrir_caja(pool, id_usuario, fondo).await.unwrap()
+}
+
+/// Carrito en curso con las líneas (id_producto, cantidad).
+pub async fn carrito(state: &State<'_, AppState>, lineas: &[(i64, i64)]) -> i64 {
+    let id_venta = commands::venta_iniciar(state.clone()).await.unwrap();
+    for (id_producto, cantidad) in lineas {
+        let input: AgregarItemInput = serde_json::from_value(json!({
+            "id_venta": id_venta, "id_producto": id_producto, "cantidad": cantidad
+        }))
+        .unwrap();
+        commands::venta_agregar_item(state.clone(), input).await.unwrap();
+    }
+    id_venta
+}
+
+/// Venta finalizada. `pagos` va tal cual al command (lista de PagoInput).
+pub async fn venta(state: &State<'_, AppState>, lineas: &[(i64, i64)], pagos: Value) -> i64 {
+    let id_venta = carrito(state, lineas).await;
+    finalizar(state, id_venta, pagos).await.unwrap();
+    id_venta
+}
+
+pub async fn finalizar(
+    state: &State<'_, AppState>,
+    id_venta: i64,
+    pagos: Value,
+) -> Result<commands::VentaFinalizarOut, String> {
+    let input: VentaFinalizarInput =
+        serde_json::from_value(json!({ "id_venta": id_venta, "pagos": pagos })).unwrap();
+    commands::venta_finalizar(state.clone(), input).await
+}
@@ -1,10 +1,51 @@
-use sqlx::{Row, Sqlite, Transaction, SqlitePool};
+use sqlx::{Row, SqliteConnection};
 use uuid::Uuid;
 
-use crate::promos::repo::{repartir_total_proporcional, dividir_total_en_unitarios};
+use crate::promos::repo::{repartir_total_proporcional, dividir_total_en_unitarios, verificar_aplicable};
+
+/// Línea de catálogo todavía fuera de cualquier combo (y sin descuento
+/// manual propio): es lo que un combo puede consumir.
+#[derive(sqlx::FromRow)]
+pub(crate) struct LineaLibre {
+    pub id_item: i64,
+    pub id_producto: i64,
+    pub cantidad: i64,
+    pub precio_unitario: i64,
+    pub costo_unitario_en_venta: i64,
+    pub alicuota_iva: f64,
+}
+
+pub(crate) async fn lineas_libres(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_producto: Option<i64>,
+) -> Result<Vec<LineaLibre>, String> {
+    sqlx::query_as::<_, LineaLibre>(
+        r#"
+        SELECT vi.id_item, vi.id_producto, vi.cantidad, vi.precio_unitario,
+               vi.costo_unitario_en_venta, vi.alicuota_iva
+          FROM venta_item vi
+         WHERE vi.id_venta = ?1
+           AND (?2 IS NULL OR vi.id_producto = ?2)
+           AND vi.fuente_precio = 'catalogo'
+           AND vi.promo_combo_id IS NULL
+           AND vi.promo_grupo_id IS NULL
+           AND NOT EXISTS (SELECT 1 FROM venta_descuento d WHERE d.id_item = vi.id_item AND d.id_regla IS NULL)
+         ORDER BY vi.id_item DESC
+        "#,
+    )
+    .bind(id_venta)
+    .bind(id_producto)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())
+}
 
+/// Aplica una vez el combo: consume del carrito las líneas de catálogo de sus
+/// productos y las reemplaza por líneas 'promo' del mismo grupo, con el
+/// precio del pack prorrateado. Devuelve el promo_grupo_id.
 pub async fn venta_aplicar_promo_combo_db(
-    pool: &SqlitePool,
+    conn: &mut SqliteConnection,
     id_venta: i64,
     id_combo: i64,
     precio_total_pack: i64,
@@ -13,8 +54,6 @@ pub async fn venta_aplicar_promo_combo_db(
         return Err("precio_total_pack inválido".into());
     }
 
-    let mut tx: Transaction<'_, Sqlite> = pool.begin().await.map_err(|e| e.to_string())?;
-
     // 1) Validar combo
     let combo_row = sqlx::query(
         r#"
@@ -24,9 +63,10 @@ pub async fn venta_aplicar_promo_combo_db(
         "#
     )
     .bind(id_combo)
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *conn)
     .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| e.to_string())?
+    .ok_or("Combo no encontrado")?;
 
     let precio_min_total: i64 = combo_row.get(0);
     let activo: i64 = combo_row.get(1);
@@ -34,21 +74,21 @@ pub async fn venta_aplicar_promo_combo_db(
     if activo != 1 {
         return Err("La promoción no está activa".into());
     }
+    verificar_aplicable(conn, id_combo).await?;
     if precio_total_pack < precio_min_total {
         return Err("El precio ingresado está por debajo del mínimo".into());
     }
 
-    // 2) Leer items del combo + precio normal actual
-    let combo_items = sqlx::query(
+    // 2) Leer items del combo y lo que hay libre de cada uno en el carrito
+    let combo_items: Vec<(i64, i64)> = sqlx::query_as(
         r#"
-        SELECT pci.id_producto, pci.cantidad, p.precio_venta_actual
-        FROM promo_combo_item pci
-        JOIN producto p ON p.id_producto = pci.id_producto
-        WHERE pci.id_combo = ?1
+        SELECT id_producto, cantidad
+        FROM promo_combo_item
+        WHERE id_combo = ?1
         "#
     )
     .bind(id_combo)
-    .fetch_all(&mut *tx)
+    .fetch_all(&mut *conn)
     .await
     .map_err(|e| e.to_string())?;
 
@@ -57,15 +97,16 @@ pub async fn venta_aplicar_promo_combo_db(
     }
 
     let mut pesos: Vec<(i64, i64)> = Vec::with_capacity(combo_items.len());
-    let mut qty_por_prod: Vec<(i64, i64)> = Vec::with_capacity(combo_items.len());
-
-    for r in &combo_items {
-        let id_producto: i64 = r.get(0);
-        let cantidad: i64 = r.get(1);
-        let precio: i64 = r.get(2);
-        let peso = precio.saturating_mul(cantidad);
-        pesos.push((id_producto, peso));
-        qty_por_prod.push((id_producto, cantidad));
+    let mut libres: Vec<(i64, i64, Vec<LineaLibre>)> = Vec::with_capacity(combo_items.len());
+
+    for (id_producto, cantidad) in combo_items {
+        let filas = lineas_libres(conn, id_venta, Some(id_producto)).await?;
+        if filas.iter().map(|f| f.cantidad).sum::<i64>() < cantidad {
+            return Err(format!("No hay cantidad suficiente en carrito para producto {id_producto}"));
+        }
+        // pondera con el precio cobrado en el carrito
+        pesos.push((id_producto, filas[0].precio_unitario.saturating_mul(cantidad)));
+        libres.push((id_producto, cantidad, filas));
     }
 
     // 3) Reparto proporcional por producto
@@ -76,7 +117,7 @@ pub async fn venta_aplicar_promo_combo_db(
     let promo_grupo_id = Uuid::new_v4().to_string();
 
     // 5) Consumir del carrito (venta_item) y reemplazar por líneas promo exactas
-    for (id_producto, qty_necesaria) in qty_por_prod {
+    for (id_producto, qty_necesaria, filas) in libres {
         let total_prod = asignado_por_prod
             .iter()
             .find(|(id, _)| *id == id_producto)
@@ -86,110 +127,120 @@ pub async fn venta_aplicar_promo_combo_db(
         let (base_unit, resto) = dividir_total_en_unitarios(total_prod, qty_necesaria)
             .map_err(|e| e.to_string())?;
 
-        // buscar fila sin promo con cantidad suficiente
-        let fila = sqlx::query(
-            r#"
-            SELECT id_item, cantidad, precio_unitario, costo_unitario_en_venta
-            FROM venta_item
-            WHERE id_venta = ?1
-              AND id_producto = ?2
-              AND promo_grupo_id IS NULL
-              AND promo_combo_id IS NULL
-              AND cantidad >= ?3
-            ORDER BY id_item DESC
-            LIMIT 1
-            "#
-        )
-        .bind(id_venta)
-        .bind(id_producto)
-        .bind(qty_necesaria)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
-
-        let fila = match fila {
-            Some(f) => f,
-            None => return Err(format!("No hay cantidad suficiente en carrito para producto {id_producto}")),
-        };
-
-        let id_item: i64 = fila.get(0);
-        let cantidad_actual: i64 = fila.get(1);
-        let precio_unitario_original: i64 = fila.get(2);
-        let costo_unitario_en_venta: i64 = fila.get(3);
-
-        // reducir o borrar fila original
-        if cantidad_actual > qty_necesaria {
-            let sobrante = cantidad_actual - qty_necesaria;
-            sqlx::query(
-                r#"UPDATE venta_item SET cantidad = ?1 WHERE id_item = ?2"#
-            )
-            .bind(sobrante)
-            .bind(id_item)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-        } else {
-            sqlx::query(r#"DELETE FROM venta_item WHERE id_item = ?1"#)
-                .bind(id_item)
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| e.to_string())?;
+        let costo_unitario_en_venta = filas[0].costo_unitario_en_venta;
+        let alicuota_iva = filas[0].alicuota_iva;
+
+        // reducir o borrar las filas originales, de la más nueva a la más vieja
+        let mut falta = qty_necesaria;
+        for f in filas {
+            if falta == 0 {
+                break;
+            }
+            let toma = f.cantidad.min(falta);
+            if toma < f.cantidad {
+                sqlx::query(r#"UPDATE venta_item SET cantidad = ?1 WHERE id_item = ?2"#)
+                    .bind(f.cantidad - toma)
+                    .bind(f.id_item)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            } else {
+                sqlx::query(r#"DELETE FROM venta_item WHERE id_item = ?1"#)
+                    .bind(f.id_item)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            falta -= toma;
         }
 
-        // insertar líneas promo exactas
-        let qty_base = qty_necesaria - resto;
-
-        if qty_base > 0 {
+        // insertar líneas promo exactas: (base) y (base+1) para el resto
+        for (qty, unit) in [(qty_necesaria - resto, base_unit), (resto, base_unit + 1)] {
+            if qty <= 0 {
+                continue;
+            }
             sqlx::query(
                 r#"
                 INSERT INTO venta_item (
                   id_venta, id_producto, cantidad,
                   precio_unitario, costo_unitario_en_venta,
-                  precio_unitario_efectivo,
-                  promo_combo_id, promo_grupo_id, promo_precio_total
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                  fuente_precio, subtotal, precio_unitario_efectivo,
+                  promo_combo_id, promo_grupo_id, promo_precio_total, alicuota_iva
+                ) VALUES (?1, ?2, ?3, ?4, ?5, 'promo', ?6, ?4, ?7, ?8, ?9, ?10)
                 "#
             )
             .bind(id_venta)
             .bind(id_producto)
-            .bind(qty_base)
-            .bind(precio_unitario_original)
+            .bind(qty)
+            .bind(unit)
             .bind(costo_unitario_en_venta)
-            .bind(base_unit)
+            .bind(qty * unit)
             .bind(id_combo)
             .bind(&promo_grupo_id)
             .bind(precio_total_pack)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-        }
-
-        if resto > 0 {
-            sqlx::query(
-                r#"
-                INSERT INTO venta_item (
-                  id_venta, id_producto, cantidad,
-                  precio_unitario, costo_unitario_en_venta,
-                  precio_unitario_efectivo,
-                  promo_combo_id, promo_grupo_id, promo_precio_total
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-                "#
-            )
-            .bind(id_venta)
-            .bind(id_producto)
-            .bind(resto)
-            .bind(precio_unitario_original)
-            .bind(costo_unitario_en_venta)
-            .bind(base_unit + 1)
-            .bind(id_combo)
-            .bind(&promo_grupo_id)
-            .bind(precio_total_pack)
-            .execute(&mut *tx)
+            .bind(alicuota_iva)
+            .execute(&mut *conn)
             .await
             .map_err(|e| e.to_string())?;
         }
     }
 
-    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(promo_grupo_id)
 }
+
+/// Descarta un carrito en_curso: borra sus líneas y lo deja anulado en 0.
+/// No toca stock (el carrito todavía no lo movió).
+pub async fn carrito_descartar(conn: &mut SqliteConnection, id_venta: i64) -> Result<(), String> {
+    sqlx::query("DELETE FROM venta_item WHERE id_venta=?")
+        .bind(id_venta)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        "UPDATE venta SET estado='anulada', total=0, estacionada_en=NULL WHERE id_venta=? AND estado='en_curso'",
+    )
+    .bind(id_venta)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Vuelto de un pago. `monto` es el neto que cuenta para la venta; sólo el
+/// efectivo admite que el cliente entregue más.
+pub fn vuelto_de_pago(medio: &str, monto: i64, monto_entregado: Option<i64>) -> Result<i64, String> {
+    let Some(entregado) = monto_entregado else {
+        return Ok(0);
+    };
+    if medio != "efectivo" && entregado != monto {
+        return Err(format!("Sólo el efectivo puede tener vuelto (medio '{medio}')"));
+    }
+    if entregado < monto {
+        return Err(format!("El monto entregado ({entregado}) es menor al cobrado ({monto})"));
+    }
+    Ok(entregado - monto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vuelto_solo_en_efectivo() {
+        assert_eq!(vuelto_de_pago("efectivo", 600, None), Ok(0));
+        assert_eq!(vuelto_de_pago("efectivo", 600, Some(1000)), Ok(400));
+        assert_eq!(vuelto_de_pago("efectivo", 600, Some(600)), Ok(0));
+        // en otro medio lo entregado tiene que ser lo cobrado
+        assert_eq!(vuelto_de_pago("debito", 600, Some(600)), Ok(0));
+        assert_eq!(
+            vuelto_de_pago("debito", 600, Some(700)),
+            Err("Sólo el efectivo puede tener vuelto (medio 'debito')".to_string())
+        );
+        assert_eq!(
+            vuelto_de_pago("efectivo", 600, Some(500)),
+            Err("El monto entregado (500) es menor al cobrado (600)".to_string())
+        );
+    }
+}
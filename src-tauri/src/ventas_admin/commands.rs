@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, SqlitePool};
 use tauri::State;
 
 use crate::AppState;
+use crate::users::permisos::requerir_admin;
+use super::revision::{listar_revisiones, VentaRevisionRow};
+use crate::ventas::descuentos::{descuentos_por_venta, VentaDescuentoRow};
 
 #[derive(Debug, Deserialize)]
 pub struct VentasAdminListarInput {
@@ -11,6 +14,7 @@ pub struct VentasAdminListarInput {
     pub id_usuario: Option<i64>,        // null = todos
     pub estado: Option<String>,         // null = todos (ej: "finalizada")
     pub medio: Option<String>,          // null = todos (ej: "efectivo")
+    pub id_cliente: Option<i64>,        // null = todos
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 }
@@ -28,12 +32,15 @@ pub struct VentaAdminResumenRow {
     pub id_usuario: i64,
     pub usuario: String,
     pub id_caja: i64,
+    pub id_cliente: Option<i64>,
+    pub cliente: Option<String>,
     pub total: i64,
     pub estado: String,
 
     pub cant_items: i64,
     pub unidades: i64,
     pub costo_total: i64,
+    pub descuento_total: i64,      // descuentos manuales (ya restados del total)
 
     pub ganancia_bruta: i64,       // total - costo_total
     pub margen_pct: f64,           // 0..100
@@ -51,18 +58,48 @@ pub struct VentaAdminItemRow {
     pub cantidad: i64,
     pub precio_unitario: i64,
     pub costo_unitario_en_venta: i64,
-    pub subtotal: i64,
+    pub bruto: i64,                // cantidad * precio_unitario
+    pub descuento: i64,
+    pub subtotal: i64,             // bruto - descuento
     pub fuente_precio: String,
+    pub promo_combo_id: Option<i64>,
+    pub promo_combo: Option<String>, // nombre del combo
+    pub promo_grupo_id: Option<String>,
 
     pub costo_linea: i64,          // cantidad * costo_unitario_en_venta
     pub ganancia_linea: i64,       // subtotal - costo_linea
+
+    pub alicuota_iva: f64,         // congelada al agregar la línea (0 = exento)
+    pub neto_linea: i64,           // subtotal sin IVA
+    pub iva_linea: i64,
+}
+
+/// Desglose de IVA de la venta por alícuota
+#[derive(Debug, Serialize, FromRow)]
+pub struct VentaAdminIvaRow {
+    pub alicuota_iva: f64,
+    pub neto: i64,
+    pub iva: i64,
+    pub bruto: i64,
 }
 
 #[derive(Debug, Serialize, FromRow)]
 pub struct VentaAdminPagoRow {
     pub medio: String,
-    pub monto: i64,
+    pub monto: i64, // neto
     pub referencia: Option<String>,
+    pub monto_entregado: Option<i64>,
+    pub vuelto: i64,
+    pub cuotas: i64,
+    pub recargo: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct VentaAdminAnulacionRow {
+    pub anulada_en: String,
+    pub anulada_por: String,
+    pub aprobada_por: Option<String>,
+    pub motivo: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -70,6 +107,10 @@ pub struct VentaAdminDetalle {
     pub resumen: VentaAdminResumenRow,
     pub items: Vec<VentaAdminItemRow>,
     pub pagos: Vec<VentaAdminPagoRow>,
+    pub anulacion: Option<VentaAdminAnulacionRow>, // sólo ventas finalizadas que se anularon
+    pub revisiones: Vec<VentaRevisionRow>,         // ediciones previas (ver venta_revision_diff)
+    pub descuentos: Vec<VentaDescuentoRow>,
+    pub iva: Vec<VentaAdminIvaRow>,
 }
 
 #[derive(Debug, Serialize)]
@@ -85,6 +126,8 @@ pub async fn ventas_admin_listar(
     state: State<'_, AppState>,
     input: VentasAdminListarInput,
 ) -> Result<Paginado<VentaAdminResumenRow>, String> {
+    requerir_admin(&state).await?;
+
     let pool = &state.pool;
 
     let limit = input.limit.unwrap_or(100).clamp(1, 500);
@@ -95,12 +138,15 @@ pub async fn ventas_admin_listar(
         r#"
         WITH
         ventas_filtradas AS (
-            SELECT v.id_venta, v.fecha_hora, v.id_usuario, u.nombre AS usuario, v.id_caja, v.total, v.estado
+            SELECT v.id_venta, v.fecha_hora, v.id_usuario, u.nombre AS usuario, v.id_caja,
+                   v.id_cliente, c.nombre AS cliente, v.total, v.estado
             FROM venta v
             JOIN usuario u ON u.id_usuario = v.id_usuario
+            LEFT JOIN cliente c ON c.id_cliente = v.id_cliente
             WHERE date(v.fecha_hora) BETWEEN date(?) AND date(?)
             AND (? IS NULL OR v.id_usuario = ?)
             AND (? IS NULL OR v.estado = ?)
+            AND (? IS NULL OR v.id_cliente = ?)
             AND (
                 ? IS NULL
                 OR EXISTS (
@@ -114,7 +160,8 @@ pub async fn ventas_admin_listar(
                 vi.id_venta,
                 COUNT(*) AS cant_items,
                 COALESCE(SUM(vi.cantidad), 0) AS unidades,
-                COALESCE(SUM(vi.cantidad * vi.costo_unitario_en_venta), 0) AS costo_total
+                COALESCE(SUM(vi.cantidad * vi.costo_unitario_en_venta), 0) AS costo_total,
+                COALESCE(SUM(vi.descuento), 0) AS descuento_total
             FROM venta_item vi
             GROUP BY vi.id_venta
         ),
@@ -131,12 +178,15 @@ pub async fn ventas_admin_listar(
             vf.id_usuario,
             vf.usuario,
             vf.id_caja,
+            vf.id_cliente,
+            vf.cliente,
             vf.total,
             vf.estado,
 
             COALESCE(ia.cant_items, 0) AS cant_items,
             COALESCE(ia.unidades, 0) AS unidades,
             COALESCE(ia.costo_total, 0) AS costo_total,
+            COALESCE(ia.descuento_total, 0) AS descuento_total,
 
             (vf.total - COALESCE(ia.costo_total, 0)) AS ganancia_bruta,
 
@@ -159,6 +209,8 @@ pub async fn ventas_admin_listar(
             .bind(input.id_usuario)
             .bind(input.estado.as_deref())
             .bind(input.estado.as_deref())
+            .bind(input.id_cliente)
+            .bind(input.id_cliente)
             .bind(input.medio.as_deref())
             .bind(input.medio.as_deref())
             .bind(limit)
@@ -175,6 +227,7 @@ pub async fn ventas_admin_listar(
         WHERE date(v.fecha_hora) BETWEEN date(?) AND date(?)
         AND (? IS NULL OR v.id_usuario = ?)
         AND (? IS NULL OR v.estado = ?)
+        AND (? IS NULL OR v.id_cliente = ?)
         AND (
             ? IS NULL
             OR EXISTS (
@@ -190,6 +243,8 @@ pub async fn ventas_admin_listar(
     .bind(input.id_usuario)
     .bind(input.estado.as_deref())
     .bind(input.estado.as_deref())
+    .bind(input.id_cliente)
+    .bind(input.id_cliente)
     .bind(input.medio.as_deref())
     .bind(input.medio.as_deref())
     .fetch_one(pool)
@@ -205,16 +260,23 @@ pub async fn venta_admin_detalle(
     state: State<'_, AppState>,
     id_venta: i64,
 ) -> Result<VentaAdminDetalle, String> {
-    let pool = &state.pool;
+    requerir_admin(&state).await?;
+
+    detalle_venta(&state.pool, id_venta).await
+}
 
+/// Detalle completo de una venta (también lo usa el ticket)
+pub(crate) async fn detalle_venta(pool: &SqlitePool, id_venta: i64) -> Result<VentaAdminDetalle, String> {
     // Resumen (misma lógica de arriba, pero para una venta)
     let resumen = sqlx::query_as::<_, VentaAdminResumenRow>(
         r#"
             WITH
             vbase AS (
-                SELECT v.id_venta, v.fecha_hora, v.id_usuario, u.nombre AS usuario, v.id_caja, v.total, v.estado
+                SELECT v.id_venta, v.fecha_hora, v.id_usuario, u.nombre AS usuario, v.id_caja,
+                       v.id_cliente, c.nombre AS cliente, v.total, v.estado
                 FROM venta v
                 JOIN usuario u ON u.id_usuario = v.id_usuario
+                LEFT JOIN cliente c ON c.id_cliente = v.id_cliente
                 WHERE v.id_venta = ?
             ),
             items_aggr AS (
@@ -222,7 +284,8 @@ pub async fn venta_admin_detalle(
                     vi.id_venta,
                     COUNT(*) AS cant_items,
                     COALESCE(SUM(vi.cantidad), 0) AS unidades,
-                    COALESCE(SUM(vi.cantidad * vi.costo_unitario_en_venta), 0) AS costo_total
+                    COALESCE(SUM(vi.cantidad * vi.costo_unitario_en_venta), 0) AS costo_total,
+                    COALESCE(SUM(vi.descuento), 0) AS descuento_total
                 FROM venta_item vi
                 WHERE vi.id_venta = ?
                 GROUP BY vi.id_venta
@@ -241,12 +304,15 @@ pub async fn venta_admin_detalle(
                 vb.id_usuario,
                 vb.usuario,
                 vb.id_caja,
+                vb.id_cliente,
+                vb.cliente,
                 vb.total,
                 vb.estado,
 
                 COALESCE(ia.cant_items, 0) AS cant_items,
                 COALESCE(ia.unidades, 0) AS unidades,
                 COALESCE(ia.costo_total, 0) AS costo_total,
+                COALESCE(ia.descuento_total, 0) AS descuento_total,
 
                 (vb.total - COALESCE(ia.costo_total, 0)) AS ganancia_bruta,
 
@@ -279,13 +345,24 @@ pub async fn venta_admin_detalle(
         vi.cantidad,
         vi.precio_unitario,
         vi.costo_unitario_en_venta,
+        (vi.cantidad * vi.precio_unitario) AS bruto,
+        vi.descuento,
         vi.subtotal,
         vi.fuente_precio,
+        vi.promo_combo_id,
+        pc.nombre AS promo_combo,
+        vi.promo_grupo_id,
 
         (vi.cantidad * vi.costo_unitario_en_venta) AS costo_linea,
-        (vi.subtotal - (vi.cantidad * vi.costo_unitario_en_venta)) AS ganancia_linea
+        (vi.subtotal - (vi.cantidad * vi.costo_unitario_en_venta)) AS ganancia_linea,
+
+        vi.alicuota_iva,
+        x.neto AS neto_linea,
+        x.iva AS iva_linea
     FROM venta_item vi
     JOIN producto p ON p.id_producto = vi.id_producto
+    JOIN v_venta_item_iva x ON x.id_item = vi.id_item
+    LEFT JOIN promo_combo pc ON pc.id_combo = vi.promo_combo_id
     WHERE vi.id_venta = ?
     ORDER BY vi.id_item ASC;
         "#
@@ -299,7 +376,7 @@ pub async fn venta_admin_detalle(
     //  Pagos
     let pagos = sqlx::query_as::<_, VentaAdminPagoRow>(
         r#"
-        SELECT medio, monto, referencia
+        SELECT medio, monto, referencia, monto_entregado, vuelto, cuotas, recargo
         FROM venta_pago
         WHERE id_venta = ?
         ORDER BY medio ASC;
@@ -310,7 +387,44 @@ pub async fn venta_admin_detalle(
     .await
     .map_err(|e| format!("venta_admin_detalle(pagos): {}", e))?;
 
-    Ok(VentaAdminDetalle { resumen, items, pagos })
+    //  Anulación
+    let anulacion = sqlx::query_as::<_, VentaAdminAnulacionRow>(
+        r#"
+        SELECT
+            v.anulada_en,
+            ua.nombre AS anulada_por,
+            uap.nombre AS aprobada_por,
+            v.anulacion_motivo AS motivo
+        FROM venta v
+        JOIN usuario ua ON ua.id_usuario = v.anulada_por
+        LEFT JOIN usuario uap ON uap.id_usuario = v.anulacion_aprobada_por
+        WHERE v.id_venta = ? AND v.anulada_en IS NOT NULL;
+        "#
+    )
+    .bind(id_venta)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("venta_admin_detalle(anulacion): {}", e))?;
+
+    //  IVA por alícuota
+    let iva = sqlx::query_as::<_, VentaAdminIvaRow>(
+        r#"
+        SELECT alicuota_iva, SUM(neto) AS neto, SUM(iva) AS iva, SUM(bruto) AS bruto
+        FROM v_venta_item_iva
+        WHERE id_venta = ?
+        GROUP BY alicuota_iva
+        ORDER BY alicuota_iva DESC;
+        "#
+    )
+    .bind(id_venta)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("venta_admin_detalle(iva): {}", e))?;
+
+    let revisiones = listar_revisiones(pool, id_venta).await?;
+    let descuentos = descuentos_por_venta(pool, id_venta).await?;
+
+    Ok(VentaAdminDetalle { resumen, items, pagos, anulacion, revisiones, descuentos, iva })
 }
 
 
@@ -318,6 +432,8 @@ pub async fn venta_admin_detalle(
 pub async fn usuarios_listar_operadores(
     state: State<'_, AppState>,
 ) -> Result<Vec<UsuarioOperadorRow>, String> {
+    requerir_admin(&state).await?;
+
     let pool = &state.pool;
     let rows = sqlx::query_as::<_, UsuarioOperadorRow>(
         r#"
@@ -1,7 +1,18 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use tauri::State;
-use sqlx::FromRow;
+use sqlx::{FromRow, SqliteConnection};
 use crate::AppState;
+use crate::users::permisos::requerir_admin;
+use crate::caja::cierre_z;
+use crate::cuenta_corriente::commands as cuenta_corriente;
+use crate::devoluciones::repo as devoluciones_repo;
+use crate::fiscal::repo as fiscal_repo;
+use crate::stock::repo as stock_repo;
+use crate::audit::repo as audit_repo;
+use crate::medios_pago::{model::CargosPago, repo as medios_pago_repo};
+use super::revision;
 
 #[derive(Debug, Serialize, FromRow)]
 pub struct ProductoBasico {
@@ -14,18 +25,27 @@ pub struct ProductoBasico {
 
 #[derive(Debug, Deserialize)]
 pub struct VentaEditarItemInput {
+    /// Línea original (None = agregada en la edición)
+    #[serde(default)]
+    pub id_item: Option<i64>,
     pub id_producto: i64,
     pub cantidad: i64,
     pub precio_unitario: i64,
     pub costo_unitario_en_venta: Option<i64>, // si viene None => usa costo_actual
     pub fuente_precio: String, // 'catalogo' | 'manual' | 'promo'
+    #[serde(default)]
+    pub descuento: i64, // ARS descontados en la línea (incluye su parte del de ticket)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct VentaEditarPagoInput {
-    pub medio: String, // 'efectivo' | 'debito' | 'credito' | 'transferencia'
-    pub monto: i64,
+    pub medio: String, // codigo de medio_pago
+    pub monto: i64, // neto
     pub referencia: Option<String>,
+    #[serde(default)]
+    pub monto_entregado: Option<i64>,
+    #[serde(default)]
+    pub cuotas: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,6 +53,68 @@ pub struct VentaEditarGuardarInput {
     pub id_venta: i64,
     pub items: Vec<VentaEditarItemInput>,
     pub pagos: Vec<VentaEditarPagoInput>,
+    pub motivo: String, // obligatorio: queda en venta_revision
+}
+
+// venta_descuento de la versión que se edita
+#[derive(Debug, FromRow)]
+struct DescuentoPrevio {
+    id_descuento: i64,
+    id_item: Option<i64>,
+    tipo: String,
+    valor: i64,
+    monto: i64,
+    motivo: String,
+    id_usuario: i64,
+    aprobado_por: Option<i64>,
+    fecha_hora: String,
+    id_regla: Option<i64>,
+}
+
+// venta_item de la versión que se edita: lo que la línea conserva aunque
+// cambien cantidad o precio (peso de balanza y combo aplicado)
+#[derive(Debug, FromRow)]
+struct LineaPrevia {
+    id_item: i64,
+    id_producto: i64,
+    descuento: i64,
+    gramos: Option<i64>,
+    promo_combo_id: Option<i64>,
+    promo_grupo_id: Option<String>,
+    promo_precio_total: Option<i64>,
+}
+
+/// Vuelve a cargar un descuento de línea sobre la línea nueva, tal cual era
+/// (quién lo hizo, aprobación y regla de origen incluidos).
+async fn copiar_descuento(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_item: i64,
+    d: &DescuentoPrevio,
+) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        INSERT INTO venta_descuento (
+          id_venta, id_item, tipo, valor, monto, motivo,
+          id_usuario, aprobado_por, fecha_hora, id_regla
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+        "#,
+    )
+    .bind(id_venta)
+    .bind(id_item)
+    .bind(&d.tipo)
+    .bind(d.valor)
+    .bind(d.monto)
+    .bind(&d.motivo)
+    .bind(d.id_usuario)
+    .bind(d.aprobado_por)
+    .bind(&d.fecha_hora)
+    .bind(d.id_regla)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("copiar descuento: {e}"))?;
+    Ok(())
 }
 
 #[tauri::command]
@@ -41,6 +123,8 @@ pub async fn producto_listar_basico(
     q: Option<String>,
     limit: Option<i64>,
 ) -> Result<Vec<ProductoBasico>, String> {
+    requerir_admin(&state).await?;
+
     let pool = &state.pool;
     let q = q.unwrap_or_default();
     let limit = limit.unwrap_or(50).clamp(1, 200);
@@ -73,10 +157,16 @@ pub async fn venta_admin_editar_guardar(
     state: State<'_, AppState>,
     input: VentaEditarGuardarInput,
 ) -> Result<(), String> {
+    let uid = requerir_admin(&state).await?.id_usuario;
+
     let pool = &state.pool;
     let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
 
     // Validaciones mínimas
+    let motivo = input.motivo.trim().to_string();
+    if motivo.is_empty() {
+        return Err("Indicá el motivo de la edición.".into());
+    }
     if input.items.is_empty() {
         return Err("La venta no puede quedar sin items.".into());
     }
@@ -92,6 +182,9 @@ pub async fn venta_admin_editar_guardar(
                 return Err("Costo inválido.".into());
             }
         }
+        if it.descuento < 0 || it.descuento > it.cantidad * it.precio_unitario {
+            return Err("Descuento inválido.".into());
+        }
     }
     for p in &input.pagos {
         if p.monto <= 0 {
@@ -99,6 +192,24 @@ pub async fn venta_admin_editar_guardar(
         }
     }
 
+    // las devoluciones apuntan a venta_item: no se puede reescribir la venta
+    if devoluciones_repo::venta_tiene_devoluciones(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("No se puede editar: la venta tiene devoluciones registradas.".into());
+    }
+    fiscal_repo::exigir_sin_factura(&mut tx, input.id_venta).await?;
+
+    // cobros previos (para ajustes si la caja ya tiene cierre Z)
+    let pagos_antes = cierre_z::pagos_por_medio(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| format!("pagos previos: {e}"))?;
+
+    let antes = audit_repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| format!("audit antes: {e}"))?;
+
     //bloquear edición
     let res = sqlx::query(
         r#"
@@ -118,7 +229,72 @@ pub async fn venta_admin_editar_guardar(
         return Err("No se puede editar: la venta no está en estado 'finalizada'.".into());
     }
 
-    // Reemplazar items
+    // versión anterior completa (items + pagos) antes de pisarla
+    revision::guardar_snapshot(&mut tx, input.id_venta, uid, &motivo).await?;
+
+    // costos de la versión anterior (para productos que desaparecen):
+    // costo total y unidades de stock (gramos en los de peso) vendidas
+    let costos_previos: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_producto, SUM(cantidad * costo_unitario_en_venta), SUM(cantidad * COALESCE(gramos, 1))
+        FROM venta_item
+        WHERE id_venta = ?
+        GROUP BY id_producto;
+        "#,
+    )
+    .bind(input.id_venta)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("costos previos: {e}"))?;
+
+    // alícuotas de la versión anterior: una línea editada no cambia de IVA
+    let alicuotas_previas: Vec<(i64, f64)> = sqlx::query_as(
+        "SELECT id_producto, MAX(alicuota_iva) FROM venta_item WHERE id_venta = ? GROUP BY id_producto;",
+    )
+    .bind(input.id_venta)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("alícuotas previas: {e}"))?;
+
+    // Descuentos: una línea cuyo descuento no cambió conserva sus filas de
+    // venta_descuento; si cambió, las de línea se reemplazan por un monto
+    // con el motivo de la edición. Los de ticket se conservan con lo que
+    // quede aplicado sobre las líneas.
+    let descuentos_previos: Vec<DescuentoPrevio> = sqlx::query_as(
+        r#"
+        SELECT id_descuento, id_item, tipo, valor, monto, motivo,
+               id_usuario, aprobado_por, fecha_hora, id_regla
+        FROM venta_descuento
+        WHERE id_venta = ?
+        ORDER BY id_descuento ASC;
+        "#,
+    )
+    .bind(input.id_venta)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("descuentos previos: {e}"))?;
+
+    let lineas_previas: Vec<LineaPrevia> = sqlx::query_as(
+        r#"
+        SELECT id_item, id_producto, descuento, gramos,
+               promo_combo_id, promo_grupo_id, promo_precio_total
+        FROM venta_item
+        WHERE id_venta = ?;
+        "#,
+    )
+    .bind(input.id_venta)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("líneas previas: {e}"))?;
+
+    sqlx::query("DELETE FROM venta_descuento WHERE id_venta = ? AND id_item IS NOT NULL;")
+        .bind(input.id_venta)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("delete descuentos: {e}"))?;
+
+    let mut ticket_aplicado = 0i64;
+
     sqlx::query("DELETE FROM venta_item WHERE id_venta = ?;")
         .bind(input.id_venta)
         .execute(&mut *tx)
@@ -139,15 +315,46 @@ pub async fn venta_admin_editar_guardar(
                 row
             }
         };
+        let alicuota_iva = match alicuotas_previas.iter().find(|(id, _)| *id == it.id_producto) {
+            Some((_, a)) => *a,
+            None => sqlx::query_scalar::<_, f64>("SELECT alicuota_iva FROM producto WHERE id_producto = ?;")
+                .bind(it.id_producto)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| format!("alícuota producto {}: {e}", it.id_producto))?,
+        };
 
-        sqlx::query(
+        let previa = lineas_previas
+            .iter()
+            .find(|l| Some(l.id_item) == it.id_item && l.id_producto == it.id_producto);
+        // una etiqueta de balanza conserva su peso
+        let gramos = previa.and_then(|l| l.gramos);
+        if gramos.is_none() {
+            let unidad_venta: String =
+                sqlx::query_scalar("SELECT unidad_venta FROM producto WHERE id_producto = ?;")
+                    .bind(it.id_producto)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(|e| format!("unidad producto {}: {e}", it.id_producto))?;
+            // sin etiqueta no hay peso: la cantidad movería gramos sueltos
+            if unidad_venta == "peso" {
+                return Err(format!(
+                    "El producto {} se vende por peso: sólo se editan sus líneas de balanza",
+                    it.id_producto
+                ));
+            }
+        }
+
+        let id_item: i64 = sqlx::query_scalar(
             r#"
             INSERT INTO venta_item(
               id_venta, id_producto, cantidad,
               precio_unitario, costo_unitario_en_venta,
-              fuente_precio, subtotal
+              fuente_precio, descuento, subtotal, alicuota_iva, gramos,
+              promo_combo_id, promo_grupo_id, promo_precio_total
             )
-            VALUES (?, ?, ?, ?, ?, ?, 0);
+            VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?, ?, ?)
+            RETURNING id_item;
             "#,
         )
         .bind(input.id_venta)
@@ -156,13 +363,172 @@ pub async fn venta_admin_editar_guardar(
         .bind(it.precio_unitario)
         .bind(costo)
         .bind(it.fuente_precio)
-        .execute(&mut *tx)
+        .bind(it.descuento)
+        .bind(alicuota_iva)
+        .bind(gramos)
+        // la línea sigue en su combo (y en el grupo del ticket)
+        .bind(previa.and_then(|l| l.promo_combo_id))
+        .bind(previa.and_then(|l| l.promo_grupo_id.as_deref()))
+        .bind(previa.and_then(|l| l.promo_precio_total))
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| format!("insert item: {e}"))?;
         // subtotal/total por triggers
+
+        let propios: Vec<&DescuentoPrevio> = match previa {
+            Some(l) => descuentos_previos.iter().filter(|d| d.id_item == Some(l.id_item)).collect(),
+            None => Vec::new(),
+        };
+        let de_linea: i64 = propios.iter().map(|d| d.monto).sum();
+        let parte_ticket = previa.map(|l| (l.descuento - de_linea).max(0)).unwrap_or(0);
+
+        if previa.map(|l| l.descuento) == Some(it.descuento) {
+            for d in propios {
+                copiar_descuento(&mut tx, input.id_venta, id_item, d).await?;
+            }
+            ticket_aplicado += parte_ticket;
+        } else {
+            let parte = parte_ticket.min(it.descuento);
+            ticket_aplicado += parte;
+            let propio = it.descuento - parte;
+            if propio > 0 {
+                sqlx::query(
+                    r#"
+                    INSERT INTO venta_descuento (id_venta, id_item, tipo, valor, monto, motivo, id_usuario)
+                    VALUES (?, ?, 'monto', ?, ?, ?, ?);
+                    "#,
+                )
+                .bind(input.id_venta)
+                .bind(id_item)
+                .bind(propio)
+                .bind(propio)
+                .bind(&motivo)
+                .bind(uid)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("insert descuento: {e}"))?;
+            }
+        }
+    }
+
+    // descuentos de ticket: en orden de carga, hasta lo que sigue aplicado
+    let mut resto = ticket_aplicado;
+    for d in descuentos_previos.iter().filter(|d| d.id_item.is_none()) {
+        let monto = d.monto.min(resto);
+        resto -= monto;
+        if monto != d.monto {
+            sqlx::query("UPDATE venta_descuento SET monto = ? WHERE id_descuento = ?;")
+                .bind(monto)
+                .bind(d.id_descuento)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("descuento de ticket: {e}"))?;
+        }
     }
 
-    // Reemplazar pagos
+    // Reconciliar stock: lo registrado en stock_mov para venta:{id} tiene
+    // que quedar igual a -(cantidad vendida) por producto
+    let referencia = format!("venta:{}", input.id_venta);
+
+    let registrado: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_producto, COALESCE(SUM(cantidad_delta), 0)
+        FROM stock_mov
+        WHERE referencia = ?
+        GROUP BY id_producto;
+        "#,
+    )
+    .bind(&referencia)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("stock registrado: {e}"))?;
+
+    let nuevos: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_producto, SUM(cantidad * COALESCE(gramos, 1)), SUM(cantidad * costo_unitario_en_venta)
+        FROM venta_item
+        WHERE id_venta = ?
+        GROUP BY id_producto;
+        "#,
+    )
+    .bind(input.id_venta)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("items nuevos: {e}"))?;
+
+    // id_producto -> (delta registrado, cantidad nueva, (costo, unidades) de referencia)
+    let mut conciliacion: BTreeMap<i64, (i64, i64, (i64, i64))> = BTreeMap::new();
+    for (id_producto, costo, unidades) in costos_previos {
+        conciliacion.entry(id_producto).or_default().2 = (costo, unidades);
+    }
+    for (id_producto, delta) in registrado {
+        conciliacion.entry(id_producto).or_default().0 = delta;
+    }
+    for (id_producto, cantidad, costo) in nuevos {
+        let e = conciliacion.entry(id_producto).or_default();
+        e.1 = cantidad;
+        e.2 = (costo, cantidad);
+    }
+
+    for (id_producto, (registrado, cantidad_nueva, (costo_base, unidades_base))) in conciliacion {
+        let ajuste = -cantidad_nueva - registrado;
+        if ajuste == 0 {
+            continue;
+        }
+
+        let unidad_venta: String =
+            sqlx::query_scalar("SELECT unidad_venta FROM producto WHERE id_producto = ?;")
+                .bind(id_producto)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| format!("unidad producto {id_producto}: {e}"))?;
+        let (costo, total_costo) =
+            stock_repo::costo_movimiento(costo_base, unidades_base, ajuste, unidad_venta == "peso");
+
+        sqlx::query(
+            r#"
+            INSERT INTO stock_mov (
+              id_producto, cantidad_delta, motivo, referencia,
+              costo_unitario, total_costo, fecha_hora
+            )
+            VALUES (?, ?, 'venta_edicion', ?, ?, ?, DATETIME('now','localtime'));
+            "#,
+        )
+        .bind(id_producto)
+        .bind(ajuste)
+        .bind(&referencia)
+        .bind(costo)
+        .bind(total_costo)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("stock_mov producto {id_producto}: {e}"))?;
+    }
+
+    // Reemplazar pagos (no si ya hay cobros de cuenta corriente imputados)
+    let imputados: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM cc_imputacion i JOIN venta_pago vp ON vp.id_pago = i.id_pago WHERE vp.id_venta = ?;",
+    )
+    .bind(input.id_venta)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if imputados > 0 {
+        return Err("La venta tiene cobros de cuenta corriente imputados: no se pueden cambiar sus pagos".into());
+    }
+
+    // Un pago igual a uno que ya tenía (medio, monto, cuotas) conserva el
+    // recargo y la comisión con que se cobró; los nuevos van a la tarifa de hoy.
+    let mut pagos_previos: Vec<(String, i64, CargosPago)> = sqlx::query_as::<_, (String, i64, i64, i64, i64)>(
+        "SELECT medio, monto, cuotas, recargo, comision FROM venta_pago WHERE id_venta = ? ORDER BY id_pago;",
+    )
+    .bind(input.id_venta)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("pagos previos: {e}"))?
+    .into_iter()
+    .map(|(medio, monto, cuotas, recargo, comision)| (medio, monto, CargosPago { cuotas, recargo, comision }))
+    .collect();
+
     sqlx::query("DELETE FROM venta_pago WHERE id_venta = ?;")
         .bind(input.id_venta)
         .execute(&mut *tx)
@@ -170,21 +536,38 @@ pub async fn venta_admin_editar_guardar(
         .map_err(|e| format!("delete pagos: {e}"))?;
 
     for p in input.pagos {
+        let vuelto = crate::ventas::repo::vuelto_de_pago(&p.medio, p.monto, p.monto_entregado)?;
+        let previo = pagos_previos.iter().position(|(medio, monto, c)| {
+            *medio == p.medio && *monto == p.monto && c.cuotas == p.cuotas.unwrap_or(1)
+        });
+        let cargos = match previo {
+            Some(i) => pagos_previos.remove(i).2,
+            None => medios_pago_repo::cargos_de_pago(&mut tx, &p.medio, p.cuotas, p.monto).await?,
+        };
         sqlx::query(
             r#"
-            INSERT INTO venta_pago(id_venta, medio, monto, referencia)
-            VALUES (?, ?, ?, ?);
+            INSERT INTO venta_pago(id_venta, medio, monto, referencia, monto_entregado, vuelto, cuotas, recargo, comision)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
             "#,
         )
         .bind(input.id_venta)
         .bind(p.medio)
         .bind(p.monto)
         .bind(p.referencia)
+        .bind(p.monto_entregado)
+        .bind(vuelto)
+        .bind(cargos.cuotas)
+        .bind(cargos.recargo)
+        .bind(cargos.comision)
         .execute(&mut *tx)
         .await
         .map_err(|e| format!("insert pago: {e}"))?;
     }
 
+    // fiado: mismo control que al cobrar (la venta está en curso, su cargo
+    // anterior no cuenta en el saldo); el exceso lo autoriza el admin que edita
+    cuenta_corriente::controlar(&state, &mut tx, uid, input.id_venta, None).await?;
+
     // Finalizar SOLO si está en_curso
     let res = sqlx::query(
         r#"
@@ -203,6 +586,424 @@ pub async fn venta_admin_editar_guardar(
         return Err("No se pudo finalizar: la venta no estaba en 'en_curso'.".into());
     }
 
+    // Caja cerrada: el Z no cambia, la diferencia queda como ajuste
+    cierre_z::registrar_ajustes(&mut tx, input.id_venta, &pagos_antes, "edicion", uid)
+        .await
+        .map_err(|e| format!("ajuste cierre Z: {e}"))?;
+
+    let mut despues = audit_repo::venta_snapshot(&mut tx, input.id_venta)
+        .await
+        .map_err(|e| format!("audit después: {e}"))?;
+    if let Some(serde_json::Value::Object(m)) = despues.as_mut() {
+        m.insert("motivo".into(), serde_json::json!(motivo));
+    }
+    audit_repo::registrar(
+        &mut *tx, Some(uid), "venta", Some(input.id_venta), "editar",
+        antes.as_ref(), despues.as_ref(),
+    )
+    .await
+    .map_err(|e| format!("audit: {e}"))?;
+
     tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::{descuentos, prueba};
+
+    fn edicion(v: serde_json::Value) -> VentaEditarGuardarInput {
+        serde_json::from_value(v).unwrap()
+    }
+
+    fn linea(id_producto: i64, cantidad: i64, precio: i64) -> serde_json::Value {
+        json!({
+            "id_producto": id_producto, "cantidad": cantidad,
+            "precio_unitario": precio, "fuente_precio": "catalogo"
+        })
+    }
+
+    #[tokio::test]
+    async fn editar_concilia_el_stock() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let leche = prueba::producto(pool, "leche", 500, 300, 10).await;
+        let azucar = prueba::producto(pool, "azucar", 800, 500, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 3), (leche, 1)], json!([
+            { "medio": "efectivo", "monto": 1400 }
+        ]))
+        .await;
+
+        // baja pan, saca leche y agrega azúcar
+        let input = json!({
+            "id_venta": id_venta,
+            "items": [linea(pan, 1, 300), linea(azucar, 2, 800)],
+            "pagos": [{ "medio": "efectivo", "monto": 1900 }],
+            "motivo": "se cargó mal"
+        });
+        venta_admin_editar_guardar(state.clone(), edicion(input.clone())).await.unwrap();
+        assert_eq!(prueba::stock(pool, pan).await, 9);
+        assert_eq!(prueba::stock(pool, leche).await, 10);
+        assert_eq!(prueba::stock(pool, azucar).await, 8);
+
+        // guardar lo mismo otra vez no mueve stock
+        let movs = |p: &'static str| {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM stock_mov WHERE motivo = ?1").bind(p)
+        };
+        let antes = movs("venta_edicion").fetch_one(pool).await.unwrap();
+        venta_admin_editar_guardar(state.clone(), edicion(input)).await.unwrap();
+        assert_eq!(movs("venta_edicion").fetch_one(pool).await.unwrap(), antes);
+        assert_eq!(prueba::stock(pool, azucar).await, 8);
+
+        let (estado, total): (String, i64) =
+            sqlx::query_as("SELECT estado, total FROM venta WHERE id_venta = ?1")
+                .bind(id_venta)
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        assert_eq!((estado.as_str(), total), ("finalizada", 1900));
+    }
+
+    #[tokio::test]
+    async fn editar_rechaza_venta_con_devoluciones_o_en_curso() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        let id_caja = prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "efectivo", "monto": 600 }])).await;
+        let devolucion = serde_json::from_value(json!({ "id_venta": id_venta })).unwrap();
+        devoluciones_repo::crear_devolucion(pool, id_caja, 1, devolucion).await.unwrap();
+
+        let input = |id: i64| edicion(json!({
+            "id_venta": id,
+            "items": [linea(pan, 1, 300)],
+            "pagos": [{ "medio": "efectivo", "monto": 300 }],
+            "motivo": "ajuste"
+        }));
+        let err = venta_admin_editar_guardar(state.clone(), input(id_venta)).await.unwrap_err();
+        assert!(err.contains("devoluciones"), "{err}");
+
+        let carrito = prueba::carrito(&state, &[(pan, 1)]).await;
+        let err = venta_admin_editar_guardar(state.clone(), input(carrito)).await.unwrap_err();
+        assert!(err.contains("no está en estado 'finalizada'"), "{err}");
+        assert_eq!(prueba::stock(pool, pan).await, 10);
+    }
+
+    // (ticket, tipo, valor, monto, motivo, id_usuario, aprobado_por, id_regla)
+    type FilaDescuento = (bool, String, i64, i64, String, i64, Option<i64>, Option<i64>);
+
+    async fn filas_descuento(pool: &sqlx::SqlitePool, id_venta: i64) -> Vec<FilaDescuento> {
+        let mut filas: Vec<FilaDescuento> = sqlx::query_as(
+            "SELECT id_item IS NULL, tipo, valor, monto, motivo, id_usuario, aprobado_por, id_regla
+               FROM venta_descuento WHERE id_venta = ?1",
+        )
+        .bind(id_venta)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        filas.sort();
+        filas
+    }
+
+    async fn lineas_actuales(pool: &sqlx::SqlitePool, id_venta: i64) -> Vec<serde_json::Value> {
+        let filas: Vec<(i64, i64, i64, i64, i64)> = sqlx::query_as(
+            "SELECT id_item, id_producto, cantidad, precio_unitario, descuento
+               FROM venta_item WHERE id_venta = ?1 ORDER BY id_item",
+        )
+        .bind(id_venta)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        filas
+            .into_iter()
+            .map(|(id_item, id_producto, cantidad, precio, descuento)| json!({
+                "id_item": id_item, "id_producto": id_producto, "cantidad": cantidad,
+                "precio_unitario": precio, "fuente_precio": "catalogo", "descuento": descuento
+            }))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn editar_conserva_el_combo_de_cada_linea() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let cafe = prueba::producto(pool, "cafe", 800, 300, 10).await;
+        let medialuna = prueba::producto(pool, "medialuna", 400, 100, 10).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_combo: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_combo (nombre, precio_pack) VALUES ('desayuno', 1000) RETURNING id_combo",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO promo_combo_item (id_combo, id_producto, cantidad) VALUES (?1, ?2, 1), (?1, ?3, 1)")
+            .bind(id_combo)
+            .bind(cafe)
+            .bind(medialuna)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let id_venta = prueba::carrito(&state, &[(pan, 1)]).await;
+        let aplicar = serde_json::from_value(json!({
+            "id_venta": id_venta, "id_combo": id_combo, "precio_total_pack": 0
+        }))
+        .unwrap();
+        crate::ventas::commands::venta_aplicar_promo_combo(state.clone(), aplicar).await.unwrap();
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 1300 }]))
+            .await
+            .unwrap();
+
+        let combos = || {
+            sqlx::query_as::<_, (i64, Option<i64>, Option<String>, Option<i64>)>(
+                "SELECT id_producto, promo_combo_id, promo_grupo_id, promo_precio_total
+                   FROM venta_item WHERE id_venta = ?1 ORDER BY id_producto",
+            )
+            .bind(id_venta)
+        };
+        let antes = combos().fetch_all(pool).await.unwrap();
+        assert_eq!(antes.iter().filter(|l| l.1 == Some(id_combo)).count(), 2);
+
+        // se corrige el pan: las líneas del combo siguen en su grupo
+        let mut items = lineas_actuales(pool, id_venta).await;
+        for it in items.iter_mut() {
+            if it["id_producto"] == json!(pan) {
+                it["cantidad"] = json!(2);
+            } else {
+                it["fuente_precio"] = json!("promo");
+            }
+        }
+        let input = json!({
+            "id_venta": id_venta,
+            "items": items,
+            "pagos": [{ "medio": "efectivo", "monto": 1600 }],
+            "motivo": "eran dos panes"
+        });
+        venta_admin_editar_guardar(state.clone(), edicion(input)).await.unwrap();
+
+        assert_eq!(combos().fetch_all(pool).await.unwrap(), antes);
+    }
+
+    #[tokio::test]
+    async fn editar_conserva_los_descuentos_de_la_venta() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let leche = prueba::producto(pool, "leche", 500, 300, 10).await;
+        let id_regla: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_regla (nombre, tipo, id_producto, lleva, paga)
+             VALUES ('2x1 pan', 'nxm', ?1, 2, 1) RETURNING id_regla",
+        )
+        .bind(pan)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        // 3 panes con 2x1 (300 off) + leche, y 100 de descuento de ticket
+        let id_venta = prueba::carrito(&state, &[(pan, 3), (leche, 1)]).await;
+        let ticket = serde_json::from_value(json!({
+            "id_venta": id_venta, "tipo": "monto", "valor": 100, "motivo": "cliente habitual"
+        }))
+        .unwrap();
+        descuentos::venta_descuento_aplicar(state.clone(), ticket).await.unwrap();
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 1000 }]))
+            .await
+            .unwrap();
+
+        // la regla se da de baja: la venta cerrada conserva su descuento
+        sqlx::query("UPDATE promo_regla SET activo = 0 WHERE id_regla = ?1")
+            .bind(id_regla)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let antes = filas_descuento(pool, id_venta).await;
+        assert!(antes.iter().any(|f| f.7 == Some(id_regla) && f.3 == 300));
+
+        // sólo cambia el pago: los descuentos quedan iguales
+        let input = json!({
+            "id_venta": id_venta,
+            "items": lineas_actuales(pool, id_venta).await,
+            "pagos": [{ "medio": "debito", "monto": 1000 }],
+            "motivo": "cobrado con débito"
+        });
+        venta_admin_editar_guardar(state.clone(), edicion(input)).await.unwrap();
+        assert_eq!(filas_descuento(pool, id_venta).await, antes);
+
+        // se saca la leche: el de ticket queda con la parte del pan
+        let pan_linea: Vec<serde_json::Value> = lineas_actuales(pool, id_venta)
+            .await
+            .into_iter()
+            .filter(|l| l["id_producto"] == json!(pan))
+            .collect();
+        let desc_pan = pan_linea[0]["descuento"].as_i64().unwrap();
+        let input = json!({
+            "id_venta": id_venta,
+            "items": pan_linea,
+            "pagos": [{ "medio": "debito", "monto": 900 - desc_pan }],
+            "motivo": "la leche no se llevó"
+        });
+        venta_admin_editar_guardar(state.clone(), edicion(input)).await.unwrap();
+
+        let despues = filas_descuento(pool, id_venta).await;
+        let regla = despues.iter().find(|f| f.7 == Some(id_regla)).expect("descuento de la regla");
+        assert_eq!((regla.3, regla.5), (300, 1));
+        let de_ticket: i64 = despues.iter().filter(|f| f.0).map(|f| f.3).sum();
+        assert_eq!(de_ticket, desc_pan - 300);
+        assert_eq!(despues.iter().map(|f| f.3).sum::<i64>(), desc_pan);
+
+        // un descuento cambiado a mano queda como monto con el motivo de la edición
+        let mut linea = lineas_actuales(pool, id_venta).await.remove(0);
+        linea["descuento"] = json!(desc_pan + 50);
+        let input = json!({
+            "id_venta": id_venta,
+            "items": [linea],
+            "pagos": [{ "medio": "debito", "monto": 900 - desc_pan - 50 }],
+            "motivo": "bonificación"
+        });
+        venta_admin_editar_guardar(state.clone(), edicion(input)).await.unwrap();
+        let filas = filas_descuento(pool, id_venta).await;
+        assert!(filas.iter().all(|f| f.7.is_none()));
+        let manual = filas.iter().find(|f| !f.0).unwrap();
+        assert_eq!((manual.3, manual.4.as_str()), (350, "bonificación"));
+        assert_eq!(filas.iter().map(|f| f.3).sum::<i64>(), desc_pan + 50);
+    }
+
+    #[tokio::test]
+    async fn editar_conserva_los_cargos_de_los_pagos() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 500, 100, 10).await;
+        sqlx::query(
+            "INSERT INTO medio_pago_plan (codigo_medio, cuotas, recargo_pct, comision_pct) VALUES ('credito', 3, 10, 2)",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([
+            { "medio": "credito", "monto": 1000, "cuotas": 3 }
+        ]))
+        .await;
+
+        // cambia la tarifa y el medio se da de baja
+        sqlx::query("UPDATE medio_pago_plan SET recargo_pct = 20 WHERE codigo_medio = 'credito'")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE medio_pago SET activo = 0 WHERE codigo = 'credito'")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let cargos = || {
+            sqlx::query_as::<_, (String, i64, i64, i64)>(
+                "SELECT medio, cuotas, recargo, comision FROM venta_pago WHERE id_venta = ?1 ORDER BY id_pago",
+            )
+            .bind(id_venta)
+            .fetch_all(pool)
+        };
+
+        // el pago de crédito sigue igual: conserva lo cobrado; el débito es nuevo
+        venta_admin_editar_guardar(state.clone(), edicion(json!({
+            "id_venta": id_venta,
+            "items": [linea(pan, 3, 500)],
+            "pagos": [
+                { "medio": "credito", "monto": 1000, "cuotas": 3 },
+                { "medio": "debito", "monto": 500 },
+            ],
+            "motivo": "faltó un pan"
+        })))
+        .await
+        .unwrap();
+        assert_eq!(
+            cargos().await.unwrap(),
+            vec![("credito".to_string(), 3, 100, 22), ("debito".to_string(), 1, 0, 0)]
+        );
+
+        // un pago de crédito distinto se cobra hoy, y el medio ya no está activo
+        let err = venta_admin_editar_guardar(state.clone(), edicion(json!({
+            "id_venta": id_venta,
+            "items": [linea(pan, 3, 500)],
+            "pagos": [{ "medio": "credito", "monto": 1500, "cuotas": 3 }],
+            "motivo": "todo con crédito"
+        })))
+        .await
+        .unwrap_err();
+        assert_eq!(err, "El medio de pago 'credito' está inactivo");
+    }
+
+    #[tokio::test]
+    async fn editar_a_cuenta_corriente_controla_el_credito() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 500, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([{ "medio": "efectivo", "monto": 1000 }])).await;
+
+        let a_cuenta = || edicion(json!({
+            "id_venta": id_venta,
+            "items": [linea(pan, 2, 500)],
+            "pagos": [{ "medio": "cuenta_corriente", "monto": 1000 }],
+            "motivo": "era fiado"
+        }));
+
+        let err = venta_admin_editar_guardar(state.clone(), a_cuenta()).await.unwrap_err();
+        assert_eq!(err, "Para vender en cuenta corriente hay que asignar un cliente a la venta");
+
+        let id_cliente: i64 = sqlx::query_scalar("INSERT INTO cliente (nombre) VALUES ('Marta') RETURNING id_cliente")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE venta SET id_cliente = ?1 WHERE id_venta = ?2")
+            .bind(id_cliente)
+            .bind(id_venta)
+            .execute(pool)
+            .await
+            .unwrap();
+        let err = venta_admin_editar_guardar(state.clone(), a_cuenta()).await.unwrap_err();
+        assert_eq!(err, "El cliente no tiene cuenta corriente habilitada");
+
+        // se pasa del límite: lo autoriza el admin que edita y queda auditado
+        sqlx::query("UPDATE cliente SET limite_credito = 800 WHERE id_cliente = ?1")
+            .bind(id_cliente)
+            .execute(pool)
+            .await
+            .unwrap();
+        venta_admin_editar_guardar(state.clone(), a_cuenta()).await.unwrap();
+        let excedido: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM audit_evento WHERE entidad = 'venta' AND id_entidad = ?1 AND accion = 'credito_excedido'",
+        )
+        .bind(id_venta)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(excedido, 1);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod commands;
-pub mod editar;
\ No newline at end of file
+pub mod editar;
+pub mod revision;
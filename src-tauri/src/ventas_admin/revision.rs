@@ -0,0 +1,600 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
+use tauri::State;
+
+use crate::AppState;
+use crate::users::permisos::requerir_admin;
+
+// Cada edición admin guarda la versión que se pisa. La versión actual es la
+// que está en venta_item / venta_pago (número = última revisión + 1).
+// Los campos con default son los que no existían en revisiones viejas.
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RevisionItem {
+    #[serde(default)]
+    pub id_item: Option<i64>, // para ubicar los descuentos de línea
+    pub id_producto: i64,
+    pub producto: String,
+    pub cantidad: i64,
+    pub precio_unitario: i64,
+    pub costo_unitario_en_venta: i64,
+    pub subtotal: i64,
+    pub fuente_precio: String,
+    #[serde(default)]
+    pub descuento: i64,
+    #[serde(default = "alicuota_general")]
+    pub alicuota_iva: f64,
+    #[serde(default)]
+    pub gramos: Option<i64>,
+    #[serde(default)]
+    pub promo_combo_id: Option<i64>,
+    #[serde(default)]
+    pub promo_grupo_id: Option<String>,
+    #[serde(default)]
+    pub promo_precio_total: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RevisionDescuento {
+    pub id_item: Option<i64>, // None = ticket
+    pub tipo: String,
+    pub valor: i64,
+    pub monto: i64,
+    pub motivo: String,
+    pub id_usuario: i64,
+    pub aprobado_por: Option<i64>,
+    pub id_regla: Option<i64>,
+    pub fecha_hora: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RevisionPago {
+    pub medio: String,
+    pub monto: i64,
+    pub referencia: Option<String>,
+    #[serde(default)]
+    pub monto_entregado: Option<i64>,
+    #[serde(default)]
+    pub vuelto: i64,
+    #[serde(default = "una_cuota")]
+    pub cuotas: i64,
+    #[serde(default)]
+    pub recargo: i64,
+    #[serde(default)]
+    pub comision: i64,
+}
+
+fn alicuota_general() -> f64 {
+    21.0
+}
+
+fn una_cuota() -> i64 {
+    1
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct VentaRevisionRow {
+    pub id_revision: i64,
+    pub numero: i64,
+    pub total: i64,
+    pub id_usuario: i64,
+    pub usuario: String,
+    pub motivo: String,
+    pub fecha_hora: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemDiff {
+    pub id_producto: i64,
+    pub producto: String,
+    pub cambio: String, // 'agregado' | 'quitado' | 'modificado'
+    pub cantidad_antes: i64,
+    pub cantidad_despues: i64,
+    pub subtotal_antes: i64,
+    pub subtotal_despues: i64,
+    pub descuento_antes: i64,
+    pub descuento_despues: i64,
+    pub en_combo_antes: i64, // unidades dentro de un combo
+    pub en_combo_despues: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DescuentoDiff {
+    pub alcance: String, // producto o 'ticket'
+    pub tipo: String,
+    pub valor: i64,
+    pub motivo: String,
+    pub cambio: String, // 'agregado' | 'quitado' | 'modificado'
+    pub monto_antes: i64,
+    pub monto_despues: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PagoDiff {
+    pub medio: String,
+    pub monto_antes: i64,
+    pub monto_despues: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VentaRevisionDiff {
+    pub id_venta: i64,
+    pub version_desde: i64,
+    pub version_hasta: i64,
+    pub total_antes: i64,
+    pub total_despues: i64,
+    pub items: Vec<ItemDiff>,
+    pub pagos: Vec<PagoDiff>,
+    pub descuentos: Vec<DescuentoDiff>,
+}
+
+struct Version {
+    total: i64,
+    items: Vec<RevisionItem>,
+    pagos: Vec<RevisionPago>,
+    descuentos: Vec<RevisionDescuento>,
+}
+
+async fn version_actual(conn: &mut SqliteConnection, id_venta: i64) -> Result<Version, String> {
+    let total: i64 = sqlx::query_scalar("SELECT total FROM venta WHERE id_venta = ?;")
+        .bind(id_venta)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Venta inexistente.".to_string())?;
+
+    let items = sqlx::query_as::<_, RevisionItem>(
+        r#"
+        SELECT vi.id_item, vi.id_producto, p.nombre AS producto, vi.cantidad, vi.precio_unitario,
+               vi.costo_unitario_en_venta, vi.subtotal, vi.fuente_precio,
+               vi.descuento, vi.alicuota_iva, vi.gramos,
+               vi.promo_combo_id, vi.promo_grupo_id, vi.promo_precio_total
+        FROM venta_item vi
+        JOIN producto p ON p.id_producto = vi.id_producto
+        WHERE vi.id_venta = ?
+        ORDER BY vi.id_item ASC;
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("revision(items): {e}"))?;
+
+    let pagos = sqlx::query_as::<_, RevisionPago>(
+        r#"
+        SELECT medio, monto, referencia, monto_entregado, vuelto, cuotas, recargo, comision
+        FROM venta_pago
+        WHERE id_venta = ?
+        ORDER BY id_pago ASC;
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("revision(pagos): {e}"))?;
+
+    let descuentos = sqlx::query_as::<_, RevisionDescuento>(
+        r#"
+        SELECT id_item, tipo, valor, monto, motivo, id_usuario, aprobado_por, id_regla, fecha_hora
+        FROM venta_descuento
+        WHERE id_venta = ?
+        ORDER BY id_descuento ASC;
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("revision(descuentos): {e}"))?;
+
+    Ok(Version { total, items, pagos, descuentos })
+}
+
+/// Guarda la versión vigente de la venta antes de pisarla. Devuelve el número.
+pub(crate) async fn guardar_snapshot(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    id_usuario: i64,
+    motivo: &str,
+) -> Result<i64, String> {
+    let v = version_actual(conn, id_venta).await?;
+
+    let items_json = serde_json::to_string(&v.items).map_err(|e| e.to_string())?;
+    let pagos_json = serde_json::to_string(&v.pagos).map_err(|e| e.to_string())?;
+    let descuentos_json = serde_json::to_string(&v.descuentos).map_err(|e| e.to_string())?;
+
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO venta_revision (id_venta, numero, total, items_json, pagos_json, descuentos_json, id_usuario, motivo)
+        VALUES (
+          ?1,
+          (SELECT COALESCE(MAX(numero), 0) + 1 FROM venta_revision WHERE id_venta = ?1),
+          ?2, ?3, ?4, ?5, ?6, ?7
+        )
+        RETURNING numero;
+        "#,
+    )
+    .bind(id_venta)
+    .bind(v.total)
+    .bind(items_json)
+    .bind(pagos_json)
+    .bind(descuentos_json)
+    .bind(id_usuario)
+    .bind(motivo)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| format!("insert venta_revision: {e}"))
+}
+
+pub(crate) async fn listar_revisiones(
+    pool: &SqlitePool,
+    id_venta: i64,
+) -> Result<Vec<VentaRevisionRow>, String> {
+    sqlx::query_as::<_, VentaRevisionRow>(
+        r#"
+        SELECT r.id_revision, r.numero, r.total, r.id_usuario, u.nombre AS usuario,
+               r.motivo, r.fecha_hora
+        FROM venta_revision r
+        JOIN usuario u ON u.id_usuario = r.id_usuario
+        WHERE r.id_venta = ?
+        ORDER BY r.numero ASC;
+        "#,
+    )
+    .bind(id_venta)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("listar_revisiones: {e}"))
+}
+
+async fn cargar_version(
+    conn: &mut SqliteConnection,
+    id_venta: i64,
+    numero: i64,
+) -> Result<Option<Version>, String> {
+    let row: Option<(i64, String, String, String)> = sqlx::query_as(
+        "SELECT total, items_json, pagos_json, descuentos_json FROM venta_revision WHERE id_venta = ? AND numero = ?;",
+    )
+    .bind(id_venta)
+    .bind(numero)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some((total, items_json, pagos_json, descuentos_json)) = row else { return Ok(None) };
+
+    Ok(Some(Version {
+        total,
+        items: serde_json::from_str(&items_json).map_err(|e| format!("items_json: {e}"))?,
+        pagos: serde_json::from_str(&pagos_json).map_err(|e| format!("pagos_json: {e}"))?,
+        descuentos: serde_json::from_str(&descuentos_json).map_err(|e| format!("descuentos_json: {e}"))?,
+    }))
+}
+
+#[derive(Default)]
+struct Totales {
+    cantidad: i64,
+    subtotal: i64,
+    descuento: i64,
+    en_combo: i64,
+}
+
+impl Totales {
+    fn sumar(&mut self, it: &RevisionItem) {
+        self.cantidad += it.cantidad;
+        self.subtotal += it.subtotal;
+        self.descuento += it.descuento;
+        if it.promo_combo_id.is_some() {
+            self.en_combo += it.cantidad;
+        }
+    }
+
+    fn igual(&self, o: &Totales) -> bool {
+        (self.cantidad, self.subtotal, self.descuento, self.en_combo)
+            == (o.cantidad, o.subtotal, o.descuento, o.en_combo)
+    }
+}
+
+// alcance (producto o 'ticket'), tipo, valor, motivo
+type ClaveDescuento = (String, String, i64, String);
+
+/// Monto de cada definición de la versión, agrupado por clave
+fn descuentos_por_clave(v: &Version) -> BTreeMap<ClaveDescuento, Vec<i64>> {
+    let mut out: BTreeMap<ClaveDescuento, Vec<i64>> = BTreeMap::new();
+    for d in &v.descuentos {
+        let alcance = match d.id_item {
+            None => "ticket".to_string(),
+            Some(id) => v
+                .items
+                .iter()
+                .find(|it| it.id_item == Some(id))
+                .map(|it| it.producto.clone())
+                .unwrap_or_else(|| format!("línea {id}")),
+        };
+        out.entry((alcance, d.tipo.clone(), d.valor, d.motivo.clone())).or_default().push(d.monto);
+    }
+    out
+}
+
+fn diff_versiones(antes: &Version, despues: &Version) -> (Vec<ItemDiff>, Vec<PagoDiff>, Vec<DescuentoDiff>) {
+    let mut prods: BTreeMap<i64, (String, Totales, Totales)> = BTreeMap::new();
+    for it in &antes.items {
+        let e = prods.entry(it.id_producto).or_insert_with(|| (it.producto.clone(), Totales::default(), Totales::default()));
+        e.1.sumar(it);
+    }
+    for it in &despues.items {
+        let e = prods.entry(it.id_producto).or_insert_with(|| (it.producto.clone(), Totales::default(), Totales::default()));
+        e.2.sumar(it);
+    }
+
+    let items = prods
+        .into_iter()
+        .filter(|(_, (_, a, d))| !a.igual(d))
+        .map(|(id_producto, (producto, a, d))| ItemDiff {
+            id_producto,
+            producto,
+            cambio: if a.cantidad == 0 {
+                "agregado"
+            } else if d.cantidad == 0 {
+                "quitado"
+            } else {
+                "modificado"
+            }
+            .to_string(),
+            cantidad_antes: a.cantidad,
+            cantidad_despues: d.cantidad,
+            subtotal_antes: a.subtotal,
+            subtotal_despues: d.subtotal,
+            descuento_antes: a.descuento,
+            descuento_despues: d.descuento,
+            en_combo_antes: a.en_combo,
+            en_combo_despues: d.en_combo,
+        })
+        .collect();
+
+    let mut medios: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for p in &antes.pagos {
+        medios.entry(p.medio.clone()).or_default().0 += p.monto;
+    }
+    for p in &despues.pagos {
+        medios.entry(p.medio.clone()).or_default().1 += p.monto;
+    }
+
+    let pagos = medios
+        .into_iter()
+        .filter(|(_, (a, d))| a != d)
+        .map(|(medio, (monto_antes, monto_despues))| PagoDiff { medio, monto_antes, monto_despues })
+        .collect();
+
+    // un descuento es el mismo si coincide alcance, tipo, valor y motivo
+    // (vacío = no estaba en esa versión)
+    let mut claves: BTreeMap<ClaveDescuento, (Vec<i64>, Vec<i64>)> = BTreeMap::new();
+    for (k, m) in descuentos_por_clave(antes) {
+        claves.entry(k).or_default().0 = m;
+    }
+    for (k, m) in descuentos_por_clave(despues) {
+        claves.entry(k).or_default().1 = m;
+    }
+
+    let descuentos = claves
+        .into_iter()
+        .filter(|(_, (a, d))| a != d)
+        .map(|((alcance, tipo, valor, motivo), (a, d))| DescuentoDiff {
+            alcance,
+            tipo,
+            valor,
+            motivo,
+            cambio: if a.is_empty() {
+                "agregado"
+            } else if d.is_empty() {
+                "quitado"
+            } else {
+                "modificado"
+            }
+            .to_string(),
+            monto_antes: a.iter().sum(),
+            monto_despues: d.iter().sum(),
+        })
+        .collect();
+
+    (items, pagos, descuentos)
+}
+
+#[tauri::command]
+pub async fn venta_revision_listar(
+    state: State<'_, AppState>,
+    id_venta: i64,
+) -> Result<Vec<VentaRevisionRow>, String> {
+    requerir_admin(&state).await?;
+    listar_revisiones(&state.pool, id_venta).await
+}
+
+/// Diferencias entre dos versiones de la venta.
+/// `hasta` = None => contra la versión actual.
+#[tauri::command]
+pub async fn venta_revision_diff(
+    state: State<'_, AppState>,
+    id_venta: i64,
+    desde: i64,
+    hasta: Option<i64>,
+) -> Result<VentaRevisionDiff, String> {
+    requerir_admin(&state).await?;
+
+    let mut conn = state.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    let ultima: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(numero), 0) FROM venta_revision WHERE id_venta = ?;",
+    )
+    .bind(id_venta)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    let actual = ultima + 1;
+
+    let hasta = hasta.unwrap_or(actual);
+    if desde < 1 || hasta > actual || desde >= hasta {
+        return Err(format!("Rango de versiones inválido (1..{actual})"));
+    }
+
+    let cargar = |v: Option<Version>, n: i64| v.ok_or_else(|| format!("Versión {n} inexistente"));
+
+    let antes = cargar(cargar_version(&mut conn, id_venta, desde).await?, desde)?;
+    let despues = if hasta == actual {
+        version_actual(&mut conn, id_venta).await?
+    } else {
+        cargar(cargar_version(&mut conn, id_venta, hasta).await?, hasta)?
+    };
+
+    let (items, pagos, descuentos) = diff_versiones(&antes, &despues);
+
+    Ok(VentaRevisionDiff {
+        id_venta,
+        version_desde: desde,
+        version_hasta: hasta,
+        total_antes: antes.total,
+        total_despues: despues.total,
+        items,
+        pagos,
+        descuentos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Manager;
+
+    use super::*;
+    use crate::users::permisos::Rol;
+    use crate::ventas::prueba;
+    use crate::ventas_admin::editar;
+
+    #[test]
+    fn revision_vieja_sin_campos_nuevos() {
+        let items: Vec<RevisionItem> = serde_json::from_value(json!([{
+            "id_producto": 1, "producto": "pan", "cantidad": 2, "precio_unitario": 300,
+            "costo_unitario_en_venta": 100, "subtotal": 600, "fuente_precio": "catalogo"
+        }]))
+        .unwrap();
+        assert_eq!((items[0].descuento, items[0].alicuota_iva), (0, 21.0));
+        assert_eq!((items[0].id_item, items[0].gramos, items[0].promo_combo_id), (None, None, None));
+
+        let pagos: Vec<RevisionPago> =
+            serde_json::from_value(json!([{ "medio": "efectivo", "monto": 600, "referencia": null }])).unwrap();
+        assert_eq!((pagos[0].monto_entregado, pagos[0].vuelto, pagos[0].cuotas), (None, 0, 1));
+    }
+
+    #[tokio::test]
+    async fn la_revision_guarda_descuentos_y_cobro() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let pan = prueba::producto(pool, "pan", 300, 100, 10).await;
+        let id_venta = prueba::venta(&state, &[(pan, 2)], json!([
+            { "medio": "efectivo", "monto": 600, "monto_entregado": 1000 }
+        ]))
+        .await;
+
+        let input = serde_json::from_value(json!({
+            "id_venta": id_venta,
+            "items": [{
+                "id_producto": pan, "cantidad": 2, "precio_unitario": 300,
+                "fuente_precio": "catalogo", "descuento": 100
+            }],
+            "pagos": [{ "medio": "debito", "monto": 500 }],
+            "motivo": "descuento olvidado"
+        }))
+        .unwrap();
+        editar::venta_admin_editar_guardar(state.clone(), input).await.unwrap();
+
+        let revisiones = venta_revision_listar(state.clone(), id_venta).await.unwrap();
+        assert_eq!(revisiones.len(), 1);
+        assert_eq!((revisiones[0].numero, revisiones[0].total), (1, 600));
+
+        let mut conn = pool.acquire().await.unwrap();
+        let v1 = cargar_version(&mut conn, id_venta, 1).await.unwrap().unwrap();
+        assert_eq!(v1.pagos[0].monto_entregado, Some(1000));
+        assert_eq!(v1.pagos[0].vuelto, 400);
+        assert!(v1.descuentos.is_empty());
+        assert!(v1.items[0].id_item.is_some());
+        let actual = version_actual(&mut conn, id_venta).await.unwrap();
+        assert_eq!((actual.items[0].descuento, actual.items[0].subtotal), (100, 500));
+        assert_eq!(actual.descuentos[0].id_item, actual.items[0].id_item);
+        drop(conn);
+
+        let diff = venta_revision_diff(state.clone(), id_venta, 1, None).await.unwrap();
+        assert_eq!((diff.total_antes, diff.total_despues), (600, 500));
+        assert_eq!(diff.items[0].cambio, "modificado");
+        assert_eq!((diff.items[0].descuento_antes, diff.items[0].descuento_despues), (0, 100));
+        let descuentos: Vec<(&str, &str, &str, i64, i64)> = diff
+            .descuentos
+            .iter()
+            .map(|d| (d.alcance.as_str(), d.motivo.as_str(), d.cambio.as_str(), d.monto_antes, d.monto_despues))
+            .collect();
+        assert_eq!(descuentos, vec![("pan", "descuento olvidado", "agregado", 0, 100)]);
+        let medios: Vec<(&str, i64, i64)> =
+            diff.pagos.iter().map(|p| (p.medio.as_str(), p.monto_antes, p.monto_despues)).collect();
+        assert_eq!(medios, vec![("debito", 0, 500), ("efectivo", 600, 0)]);
+    }
+
+    #[tokio::test]
+    async fn la_revision_guarda_el_combo_y_se_ve_en_el_diff() {
+        let app = tauri::test::mock_app();
+        app.manage(AppState::prueba(Some((1, Rol::Admin))).await);
+        let state = app.state::<AppState>();
+        let pool = &state.pool;
+
+        prueba::caja(pool, 1, 0).await;
+        let cafe = prueba::producto(pool, "cafe", 800, 300, 10).await;
+        let medialuna = prueba::producto(pool, "medialuna", 400, 100, 10).await;
+        let id_combo: i64 = sqlx::query_scalar(
+            "INSERT INTO promo_combo (nombre, precio_pack) VALUES ('desayuno', 1000) RETURNING id_combo",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO promo_combo_item (id_combo, id_producto, cantidad) VALUES (?1, ?2, 1), (?1, ?3, 1)")
+            .bind(id_combo)
+            .bind(cafe)
+            .bind(medialuna)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let id_venta = prueba::carrito(&state, &[]).await;
+        let aplicar = serde_json::from_value(json!({
+            "id_venta": id_venta, "id_combo": id_combo, "precio_total_pack": 0
+        }))
+        .unwrap();
+        crate::ventas::commands::venta_aplicar_promo_combo(state.clone(), aplicar).await.unwrap();
+        prueba::finalizar(&state, id_venta, json!([{ "medio": "efectivo", "monto": 1000 }]))
+            .await
+            .unwrap();
+
+        // se deshace el combo: café y medialuna a precio de lista
+        let input = serde_json::from_value(json!({
+            "id_venta": id_venta,
+            "items": [
+                { "id_producto": cafe, "cantidad": 1, "precio_unitario": 800, "fuente_precio": "catalogo" },
+                { "id_producto": medialuna, "cantidad": 1, "precio_unitario": 400, "fuente_precio": "catalogo" }
+            ],
+            "pagos": [{ "medio": "efectivo", "monto": 1200 }],
+            "motivo": "el combo no correspondía"
+        }))
+        .unwrap();
+        editar::venta_admin_editar_guardar(state.clone(), input).await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        let v1 = cargar_version(&mut conn, id_venta, 1).await.unwrap().unwrap();
+        assert!(v1.items.iter().all(|it| it.promo_combo_id == Some(id_combo) && it.promo_grupo_id.is_some()));
+        drop(conn);
+
+        let diff = venta_revision_diff(state.clone(), id_venta, 1, None).await.unwrap();
+        let combos: Vec<(i64, i64, i64)> =
+            diff.items.iter().map(|d| (d.id_producto, d.en_combo_antes, d.en_combo_despues)).collect();
+        assert_eq!(combos, vec![(cafe, 1, 0), (medialuna, 1, 0)]);
+    }
+}
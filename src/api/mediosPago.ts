@@ -0,0 +1,38 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/* TIPOS */
+
+export type MedioPagoPlanRow = {
+  codigo_medio: string;
+  cuotas: number; // > 1
+  recargo_pct: number;
+  comision_pct: number;
+};
+
+export type MedioPagoRow = {
+  codigo: string; // ej: 'efectivo', 'qr', 'cuenta_corriente'
+  nombre: string;
+  recargo_pct: number;
+  comision_pct: number;
+  activo: number;
+  orden: number;
+  planes: MedioPagoPlanRow[];
+};
+
+/* CALLS (invoke) */
+
+// el POS pide solo los activos
+export const medioPagoListar = (solo_activos = true) =>
+  invoke<MedioPagoRow[]>("medio_pago_listar", { soloActivos: solo_activos });
+
+/* HELPERS */
+
+// recargo al cliente por cobrar `monto` en `cuotas` (mismo redondeo que el backend)
+export function recargoDePago(medio: MedioPagoRow | undefined, cuotas: number, monto: number): number {
+  if (!medio || monto <= 0) return 0;
+  const pct =
+    cuotas > 1
+      ? medio.planes.find((p) => p.cuotas === cuotas)?.recargo_pct ?? 0
+      : medio.recargo_pct;
+  return Math.round((monto * pct) / 100);
+}
@@ -14,6 +14,8 @@ export type PromoComboCrearInput = {
   items: PromoComboItemInput[];
 };
 
+export type PromoComboEstado = "vigente" | "proxima" | "vencida" | "inactiva";
+
 export type PromoComboRow = {
   id_combo: number;
   nombre: string;
@@ -21,6 +23,21 @@ export type PromoComboRow = {
   precio_min_total: number;
   activo: number;
   creado_en: string;
+  vigente_desde: string | null; // "YYYY-MM-DD", inclusive
+  vigente_hasta: string | null;
+  dias_semana: number; // bit 0 = domingo ... bit 6 = sábado
+  hora_desde: string | null; // "HH:MM"
+  hora_hasta: string | null;
+  estado: PromoComboEstado;
+  en_horario: boolean;
+};
+
+// combos activos separados por su vigencia de hoy
+export type PromoComboListado = {
+  vigentes: PromoComboRow[]; // se pueden aplicar ahora
+  fuera_de_horario: PromoComboRow[];
+  proximas: PromoComboRow[];
+  vencidas: PromoComboRow[];
 };
 
 export type PromoComboItemRow = {
@@ -40,7 +57,7 @@ export type PromoComboDetalle = {
 /* CALLS (invoke) */
 
 export const promoComboListar = () =>
-  invoke<PromoComboRow[]>("promo_combo_listar");
+  invoke<PromoComboListado>("promo_combo_listar");
 
 export const promoComboDetalle = (id_combo: number) =>
   invoke<PromoComboDetalle>("promo_combo_detalle", { idCombo: id_combo });
@@ -57,4 +74,21 @@ export const ventaAplicarPromoCombo = (input: {
 
   export async function promoComboEliminar(id_combo: number): Promise<void> {
   await invoke("promo_combo_eliminar", { idCombo: id_combo});
-}
\ No newline at end of file
+}
+
+/* HELPERS */
+
+// todos los combos del listado, en el orden en que se muestran
+export const combosDelListado = (l: PromoComboListado): PromoComboRow[] => [
+  ...l.vigentes,
+  ...l.fuera_de_horario,
+  ...l.proximas,
+  ...l.vencidas,
+];
+
+export function etiquetaVigencia(c: PromoComboRow): string {
+  if (c.estado === "proxima") return `Desde ${c.vigente_desde}`;
+  if (c.estado === "vencida") return `Vencida el ${c.vigente_hasta}`;
+  if (!c.en_horario) return "Fuera de horario";
+  return "Vigente";
+}
@@ -41,6 +41,7 @@ export type StockResumen = {
   precio_venta_actual: number;
   costo_actual: number;
   activo: number; // 1 | 0
+  unidad_venta: UnidadVenta;
 };
 
 export type StockListarInput = {
@@ -55,6 +56,13 @@ export const stockListar = (input: StockListarInput) =>
 
 /* PRODUCTO ACTIVO (alta/baja lógica) */
 
+/* UNIDAD DE VENTA ("peso" = stock en gramos, precio y costo por kg) */
+
+export type UnidadVenta = "unidad" | "peso";
+
+export const productoUnidadVentaFijar = (id_producto: number, unidad_venta: UnidadVenta) =>
+  invoke<void>("producto_unidad_venta_fijar", { input: { id_producto, unidad_venta } });
+
 export type ProductoSetActivoIn = {
   id_producto: number;
   activo: boolean;
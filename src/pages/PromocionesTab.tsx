@@ -29,7 +29,8 @@ export default function PromocionesTab({
   );
 
   const recargarPromos = async () => {
-    const data = await promoComboListar();
+    // se aplican al carrito: sólo las que rigen ahora
+    const data = (await promoComboListar()).vigentes;
     setPromos(data);
 
     // Si la promo seleccionada ya no existe (por eliminación), limpiamos
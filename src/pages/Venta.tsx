@@ -10,6 +10,7 @@ import {
   type PromoComboRow,
   type PromoComboDetalle,
 } from "../api/promos";
+import { medioPagoListar, recargoDePago, type MedioPagoRow } from "../api/mediosPago";
 
 type ProductoDisponible = {
   id_producto: number;
@@ -28,7 +29,25 @@ type Linea = {
   subtotal: number;
 };
 
-type MedioPago = "efectivo" | "debito" | "credito" | "transferencia";
+type MedioPago = string; // codigo de medio_pago
+
+// faltante según la política de stock (ver stock/politica.rs)
+type StockAdvertencia = {
+  id_producto: number;
+  nombre: string;
+  stock_actual: number;
+  requerido: number;
+  faltante: number;
+  politica: "advertir" | "bloquear";
+  autorizado_por: number | null;
+};
+
+type Aprobacion = { nombre_usuario: string; password: string };
+
+// lo que se agrega al input cuando la política bloquea y hay que autorizar
+type AutorizacionStock = { forzar_stock: boolean; aprobacion: Aprobacion | null };
+
+const ERR_STOCK_INSUFICIENTE = "Stock insuficiente";
 
 export default function VentasPage() {
   const [productos, setProductos] = useState<ProductoDisponible[]>([]);
@@ -47,10 +66,15 @@ export default function VentasPage() {
 
   const hayVentaEnCurso = idVenta != null && (carrito?.lineas?.length ?? 0) > 0;
 
+  const [medios, setMedios] = useState<MedioPagoRow[]>([]);
   const [medioPago, setMedioPago] = useState<MedioPago>("efectivo");
+  const [cuotas, setCuotas] = useState<number>(1);
   const [pagoMixto, setPagoMixto] = useState<boolean>(false);
   const [medioPago2, setMedioPago2] = useState<MedioPago>("debito");
+  const [cuotas2, setCuotas2] = useState<number>(1);
   const [montoSegundo, setMontoSegundo] = useState<number>(0);
+  const [montoEntregado, setMontoEntregado] = useState<number>(0);
+  const [ultimoVuelto, setUltimoVuelto] = useState<{ entregado: number; vuelto: number } | null>(null);
 
   const [confirmCerrarOpen, setConfirmCerrarOpen] = useState<boolean>(false);
   const [confirmAbrirOpen, setConfirmAbrirOpen] = useState<boolean>(false);
@@ -76,6 +100,15 @@ export default function VentasPage() {
 
   const [confirmLogoutOpen, setConfirmLogoutOpen] = useState(false);
 
+  const [advertencias, setAdvertencias] = useState<StockAdvertencia[]>([]);
+  const [bloqueoStock, setBloqueoStock] = useState<{
+    detalle: string;
+    reintentar: (auth: AutorizacionStock) => Promise<void>;
+  } | null>(null);
+  const [aprobUsuario, setAprobUsuario] = useState<string>("");
+  const [aprobPassword, setAprobPassword] = useState<string>("");
+  const [errorAprobacion, setErrorAprobacion] = useState<string | null>(null);
+
   const logout = useCallback(async () => {
     try {
       await invoke("auth_logout");
@@ -115,11 +148,20 @@ export default function VentasPage() {
     }
   }, []);
 
+  const cargarMedios = useCallback(async () => {
+    try {
+      setMedios(await medioPagoListar());
+    } catch (err) {
+      console.error("Error cargando medios de pago:", err);
+    }
+  }, []);
+
   const cargarCombos = useCallback(async () => {
     try {
       setCargandoCombos(true);
+      // en el POS sólo lo que se puede aplicar ahora
       const data = await promoComboListar();
-      setCombos(data);
+      setCombos(data.vigentes);
       setErrorCombos(null);
     } catch (e) {
       console.error(e);
@@ -134,8 +176,64 @@ export default function VentasPage() {
       input: { id_venta: id },
     });
     setCarrito({ total, lineas });
+    const faltantes = await invoke<StockAdvertencia[]>("venta_stock_verificar", {
+      idVenta: id,
+    });
+    setAdvertencias(faltantes);
   }, []);
 
+  // Corre la operación; si la política de stock la bloquea, abre el pedido de
+  // autorización y la reintenta desde ahí con forzar_stock / aprobación.
+  const conControlStock = useCallback(
+    async (op: (auth: AutorizacionStock) => Promise<void>) => {
+      try {
+        await op({ forzar_stock: false, aprobacion: null });
+      } catch (e) {
+        const msg = String(e);
+        if (!msg.startsWith(ERR_STOCK_INSUFICIENTE)) throw e;
+        setAprobUsuario("");
+        setAprobPassword("");
+        setErrorAprobacion(null);
+        setBloqueoStock({ detalle: msg, reintentar: op });
+      }
+    },
+    []
+  );
+
+  const autorizarStock = useCallback(async () => {
+    if (!bloqueoStock) return;
+    const usuario = aprobUsuario.trim();
+    try {
+      setBusy(true);
+      // sin credenciales: vale si la sesión es de un admin
+      await bloqueoStock.reintentar({
+        forzar_stock: true,
+        aprobacion: usuario ? { nombre_usuario: usuario, password: aprobPassword } : null,
+      });
+      setBloqueoStock(null);
+    } catch (e) {
+      setErrorAprobacion(String(e));
+    } finally {
+      setBusy(false);
+    }
+  }, [bloqueoStock, aprobUsuario, aprobPassword]);
+
+  const setCantidad = useCallback(
+    async (id_item: number, cantidad: number) => {
+      try {
+        await conControlStock(async (auth) => {
+          await invoke<StockAdvertencia[]>("venta_set_cantidad", {
+            input: { id_item, cantidad, ...auth },
+          });
+          if (idVenta) await refrescarCarrito(idVenta);
+        });
+      } catch (e) {
+        alert("No se pudo cambiar la cantidad:\n" + String(e));
+      }
+    },
+    [conControlStock, idVenta, refrescarCarrito]
+  );
+
   const asegurarVenta = useCallback(async () => {
     if (idVenta != null) return idVenta;
     const id = await invoke<number>("venta_iniciar");
@@ -162,7 +260,8 @@ export default function VentasPage() {
     cargarProductos();
     cargarCombos();
     cargarEstadoCaja();
-  }, [cargarProductos, cargarCombos, cargarEstadoCaja]);
+    cargarMedios();
+  }, [cargarProductos, cargarCombos, cargarEstadoCaja, cargarMedios]);
 
   const abrirCaja = useCallback(async () => {
     setBusy(true);
@@ -239,22 +338,24 @@ export default function VentasPage() {
         setBusy(true);
         const id_venta = await asegurarVenta();
 
-        await invoke("venta_agregar_item", {
-          input: {
-            id_venta,
-            id_producto: p.id_producto,
-            cantidad: 1,
-          },
+        await conControlStock(async (auth) => {
+          await invoke<StockAdvertencia[]>("venta_agregar_item", {
+            input: {
+              id_venta,
+              id_producto: p.id_producto,
+              cantidad: 1,
+              ...auth,
+            },
+          });
+          await refrescarCarrito(id_venta);
         });
-
-        await refrescarCarrito(id_venta);
       } catch (e) {
         alert("No se pudo agregar el producto:\n" + String(e));
       } finally {
         setBusy(false);
       }
     },
-    [asegurarVenta, refrescarCarrito]
+    [asegurarVenta, conControlStock, refrescarCarrito]
   );
 
   const abrirAplicarCombo = useCallback(
@@ -334,13 +435,23 @@ export default function VentasPage() {
         medio: MedioPago;
         monto: number;
         referencia: string | null;
+        monto_entregado?: number | null;
+        cuotas?: number | null;
       }[] = [];
 
       if (!pagoMixto) {
+        const entregado = Math.trunc(montoEntregado) || 0;
+        if (medioPago === "efectivo" && entregado > 0 && entregado < carrito.total) {
+          alert("El monto entregado es menor que el total.");
+          return;
+        }
+
         pagos.push({
           medio: medioPago,
           monto: carrito.total,
           referencia: null,
+          monto_entregado: medioPago === "efectivo" && entregado > 0 ? entregado : null,
+          cuotas: cuotas > 1 ? cuotas : null,
         });
       } else {
         const segundo = Math.trunc(montoSegundo) || 0;
@@ -353,24 +464,47 @@ export default function VentasPage() {
           return;
         }
 
-        pagos.push({ medio: medioPago, monto: primero, referencia: null });
-        pagos.push({ medio: medioPago2, monto: segundo, referencia: null });
+        pagos.push({
+          medio: medioPago,
+          monto: primero,
+          referencia: null,
+          cuotas: cuotas > 1 ? cuotas : null,
+        });
+        pagos.push({
+          medio: medioPago2,
+          monto: segundo,
+          referencia: null,
+          cuotas: cuotas2 > 1 ? cuotas2 : null,
+        });
       }
 
-      await invoke("venta_finalizar", {
-        input: {
-          id_venta: idVenta,
-          pagos,
-        },
+      await conControlStock(async (auth) => {
+        const res = await invoke<{
+          total: number;
+          entregado: number;
+          vuelto: number;
+          advertencias: StockAdvertencia[];
+        }>("venta_finalizar", {
+          input: {
+            id_venta: idVenta,
+            pagos,
+            ...auth,
+          },
+        });
+        setUltimoVuelto(res.vuelto > 0 ? { entregado: res.entregado, vuelto: res.vuelto } : null);
+
+        await cargarProductos();
+
+        setIdVenta(null);
+        setCarrito({ total: 0, lineas: [] });
+        setAdvertencias([]);
+        setPagoMixto(false);
+        setMontoSegundo(0);
+        setMontoEntregado(0);
+        setCuotas(1);
+        setCuotas2(1);
+        setVentaOkOpen(true);
       });
-
-      await cargarProductos();
-
-      setIdVenta(null);
-      setCarrito({ total: 0, lineas: [] });
-      setPagoMixto(false);
-      setMontoSegundo(0);
-      setVentaOkOpen(true);
     } catch (e) {
       alert("Error al registrar venta:\n" + String(e));
     } finally {
@@ -381,8 +515,12 @@ export default function VentasPage() {
     carrito.total,
     medioPago,
     medioPago2,
+    cuotas,
+    cuotas2,
     pagoMixto,
     montoSegundo,
+    montoEntregado,
+    conControlStock,
     cargarProductos,
   ]);
 
@@ -395,6 +533,7 @@ export default function VentasPage() {
       });
       setIdVenta(null);
       setCarrito({ total: 0, lineas: [] });
+      setAdvertencias([]);
     } catch (e) {
       alert("No se pudo cancelar la venta:\n" + String(e));
     } finally {
@@ -418,6 +557,13 @@ export default function VentasPage() {
 
   const totalItems = carrito.lineas.reduce((a, l) => a + l.cantidad, 0);
 
+  const medioSel = medios.find((m) => m.codigo === medioPago);
+  const medioSel2 = medios.find((m) => m.codigo === medioPago2);
+  const montoSegundoNeto = pagoMixto ? Math.trunc(montoSegundo) || 0 : 0;
+  const recargo =
+    recargoDePago(medioSel, cuotas, carrito.total - montoSegundoNeto) +
+    recargoDePago(medioSel2, cuotas2, montoSegundoNeto);
+
   return (
     <div className="flex min-h-screen bg-gradient-to-b from-yellow-50 via-white/80 to-white/95">
       <main className="flex-1 mx-auto px-6 md:px-8 py-7 space-y-5 max-w-[1600px] 2xl:max-w-[1800px]">
@@ -650,6 +796,23 @@ export default function VentasPage() {
               </div>
             </div>
 
+            {advertencias.length > 0 && (
+              <div className="mx-5 mt-4 rounded-lg border border-amber-200 bg-amber-50 p-2 text-sm text-amber-800">
+                <div className="font-semibold mb-1">Stock insuficiente</div>
+                <ul className="space-y-0.5">
+                  {advertencias.map((a) => (
+                    <li key={a.id_producto}>
+                      {a.nombre}: stock {a.stock_actual}, pedido {a.requerido} (faltan{" "}
+                      {a.faltante})
+                      {a.politica === "bloquear" && (
+                        <span className="text-red-700"> · requiere autorización al cobrar</span>
+                      )}
+                    </li>
+                  ))}
+                </ul>
+              </div>
+            )}
+
             <div className="max-h-[360px] overflow-auto">
               {carrito.lineas.length === 0 ? (
                 <div className="px-5 py-6 text-sm text-gray-500">
@@ -672,13 +835,7 @@ export default function VentasPage() {
                             <button
                               className="h-8 w-8 rounded-md border border-gray-300 bg-white hover:bg-gray-50 disabled:opacity-50"
                               disabled={busy}
-                              onClick={async () => {
-                                const nueva = l.cantidad - 1;
-                                await invoke("venta_set_cantidad", {
-                                  input: { id_item: l.id_item, cantidad: nueva },
-                                });
-                                if (idVenta) await refrescarCarrito(idVenta);
-                              }}
+                              onClick={() => setCantidad(l.id_item, l.cantidad - 1)}
                             >
                               -
                             </button>
@@ -697,18 +854,11 @@ export default function VentasPage() {
                                 let nueva = Number(raw);
                                 if (nueva < 1) nueva = 1;
 
-                                await invoke("venta_set_cantidad", {
-                                  input: { id_item: l.id_item, cantidad: nueva },
-                                });
-
-                                if (idVenta) await refrescarCarrito(idVenta);
+                                await setCantidad(l.id_item, nueva);
                               }}
                               onBlur={async (e) => {
                                 if (e.target.value === "") {
-                                  await invoke("venta_set_cantidad", {
-                                    input: { id_item: l.id_item, cantidad: 1 },
-                                  });
-                                  if (idVenta) await refrescarCarrito(idVenta);
+                                  await setCantidad(l.id_item, 1);
                                 }
                               }}
                             />
@@ -716,13 +866,7 @@ export default function VentasPage() {
                             <button
                               className="h-8 w-8 rounded-md border border-gray-300 bg-white hover:bg-gray-50 disabled:opacity-50"
                               disabled={busy}
-                              onClick={async () => {
-                                const nueva = l.cantidad + 1;
-                                await invoke("venta_set_cantidad", {
-                                  input: { id_item: l.id_item, cantidad: nueva },
-                                });
-                                if (idVenta) await refrescarCarrito(idVenta);
-                              }}
+                              onClick={() => setCantidad(l.id_item, l.cantidad + 1)}
                             >
                               +
                             </button>
@@ -777,16 +921,50 @@ export default function VentasPage() {
                   </label>
                   <select
                     value={medioPago}
-                    onChange={(e) => setMedioPago(e.target.value as MedioPago)}
+                    onChange={(e) => {
+                      setMedioPago(e.target.value);
+                      setCuotas(1);
+                    }}
                     className="w-full h-9 rounded-lg border border-gray-300 bg-white px-3 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-400 focus:border-yellow-400"
                   >
-                    <option value="efectivo">Efectivo</option>
-                    <option value="debito">Débito</option>
-                    <option value="credito">Crédito</option>
-                    <option value="transferencia">Transferencia</option>
+                    {medios.map((m) => (
+                      <option key={m.codigo} value={m.codigo}>
+                        {m.nombre}
+                      </option>
+                    ))}
                   </select>
                 </div>
 
+                {medioSel && medioSel.planes.length > 0 && (
+                  <SelectorCuotas medio={medioSel} cuotas={cuotas} onChange={setCuotas} />
+                )}
+
+                {medioPago === "efectivo" && !pagoMixto && (
+                  <div>
+                    <label className="block text-xs font-medium text-gray-600 mb-1">
+                      Entregado (opcional)
+                    </label>
+                    <input
+                      type="number"
+                      min={0}
+                      value={montoEntregado === 0 ? "" : montoEntregado}
+                      onChange={(e) => {
+                        const v = Number(e.target.value);
+                        setMontoEntregado(Number.isFinite(v) ? v : 0);
+                      }}
+                      className="w-full h-9 rounded-lg border border-gray-300 bg-white px-3 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-400 focus:border-yellow-400"
+                    />
+                    {montoEntregado > carrito.total && (
+                      <div className="mt-1 text-xs text-gray-700">
+                        Vuelto:{" "}
+                        <span className="font-mono tabular-nums font-semibold">
+                          $ {Math.trunc(montoEntregado) - carrito.total}
+                        </span>
+                      </div>
+                    )}
+                  </div>
+                )}
+
                 <label className="inline-flex items-center gap-2 text-xs text-gray-700">
                   <input
                     type="checkbox"
@@ -805,16 +983,24 @@ export default function VentasPage() {
                       </label>
                       <select
                         value={medioPago2}
-                        onChange={(e) => setMedioPago2(e.target.value as MedioPago)}
+                        onChange={(e) => {
+                          setMedioPago2(e.target.value);
+                          setCuotas2(1);
+                        }}
                         className="w-full h-9 rounded-lg border border-gray-300 bg-white px-3 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-400 focus:border-yellow-400"
                       >
-                        <option value="efectivo">Efectivo</option>
-                        <option value="debito">Débito</option>
-                        <option value="credito">Crédito</option>
-                        <option value="transferencia">Transferencia</option>
+                        {medios.map((m) => (
+                          <option key={m.codigo} value={m.codigo}>
+                            {m.nombre}
+                          </option>
+                        ))}
                       </select>
                     </div>
 
+                    {medioSel2 && medioSel2.planes.length > 0 && (
+                      <SelectorCuotas medio={medioSel2} cuotas={cuotas2} onChange={setCuotas2} />
+                    )}
+
                     <div>
                       <label className="block text-xs font-medium text-gray-600 mb-1">
                         Monto segundo método
@@ -843,6 +1029,14 @@ export default function VentasPage() {
                   $ {carrito.total}
                 </span>
               </div>
+              {recargo > 0 && (
+                <div className="flex items-baseline justify-between mb-3 text-sm text-gray-700">
+                  <span>Recargo por medio de pago</span>
+                  <span className="font-mono tabular-nums">
+                    + $ {recargo} (cobrar $ {carrito.total + recargo})
+                  </span>
+                </div>
+              )}
 
               <div className="flex justify-end gap-2">
                 <button
@@ -971,6 +1165,18 @@ export default function VentasPage() {
               La operación se registró correctamente. El carrito quedó vacío y
               podés iniciar una nueva venta.
             </p>
+            {ultimoVuelto && (
+              <div className="mb-5 rounded-xl border border-neutral-200 bg-neutral-50 px-3 py-2 text-sm">
+                <div className="flex justify-between">
+                  <span className="text-neutral-600">Entregado</span>
+                  <span className="font-mono tabular-nums">$ {ultimoVuelto.entregado}</span>
+                </div>
+                <div className="flex justify-between font-semibold">
+                  <span>Vuelto</span>
+                  <span className="font-mono tabular-nums">$ {ultimoVuelto.vuelto}</span>
+                </div>
+              </div>
+            )}
             <div className="flex justify-end">
               <button
                 onClick={() => setVentaOkOpen(false)}
@@ -1080,6 +1286,66 @@ export default function VentasPage() {
         </div>
       )}
 
+      {bloqueoStock && (
+        <div className="fixed inset-0 z-[140] flex items-center justify-center">
+          <div
+            className="absolute inset-0 bg-black/40 backdrop-blur-[1px]"
+            onClick={() => {
+              if (busy) return;
+              setBloqueoStock(null);
+            }}
+          />
+          <div className="relative w-full max-w-sm mx-4 rounded-2xl border border-neutral-200 bg-white shadow-2xl p-5">
+            <h2 className="text-base font-semibold mb-2">Autorizar venta sin stock</h2>
+            <p className="text-sm text-neutral-600 mb-3">{bloqueoStock.detalle}</p>
+            <p className="text-xs text-neutral-500 mb-3">
+              Un administrador tiene que autorizarlo. Si la sesión es de un
+              administrador, dejá los datos vacíos.
+            </p>
+
+            <div className="grid gap-2 mb-3">
+              <input
+                type="text"
+                placeholder="Usuario administrador"
+                value={aprobUsuario}
+                onChange={(e) => setAprobUsuario(e.target.value)}
+                className="w-full h-9 rounded-lg border border-gray-300 bg-white px-3 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-400 focus:border-yellow-400"
+              />
+              <input
+                type="password"
+                placeholder="Contraseña"
+                value={aprobPassword}
+                onChange={(e) => setAprobPassword(e.target.value)}
+                className="w-full h-9 rounded-lg border border-gray-300 bg-white px-3 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-400 focus:border-yellow-400"
+              />
+            </div>
+
+            {errorAprobacion && (
+              <div className="mb-3 rounded-lg border border-red-200 bg-red-50 p-2 text-sm text-red-700">
+                {errorAprobacion}
+              </div>
+            )}
+
+            <div className="flex justify-end gap-2">
+              <button
+                disabled={busy}
+                onClick={() => setBloqueoStock(null)}
+                className="h-9 px-3 rounded-xl border border-neutral-200 bg-white hover:bg-neutral-50 font-semibold disabled:opacity-60"
+              >
+                Cancelar
+              </button>
+              <button
+                disabled={busy}
+                onClick={autorizarStock}
+                className="h-9 px-3 rounded-xl border border-red-600 text-white bg-red-600 hover:brightness-95 disabled:opacity-60 font-semibold"
+              >
+                Autorizar
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+
       {confirmCancelarVentaOpen && (
         <div className="fixed inset-0 z-[105] flex items-center justify-center">
           <div
@@ -1115,3 +1381,34 @@ export default function VentasPage() {
     </div>
   );
 }
+
+// 1 pago o uno de los planes en cuotas del medio
+function SelectorCuotas({
+  medio,
+  cuotas,
+  onChange,
+}: {
+  medio: MedioPagoRow;
+  cuotas: number;
+  onChange: (cuotas: number) => void;
+}) {
+  return (
+    <div>
+      <label className="block text-xs font-medium text-gray-600 mb-1">Cuotas</label>
+      <select
+        value={cuotas}
+        onChange={(e) => onChange(Number(e.target.value))}
+        className="w-full h-9 rounded-lg border border-gray-300 bg-white px-3 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-400 focus:border-yellow-400"
+      >
+        <option value={1}>
+          1 pago{medio.recargo_pct > 0 ? ` (+${medio.recargo_pct}%)` : ""}
+        </option>
+        {medio.planes.map((p) => (
+          <option key={p.cuotas} value={p.cuotas}>
+            {p.cuotas} cuotas{p.recargo_pct > 0 ? ` (+${p.recargo_pct}%)` : ""}
+          </option>
+        ))}
+      </select>
+    </div>
+  );
+}
@@ -5,6 +5,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { motion, AnimatePresence } from "framer-motion";
 import ModalMerma from "./ModalMerma";
 import CrearPromoComboModal from "../CrearPromoComboModal";
+import { combosDelListado, etiquetaVigencia, promoComboListar } from "../../api/promos";
 /*  Tipos  */
 type StockResumen = {
   id_producto: number;
@@ -1244,8 +1245,7 @@ async function crearProducto() {
     setLoading(true);
     setError(null);
     try {
-      const data = await invoke<any[]>("promo_combo_listar", { soloActivos: true });
-      setCombos(Array.isArray(data) ? data : []);
+      setCombos(combosDelListado(await promoComboListar()));
     } catch (e: any) {
       setError(String(e?.message ?? e));
       setCombos([]);
@@ -1326,6 +1326,7 @@ const eliminar = (idCombo: number) => {
               className="group relative rounded-lg border border-gray-200 bg-white p-3 hover:bg-gray-50"
             >
               <div className="font-medium text-gray-900">{c.nombre}</div>
+              <div className="text-xs text-gray-500">{etiquetaVigencia(c)}</div>
 
               {c.resumen && (
                 <div className="mt-1 text-xs text-gray-500 truncate">{c.resumen}</div>
@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useEffect, useState } from "react";
+import { combosDelListado, etiquetaVigencia, promoComboListar } from "../../api/promos";
 
 function StockPromocionesPanel() {
   const [loading, setLoading] = useState(false);
@@ -10,8 +11,7 @@ function StockPromocionesPanel() {
     setLoading(true);
     setError("");
     try {
-      const data = await invoke<any[]>("promo_combo_listar", { soloActivos: true });
-      setCombos(Array.isArray(data) ? data : []);
+      setCombos(combosDelListado(await promoComboListar()));
     } catch (e) {
       setError(String(e));
       setCombos([]);
@@ -58,6 +58,7 @@ function StockPromocionesPanel() {
           {combos.map((c) => (
             <div key={c.id_combo} className="group relative rounded-lg border border-gray-200 bg-white p-3 hover:bg-gray-50">
               <div className="font-medium text-gray-900">{c.nombre}</div>
+              <div className="text-xs text-gray-500">{etiquetaVigencia(c)}</div>
 
               {c.resumen && (
                 <div className="mt-1 text-xs text-gray-500 truncate">
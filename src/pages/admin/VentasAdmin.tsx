@@ -30,6 +30,8 @@ type VentaAdminItemRow = {
   cantidad: number;
   precio_unitario: number;
   costo_unitario_en_venta: number;
+  bruto: number;
+  descuento: number;
   subtotal: number;
   fuente_precio: string;
 
@@ -44,6 +46,8 @@ type VentaAdminPagoRow = {
   medio: string;
   monto: number;
   referencia?: string | null;
+  monto_entregado?: number | null;
+  vuelto?: number;
 };
 
 type VentaAdminDetalle = {
@@ -71,6 +75,7 @@ type ProductoBasico = {
 };
 
 type EditItem = {
+  id_item: number | null; // línea original (null = agregada en la edición)
   id_producto: number;
   codigo_producto: string;
   nombre: string;
@@ -78,12 +83,14 @@ type EditItem = {
   precio_unitario: number;
   costo_unitario_en_venta: number;
   fuente_precio: "catalogo" | "manual" | "promo";
+  descuento: number;
 };
 
 type EditPago = {
   medio: "efectivo" | "debito" | "credito" | "transferencia";
   monto: number;
   referencia?: string | null;
+  monto_entregado?: number | null;
 };
 
 /* 
@@ -111,6 +118,11 @@ function clampInt(v: unknown, min: number, max: number) {
   return Math.min(max, Math.max(min, Math.trunc(n)));
 }
 
+function lineaNeta(it: EditItem) {
+  const bruto = it.cantidad * it.precio_unitario;
+  return bruto - Math.min(it.descuento, bruto);
+}
+
 /* Estilos y UI  */
 
 const ui = {
@@ -297,6 +309,7 @@ useEffect(() => {
       setEditVentaId(id_venta);
       setEditItems(
         det.items.map((it) => ({
+          id_item: it.id_item,
           id_producto: it.id_producto,
           codigo_producto: it.codigo,
           nombre: it.producto,
@@ -304,6 +317,7 @@ useEffect(() => {
           precio_unitario: it.precio_unitario,
           costo_unitario_en_venta: it.costo_unitario_en_venta,
           fuente_precio: (it.fuente_precio as any) ?? "manual",
+          descuento: it.descuento ?? 0,
         }))
       );
 
@@ -312,6 +326,7 @@ useEffect(() => {
           medio: (p.medio as any) ?? "efectivo",
           monto: p.monto,
           referencia: p.referencia ?? null,
+          monto_entregado: p.monto_entregado ?? null,
         }))
       );
 
@@ -343,7 +358,7 @@ useEffect(() => {
   }, []);
 
   const totalNuevo = useMemo(
-    () => editItems.reduce((acc, it) => acc + it.cantidad * it.precio_unitario, 0),
+    () => editItems.reduce((acc, it) => acc + lineaNeta(it), 0),
     [editItems]
   );
 
@@ -367,6 +382,7 @@ useEffect(() => {
     const onPickProducto = useCallback(
   (p: ProductoBasico) => {
     const nuevoBase: EditItem = {
+      id_item: null,
       id_producto: p.id_producto,
       codigo_producto: p.codigo_producto,
       nombre: p.nombre,
@@ -374,6 +390,7 @@ useEffect(() => {
       precio_unitario: p.precio_venta_actual,
       costo_unitario_en_venta: p.costo_actual,
       fuente_precio: "catalogo",
+      descuento: 0,
     };
 
     setEditItems((prev) => {
@@ -415,6 +432,12 @@ useEffect(() => {
       return;
     }
 
+    const motivo = window.prompt("Motivo de la edición (obligatorio):")?.trim();
+    if (!motivo) {
+      setError("Indicá el motivo de la edición.");
+      return;
+    }
+
     setEditBusy(true);
 
     try {
@@ -422,17 +445,23 @@ useEffect(() => {
         input: {
           id_venta: editVentaId,
           items: editItems.map((it) => ({
+            id_item: it.id_item,
             id_producto: it.id_producto,
             cantidad: it.cantidad,
             precio_unitario: it.precio_unitario,
             costo_unitario_en_venta: it.costo_unitario_en_venta,
             fuente_precio: it.fuente_precio,
+            descuento: Math.min(it.descuento, it.cantidad * it.precio_unitario),
           })),
           pagos: editPagos.map((p) => ({
             medio: p.medio,
             monto: p.monto,
             referencia: p.referencia ?? null,
+            // el vuelto sólo se conserva si sigue siendo efectivo y alcanza
+            monto_entregado:
+              p.medio === "efectivo" && (p.monto_entregado ?? 0) >= p.monto ? p.monto_entregado : null,
           })),
+          motivo,
         },
       });
 
@@ -811,6 +840,8 @@ function DetalleVenta({ detalle }: { detalle: VentaAdminDetalle }) {
               <tr>
                 <th className={ui.th}>Medio</th>
                 <th className={`${ui.th} text-right`}>Monto</th>
+                <th className={`${ui.th} text-right`}>Entregado</th>
+                <th className={`${ui.th} text-right`}>Vuelto</th>
                 <th className={ui.th}>Referencia</th>
               </tr>
             </thead>
@@ -819,6 +850,8 @@ function DetalleVenta({ detalle }: { detalle: VentaAdminDetalle }) {
                 <tr key={idx} className="border-t border-gray-200 dark:border-gray-800">
                   <td className={ui.td}>{p.medio}</td>
                   <td className={ui.tdRight}>{moneyARS(p.monto)}</td>
+                  <td className={ui.tdRight}>{p.monto_entregado != null ? moneyARS(p.monto_entregado) : "-"}</td>
+                  <td className={ui.tdRight}>{p.vuelto ? moneyARS(p.vuelto) : "-"}</td>
                   <td className={`${ui.td} text-xs text-gray-600 dark:text-gray-300`}>{p.referencia ?? "-"}</td>
                 </tr>
               ))}
@@ -1049,7 +1082,7 @@ useEffect(() => {
                     />
                   </td>
 
-                  <td className={ui.tdRight}>{moneyARS(it.cantidad * it.precio_unitario)}</td>
+                  <td className={ui.tdRight}>{moneyARS(lineaNeta(it))}</td>
 
                   <td className={ui.td}>
                     <div className="flex gap-2">